@@ -1,29 +1,51 @@
 use chrono::NaiveDate;
 use finql::bond::Bond;
+use finql::coupon_date::CouponDate;
 use finql::currency::Currency;
+use finql::day_adjust::DayAdjust;
 use finql::fixed_income::{get_cash_flows_after, CashFlow, FixedIncome};
 use finql::market::Market;
-use serde_json;
-use std::fs::File;
-use std::io::Read;
+use finql::time_period::TimePeriod;
 use std::str::FromStr;
 
 fn main() {
-    let mut file = File::open("./examples/Euroboden_deb_bond.json").unwrap();
-    let mut data = String::new();
-    file.read_to_string(&mut data).unwrap();
-
     let today = NaiveDate::from_ymd(2019, 12, 11);
-    let bond1: Bond = serde_json::from_str(&data).unwrap();
     let market = Market::new();
+    let eur_curr = Currency::from_str("EUR").unwrap();
+
+    // same terms as in examples/Euroboden_deb_bond.json
+    let bond1 = Bond::builder()
+        .isin("DE000A2YNXQ5")
+        .security_id("A2YNXQ")
+        .issuer("Euroboden GmbH")
+        .currency(eur_curr)
+        .coupon(0.055)
+        .coupon_date(CouponDate::new(1, 4).unwrap())
+        .frequency(TimePeriod::from_str("6M").unwrap())
+        .business_day_rule(DayAdjust::Following)
+        .issue_date(NaiveDate::from_ymd(2019, 10, 1))
+        .maturity(NaiveDate::from_ymd(2024, 10, 1))
+        .volume(40_000_000.)
+        .build(&market)
+        .unwrap();
     let cfs1 = bond1.rollout_cash_flows(1., &market).unwrap();
     let cfs1 = get_cash_flows_after(&cfs1, today);
 
-    let mut file = File::open("./examples/photon_energy_bond.json").unwrap();
-    let mut data = String::new();
-    file.read_to_string(&mut data).unwrap();
-
-    let bond2: Bond = serde_json::from_str(&data).unwrap();
+    // same terms as in examples/photon_energy_bond.json
+    let bond2 = Bond::builder()
+        .isin("DE000A19MFH4")
+        .security_id("A19MFH")
+        .issuer("Photon Energy N.V.")
+        .currency(eur_curr)
+        .coupon(0.0775)
+        .coupon_date(CouponDate::new(27, 1).unwrap())
+        .frequency(TimePeriod::from_str("3M").unwrap())
+        .business_day_rule(DayAdjust::Following)
+        .issue_date(NaiveDate::from_ymd(2017, 10, 27))
+        .maturity(NaiveDate::from_ymd(2022, 10, 27))
+        .volume(30_000_000.)
+        .build(&market)
+        .unwrap();
     let cfs2 = bond2.rollout_cash_flows(1., &market).unwrap();
     let cfs2 = get_cash_flows_after(&cfs2, today);
 
@@ -51,7 +73,6 @@ fn main() {
         dirty_price1, dirty_price2
     );
 
-    let eur_curr = Currency::from_str("EUR").unwrap();
     let purchase1_cash_flow = CashFlow::new(-dirty_price1 * price_quote_factor1, eur_curr, today);
     let purchase2_cash_flow = CashFlow::new(-dirty_price2 * price_quote_factor2, eur_curr, today);
     println!(
@@ -0,0 +1,97 @@
+//! Compare the time to construct calendars for a growing range of years,
+//! either by recalculating the whole calendar each time or by extending it year by year.
+use chrono::{NaiveDate, Weekday};
+use finql::calendar::{Calendar, Holiday, NthWeek, WeekdayDirection};
+use std::time::Instant;
+
+fn holiday_rules() -> Vec<Holiday> {
+    let yearly = |month, day| Holiday::YearlyDay {
+        month,
+        day,
+        first: None,
+        last: None,
+    };
+    let movable = |month, day| Holiday::MovableYearlyDay {
+        month,
+        day,
+        first: None,
+        last: None,
+        direction: None,
+    };
+    let easter = |offset| Holiday::EasterOffset {
+        offset,
+        first: None,
+        last: None,
+    };
+    let month_weekday = |month, nth| Holiday::MonthWeekday {
+        month,
+        weekday: Weekday::Mon,
+        nth,
+        first: None,
+        last: None,
+    };
+    vec![
+        Holiday::WeekDay(Weekday::Sat),
+        Holiday::WeekDay(Weekday::Sun),
+        movable(1, 1),
+        easter(-2),
+        easter(1),
+        easter(39),
+        easter(50),
+        easter(60),
+        yearly(5, 1),
+        yearly(10, 3),
+        yearly(11, 1),
+        month_weekday(1, NthWeek::Third),
+        month_weekday(2, NthWeek::Third),
+        month_weekday(5, NthWeek::Last),
+        month_weekday(9, NthWeek::First),
+        Holiday::WeekdayRelativeTo {
+            month: 6,
+            day: 1,
+            weekday: Weekday::Fri,
+            direction: WeekdayDirection::OnOrBefore,
+            first: None,
+            last: None,
+        },
+        movable(7, 4),
+        movable(12, 25),
+        movable(12, 26),
+        Holiday::SingularDay(NaiveDate::from_ymd(1999, 12, 31)),
+    ]
+}
+
+fn main() {
+    let rules = holiday_rules();
+    let (start, end) = (1950, 2049);
+
+    let now = Instant::now();
+    let mut full = Calendar::calc_calendar(&rules, start, start).unwrap();
+    for year in start + 1..=end {
+        full = Calendar::calc_calendar(&rules, start, year).unwrap();
+    }
+    let full_time = now.elapsed();
+
+    let now = Instant::now();
+    let mut extended = Calendar::calc_calendar(&rules, start, start).unwrap();
+    for year in start + 1..=end {
+        extended.extend_years(year).unwrap();
+    }
+    let extended_time = now.elapsed();
+
+    assert_eq!(full, extended);
+    let date = NaiveDate::from_ymd(start, 1, 1);
+    let end_date = NaiveDate::from_ymd(end + 1, 1, 1);
+    assert_eq!(
+        full.business_days_in(date, end_date),
+        extended.business_days_in(date, end_date)
+    );
+    println!(
+        "{} rules, years {} to {}, growing by one year at a time:",
+        rules.len(),
+        start,
+        end
+    );
+    println!("full recalculation: {:?}", full_time);
+    println!("incremental extension: {:?}", extended_time);
+}
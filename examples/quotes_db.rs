@@ -1,11 +1,12 @@
 ///! Demonstration of storing quotes and related data in Sqlite3, PostgreSQL or in-memory database
 ///! Please note: The postgres example will delete all existing content of the database
+use finql::asset::Asset;
 use finql::currency::Currency;
 use finql::data_handler::QuoteHandler;
 use finql::helpers::make_time;
 use finql::memory_handler::InMemoryDB;
 use finql::postgres_handler::PostgresDB;
-use finql::quote::{MarketDataSource, Quote, Ticker};
+use finql::quote::{MarketDataSource, Quote, Ticker, UpdatePeriod};
 use finql::sqlite_handler::SqliteDB;
 use std::fs;
 use std::str::FromStr;
@@ -55,6 +56,19 @@ fn quote_tests<DB: QuoteHandler>(db: &mut DB) {
         println!("failed");
     }
 
+    // Tickers refer to assets, therefore we need some assets first
+    print!("Insert assets...");
+    let basf_asset = db
+        .insert_asset(&Asset::new(None, "BASF AG", None, None, None))
+        .unwrap();
+    let siemens_asset = db
+        .insert_asset(&Asset::new(None, "Siemens AG", None, None, None))
+        .unwrap();
+    let bhp_asset = db
+        .insert_asset(&Asset::new(None, "BHP Group", None, None, None))
+        .unwrap();
+    println!("ok");
+
     // Dealing with ticker data
     let eur = Currency::from_str("EUR").unwrap();
     let aus = Currency::from_str("AUS").unwrap();
@@ -63,8 +77,10 @@ fn quote_tests<DB: QuoteHandler>(db: &mut DB) {
     let basf = Ticker {
         id: None,
         name: "BAS.DE".to_string(),
+        asset: basf_asset,
         currency: eur,
         source: yahoo_id,
+        update_policy: UpdatePeriod::Daily,
     };
     let basf_id = db.insert_ticker(&basf).unwrap();
     // Get ticker back
@@ -73,16 +89,20 @@ fn quote_tests<DB: QuoteHandler>(db: &mut DB) {
     let siemens = Ticker {
         id: None,
         name: "SIE.DE".to_string(),
+        asset: siemens_asset,
         currency: eur,
         source: yahoo_id,
+        update_policy: UpdatePeriod::Daily,
     };
     let siemens_id = db.insert_ticker(&siemens).unwrap();
     // Insert another ticker, with other source
     let mut bhp = Ticker {
         id: None,
         name: "BHP.AUS".to_string(),
+        asset: bhp_asset,
         currency: eur,
         source: av_id,
+        update_policy: UpdatePeriod::Daily,
     };
     let bhp_id = db.insert_ticker(&bhp).unwrap();
     println!("ok");
@@ -170,6 +190,13 @@ fn quote_tests<DB: QuoteHandler>(db: &mut DB) {
     } else {
         println!("failed");
     }
+    print!("get last quote by asset...");
+    let (quote, _) = db.get_last_quote_before_by_id(basf_asset, time).unwrap();
+    if (quote.price - 67.27) < 1e-10 {
+        println!("ok");
+    } else {
+        println!("failed");
+    }
     print!("get all quotes for ticker...");
     let quotes = db.get_all_quotes_for_ticker(basf_id).unwrap();
     if quotes.len() == 5 {
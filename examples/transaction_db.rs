@@ -7,7 +7,7 @@ use finql::fixed_income::CashFlow;
 use finql::memory_handler::InMemoryDB;
 use finql::postgres_handler::PostgresDB;
 use finql::sqlite_handler::SqliteDB;
-use finql::transaction::{Transaction, TransactionType};
+use finql::transaction::{TaxKind, Transaction, TransactionType};
 use std::fs;
 use std::str::FromStr;
 
@@ -36,10 +36,8 @@ fn transaction_tests<DB: DataHandler>(db: &mut DB) {
     let eur = Currency::from_str("EUR").unwrap();
     let cash_flow = CashFlow::new(10_000.0, eur, NaiveDate::from_ymd(2020, 01, 15));
     let cash_in = Transaction {
-        id: None,
-        transaction_type: TransactionType::Cash,
-        cash_flow,
         note: Some("start capital".to_string()),
+        ..Transaction::new(TransactionType::Cash, cash_flow)
     };
     let result = db.insert_transaction(&cash_in);
     match result {
@@ -50,52 +48,45 @@ fn transaction_tests<DB: DataHandler>(db: &mut DB) {
     // Lets buy the asset!
     print!("Store buy asset transaction...");
     let cash_flow = CashFlow::new(-9_000.0, eur, NaiveDate::from_ymd(2020, 01, 15));
-    let asset_buy = Transaction {
-        id: None,
-        transaction_type: TransactionType::Asset {
+    let asset_buy = Transaction::new(
+        TransactionType::Asset {
             asset_id: 1,
             position: 10.0,
         },
         cash_flow,
-        note: None,
-    };
+    );
     let trans_id = db.insert_transaction(&asset_buy).unwrap();
     println!("ok");
 
     print!("Store associated fee transaction...");
     // Associate some fees with the trade
-    let fee = Transaction {
-        id: None,
-        transaction_type: TransactionType::Fee {
+    let fee = Transaction::new(
+        TransactionType::Fee {
             transaction_ref: Some(trans_id),
         },
-        cash_flow: CashFlow::new(-30.0, eur, NaiveDate::from_ymd(2020, 01, 15)),
-        note: None,
-    };
+        CashFlow::new(-30.0, eur, NaiveDate::from_ymd(2020, 01, 15)),
+    );
     let _ = db.insert_transaction(&fee).unwrap();
     println!("ok");
 
     // You got some dividends later
     print!("Store dividend transaction...");
-    let dividend = Transaction {
-        id: None,
-        transaction_type: TransactionType::Dividend { asset_id: 1 },
-        cash_flow: CashFlow::new(90.0, eur, NaiveDate::from_ymd(2020, 01, 30)),
-        note: None,
-    };
+    let dividend = Transaction::new(
+        TransactionType::Dividend { asset_id: 1 },
+        CashFlow::new(90.0, eur, NaiveDate::from_ymd(2020, 01, 30)),
+    );
     let dividend_id = db.insert_transaction(&dividend).unwrap();
     println!("ok");
 
     // But you get taxed, too!
     print!("Insert related tax transaction...");
-    let tax = Transaction {
-        id: None,
-        transaction_type: TransactionType::Tax {
+    let tax = Transaction::new(
+        TransactionType::Tax {
             transaction_ref: Some(dividend_id),
+            kind: TaxKind::Domestic,
         },
-        cash_flow: CashFlow::new(-40.0, eur, NaiveDate::from_ymd(2020, 01, 30)),
-        note: None,
-    };
+        CashFlow::new(-40.0, eur, NaiveDate::from_ymd(2020, 01, 30)),
+    );
     let _ = db.insert_transaction(&tax).unwrap();
     println!("ok");
 
@@ -14,6 +14,7 @@ fn main() {
             day: 1,
             first: None,
             last: None,
+            direction: None,
         },
         // Good Friday
         Holiday::EasterOffset {
@@ -87,6 +88,7 @@ fn main() {
             day: 25,
             first: None,
             last: None,
+            direction: None,
         },
         // Boxing Day
         Holiday::MovableYearlyDay {
@@ -94,6 +96,7 @@ fn main() {
             day: 26,
             first: None,
             last: None,
+            direction: None,
         },
         // Golden Jubilee
         Holiday::SingularDay(NaiveDate::from_ymd(2002, 6, 3)),
@@ -108,6 +111,6 @@ fn main() {
         // Introduction of EUR
         Holiday::SingularDay(NaiveDate::from_ymd(1999, 12, 31)),
     ];
-    let uk_cal = Calendar::calc_calendar(&uk_settlement_holidays, 1999, 2020);
+    let uk_cal = Calendar::calc_calendar(&uk_settlement_holidays, 1999, 2020).unwrap();
     println!("{:#?}", uk_cal);
 }
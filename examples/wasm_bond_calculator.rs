@@ -0,0 +1,33 @@
+///! Client-side bond calculator, exposing the yield to maturity of a bond to JavaScript.
+///! Build with
+///!     cargo build --example wasm_bond_calculator --target wasm32-unknown-unknown --no-default-features
+///! and generate the JavaScript bindings with `wasm-bindgen`. On other targets, this example is empty.
+
+#[cfg(target_arch = "wasm32")]
+mod bindings {
+    use chrono::NaiveDate;
+    use finql::bond::Bond;
+    use finql::fixed_income::{CashFlow, FixedIncome};
+    use finql::market::Market;
+    use wasm_bindgen::prelude::*;
+
+    /// Calculate the yield to maturity of a bond given as JSON string for a purchase
+    /// at `clean_price` (in percent of the denomination) at `date` (formatted as "YYYY-MM-DD")
+    #[wasm_bindgen]
+    pub fn yield_to_maturity(
+        bond_json: &str,
+        clean_price: f64,
+        date: &str,
+    ) -> Result<f64, JsValue> {
+        let to_js = |err: &dyn std::fmt::Display| JsValue::from_str(&err.to_string());
+        let bond: Bond = serde_json::from_str(bond_json).map_err(|e| to_js(&e))?;
+        let today = NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|e| to_js(&e))?;
+        let market = Market::new();
+        let accrued = bond.accrued_interest(today).map_err(|e| to_js(&e))?;
+        let price_quote_factor = (bond.denomination as f64) / 100.;
+        let dirty_price = clean_price * price_quote_factor + accrued;
+        let purchase = CashFlow::new(-dirty_price, bond.currency(), today);
+        bond.calculate_ytm(&purchase, &market)
+            .map_err(|e| to_js(&e))
+    }
+}
@@ -1,25 +1,145 @@
+use crate::bond::Bond;
+use crate::currency::Currency;
 use crate::data_handler::{DataError, DataItem};
+use crate::day_count_conv::DayCountConv;
+use crate::inflation::InflationLinkedBond;
+use crate::options::EuropeanOption;
+use crate::time_period::TimePeriod;
+use chrono::NaiveDate;
 ///! Implementation of a container for basic asset data
 use serde::{Deserialize, Serialize};
+use std::fmt;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AssetCategory {
-    id: usize,
+    id: u64,
     pub name: String,
 }
 
+/// Basic asset data
+/// Optional fields may be omitted in serialized assets, such that payloads
+/// written before a field has been added still could be parsed.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Asset {
-    pub id: Option<usize>,
+    #[serde(default)]
+    pub id: Option<u64>,
     pub name: String,
+    #[serde(default)]
     pub wkn: Option<String>,
+    #[serde(default)]
     pub isin: Option<String>,
+    #[serde(default)]
     pub note: Option<String>,
+    /// Full definition of the instrument, if available
+    #[serde(default)]
+    pub instrument: Option<Instrument>,
+    /// Labels to group assets by arbitrary themes, e.g. sector or strategy
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Inactive assets are excluded from quote updates
+    #[serde(default = "default_active")]
+    pub active: bool,
+    /// Date from which on the asset is no longer listed
+    #[serde(default)]
+    pub delisted_at: Option<NaiveDate>,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+/// Definition of the financial instrument represented by an asset
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum Instrument {
+    #[serde(rename = "bond")]
+    Bond(Box<Bond>),
+    #[serde(rename = "frn")]
+    FloatingRateNote(FloatingRateNote),
+    #[serde(rename = "linker")]
+    InflationLinkedBond(Box<InflationLinkedBond>),
+    #[serde(rename = "stock")]
+    Stock,
+    #[serde(rename = "fund")]
+    Fund,
+    #[serde(rename = "option")]
+    EuropeanOption(EuropeanOption),
+}
+
+/// Kind of an instrument, e.g. to query all assets of a given kind
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum InstrumentType {
+    #[serde(rename = "bond")]
+    Bond,
+    #[serde(rename = "frn")]
+    FloatingRateNote,
+    #[serde(rename = "linker")]
+    InflationLinkedBond,
+    #[serde(rename = "stock")]
+    Stock,
+    #[serde(rename = "fund")]
+    Fund,
+    #[serde(rename = "option")]
+    EuropeanOption,
+}
+
+impl fmt::Display for InstrumentType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InstrumentType::Bond => write!(f, "bond"),
+            InstrumentType::FloatingRateNote => write!(f, "frn"),
+            InstrumentType::InflationLinkedBond => write!(f, "linker"),
+            InstrumentType::Stock => write!(f, "stock"),
+            InstrumentType::Fund => write!(f, "fund"),
+            InstrumentType::EuropeanOption => write!(f, "option"),
+        }
+    }
+}
+
+impl Instrument {
+    pub fn instrument_type(&self) -> InstrumentType {
+        match self {
+            Instrument::Bond(_) => InstrumentType::Bond,
+            Instrument::FloatingRateNote(_) => InstrumentType::FloatingRateNote,
+            Instrument::InflationLinkedBond(_) => InstrumentType::InflationLinkedBond,
+            Instrument::Stock => InstrumentType::Stock,
+            Instrument::Fund => InstrumentType::Fund,
+            Instrument::EuropeanOption(_) => InstrumentType::EuropeanOption,
+        }
+    }
+
+    /// Serialize instrument definition to JSON, e.g. for storage in a database
+    pub fn to_json(&self) -> Result<String, DataError> {
+        serde_json::to_string(self).map_err(|e| DataError::InvalidAsset(e.to_string()))
+    }
+
+    /// Parse instrument definition from JSON
+    pub fn from_json(json: &str) -> Result<Instrument, DataError> {
+        serde_json::from_str(json).map_err(|e| DataError::InvalidAsset(e.to_string()))
+    }
+}
+
+/// Terms of a floating rate note. Only the terms are stored,
+/// rollout and valuation of floating cash flows are not supported yet.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FloatingRateNote {
+    pub currency: Currency,
+    /// Name of the reference rate, e.g. "EURIBOR 3M"
+    pub reference_rate: String,
+    /// Spread over the reference rate in percent
+    pub spread: f64,
+    pub period: TimePeriod,
+    #[serde(default)]
+    pub day_count_convention: DayCountConv,
+    pub issue_date: NaiveDate,
+    pub maturity: NaiveDate,
+    pub denomination: u32,
 }
 
 impl Asset {
     pub fn new(
-        id: Option<usize>,
+        id: Option<u64>,
         name: &str,
         wkn: Option<String>,
         isin: Option<String>,
@@ -31,13 +151,66 @@ impl Asset {
             wkn,
             isin,
             note,
+            instrument: None,
+            tags: Vec::new(),
+            active: true,
+            delisted_at: None,
+        }
+    }
+
+    /// Returns true if the asset is still listed at the given date
+    pub fn is_listed_at(&self, date: NaiveDate) -> bool {
+        match self.delisted_at {
+            Some(delisted_at) => date < delisted_at,
+            None => true,
+        }
+    }
+
+    /// Returns true if the asset is labeled with `tag`
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+    /// Type of the asset's instrument, if its definition is known
+    pub fn instrument_type(&self) -> Option<InstrumentType> {
+        self.instrument.as_ref().map(|i| i.instrument_type())
+    }
+
+    /// Split the asset's instrument in kind and JSON representation for storage
+    #[cfg(any(feature = "sqlite", feature = "postgresql"))]
+    pub(crate) fn instrument_columns(&self) -> Result<(Option<String>, Option<String>), DataError> {
+        match &self.instrument {
+            Some(instrument) => Ok((
+                Some(instrument.instrument_type().to_string()),
+                Some(instrument.to_json()?),
+            )),
+            None => Ok((None, None)),
+        }
+    }
+}
+
+/// Selection of assets, either a single asset or all assets labeled with a tag
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AssetOrTag {
+    /// The asset with the given id
+    Asset(u64),
+    /// All assets labeled with the tag, e.g. "asset_class=equity"
+    Tag(String),
+}
+
+impl AssetOrTag {
+    /// Returns true if `asset` is selected
+    pub fn matches(&self, asset: &Asset) -> bool {
+        match self {
+            AssetOrTag::Asset(id) => asset.id == Some(*id),
+            AssetOrTag::Tag(tag) => asset.has_tag(tag),
         }
     }
 }
 
 impl DataItem for Asset {
     // get id or return error if id hasn't been set yet
-    fn get_id(&self) -> Result<usize, DataError> {
+    fn get_id(&self) -> Result<u64, DataError> {
         match self.id {
             Some(id) => Ok(id),
             None => Err(DataError::DataAccessFailure(
@@ -46,7 +219,7 @@ impl DataItem for Asset {
         }
     }
     // set id or return error if id has already been set
-    fn set_id(&mut self, id: usize) -> Result<(), DataError> {
+    fn set_id(&mut self, id: u64) -> Result<(), DataError> {
         match self.id {
             Some(_) => Err(DataError::DataAccessFailure(
                 "tried to change valid asset id".to_string(),
@@ -58,3 +231,217 @@ impl DataItem for Asset {
         }
     }
 }
+
+/// Maximal edit distance of fuzzy search matches
+const MAX_EDIT_DISTANCE: usize = 2;
+
+/// Quality of an asset matching a search query, lower values are better matches.
+/// Prefix matches of name or ISIN come first, then substring matches ordered by
+/// position of the match, then fuzzy matches ordered by edit distance.
+fn search_rank(asset: &Asset, query: &str, fuzzy: bool) -> Option<(u8, usize)> {
+    // ASCII case folding only, like sqlite's LIKE, to get the same matches in all data handlers
+    let query = query.to_ascii_lowercase();
+    let mut fields = vec![asset.name.to_ascii_lowercase()];
+    if let Some(isin) = &asset.isin {
+        fields.push(isin.to_ascii_lowercase());
+    }
+    let position = fields.iter().filter_map(|field| field.find(&query)).min();
+    match position {
+        Some(0) => return Some((0, 0)),
+        Some(pos) => return Some((1, pos)),
+        None => {}
+    }
+    if !fuzzy {
+        return None;
+    }
+    // compare with the whole field and with each of its words
+    let distance = fields
+        .iter()
+        .flat_map(|field| std::iter::once(field.as_str()).chain(field.split_whitespace()))
+        .map(|word| edit_distance(word, &query))
+        .min()?;
+    if distance <= MAX_EDIT_DISTANCE {
+        Some((2, distance))
+    } else {
+        None
+    }
+}
+
+/// Levenshtein distance of two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diag = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diag + if ca == *cb { 0 } else { 1 };
+            diag = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diag + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Escape wildcards in a search query for (I)LIKE patterns of database backends,
+/// using backslash as escape character
+#[cfg(any(feature = "sqlite", feature = "postgresql"))]
+pub(crate) fn escape_like(query: &str) -> String {
+    query
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// Filter assets matching the search query and order them by match quality,
+/// see `DataHandler::search_assets`. Shared by all data handlers to rank search
+/// results identically.
+pub fn rank_search_results(
+    assets: Vec<Asset>,
+    query: &str,
+    limit: usize,
+    fuzzy: bool,
+) -> Vec<Asset> {
+    let mut ranked: Vec<((u8, usize), Asset)> = assets
+        .into_iter()
+        .filter_map(|asset| search_rank(&asset, query, fuzzy).map(|rank| (rank, asset)))
+        .collect();
+    ranked.sort_by(|(rank_a, a), (rank_b, b)| rank_a.cmp(rank_b).then(a.name.cmp(&b.name)));
+    ranked
+        .into_iter()
+        .take(limit)
+        .map(|(_, asset)| asset)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_handler::DataHandler;
+    use crate::memory_handler::InMemoryDB;
+
+    #[test]
+    fn instrument_round_trip_in_memory() {
+        let bond: Bond =
+            serde_json::from_str(include_str!("../examples/photon_energy_bond.json")).unwrap();
+        let mut bond_asset = Asset::new(None, "Photon Energy 17/22", None, None, None);
+        bond_asset.instrument = Some(Instrument::Bond(Box::new(bond)));
+        let mut stock_asset = Asset::new(None, "BASF AG", None, None, None);
+        stock_asset.instrument = Some(Instrument::Stock);
+
+        let mut db = InMemoryDB::new();
+        let bond_id = db.insert_asset(&bond_asset).unwrap();
+        db.insert_asset(&stock_asset).unwrap();
+        db.insert_asset(&Asset::new(None, "unknown", None, None, None))
+            .unwrap();
+
+        let stored = db.get_asset_by_id(bond_id).unwrap();
+        assert_eq!(stored.instrument_type(), Some(InstrumentType::Bond));
+        assert_eq!(
+            stored.instrument.unwrap().to_json().unwrap(),
+            bond_asset.instrument.unwrap().to_json().unwrap()
+        );
+        let bonds = db
+            .get_assets_by_instrument_type(InstrumentType::Bond)
+            .unwrap();
+        assert_eq!(bonds.len(), 1);
+        assert_eq!(bonds[0].name, "Photon Energy 17/22");
+        let stocks = db
+            .get_assets_by_instrument_type(InstrumentType::Stock)
+            .unwrap();
+        assert_eq!(stocks.len(), 1);
+        assert_eq!(stocks[0].name, "BASF AG");
+        let funds = db
+            .get_assets_by_instrument_type(InstrumentType::Fund)
+            .unwrap();
+        assert!(funds.is_empty());
+    }
+
+    #[test]
+    fn search_assets_ranking() {
+        let mut db = InMemoryDB::new();
+        for (name, isin) in &[
+            ("Siemens AG", "DE0007236101"),
+            ("BASF SE", "DE000BASF111"),
+            ("Deutsche Bank AG", "DE0005140008"),
+            ("Bank of America", "US0605051046"),
+        ] {
+            db.insert_asset(&Asset::new(None, name, None, Some(isin.to_string()), None))
+                .unwrap();
+        }
+        let names = |assets: Vec<Asset>| assets.into_iter().map(|a| a.name).collect::<Vec<_>>();
+
+        // prefix match before mid-string match
+        let result = db.search_assets("bank", 10).unwrap();
+        assert_eq!(names(result), vec!["Bank of America", "Deutsche Bank AG"]);
+        let result = db.search_assets("bank", 1).unwrap();
+        assert_eq!(names(result), vec!["Bank of America"]);
+        // match on ISIN
+        let result = db.search_assets("basf1", 10).unwrap();
+        assert_eq!(names(result), vec!["BASF SE"]);
+        // typo is found by fuzzy search only
+        assert!(db.search_assets("siemenz", 10).unwrap().is_empty());
+        let result = db.search_assets_fuzzy("siemenz", 10).unwrap();
+        assert_eq!(names(result), vec!["Siemens AG"]);
+        assert_eq!(edit_distance("siemens", "siemenz"), 1);
+    }
+
+    #[test]
+    fn search_assets_ascii_case_folding() {
+        let mut db = InMemoryDB::new();
+        db.insert_asset(&Asset::new(None, "Ökoworld AG", None, None, None))
+            .unwrap();
+        assert_eq!(db.search_assets("ökoworld", 10).unwrap().len(), 0);
+        assert_eq!(db.search_assets("ÖKOWORLD", 10).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn assets_by_tag() {
+        let mut db = InMemoryDB::new();
+        let mut solar = Asset::new(None, "Photon Energy", None, None, None);
+        solar.tags = vec!["energy".to_string(), "esg".to_string()];
+        let mut oil = Asset::new(None, "Royal Dutch Shell", None, None, None);
+        oil.tags = vec!["energy".to_string()];
+        let untagged = Asset::new(None, "BASF AG", None, None, None);
+        let solar_id = db.insert_asset(&solar).unwrap();
+        let oil_id = db.insert_asset(&oil).unwrap();
+        db.insert_asset(&untagged).unwrap();
+
+        let ids = |assets: Vec<Asset>| assets.iter().map(|a| a.id.unwrap()).collect::<Vec<u64>>();
+        assert_eq!(
+            ids(db.get_assets_by_tag("energy").unwrap()),
+            vec![solar_id, oil_id]
+        );
+        assert_eq!(ids(db.get_assets_by_tag("esg").unwrap()), vec![solar_id]);
+        assert!(db.get_assets_by_tag("Energy").unwrap().is_empty());
+    }
+
+    #[test]
+    fn deserialize_assets() {
+        let asset: Asset = serde_json::from_str(r#"{ "name": "BASF AG" }"#).unwrap();
+        assert_eq!(asset.id, None);
+        assert_eq!(asset.name, "BASF AG");
+        assert_eq!(asset.wkn, None);
+        assert_eq!(asset.isin, None);
+        assert_eq!(asset.note, None);
+        assert!(asset.instrument.is_none());
+        assert!(asset.tags.is_empty());
+
+        let json = r#"{
+            "id": 3,
+            "name": "BASF AG",
+            "wkn": "BASF11",
+            "isin": "DE000BASF111",
+            "note": "chemicals",
+            "instrument": "stock",
+            "tags": ["dax", "chemicals"]
+        }"#;
+        let asset: Asset = serde_json::from_str(json).unwrap();
+        assert_eq!(asset.id, Some(3));
+        assert_eq!(asset.wkn.as_deref(), Some("BASF11"));
+        assert_eq!(asset.isin.as_deref(), Some("DE000BASF111"));
+        assert_eq!(asset.note.as_deref(), Some("chemicals"));
+        assert_eq!(asset.instrument_type(), Some(InstrumentType::Stock));
+        assert_eq!(asset.tags, vec!["dax", "chemicals"]);
+    }
+}
@@ -2,17 +2,22 @@
 //! and functionality to rollout cashflows and calculate basic
 //! valuation figures
 
-use crate::currency::Currency;
+use crate::calendar::last_day_of_month;
+use crate::currency::{Currency, RoundingRule};
+#[cfg(feature = "persistence")]
+use crate::data_handler::DataError;
 use crate::day_adjust::DayAdjust;
 use crate::day_count_conv::{DayCountConv, DayCountConvError};
-use crate::fixed_income::{CashFlow, FixedIncome};
+use crate::fixed_income::{amount_from_f64, price_bond_curve, CashAmount, CashFlow, FixedIncome};
 use crate::market::{Market, MarketError};
-use crate::rates::DiscountError;
+use crate::math::bisect;
+use crate::rates::{Compounding, DiscountCurve, DiscountError, Discounter, FlatRate};
 use crate::time_period::TimePeriod;
 use chrono::{Datelike, NaiveDate};
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use std::error::Error;
 use std::fmt;
+use std::str::FromStr;
 
 /// Error related to bonds
 #[derive(Debug)]
@@ -20,6 +25,21 @@ pub enum BondError {
     DiscountingFailure(DiscountError),
     MissingCalendar,
     DayCountError(DayCountConvError),
+    /// Neither market quotes nor a discount curve are available for valuation
+    NoValuationSource,
+    #[cfg(feature = "persistence")]
+    MarketDataError(DataError),
+    /// The bond's terms are incomplete or inconsistent
+    InvalidTerms(String),
+    /// The calendar's range of years could not be extended to the bond's lifetime
+    CalendarCoverage {
+        calendar: String,
+        range: (i32, i32),
+    },
+    /// The price implies a z-spread outside of ±100%
+    SpreadOutOfRange {
+        price: f64,
+    },
 }
 
 impl fmt::Display for BondError {
@@ -30,6 +50,21 @@ impl fmt::Display for BondError {
                 write!(f, "invalid day count convention in this context")
             }
             BondError::DiscountingFailure(_) => write!(f, "discounting cash flows failed"),
+            BondError::NoValuationSource => write!(
+                f,
+                "bond can't be valued, neither quotes nor a discount curve are available"
+            ),
+            #[cfg(feature = "persistence")]
+            BondError::MarketDataError(_) => write!(f, "fetching market data failed"),
+            BondError::InvalidTerms(msg) => write!(f, "invalid bond terms: {}", msg),
+            BondError::CalendarCoverage { calendar, range } => write!(
+                f,
+                "calendar {} covering the years {} to {} can't be extended to the bond's lifetime",
+                calendar, range.0, range.1
+            ),
+            BondError::SpreadOutOfRange { price } => {
+                write!(f, "price {} implies a z-spread outside of ±100%", price)
+            }
         }
     }
 }
@@ -39,6 +74,8 @@ impl Error for BondError {
         match self {
             BondError::DayCountError(err) => Some(err),
             BondError::DiscountingFailure(err) => Some(err),
+            #[cfg(feature = "persistence")]
+            BondError::MarketDataError(err) => Some(err),
             _ => None,
         }
     }
@@ -50,6 +87,13 @@ impl From<DayCountConvError> for BondError {
     }
 }
 
+#[cfg(feature = "persistence")]
+impl From<DataError> for BondError {
+    fn from(error: DataError) -> Self {
+        BondError::MarketDataError(error)
+    }
+}
+
 impl From<MarketError> for BondError {
     fn from(_: MarketError) -> Self {
         BondError::MissingCalendar
@@ -62,8 +106,38 @@ impl From<crate::rates::DiscountError> for BondError {
     }
 }
 
+/// Value at `settlement` of the cash flows after `settlement`, discounted with the zero rates
+/// of `curve` shifted by `spread`
+fn z_spread_value(
+    cash_flows: &[CashFlow],
+    spread: f64,
+    curve: &DiscountCurve,
+    settlement: NaiveDate,
+    compounding: Compounding,
+) -> f64 {
+    let dc = curve.day_count_conv();
+    let reference = curve.reference_date();
+    let currency = cash_flows
+        .first()
+        .map(|cf| cf.amount.currency)
+        .unwrap_or_else(|| curve.currency());
+    let discount_factor = |date: NaiveDate| {
+        let rate = curve.zero_rate(date, dc, compounding) + spread;
+        FlatRate::new(rate, dc, compounding, currency).discount_factor(reference, date)
+    };
+    let settlement_df = discount_factor(settlement);
+    cash_flows
+        .iter()
+        .filter(|cf| cf.date > settlement)
+        .map(|cf| cf.amount.value() * discount_factor(cf.date) / settlement_df)
+        .sum()
+}
+
 /// Container for bonds and similar fixed income assets
-#[derive(Deserialize, Serialize, Debug)]
+/// Omitted fields of the bond's terms get market standard defaults when parsed.
+/// The calendar is only referenced by name and resolved when cash flows are rolled out.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(remote = "Self")]
 pub struct Bond {
     /// International security identification number
     isin: Option<String>,
@@ -76,18 +150,69 @@ pub struct Bond {
     bond_type: String,
     currency: Currency,
     coupon: Coupon,
+    /// Business day convention, modified following by default
+    #[serde(default)]
     business_day_rule: DayAdjust,
+    /// Name of the calendar used for adjusting payment dates, TARGET by default
+    #[serde(default = "default_calendar")]
     calendar: String,
+    /// If true and the coupon date is the last day of a month,
+    /// all coupon dates are rolled to the end of the month
+    #[serde(default, alias = "eom")]
+    end_of_month: bool,
     issue_date: NaiveDate,
     maturity: NaiveDate,
     /// Smallest purchasable unit
     pub denomination: u32,
     volume: Option<f64>,
+    /// Repayment scheme of the notional, bullet repayment at maturity by default
+    #[serde(default)]
+    amortization: Amortization,
+    /// Rounding of cash flows to the currency's minor unit, no rounding by default
+    #[serde(default)]
+    rounding: RoundingRule,
+}
+
+fn default_calendar() -> String {
+    "TARGET".to_string()
+}
+
+impl<'de> Deserialize<'de> for Bond {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let bond = Bond::deserialize(deserializer)?;
+        bond.validate().map_err(de::Error::custom)?;
+        Ok(bond)
+    }
+}
+
+impl Serialize for Bond {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        Bond::serialize(self, serializer)
+    }
+}
+
+/// Repayment schemes of the notional
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum Amortization {
+    /// Full repayment of the notional at maturity
+    #[serde(rename = "bullet")]
+    #[default]
+    Bullet,
+    /// Repayment in equal parts at each coupon date,
+    /// coupons are paid on the outstanding notional
+    #[serde(rename = "linear")]
+    Linear,
 }
 
 /// Information regarding the issuer of an asset
 /// This is required for determination of some asset's credit worthiness.
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 struct Issuer {
     /// Minimal obligatory information is the name of the issuer
     name: String,
@@ -95,7 +220,7 @@ struct Issuer {
 }
 
 /// Address of an issuer, e.g. city and country of headquarter
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 struct IssuerAddress {
     city: String,
     country: String,
@@ -104,13 +229,15 @@ struct IssuerAddress {
 use super::coupon_date::CouponDate;
 
 /// Coupon specification of fixed income instruments
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 struct Coupon {
     coupon_type: String,
     rate: f64,
     /// (Unadjusted) first coupon end date used as a basis for cash flow rollout
     coupon_date: CouponDate,
     period: TimePeriod,
+    /// Day count convention, Act/Act ICMA by default
+    #[serde(default)]
     day_count_convention: DayCountConv,
 }
 
@@ -133,6 +260,73 @@ impl Coupon {
 }
 
 impl Bond {
+    /// Currency of the bond's cash flows
+    pub fn currency(&self) -> Currency {
+        self.currency
+    }
+
+    /// Fraction of the denomination that is not yet repaid at `date`.
+    /// Repayments are taken into account after the end of their coupon period.
+    pub fn outstanding_fraction(&self, date: NaiveDate) -> f64 {
+        let periods = self.coupon_periods();
+        let repaid_periods = periods
+            .iter()
+            .filter(|(_, end_date, _)| *end_date < date)
+            .count();
+        match self.amortization {
+            Amortization::Bullet if date > self.maturity => 0.,
+            Amortization::Bullet => 1.,
+            Amortization::Linear => 1. - repaid_periods as f64 / periods.len() as f64,
+        }
+    }
+
+    /// Check the bond's terms for inconsistencies
+    fn validate(&self) -> Result<(), String> {
+        if self.maturity <= self.issue_date {
+            return Err(format!(
+                "maturity {} must be after issue date {}",
+                self.maturity, self.issue_date
+            ));
+        }
+        if !self.coupon.period.is_positive() {
+            return Err(format!(
+                "coupon period {} has zero frequency, a positive period is required",
+                self.coupon.period
+            ));
+        }
+        if self.denomination == 0 {
+            return Err("denomination must be positive".to_string());
+        }
+        Ok(())
+    }
+
+    /// Builder for bonds, see `BondBuilder` for the defaults of omitted terms
+    pub fn builder() -> BondBuilder {
+        BondBuilder::default()
+    }
+
+    /// Unadjusted coupon periods as tuples of start date, end date and roll date
+    fn coupon_periods(&self) -> Vec<(NaiveDate, NaiveDate, NaiveDate)> {
+        let start_date = self.issue_date;
+        let mut end_date = self.first_coupon_end(start_date);
+        let eom = self.end_of_month
+            && end_date.day() == last_day_of_month(end_date.year(), end_date.month());
+        let mut periods = vec![(start_date, end_date, end_date)];
+        while end_date < self.maturity {
+            let start_date = end_date;
+            end_date = self.coupon.period.add_to(start_date, None);
+            if eom {
+                end_date = NaiveDate::from_ymd(
+                    end_date.year(),
+                    end_date.month(),
+                    last_day_of_month(end_date.year(), end_date.month()),
+                );
+            }
+            periods.push((start_date, end_date, start_date));
+        }
+        periods
+    }
+
     /// Calculate first coupon period end date
     fn first_coupon_end(&self, start_date: NaiveDate) -> NaiveDate {
         if self.coupon.coupon_month() <= start_date.month() {
@@ -149,6 +343,350 @@ impl Bond {
             )
         }
     }
+
+    /// Principal repayments of a position in the bond, excluding interest payments
+    pub fn principal_cash_flows(
+        &self,
+        position: f64,
+        market: &Market,
+    ) -> Result<Vec<CashFlow>, BondError> {
+        Ok(self.interest_and_principal_flows(position, market)?.1)
+    }
+
+    /// Interest and principal payments of a position in the bond, each ordered by date.
+    /// If the bond's calendar does not cover the years from issue to maturity, a copy
+    /// of the calendar is extended to these years, since payment dates would be adjusted
+    /// to weekends only otherwise. Fails if the calendar could not be extended.
+    pub(crate) fn interest_and_principal_flows(
+        &self,
+        position: f64,
+        market: &Market,
+    ) -> Result<(Vec<CashFlow>, Vec<CashFlow>), BondError> {
+        let market_cal = market.get_calendar(&self.calendar)?;
+        let extended;
+        let cal = if market_cal.covers(self.issue_date, self.maturity) {
+            market_cal
+        } else {
+            let (start, end) = market_cal.year_range();
+            let mut cal = market_cal.clone();
+            cal.recompute(
+                start.min(self.issue_date.year()),
+                end.max(self.maturity.year()),
+            )
+            .map_err(|_| BondError::CalendarCoverage {
+                calendar: self.calendar.clone(),
+                range: (start, end),
+            })?;
+            extended = cal;
+            &extended
+        };
+        let notional = position * (self.denomination as f64);
+        let periods = self.coupon_periods();
+        let num_periods = periods.len() as f64;
+        let mut interest_cfs = Vec::new();
+        let mut principal_cfs = Vec::new();
+        let mut outstanding = notional;
+        for (start_date, end_date, roll_date) in periods {
+            let year_fraction = self.coupon.year_fraction(start_date, end_date, roll_date)?;
+            let amount = outstanding * self.coupon.rate / 100. * year_fraction;
+            let pay_date = self.business_day_rule.adjust_date(end_date, cal);
+            interest_cfs.push(CashFlow::new(
+                self.rounding.round(amount, self.currency),
+                self.currency,
+                pay_date,
+            ));
+            if self.amortization == Amortization::Linear {
+                let principal = self.rounding.round(notional / num_periods, self.currency);
+                principal_cfs.push(CashFlow::new(principal, self.currency, pay_date));
+                outstanding -= notional / num_periods;
+            }
+        }
+        if self.amortization == Amortization::Bullet {
+            principal_cfs.push(CashFlow::new(
+                notional,
+                self.currency,
+                self.business_day_rule.adjust_date(self.maturity, cal),
+            ));
+        }
+        // The final principal payment absorbs all rounding differences,
+        // such that the total principal sums up exactly to the (rounded) notional.
+        let last = principal_cfs.len() - 1;
+        let repaid: CashAmount = principal_cfs[..last]
+            .iter()
+            .map(|cf| cf.amount.amount)
+            .sum();
+        principal_cfs[last].amount.amount =
+            amount_from_f64(self.rounding.round(notional, self.currency)) - repaid;
+
+        Ok((interest_cfs, principal_cfs))
+    }
+
+    /// Present values at `settlement` of the principal payments and of the interest
+    /// payments for a coupon rate of 100%, per denomination, and the accrued interest
+    /// for a coupon rate of 100%. Since interest is linear in the coupon rate, these
+    /// are the building blocks for solving for rates or spreads in closed form.
+    fn unit_coupon_values(
+        &self,
+        curve: &DiscountCurve,
+        settlement: NaiveDate,
+        market: &Market,
+    ) -> Result<(f64, f64, f64), BondError> {
+        let mut unit_bond = self.clone();
+        unit_bond.coupon.rate = 100.;
+        unit_bond.rounding = RoundingRule::None;
+        let (interest_cfs, principal_cfs) = unit_bond.interest_and_principal_flows(1., market)?;
+        let annuity = price_bond_curve(&interest_cfs, curve, settlement, 0.)?.dirty;
+        let principal = price_bond_curve(&principal_cfs, curve, settlement, 0.)?.dirty;
+        let accrued = unit_bond.accrued_interest(settlement)?;
+        Ok((annuity, principal, accrued))
+    }
+
+    /// Coupon rate as decimal fraction for which the clean price of the bond, discounted
+    /// with `curve` to `settlement`, equals its denomination (par). All other terms of the
+    /// bond are kept, rounding of cash flows is ignored.
+    pub fn par_coupon(
+        &self,
+        curve: &DiscountCurve,
+        settlement: NaiveDate,
+        market: &Market,
+    ) -> Result<f64, BondError> {
+        let (annuity, principal, accrued) = self.unit_coupon_values(curve, settlement, market)?;
+        Ok((self.denomination as f64 - principal) / (annuity - accrued))
+    }
+
+    /// Spread as decimal fraction over the floating leg of a par-par asset swap, given the
+    /// bond's `dirty_price` per denomination at `settlement`. The spread is the difference
+    /// between the bond's present value with the `discount_curve` and its dirty price,
+    /// paid on the outstanding notional over the bond's remaining coupon periods.
+    /// The floating leg is assumed to follow the bond's coupon schedule and day count.
+    pub fn asset_swap_spread(
+        &self,
+        dirty_price: f64,
+        discount_curve: &DiscountCurve,
+        settlement: NaiveDate,
+        market: &Market,
+    ) -> Result<f64, BondError> {
+        let cash_flows = self.rollout_cash_flows(1., market)?;
+        let value = price_bond_curve(&cash_flows, discount_curve, settlement, 0.)?.dirty;
+        let (annuity, _, _) = self.unit_coupon_values(discount_curve, settlement, market)?;
+        Ok((value - dirty_price) / annuity)
+    }
+
+    /// Dirty price per denomination at `settlement`, discounting the cash flows after
+    /// `settlement` with the zero rates of `curve` shifted by `spread`. Zero rates and
+    /// spread refer to the curve's day count convention and the given `compounding`.
+    pub fn price_with_z_spread(
+        &self,
+        spread: f64,
+        curve: &DiscountCurve,
+        settlement: NaiveDate,
+        compounding: Compounding,
+        market: &Market,
+    ) -> Result<f64, BondError> {
+        let cash_flows = self.rollout_cash_flows(1., market)?;
+        Ok(z_spread_value(
+            &cash_flows,
+            spread,
+            curve,
+            settlement,
+            compounding,
+        ))
+    }
+
+    /// Constant spread to the zero rates of `curve` for which the discounted cash flows
+    /// after `settlement` equal `dirty_price` per denomination, see `price_with_z_spread`.
+    /// The spread is searched by bisection between -100% and 100%, prices implying
+    /// a spread outside of this range are rejected.
+    pub fn z_spread(
+        &self,
+        dirty_price: f64,
+        curve: &DiscountCurve,
+        settlement: NaiveDate,
+        compounding: Compounding,
+        market: &Market,
+    ) -> Result<f64, BondError> {
+        let cash_flows = self.rollout_cash_flows(1., market)?;
+        let excess = |spread: f64| {
+            z_spread_value(&cash_flows, spread, curve, settlement, compounding) - dirty_price
+        };
+        bisect(excess, -1., 1., 1e-15).ok_or(BondError::SpreadOutOfRange { price: dirty_price })
+    }
+}
+
+/// Builder for fixed rate bonds, terms are checked for consistency when the bond is built.
+/// Issue date, maturity, coupon rate and currency are required. Omitted terms are set to
+/// * annual coupon payments (`frequency`)
+/// * Act/Act ICMA day count convention (`day_count`)
+/// * calendar TARGET (`calendar`) and modified following business day rule (`business_day_rule`)
+/// * coupon date at the day and month of maturity (`coupon_date`)
+/// * denomination of 1000 (`denomination`)
+/// * bullet repayment (`amortization`) without rounding of cash flows (`rounding`)
+#[derive(Debug, Clone, Default)]
+pub struct BondBuilder {
+    isin: Option<String>,
+    security_id: Option<String>,
+    issuer: Option<String>,
+    currency: Option<Currency>,
+    rate: Option<f64>,
+    coupon_date: Option<CouponDate>,
+    frequency: Option<TimePeriod>,
+    day_count: DayCountConv,
+    business_day_rule: DayAdjust,
+    calendar: Option<String>,
+    end_of_month: bool,
+    issue_date: Option<NaiveDate>,
+    maturity: Option<NaiveDate>,
+    denomination: Option<u32>,
+    volume: Option<f64>,
+    amortization: Amortization,
+    rounding: RoundingRule,
+}
+
+impl BondBuilder {
+    pub fn isin(mut self, isin: &str) -> Self {
+        self.isin = Some(isin.to_string());
+        self
+    }
+
+    pub fn security_id(mut self, security_id: &str) -> Self {
+        self.security_id = Some(security_id.to_string());
+        self
+    }
+
+    /// Name of the issuer
+    pub fn issuer(mut self, name: &str) -> Self {
+        self.issuer = Some(name.to_string());
+        self
+    }
+
+    pub fn currency(mut self, currency: Currency) -> Self {
+        self.currency = Some(currency);
+        self
+    }
+
+    /// Annual coupon rate as decimal fraction, e.g. 0.025 for 2.5%
+    pub fn coupon(mut self, rate: f64) -> Self {
+        self.rate = Some(rate);
+        self
+    }
+
+    /// Day and month of the (unadjusted) end of the first coupon period
+    pub fn coupon_date(mut self, coupon_date: CouponDate) -> Self {
+        self.coupon_date = Some(coupon_date);
+        self
+    }
+
+    /// Period between coupon payments, e.g. "6M" for semi-annual payments
+    pub fn frequency(mut self, period: TimePeriod) -> Self {
+        self.frequency = Some(period);
+        self
+    }
+
+    pub fn day_count(mut self, day_count: DayCountConv) -> Self {
+        self.day_count = day_count;
+        self
+    }
+
+    pub fn business_day_rule(mut self, rule: DayAdjust) -> Self {
+        self.business_day_rule = rule;
+        self
+    }
+
+    /// Name of the calendar as known by the `Market`
+    pub fn calendar(mut self, name: &str) -> Self {
+        self.calendar = Some(name.to_string());
+        self
+    }
+
+    pub fn end_of_month(mut self, end_of_month: bool) -> Self {
+        self.end_of_month = end_of_month;
+        self
+    }
+
+    pub fn issue_date(mut self, date: NaiveDate) -> Self {
+        self.issue_date = Some(date);
+        self
+    }
+
+    pub fn maturity(mut self, date: NaiveDate) -> Self {
+        self.maturity = Some(date);
+        self
+    }
+
+    pub fn denomination(mut self, denomination: u32) -> Self {
+        self.denomination = Some(denomination);
+        self
+    }
+
+    /// Total volume of the issue
+    pub fn volume(mut self, volume: f64) -> Self {
+        self.volume = Some(volume);
+        self
+    }
+
+    pub fn amortization(mut self, amortization: Amortization) -> Self {
+        self.amortization = amortization;
+        self
+    }
+
+    pub fn rounding(mut self, rounding: RoundingRule) -> Self {
+        self.rounding = rounding;
+        self
+    }
+
+    /// Build the bond, checking that all required terms are given, the maturity is
+    /// after the issue date, the denomination is positive and the calendar is known
+    /// by `market`
+    pub fn build(self, market: &Market) -> Result<Bond, BondError> {
+        let missing = |term: &str| BondError::InvalidTerms(format!("missing {}", term));
+        let issue_date = self.issue_date.ok_or_else(|| missing("issue date"))?;
+        let maturity = self.maturity.ok_or_else(|| missing("maturity"))?;
+        let rate = self.rate.ok_or_else(|| missing("coupon rate"))?;
+        let currency = self.currency.ok_or_else(|| missing("currency"))?;
+        let calendar = self.calendar.unwrap_or_else(default_calendar);
+        if market.get_calendar(&calendar).is_err() {
+            return Err(BondError::InvalidTerms(format!(
+                "unknown calendar {}",
+                calendar
+            )));
+        }
+        let coupon_date = match self.coupon_date {
+            Some(coupon_date) => coupon_date,
+            None => CouponDate::new(maturity.day(), maturity.month())
+                .map_err(|err| BondError::InvalidTerms(err.to_string()))?,
+        };
+        let bond = Bond {
+            isin: self.isin,
+            security_id: self.security_id,
+            prospect_url: None,
+            issuer: self.issuer.map(|name| Issuer {
+                name,
+                address: None,
+            }),
+            bond_type: "bond".to_string(),
+            currency,
+            coupon: Coupon {
+                coupon_type: "fixed".to_string(),
+                rate: rate * 100.,
+                coupon_date,
+                period: self
+                    .frequency
+                    .unwrap_or(TimePeriod::from_str("1Y").unwrap()),
+                day_count_convention: self.day_count,
+            },
+            business_day_rule: self.business_day_rule,
+            calendar,
+            end_of_month: self.end_of_month,
+            issue_date,
+            maturity,
+            denomination: self.denomination.unwrap_or(1000),
+            volume: self.volume,
+            amortization: self.amortization,
+            rounding: self.rounding,
+        };
+        bond.validate().map_err(BondError::InvalidTerms)?;
+        Ok(bond)
+    }
 }
 
 impl FixedIncome for Bond {
@@ -160,57 +698,38 @@ impl FixedIncome for Bond {
         position: f64,
         market: &Market,
     ) -> Result<Vec<CashFlow>, BondError> {
-        let mut cfs = Vec::new();
-        let start_date = self.issue_date;
-        let mut end_date = self.first_coupon_end(start_date);
-        let year_fraction = self.coupon.year_fraction(start_date, end_date, end_date)?;
-        let amount =
-            position * (self.denomination as f64) * self.coupon.rate / 100. * year_fraction;
-        let cal = market.get_calendar(&self.calendar)?;
-        let pay_date = self.business_day_rule.adjust_date(end_date, cal);
-        let cf = CashFlow::new(amount, self.currency, pay_date);
-        cfs.push(cf);
-        let maturity = self.maturity;
-        while end_date < maturity {
-            let start_date = end_date;
-            end_date = self.coupon.period.add_to(start_date, None);
-            let year_fraction = self
-                .coupon
-                .year_fraction(start_date, end_date, start_date)?;
-            let amount =
-                position * (self.denomination as f64) * self.coupon.rate / 100. * year_fraction;
-            let pay_date = self.business_day_rule.adjust_date(end_date, cal);
-            let cf = CashFlow::new(amount, self.currency, pay_date);
-            cfs.push(cf);
-        }
-        // final nominal payment
-        let cf = CashFlow::new(
-            position * (self.denomination as f64),
-            self.currency,
-            self.business_day_rule.adjust_date(maturity, cal),
-        );
-        cfs.push(cf);
-
+        let (mut interest_cfs, principal_cfs) =
+            self.interest_and_principal_flows(position, market)?;
+        let cfs = match self.amortization {
+            Amortization::Bullet => {
+                interest_cfs.extend(principal_cfs);
+                interest_cfs
+            }
+            Amortization::Linear => interest_cfs
+                .into_iter()
+                .zip(principal_cfs)
+                .flat_map(|(interest, principal)| vec![interest, principal])
+                .collect(),
+        };
         Ok(cfs)
     }
 
     fn accrued_interest(&self, today: NaiveDate) -> Result<f64, BondError> {
-        let mut start_date = self.issue_date;
-        if today < start_date {
-            return Ok(0.);
-        }
-        let mut end_date = self.first_coupon_end(start_date);
-        while today > end_date && end_date < self.maturity {
-            start_date = end_date;
-            end_date = self.coupon.period.add_to(start_date, None);
-        }
-        if end_date >= self.maturity {
+        if today < self.issue_date {
             return Ok(0.);
         }
+        let periods = self.coupon_periods();
+        let current = periods.iter().find(|(_, end_date, _)| today <= *end_date);
+        let (start_date, end_date) = match current {
+            Some(&(start_date, end_date, _)) if end_date < self.maturity => (start_date, end_date),
+            _ => return Ok(0.),
+        };
+        let outstanding = self.outstanding_fraction(today);
         let year_fraction = self
             .coupon
             .year_fraction(start_date, end_date, start_date)?;
-        let amount = (self.denomination as f64) * self.coupon.rate / 100. * year_fraction;
+        let amount =
+            outstanding * (self.denomination as f64) * self.coupon.rate / 100. * year_fraction;
         let fraction = today.signed_duration_since(start_date).num_days() as f64
             / end_date.signed_duration_since(start_date).num_days() as f64;
 
@@ -314,4 +833,420 @@ mod tests {
         assert!(reference_cash_flows[3].fuzzy_cash_flows_cmp_eq(&cash_flows[3], tol));
         assert!(reference_cash_flows[4].fuzzy_cash_flows_cmp_eq(&cash_flows[4], tol));
     }
+
+    #[test]
+    fn cash_flow_rounding() {
+        let data = r#"{
+            "bond_type": "bond",
+            "currency": "EUR",
+            "coupon" : {
+                "coupon_type": "fixed",
+                "rate": 0.225,
+                "coupon_date": "01.04",
+                "period": "6M",
+                "day_count_convention": "icma"
+            },
+            "business_day_rule": "none",
+            "calendar": "TARGET",
+            "issue_date": "2019-10-01",
+            "maturity": "2020-10-01",
+            "denomination": 1000,
+            "rounding": "half up"
+        }"#;
+        let mut bond: Bond = serde_json::from_str(&data).unwrap();
+        let market = Market::new();
+        let tol = 1e-11;
+        // unrounded coupon is 1000 * 0.225% / 2 = 1.125
+        let cash_flows = bond.rollout_cash_flows(1., &market).unwrap();
+        assert_eq!(cash_flows.len(), 3);
+        assert_fuzzy_eq!(cash_flows[0].amount.value(), 1.13, tol);
+        assert_fuzzy_eq!(cash_flows[1].amount.value(), 1.13, tol);
+        assert_fuzzy_eq!(cash_flows[2].amount.value(), 1000., tol);
+
+        bond.rounding = RoundingRule::HalfEven;
+        let cash_flows = bond.rollout_cash_flows(1., &market).unwrap();
+        assert_fuzzy_eq!(cash_flows[0].amount.value(), 1.12, tol);
+        assert_fuzzy_eq!(cash_flows[1].amount.value(), 1.12, tol);
+
+        bond.rounding = RoundingRule::None;
+        let cash_flows = bond.rollout_cash_flows(1., &market).unwrap();
+        assert_fuzzy_eq!(cash_flows[0].amount.value(), 1.125, tol);
+    }
+
+    #[test]
+    fn amortizing_rounded_principal() {
+        let data = r#"{
+            "bond_type": "bond",
+            "currency": "EUR",
+            "coupon" : {
+                "coupon_type": "fixed",
+                "rate": 5,
+                "coupon_date": "01.10",
+                "period": "1Y",
+                "day_count_convention": "icma"
+            },
+            "business_day_rule": "none",
+            "calendar": "TARGET",
+            "issue_date": "2019-10-01",
+            "maturity": "2022-10-01",
+            "denomination": 1000,
+            "amortization": "linear",
+            "rounding": "half up"
+        }"#;
+        let bond: Bond = serde_json::from_str(&data).unwrap();
+        let market = Market::new();
+        let cash_flows = bond.rollout_cash_flows(1., &market).unwrap();
+        assert_eq!(cash_flows.len(), 6);
+        let curr = Currency::from_str("EUR").unwrap();
+        let reference_cash_flows = vec![
+            CashFlow::new(50., curr, NaiveDate::from_ymd(2020, 10, 1)),
+            CashFlow::new(333.33, curr, NaiveDate::from_ymd(2020, 10, 1)),
+            CashFlow::new(33.33, curr, NaiveDate::from_ymd(2021, 10, 1)),
+            CashFlow::new(333.33, curr, NaiveDate::from_ymd(2021, 10, 1)),
+            CashFlow::new(16.67, curr, NaiveDate::from_ymd(2022, 10, 1)),
+            CashFlow::new(333.34, curr, NaiveDate::from_ymd(2022, 10, 1)),
+        ];
+        let tol = 1e-11;
+        for (reference, cf) in reference_cash_flows.iter().zip(cash_flows.iter()) {
+            assert!(reference.fuzzy_cash_flows_cmp_eq(cf, tol));
+        }
+        let principal = cash_flows[1].amount.value()
+            + cash_flows[3].amount.value()
+            + cash_flows[5].amount.value();
+        assert_fuzzy_eq!(principal, 1000., tol);
+
+        let principal_flows = bond.principal_cash_flows(1., &market).unwrap();
+        assert_eq!(principal_flows.len(), 3);
+        for (i, cf) in principal_flows.iter().enumerate() {
+            assert!(cf.fuzzy_cash_flows_cmp_eq(&reference_cash_flows[2 * i + 1], tol));
+        }
+    }
+
+    #[test]
+    fn parse_bond_terms_with_defaults() {
+        let bond: Bond =
+            serde_json::from_str(include_str!("../examples/minimal_terms_bond.json")).unwrap();
+        assert!(matches!(bond.business_day_rule, DayAdjust::Modified));
+        assert!(matches!(
+            bond.coupon.day_count_convention,
+            DayCountConv::ActActICMA
+        ));
+        assert_eq!(bond.calendar, "TARGET");
+        assert!(!bond.end_of_month);
+        assert_eq!(bond.amortization, Amortization::Bullet);
+        let market = Market::new();
+        let cash_flows = bond.rollout_cash_flows(1., &market).unwrap();
+        assert_eq!(cash_flows.len(), 6);
+        // 2024-06-15 is a Saturday
+        assert_eq!(cash_flows[3].date, NaiveDate::from_ymd(2024, 6, 17));
+
+        let bond: Bond =
+            serde_json::from_str(include_str!("../examples/end_of_month_bond.json")).unwrap();
+        assert!(matches!(bond.business_day_rule, DayAdjust::Following));
+        assert!(matches!(
+            bond.coupon.day_count_convention,
+            DayCountConv::Act365
+        ));
+        assert_eq!(bond.calendar, "uk");
+        assert!(bond.end_of_month);
+        let cash_flows = bond.rollout_cash_flows(1., &market).unwrap();
+        let dates: Vec<NaiveDate> = cash_flows.iter().map(|cf| cf.date).collect();
+        assert_eq!(
+            dates,
+            vec![
+                NaiveDate::from_ymd(2021, 8, 31),
+                NaiveDate::from_ymd(2022, 2, 28),
+                NaiveDate::from_ymd(2022, 8, 31),
+                NaiveDate::from_ymd(2023, 2, 28),
+                NaiveDate::from_ymd(2023, 2, 28),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_bond_terms() {
+        let terms = |issue_date: &str, maturity: &str, period: &str| {
+            format!(
+                r#"{{
+                "bond_type": "bond",
+                "currency": "EUR",
+                "coupon" : {{
+                    "coupon_type": "fixed",
+                    "rate": 5,
+                    "coupon_date": "01.04",
+                    "period": "{}"
+                }},
+                "issue_date": "{}",
+                "maturity": "{}",
+                "denomination": 1000
+            }}"#,
+                period, issue_date, maturity
+            )
+        };
+        let bond = serde_json::from_str::<Bond>(&terms("2020-04-01", "2025-04-01", "1Y"));
+        assert!(bond.is_ok());
+
+        let err = serde_json::from_str::<Bond>(&terms("2020-04-01", "2019-04-01", "1Y"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("maturity 2019-04-01 must be after issue date 2020-04-01"));
+
+        let err = serde_json::from_str::<Bond>(&terms("2020-04-01", "2025-04-01", "0M"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("coupon period 0M has zero frequency"));
+    }
+
+    #[test]
+    fn build_bond_with_defaults() {
+        let eur = Currency::from_str("EUR").unwrap();
+        let market = Market::new();
+        let bond = Bond::builder()
+            .issue_date(NaiveDate::from_ymd(2020, 4, 1))
+            .maturity(NaiveDate::from_ymd(2025, 6, 15))
+            .coupon(0.025)
+            .currency(eur)
+            .build(&market)
+            .unwrap();
+        assert_eq!(bond.calendar, "TARGET");
+        assert!(matches!(bond.business_day_rule, DayAdjust::Modified));
+        assert!(matches!(
+            bond.coupon.day_count_convention,
+            DayCountConv::ActActICMA
+        ));
+        assert_eq!(bond.coupon.period, TimePeriod::from_str("1Y").unwrap());
+        assert_eq!(bond.coupon.coupon_date, CouponDate::new(15, 6).unwrap());
+        assert_fuzzy_eq!(bond.coupon.rate, 2.5, 1e-12);
+        assert_eq!(bond.denomination, 1000);
+        assert_eq!(bond.amortization, Amortization::Bullet);
+
+        // same cash flows as the equivalent terms in JSON
+        let json: Bond = serde_json::from_str(
+            r#"{
+            "bond_type": "bond",
+            "currency": "EUR",
+            "coupon" : {
+                "coupon_type": "fixed",
+                "rate": 2.5,
+                "coupon_date": "15.06",
+                "period": "1Y"
+            },
+            "issue_date": "2020-04-01",
+            "maturity": "2025-06-15",
+            "denomination": 1000
+        }"#,
+        )
+        .unwrap();
+        let cash_flows = bond.rollout_cash_flows(1., &market).unwrap();
+        let expected = json.rollout_cash_flows(1., &market).unwrap();
+        assert_eq!(cash_flows.len(), expected.len());
+        for (cf, expected) in cash_flows.iter().zip(expected.iter()) {
+            assert!(cf.fuzzy_cash_flows_cmp_eq(expected, 1e-10));
+        }
+
+        let bond = Bond::builder()
+            .issue_date(NaiveDate::from_ymd(2020, 4, 1))
+            .maturity(NaiveDate::from_ymd(2025, 4, 1))
+            .coupon(0.05)
+            .currency(eur)
+            .frequency(TimePeriod::from_str("6M").unwrap())
+            .day_count(DayCountConv::D30_360)
+            .calendar("uk")
+            .denomination(100)
+            .build(&market)
+            .unwrap();
+        assert_eq!(bond.calendar, "uk");
+        assert_eq!(bond.denomination, 100);
+        assert_eq!(bond.rollout_cash_flows(1., &market).unwrap().len(), 10);
+    }
+
+    #[test]
+    fn invalid_bond_builder_terms() {
+        let eur = Currency::from_str("EUR").unwrap();
+        let market = Market::new();
+        let builder = Bond::builder()
+            .issue_date(NaiveDate::from_ymd(2020, 4, 1))
+            .maturity(NaiveDate::from_ymd(2025, 4, 1))
+            .coupon(0.05)
+            .currency(eur);
+        assert!(builder.clone().build(&market).is_ok());
+        let error = |builder: BondBuilder| builder.build(&market).unwrap_err().to_string();
+
+        assert_eq!(
+            error(builder.clone().maturity(NaiveDate::from_ymd(2019, 4, 1))),
+            "invalid bond terms: maturity 2019-04-01 must be after issue date 2020-04-01"
+        );
+        assert_eq!(
+            error(builder.clone().denomination(0)),
+            "invalid bond terms: denomination must be positive"
+        );
+        assert_eq!(
+            error(builder.clone().calendar("Mars")),
+            "invalid bond terms: unknown calendar Mars"
+        );
+        assert_eq!(
+            error(builder.clone().frequency(TimePeriod::from_str("0M").unwrap())),
+            "invalid bond terms: coupon period 0M has zero frequency, a positive period is required"
+        );
+        assert_eq!(
+            error(Bond::builder().maturity(NaiveDate::from_ymd(2025, 4, 1))),
+            "invalid bond terms: missing issue date"
+        );
+        assert_eq!(
+            error(Bond::builder().issue_date(NaiveDate::from_ymd(2020, 4, 1))),
+            "invalid bond terms: missing maturity"
+        );
+        assert_eq!(
+            error(
+                Bond::builder()
+                    .issue_date(NaiveDate::from_ymd(2020, 4, 1))
+                    .maturity(NaiveDate::from_ymd(2025, 4, 1))
+            ),
+            "invalid bond terms: missing coupon rate"
+        );
+        assert_eq!(
+            error(
+                Bond::builder()
+                    .issue_date(NaiveDate::from_ymd(2020, 4, 1))
+                    .maturity(NaiveDate::from_ymd(2025, 4, 1))
+                    .coupon(0.05)
+            ),
+            "invalid bond terms: missing currency"
+        );
+    }
+
+    #[test]
+    fn calendar_coverage_of_rollout() {
+        let eur = Currency::from_str("EUR").unwrap();
+        let market = Market::new();
+        assert_eq!(
+            market.get_calendar("TARGET").unwrap().year_range(),
+            (1990, 2050)
+        );
+        // the TARGET calendar is extended beyond 2050: April 1, 2051 is a Saturday
+        // followed by Easter Monday
+        let bond = Bond::builder()
+            .issue_date(NaiveDate::from_ymd(2021, 4, 1))
+            .maturity(NaiveDate::from_ymd(2051, 4, 1))
+            .coupon(0.02)
+            .currency(eur)
+            .build(&market)
+            .unwrap();
+        let cash_flows = bond.rollout_cash_flows(1., &market).unwrap();
+        assert_eq!(
+            cash_flows.last().unwrap().date,
+            NaiveDate::from_ymd(2051, 4, 4)
+        );
+        assert_eq!(bond.principal_cash_flows(1., &market).unwrap().len(), 1);
+
+        // seasoned bonds issued before 1990 are rolled out as well
+        let bond = Bond::builder()
+            .issue_date(NaiveDate::from_ymd(1985, 4, 1))
+            .maturity(NaiveDate::from_ymd(2025, 4, 1))
+            .coupon(0.07)
+            .currency(eur)
+            .build(&market)
+            .unwrap();
+        assert_eq!(bond.rollout_cash_flows(1., &market).unwrap().len(), 41);
+    }
+
+    #[test]
+    fn par_coupon_and_asset_swap_spread() {
+        let eur = Currency::from_str("EUR").unwrap();
+        let market = Market::new();
+        let settlement = NaiveDate::from_ymd(2021, 2, 10);
+        let curve = DiscountCurve::new(
+            settlement,
+            vec![
+                (NaiveDate::from_ymd(2022, 2, 10), 0.01),
+                (NaiveDate::from_ymd(2026, 2, 10), 0.02),
+            ],
+            DayCountConv::Act365,
+            eur,
+        );
+        let bond = Bond::builder()
+            .issue_date(NaiveDate::from_ymd(2020, 6, 15))
+            .maturity(NaiveDate::from_ymd(2025, 6, 15))
+            .coupon(0.03)
+            .currency(eur)
+            .build(&market)
+            .unwrap();
+        let par_coupon = bond.par_coupon(&curve, settlement, &market).unwrap();
+        assert!(par_coupon > 0.01 && par_coupon < 0.02);
+
+        // a bond paying the par coupon is priced at par and has its own coupon as par coupon
+        let mut par_bond = bond.clone();
+        par_bond.coupon.rate = par_coupon * 100.;
+        let cash_flows = par_bond.rollout_cash_flows(1., &market).unwrap();
+        let accrued = par_bond.accrued_interest(settlement).unwrap();
+        let price = price_bond_curve(&cash_flows, &curve, settlement, accrued).unwrap();
+        assert_fuzzy_eq!(price.clean, 1000., 1e-9);
+        assert_fuzzy_eq!(
+            par_bond.par_coupon(&curve, settlement, &market).unwrap(),
+            par_coupon,
+            1e-12
+        );
+
+        // no spread if priced off the discount curve, positive spread for cheaper bonds
+        let cash_flows = bond.rollout_cash_flows(1., &market).unwrap();
+        let price = price_bond_curve(&cash_flows, &curve, settlement, 0.).unwrap();
+        let asw = bond
+            .asset_swap_spread(price.dirty, &curve, settlement, &market)
+            .unwrap();
+        assert_fuzzy_eq!(asw, 0., 1e-12);
+        let asw = bond
+            .asset_swap_spread(price.dirty - 10., &curve, settlement, &market)
+            .unwrap();
+        assert!(asw > 0.002 && asw < 0.003);
+    }
+
+    #[test]
+    fn z_spread_round_trip() {
+        let eur = Currency::from_str("EUR").unwrap();
+        let market = Market::new();
+        let settlement = NaiveDate::from_ymd(2021, 2, 10);
+        let curve = DiscountCurve::new(
+            settlement,
+            vec![
+                (NaiveDate::from_ymd(2022, 2, 10), 0.01),
+                (NaiveDate::from_ymd(2026, 2, 10), 0.02),
+            ],
+            DayCountConv::Act365,
+            eur,
+        );
+        let bond = Bond::builder()
+            .issue_date(NaiveDate::from_ymd(2020, 6, 15))
+            .maturity(NaiveDate::from_ymd(2025, 6, 15))
+            .coupon(0.03)
+            .currency(eur)
+            .build(&market)
+            .unwrap();
+        let cash_flows = bond.rollout_cash_flows(1., &market).unwrap();
+        let price = price_bond_curve(&cash_flows, &curve, settlement, 0.).unwrap();
+        for compounding in &[Compounding::Annual, Compounding::Continuous] {
+            let spread = bond
+                .z_spread(price.dirty, &curve, settlement, *compounding, &market)
+                .unwrap();
+            assert_fuzzy_eq!(spread, 0., 1e-10);
+        }
+
+        let compounding = Compounding::SemiAnnual;
+        let shifted = bond
+            .price_with_z_spread(0.015, &curve, settlement, compounding, &market)
+            .unwrap();
+        assert!(shifted < price.dirty);
+        let spread = bond
+            .z_spread(shifted, &curve, settlement, compounding, &market)
+            .unwrap();
+        assert_fuzzy_eq!(spread, 0.015, 1e-10);
+
+        assert!(matches!(
+            bond.z_spread(1., &curve, settlement, compounding, &market),
+            Err(BondError::SpreadOutOfRange { .. })
+        ));
+        assert!(matches!(
+            bond.z_spread(1e6, &curve, settlement, compounding, &market),
+            Err(BondError::SpreadOutOfRange { .. })
+        ));
+    }
 }
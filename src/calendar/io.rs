@@ -0,0 +1,231 @@
+//! Import and export of holiday lists in CSV format
+//!
+//! A holiday list has an optional header line followed by one holiday per line with the columns
+//!
+//! `date,name,exchange`
+//!
+//! * `date` is formatted as "YYYY-MM-DD" or in a configurable format
+//! * `name` is the name of the holiday, it may be empty
+//! * `exchange` is the exchange the holiday applies to, it may be empty if the list
+//!   contains a single market
+//!
+//! Name and exchange columns may be omitted on import. Fields containing commas may be
+//! enclosed in double quotes.
+
+use super::{Calendar, CalendarError, Holiday};
+use crate::helpers::{quote_csv_field, split_csv_line};
+use chrono::{NaiveDate, Weekday};
+use std::io::{BufRead, BufReader, Read, Write};
+
+/// Header line of the CSV layout
+const HEADER: &str = "date,name,exchange";
+
+/// Read a list of holidays in the CSV layout documented above. Dates are parsed as
+/// "YYYY-MM-DD" or, if given, in `date_format` (see `chrono::format::strftime`).
+/// If `exchange` is given, rows of other exchanges are skipped, while rows without
+/// exchange are kept. Unnamed holidays result in `Holiday::SingularDay` rules, named
+/// holidays in a `Holiday::Tabulated` rule per name, in the order of first appearance.
+/// A first line consisting of (a prefix of) the column names is skipped as header.
+/// Malformed rows are reported with their line number.
+pub fn read_singular_holidays_csv(
+    reader: impl Read,
+    date_format: Option<&str>,
+    exchange: Option<&str>,
+) -> Result<Vec<Holiday>, CalendarError> {
+    let mut holidays = Vec::new();
+    for (idx, line) in BufReader::new(reader).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let line_error = |message: String| CalendarError::InvalidCsv {
+            line: idx + 1,
+            message,
+        };
+        let fields = split_csv_line(&line).map_err(line_error)?;
+        if idx == 0 && is_header(&fields) {
+            continue;
+        }
+        if fields.len() > 3 {
+            return Err(line_error(format!(
+                "expected at most 3 columns, but found {}",
+                fields.len()
+            )));
+        }
+        let row_exchange = fields.get(2).filter(|e| !e.is_empty());
+        if let (Some(exchange), Some(row_exchange)) = (exchange, row_exchange) {
+            if exchange != row_exchange {
+                continue;
+            }
+        }
+        let date = parse_date(&fields[0], date_format).map_err(line_error)?;
+        match fields.get(1).filter(|name| !name.is_empty()) {
+            None => holidays.push(Holiday::SingularDay(date)),
+            Some(name) => add_named_day(&mut holidays, name, date),
+        }
+    }
+    Ok(holidays)
+}
+
+/// Returns true if the fields are column names of the CSV layout, in the given order
+fn is_header(fields: &[String]) -> bool {
+    fields.len() <= 3
+        && fields
+            .iter()
+            .zip(HEADER.split(','))
+            .all(|(field, column)| field.eq_ignore_ascii_case(column))
+}
+
+/// Parse date in ISO format or the given format
+fn parse_date(field: &str, date_format: Option<&str>) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(field, "%Y-%m-%d")
+        .or_else(|err| match date_format {
+            Some(format) => NaiveDate::parse_from_str(field, format),
+            None => Err(err),
+        })
+        .map_err(|_| format!("invalid date '{}'", field))
+}
+
+/// Add date to the tabulated holidays of the given name
+fn add_named_day(holidays: &mut Vec<Holiday>, holiday_name: &str, date: NaiveDate) {
+    for holiday in holidays.iter_mut() {
+        if let Holiday::Tabulated {
+            name: Some(name),
+            dates,
+        } = holiday
+        {
+            if name == holiday_name {
+                dates.push(date);
+                return;
+            }
+        }
+    }
+    holidays.push(Holiday::Tabulated {
+        name: Some(holiday_name.to_string()),
+        dates: vec![date],
+    });
+}
+
+/// Combine holiday rules, e.g. read by `read_singular_holidays_csv`, with rules for the
+/// given weekend days to a complete set of calendar rules
+pub fn with_weekends(holidays: Vec<Holiday>, weekend: &[Weekday]) -> Vec<Holiday> {
+    weekend
+        .iter()
+        .map(|day| Holiday::WeekDay(*day))
+        .chain(holidays)
+        .collect()
+}
+
+/// Write all holidays of the calendar from `start` to `end` (inclusively) in ascending order
+/// in the CSV layout documented above, including holidays falling on a weekend.
+/// The exchange column is left empty.
+pub fn write_holidays_csv(
+    calendar: &Calendar,
+    start: NaiveDate,
+    end: NaiveDate,
+    mut writer: impl Write,
+) -> Result<(), CalendarError> {
+    writeln!(writer, "{}", HEADER)?;
+    for date in calendar.holidays().range(start..=end) {
+        let info = calendar.day_info(*date);
+        writeln!(
+            writer,
+            "{},{},",
+            date.format("%Y-%m-%d"),
+            quote_csv_field(info.holiday_name().unwrap_or_default())
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn holidays_csv_round_trip() {
+        let d = NaiveDate::from_ymd;
+        let rules = vec![
+            Holiday::WeekDay(Weekday::Sat),
+            Holiday::WeekDay(Weekday::Sun),
+            Holiday::YearlyDay {
+                month: 12,
+                day: 25,
+                first: None,
+                last: None,
+            },
+            Holiday::Tabulated {
+                name: Some("Lunar New Year, day 1".to_string()),
+                dates: vec![d(2020, 1, 25), d(2021, 2, 12)],
+            },
+            Holiday::SingularDay(d(2021, 6, 14)),
+        ];
+        let cal = Calendar::calc_calendar(&rules, 2020, 2021).unwrap();
+        let (start, end) = (d(2020, 1, 1), d(2021, 12, 31));
+        let mut csv = Vec::new();
+        write_holidays_csv(&cal, start, end, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.starts_with("date,name,exchange\n2020-01-25,\"Lunar New Year, day 1\",\n"));
+
+        let holidays = read_singular_holidays_csv(csv.as_bytes(), None, None).unwrap();
+        assert_eq!(holidays.len(), 4);
+        let rules = with_weekends(holidays, cal.weekend_days());
+        let restored = Calendar::calc_calendar(&rules, 2020, 2021).unwrap();
+        assert_eq!(restored.holidays(), cal.holidays());
+        assert_eq!(
+            restored.day_info(d(2021, 2, 12)).holiday_name(),
+            Some("Lunar New Year, day 1")
+        );
+    }
+
+    #[test]
+    fn read_holidays_of_exchange() {
+        let d = NaiveDate::from_ymd;
+        let csv = "date,name,exchange
+            2021-01-01,New Year,XNYS
+            18.01.2021,Martin Luther King Jr. Day,XNYS
+            2021-01-01,New Year,XLON
+
+            02.04.2021,Good Friday,XLON
+            05.04.2021,,XLON";
+        let holidays =
+            read_singular_holidays_csv(csv.as_bytes(), Some("%d.%m.%Y"), Some("XLON")).unwrap();
+        assert_eq!(holidays.len(), 3);
+        assert_eq!(
+            holidays[0],
+            Holiday::Tabulated {
+                name: Some("New Year".to_string()),
+                dates: vec![d(2021, 1, 1)],
+            }
+        );
+        assert_eq!(holidays[2], Holiday::SingularDay(d(2021, 4, 5)));
+
+        let error = read_singular_holidays_csv(csv.as_bytes(), None, None).unwrap_err();
+        assert_eq!(
+            error,
+            CalendarError::InvalidCsv {
+                line: 3,
+                message: "invalid date '18.01.2021'".to_string()
+            }
+        );
+        // header line is optional
+        let csv = "2021-01-01,New Year\n2021-12-24";
+        assert_eq!(
+            read_singular_holidays_csv(csv.as_bytes(), None, None).unwrap(),
+            vec![
+                Holiday::Tabulated {
+                    name: Some("New Year".to_string()),
+                    dates: vec![d(2021, 1, 1)],
+                },
+                Holiday::SingularDay(d(2021, 12, 24))
+            ]
+        );
+        let csv = "date,name\n2021-01-01,New Year,XLON,closed";
+        assert_eq!(
+            read_singular_holidays_csv(csv.as_bytes(), None, None)
+                .unwrap_err()
+                .to_string(),
+            "invalid holiday list in line 2: expected at most 3 columns, but found 4"
+        );
+    }
+}
@@ -0,0 +1,2369 @@
+//! Implementation of (bank) holidays.
+//! Calendars are required to verify whether an exchange is open or if a certain
+//! cash flow could be settled on a specific day. They are also needed to calculate
+//! the amount of business days between to given dates.
+//! Because of the settlement rules, bank holidays have an impact on how to
+//! rollout cash flows from fixed income products.
+//! The approach taken here is to define a set of rules to determine bank holidays.
+//! From this set of rules, a calendar is generated by calculating all bank holidays
+//! within a given range of years for fast access.
+
+use crate::currency::Currency;
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+use computus;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::error::Error;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+
+/// First and last year for which the date of Easter can be calculated
+const EASTER_YEARS: (i32, i32) = (1583, 9999);
+
+/// Error related to the calculation of calendars
+#[derive(Debug, PartialEq)]
+pub enum CalendarError {
+    /// The range of years is invalid, i.e. it starts before year 1 or ends before it starts
+    InvalidYearRange { start: i32, end: i32 },
+    /// The date of Easter is not supported for the given year
+    EasterOutOfRange(i32),
+    /// Some rules are invalid, all issues found are listed
+    InvalidRules(Vec<validation::RuleIssue>),
+    /// Applying a rule to the given year results in a date outside of the supported range of dates
+    DateOutOfRange(i32),
+    /// Reading or writing holidays failed
+    Io(String),
+    /// A row of a holiday list in CSV format is malformed
+    InvalidCsv { line: usize, message: String },
+    /// No business day was found within the maximal number of days searched from the given date
+    NoBusinessDay(NaiveDate),
+}
+
+impl fmt::Display for CalendarError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CalendarError::InvalidYearRange { start, end } => write!(
+                f,
+                "invalid range of years from {} to {}, years must be positive and in ascending order",
+                start, end
+            ),
+            CalendarError::EasterOutOfRange(year) => write!(
+                f,
+                "date of Easter can only be calculated for years {} to {}, but got {}",
+                EASTER_YEARS.0, EASTER_YEARS.1, year
+            ),
+            CalendarError::InvalidRules(issues) => {
+                write!(f, "invalid calendar rules")?;
+                for issue in issues {
+                    write!(f, "; {}", issue)?;
+                }
+                Ok(())
+            }
+            CalendarError::DateOutOfRange(year) => write!(
+                f,
+                "holiday rule applied to year {} results in a date outside of the supported range",
+                year
+            ),
+            CalendarError::Io(err) => write!(f, "reading or writing holidays failed: {}", err),
+            CalendarError::InvalidCsv { line, message } => {
+                write!(f, "invalid holiday list in line {}: {}", line, message)
+            }
+            CalendarError::NoBusinessDay(date) => {
+                write!(f, "no business day found in the days following {}", date)
+            }
+        }
+    }
+}
+
+impl Error for CalendarError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
+
+impl From<std::io::Error> for CalendarError {
+    fn from(err: std::io::Error) -> CalendarError {
+        CalendarError::Io(err.to_string())
+    }
+}
+
+/// Specifies the nth week of a month
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum NthWeek {
+    First,
+    Second,
+    Third,
+    Fourth,
+    Last,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Holiday {
+    /// Though weekends are no holidays, they need to be specified in the calendar. Weekends are assumed to be non-business days.
+    /// In most countries, weekends include Saturday (`Sat`) and Sunday (`Sun`). Unfortunately, there are a few exceptions.
+    WeekDay(Weekday),
+    /// A holiday that occurs every year on the same day.
+    /// `first` and `last` are the first and last year this day is a holiday (inclusively).
+    YearlyDay {
+        month: u32,
+        day: u32,
+        first: Option<i32>,
+        last: Option<i32>,
+    },
+    /// Occurs every year, but is moved to next non-weekend day if it falls on a weekday.
+    /// Note that Saturday and Sunday here assumed to be weekend days, even if these days
+    /// are not defined as weekends in this calendar. If the next Monday is already a holiday,
+    /// the date will be moved to the next available business day.
+    /// `first` and `last` are the first and last year this day is a holiday (inclusively).
+    /// `direction` specifies where to move the day to, by default it is moved forward.
+    MovableYearlyDay {
+        month: u32,
+        day: u32,
+        first: Option<i32>,
+        last: Option<i32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        direction: Option<RollDirection>,
+    },
+    /// A single holiday which is valid only once in time.
+    SingularDay(NaiveDate),
+    /// A list of dates of a holiday that can't be calculated by any other rule, e.g. dates
+    /// of a lunar holiday like Chinese New Year taken from an almanac. Only the dates within
+    /// the calendar's range of years are taken into account. The optional `name` is reported
+    /// for all dates, see `Calendar::day_info`.
+    Tabulated {
+        name: Option<String>,
+        dates: Vec<NaiveDate>,
+    },
+    /// A holiday that is defined in relative days (e.g. -2 for Good Friday) to Easter (Sunday).
+    EasterOffset {
+        offset: i32,
+        first: Option<i32>,
+        last: Option<i32>,
+    },
+    /// A holiday that falls on the nth (or last) weekday of a specific month, e.g. the first Monday in May.
+    /// `first` and `last` are the first and last year this day is a holiday (inclusively).
+    MonthWeekday {
+        month: u32,
+        weekday: Weekday,
+        nth: NthWeek,
+        first: Option<i32>,
+        last: Option<i32>,
+    },
+    /// A holiday that falls on a weekday relative to a fixed anchor date, e.g. the Friday
+    /// on or before 2nd of June or the Monday nearest to 24th of May.
+    /// The holiday may fall into the month before or after the anchor's month.
+    /// `first` and `last` are the first and last year this day is a holiday (inclusively).
+    WeekdayRelativeTo {
+        month: u32,
+        day: u32,
+        weekday: Weekday,
+        direction: WeekdayDirection,
+        first: Option<i32>,
+        last: Option<i32>,
+    },
+}
+
+/// Position of a weekday relative to an anchor date
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum WeekdayDirection {
+    /// The anchor date itself or the last such weekday before
+    OnOrBefore,
+    /// The anchor date itself or the first such weekday after
+    OnOrAfter,
+    /// The closest such weekday, which is at most three days away. Since the
+    /// weekday before and after are seven days apart, there are never ties.
+    Nearest,
+}
+
+/// Direction in which a movable holiday falling on a weekend is moved
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum RollDirection {
+    /// Move to the following Monday, or later if that is a holiday already
+    Forward,
+    /// Move to the preceding Friday, or earlier if that is a holiday already
+    Backward,
+    /// Move to the closest of both candidates, ties are moved forward
+    Nearest,
+}
+
+/// Treatment of a holiday whose date is a holiday already by another rule
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub enum Substitution {
+    /// Colliding holidays are merged into a single holiday
+    #[default]
+    None,
+    /// The next business day (neither weekend nor holiday) becomes a substitute holiday
+    NextBusinessDay,
+}
+
+/// Classification of a day by a calendar
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DayInfo {
+    BusinessDay,
+    Weekend,
+    /// A holiday on a day that is not part of the weekend
+    Holiday {
+        name: Option<String>,
+    },
+    /// A holiday falling on a weekend
+    WeekendAndHoliday {
+        name: Option<String>,
+    },
+}
+
+impl DayInfo {
+    /// Name of the holiday, if the day is a holiday and its name is known.
+    /// Only tabulated holidays carry names for now.
+    pub fn holiday_name(&self) -> Option<&str> {
+        match self {
+            DayInfo::Holiday { name } | DayInfo::WeekendAndHoliday { name } => name.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+/// Calendar for arbitrary complex holiday rules
+/// Two calendars are equal if they are calculated from the same rules for the same range of years
+/// and have the same trading hours.
+#[derive(Debug, Clone)]
+pub struct Calendar {
+    holidays: BTreeSet<NaiveDate>,
+    weekdays: Vec<Weekday>,
+    /// Substitute holidays mapped to the date of the holiday they substitute
+    substitutes: BTreeMap<NaiveDate, NaiveDate>,
+    /// Names of holidays, as far as known
+    names: BTreeMap<NaiveDate, String>,
+    rules: Vec<Holiday>,
+    start: i32,
+    end: i32,
+    substitution: Substitution,
+    /// Optional trading hours on business days
+    sessions: Option<Sessions>,
+}
+
+/// Trading hours, with the opening time included and the closing time excluded
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Sessions {
+    open: NaiveTime,
+    close: NaiveTime,
+    /// Alternative closing times, e.g. of half trading days
+    early_closes: BTreeMap<NaiveDate, NaiveTime>,
+}
+
+impl PartialEq for Calendar {
+    fn eq(&self, other: &Self) -> bool {
+        self.rules == other.rules
+            && self.start == other.start
+            && self.end == other.end
+            && self.substitution == other.substitution
+            && self.sessions == other.sessions
+    }
+}
+
+impl Eq for Calendar {}
+
+impl Hash for Calendar {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.rules.hash(state);
+        self.start.hash(state);
+        self.end.hash(state);
+        self.substitution.hash(state);
+        self.sessions.hash(state);
+    }
+}
+
+impl Calendar {
+    /// Calendar without holidays, where Saturday and Sunday are the only non-business days.
+    /// Since no holidays need to be calculated, the calendar covers any date.
+    /// This is the default wherever a calendar is optional.
+    pub fn weekends_only() -> Calendar {
+        Calendar::calc_calendar(
+            &[
+                Holiday::WeekDay(Weekday::Sat),
+                Holiday::WeekDay(Weekday::Sun),
+            ],
+            1,
+            9999,
+        )
+        .unwrap()
+    }
+
+    /// Calendar without any weekend days or holidays, e.g. for crypto currencies traded
+    /// around the clock. Every day is a business day, i.e. business days are calendar days.
+    pub fn always_open() -> Calendar {
+        Calendar::calc_calendar(&[], 1, 9999).unwrap()
+    }
+
+    /// Calculate all holidays and recognize weekend days for a given range of years
+    /// from `start` to `end` (inclusively). The calculation is performed on the basis
+    /// of a vector of holiday rules.
+    /// Returns an error if `start` is before year 1 or after `end`, or if Easter
+    /// related holidays are requested for years not supported by the calculation of Easter.
+    pub fn calc_calendar(
+        holiday_rules: &[Holiday],
+        start: i32,
+        end: i32,
+    ) -> Result<Calendar, CalendarError> {
+        Self::calc_calendar_with_substitution(holiday_rules, start, end, Substitution::None)
+    }
+
+    /// Like `calc_calendar`, but if a holiday falls on a date that is a holiday already,
+    /// the given `substitution` is applied. Substitute days are recorded and could be
+    /// queried via `substitute_for`. Movable yearly days are not substituted, since they
+    /// are moved to the next free day anyway.
+    pub fn calc_calendar_with_substitution(
+        holiday_rules: &[Holiday],
+        start: i32,
+        end: i32,
+        substitution: Substitution,
+    ) -> Result<Calendar, CalendarError> {
+        if start < 1 || start > end {
+            return Err(CalendarError::InvalidYearRange { start, end });
+        }
+        Self::check_easter_range(holiday_rules, start, end)?;
+        // weekends must be known before substitute business days could be calculated,
+        // each weekday is recorded once even if given by duplicate rules
+        let mut weekdays = Vec::new();
+        for rule in holiday_rules {
+            if let Holiday::WeekDay(weekday) = rule {
+                if !weekdays.contains(weekday) {
+                    weekdays.push(*weekday);
+                }
+            }
+        }
+        let mut cal = Calendar {
+            holidays: BTreeSet::new(),
+            weekdays,
+            substitutes: BTreeMap::new(),
+            names: BTreeMap::new(),
+            rules: holiday_rules.to_vec(),
+            start,
+            end,
+            substitution,
+            sessions: None,
+        };
+        cal.add_years(start, end)?;
+        Ok(cal)
+    }
+
+    /// First and last year (inclusively) the holidays of the calendar are calculated for
+    pub fn year_range(&self) -> (i32, i32) {
+        (self.start, self.end)
+    }
+
+    /// Returns true if the holidays of all years from `start` to `end` are calculated.
+    /// Outside of its range of years, a calendar knows weekends only. Calendars with
+    /// weekend rules only, e.g. `weekends_only`, cover any range of dates.
+    pub fn covers(&self, start: NaiveDate, end: NaiveDate) -> bool {
+        let weekends_only = self
+            .rules
+            .iter()
+            .all(|rule| matches!(rule, Holiday::WeekDay(_)));
+        weekends_only || (start.year() >= self.start && end.year() <= self.end)
+    }
+
+    /// Extend the calendar up to year `new_end` (inclusively), calculating the holidays
+    /// of the additional years only. The result is the same as recalculating the calendar
+    /// for the whole range of years, as long as no rule results in holidays outside
+    /// the year the rule is applied to. Nothing is done if `new_end` is not after the
+    /// current last year. On error, the calendar remains unchanged.
+    pub fn extend_years(&mut self, new_end: i32) -> Result<(), CalendarError> {
+        if new_end <= self.end {
+            return Ok(());
+        }
+        Self::check_easter_range(&self.rules, self.end + 1, new_end)?;
+        let mut scratch = self.scratch(self.end + 1, new_end);
+        // holidays of the last calculated year, which those of the new years may collide with
+        scratch.holidays.extend(
+            self.holidays
+                .range(NaiveDate::from_ymd(self.end, 1, 1)..)
+                .copied(),
+        );
+        scratch.add_years(self.end + 1, new_end)?;
+        self.holidays.append(&mut scratch.holidays);
+        self.substitutes.append(&mut scratch.substitutes);
+        self.names.append(&mut scratch.names);
+        self.end = new_end;
+        Ok(())
+    }
+
+    /// Recalculate all holidays from the calendar's rules for the range of years from
+    /// `start` to `end` (inclusively), which may be wider or narrower than the current one.
+    /// Trading hours are kept. On error, the calendar remains unchanged.
+    pub fn recompute(&mut self, start: i32, end: i32) -> Result<(), CalendarError> {
+        if start < 1 || start > end {
+            return Err(CalendarError::InvalidYearRange { start, end });
+        }
+        Self::check_easter_range(&self.rules, start, end)?;
+        let mut scratch = self.scratch(start, end);
+        scratch.add_years(start, end)?;
+        self.holidays = scratch.holidays;
+        self.substitutes = scratch.substitutes;
+        self.names = scratch.names;
+        self.start = start;
+        self.end = end;
+        Ok(())
+    }
+
+    /// Calendar with the same rules and weekend days, but without any holidays calculated,
+    /// to calculate holidays into without changing this calendar in case of errors
+    fn scratch(&self, start: i32, end: i32) -> Calendar {
+        Calendar {
+            holidays: BTreeSet::new(),
+            weekdays: self.weekdays.clone(),
+            substitutes: BTreeMap::new(),
+            names: BTreeMap::new(),
+            rules: self.rules.clone(),
+            start,
+            end,
+            substitution: self.substitution,
+            sessions: None,
+        }
+    }
+
+    /// Returns an error if Easter related holidays are requested for years not supported
+    fn check_easter_range(
+        holiday_rules: &[Holiday],
+        start: i32,
+        end: i32,
+    ) -> Result<(), CalendarError> {
+        for rule in holiday_rules {
+            if let Holiday::EasterOffset { first, last, .. } = rule {
+                let (first, last) = Self::calc_first_and_last(start, end, first, last);
+                if first <= last {
+                    if first < EASTER_YEARS.0 {
+                        return Err(CalendarError::EasterOutOfRange(first));
+                    }
+                    if last > EASTER_YEARS.1 {
+                        return Err(CalendarError::EasterOutOfRange(last));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply all rules to the years from `start` to `end` (inclusively)
+    /// Returns an error if a rule results in a date chrono could not represent.
+    fn add_years(&mut self, start: i32, end: i32) -> Result<(), CalendarError> {
+        let rules = self.rules.clone();
+        for rule in &rules {
+            match rule {
+                Holiday::SingularDay(date) => {
+                    let year = date.year();
+                    if year >= start && year <= end {
+                        self.add_holiday(*date);
+                    }
+                }
+                Holiday::Tabulated { name, dates } => {
+                    for date in dates {
+                        let year = date.year();
+                        if year >= start && year <= end {
+                            self.add_holiday(*date);
+                            if let Some(name) = name {
+                                self.names.insert(*date, name.clone());
+                            }
+                        }
+                    }
+                }
+                Holiday::WeekDay(_) => {}
+                Holiday::YearlyDay {
+                    month,
+                    day,
+                    first,
+                    last,
+                } => {
+                    let (first, last) = Self::calc_first_and_last(start, end, first, last);
+                    for year in first..last + 1 {
+                        self.add_holiday(NaiveDate::from_ymd(year, *month, *day));
+                    }
+                }
+                Holiday::MovableYearlyDay {
+                    month,
+                    day,
+                    first,
+                    last,
+                    direction,
+                } => {
+                    let (first, last) = Self::calc_first_and_last(start, end, first, last);
+                    for year in first..last + 1 {
+                        let date = NaiveDate::from_ymd(year, *month, *day);
+                        // must not fall on weekend, but also not on another holiday
+                        let shift = |date: NaiveDate, days: i64| {
+                            date.checked_add_signed(Duration::days(days))
+                                .ok_or(CalendarError::DateOutOfRange(year))
+                        };
+                        let (mut forward, mut backward) = match date.weekday() {
+                            Weekday::Sat => (shift(date, 2)?, shift(date, -1)?),
+                            Weekday::Sun => (shift(date, 1)?, shift(date, -2)?),
+                            _ => (date, date),
+                        };
+                        while self.holidays.contains(&forward) {
+                            forward = shift(forward, 1)?;
+                        }
+                        while self.holidays.contains(&backward) {
+                            backward = shift(backward, -1)?;
+                        }
+                        let date = match direction.unwrap_or(RollDirection::Forward) {
+                            RollDirection::Forward => forward,
+                            RollDirection::Backward => backward,
+                            RollDirection::Nearest => {
+                                if forward - date <= date - backward {
+                                    forward
+                                } else {
+                                    backward
+                                }
+                            }
+                        };
+                        self.holidays.insert(date);
+                    }
+                }
+                Holiday::EasterOffset {
+                    offset,
+                    first,
+                    last,
+                } => {
+                    let (first, last) = Self::calc_first_and_last(start, end, first, last);
+                    for year in first..last + 1 {
+                        let easter = computus::gregorian(year).unwrap();
+                        let easter = NaiveDate::from_ymd(easter.year, easter.month, easter.day);
+                        let date = easter
+                            .checked_add_signed(Duration::days(*offset as i64))
+                            .ok_or(CalendarError::DateOutOfRange(year))?;
+                        self.add_holiday(date);
+                    }
+                }
+                Holiday::MonthWeekday {
+                    month,
+                    weekday,
+                    nth,
+                    first,
+                    last,
+                } => {
+                    let (first, last) = Self::calc_first_and_last(start, end, first, last);
+                    for year in first..last + 1 {
+                        let day = match nth {
+                            NthWeek::First => 1,
+                            NthWeek::Second => 8,
+                            NthWeek::Third => 15,
+                            NthWeek::Fourth => 22,
+                            NthWeek::Last => last_day_of_month(year, *month),
+                        };
+                        let mut date = NaiveDate::from_ymd(year, *month, day);
+                        while date.weekday() != *weekday {
+                            date = match nth {
+                                NthWeek::Last => date.pred(),
+                                _ => date.succ(),
+                            }
+                        }
+                        self.add_holiday(date);
+                    }
+                }
+                Holiday::WeekdayRelativeTo {
+                    month,
+                    day,
+                    weekday,
+                    direction,
+                    first,
+                    last,
+                } => {
+                    let (first, last) = Self::calc_first_and_last(start, end, first, last);
+                    for year in first..last + 1 {
+                        let anchor = NaiveDate::from_ymd(year, *month, *day);
+                        let anchor_day = anchor.weekday().num_days_from_monday() as i64;
+                        let target_day = weekday.num_days_from_monday() as i64;
+                        let days_after = (target_day - anchor_day).rem_euclid(7);
+                        let days_before = (anchor_day - target_day).rem_euclid(7);
+                        let offset = match direction {
+                            WeekdayDirection::OnOrAfter => days_after,
+                            WeekdayDirection::OnOrBefore => -days_before,
+                            WeekdayDirection::Nearest => {
+                                if days_after <= days_before {
+                                    days_after
+                                } else {
+                                    -days_before
+                                }
+                            }
+                        };
+                        let date = anchor
+                            .checked_add_signed(Duration::days(offset))
+                            .ok_or(CalendarError::DateOutOfRange(year))?;
+                        self.add_holiday(date);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Add a holiday, applying the substitution rule if the date is a holiday already
+    fn add_holiday(&mut self, date: NaiveDate) {
+        if self.holidays.insert(date) {
+            return;
+        }
+        if self.substitution == Substitution::NextBusinessDay {
+            let substitute = self.next_bday(date);
+            self.holidays.insert(substitute);
+            self.substitutes.insert(substitute, date);
+        }
+    }
+
+    /// Returns the date of the original holiday if `date` is a substitute holiday
+    pub fn substitute_for(&self, date: NaiveDate) -> Option<NaiveDate> {
+        self.substitutes.get(&date).copied()
+    }
+
+    /// Calculate the next business day
+    pub fn next_bday(&self, mut date: NaiveDate) -> NaiveDate {
+        date = date.succ();
+        while !self.is_business_day(date) {
+            date = date.succ();
+        }
+        date
+    }
+
+    /// Calculate the previous business day
+    pub fn prev_bday(&self, mut date: NaiveDate) -> NaiveDate {
+        date = date.pred();
+        while !self.is_business_day(date) {
+            date = date.pred();
+        }
+        date
+    }
+
+    /// Unbounded sequence of business days at or after `start`, calculated lazily,
+    /// e.g. to take the next `n` business days
+    pub fn business_days_from(&self, start: NaiveDate) -> impl Iterator<Item = NaiveDate> + '_ {
+        let first = if self.is_business_day(start) {
+            start
+        } else {
+            self.next_bday(start)
+        };
+        std::iter::successors(Some(first), move |date| Some(self.next_bday(*date)))
+    }
+
+    /// Calculate the next day that is a business day in this and the `other` calendar
+    pub fn next_bday_joint(&self, mut date: NaiveDate, other: &Calendar) -> NaiveDate {
+        date = date.succ();
+        while !self.is_business_day(date) || !other.is_business_day(date) {
+            date = date.succ();
+        }
+        date
+    }
+
+    /// Calculate the previous day that is a business day in this and the `other` calendar
+    pub fn prev_bday_joint(&self, mut date: NaiveDate, other: &Calendar) -> NaiveDate {
+        date = date.pred();
+        while !self.is_business_day(date) || !other.is_business_day(date) {
+            date = date.pred();
+        }
+        date
+    }
+
+    /// Number of business days in the half-open interval from `start` (inclusively)
+    /// to `end` (exclusively), i.e. counting `start` but not `end`. This is the
+    /// convention for business day based accruals like Bus/252, where the number of
+    /// days of adjacent periods add up. Returns 0 if `end` is not after `start`.
+    pub fn business_days_in(&self, start: NaiveDate, end: NaiveDate) -> u32 {
+        let days = Self::calendar_days_in(start, end);
+        if days == 0 {
+            return 0;
+        }
+        // count weekend days of all full weeks at once, and of the remaining days separately
+        let weekend_days = self.weekdays.len() as u32;
+        let full_weeks = days / 7;
+        let mut business_days = full_weeks * (7 - weekend_days);
+        let mut date = start + Duration::days(7 * full_weeks as i64);
+        while date < end {
+            if !self.is_weekend(date) {
+                business_days += 1;
+            }
+            date = date.succ();
+        }
+        let holidays = self
+            .holidays
+            .range(start..end)
+            .filter(|date| !self.is_weekend(**date))
+            .count() as u32;
+        business_days - holidays
+    }
+
+    /// The 1-based index of `date` among the business days of its month,
+    /// or `None` if `date` is not a business day
+    pub fn business_day_index(&self, date: NaiveDate) -> Option<u32> {
+        if !self.is_business_day(date) {
+            return None;
+        }
+        let first_of_month = NaiveDate::from_ymd(date.year(), date.month(), 1);
+        Some(self.business_days_in(first_of_month, date) + 1)
+    }
+
+    /// Number of business days after `date` up to and including the last day of its
+    /// calendar quarter (January to March, April to June, etc.)
+    pub fn business_days_remaining_in_quarter(&self, date: NaiveDate) -> i64 {
+        let next_quarter_month = (date.month() - 1) / 3 * 3 + 4;
+        let next_quarter = if next_quarter_month > 12 {
+            NaiveDate::from_ymd(date.year() + 1, 1, 1)
+        } else {
+            NaiveDate::from_ymd(date.year(), next_quarter_month, 1)
+        };
+        self.business_days_in(date.succ(), next_quarter) as i64
+    }
+
+    /// Returns true if the `days` calendar days starting at `from` (inclusively) contain
+    /// no holidays. Weekend days are tolerated only if `allow_weekends` is true.
+    /// This is useful to assess settlement risk around long weekends.
+    pub fn clean_window(&self, from: NaiveDate, days: i64, allow_weekends: bool) -> bool {
+        let end = from + Duration::days(std::cmp::max(0, days));
+        if self.holidays.range(from..end).next().is_some() {
+            return false;
+        }
+        allow_weekends || (0..days).all(|n| !self.is_weekend(from + Duration::days(n)))
+    }
+
+    /// Number of calendar days in the half-open interval from `start` (inclusively)
+    /// to `end` (exclusively), following the same convention as `business_days_in`.
+    /// Returns 0 if `end` is not after `start`.
+    pub fn calendar_days_in(start: NaiveDate, end: NaiveDate) -> u32 {
+        std::cmp::max(0, (end - start).num_days()) as u32
+    }
+
+    fn calc_first_and_last(
+        start: i32,
+        end: i32,
+        first: &Option<i32>,
+        last: &Option<i32>,
+    ) -> (i32, i32) {
+        let first = match first {
+            Some(year) => std::cmp::max(start, *year),
+            _ => start,
+        };
+        let last = match last {
+            Some(year) => std::cmp::min(end, *year),
+            _ => end,
+        };
+        (first, last)
+    }
+
+    /// All holidays of the calendar's range of years in ascending order, including
+    /// holidays falling on a weekend and substitute holidays
+    pub fn holidays(&self) -> &BTreeSet<NaiveDate> {
+        &self.holidays
+    }
+
+    /// Days of the week which are no business days
+    pub fn weekend_days(&self) -> &[Weekday] {
+        &self.weekdays
+    }
+
+    /// Holidays of the given year that do not fall on a weekend, i.e. only those
+    /// reducing the number of business days, in ascending order
+    pub fn effective_holidays(&self, year: i32) -> Vec<NaiveDate> {
+        self.holidays
+            .range(NaiveDate::from_ymd(year, 1, 1)..=NaiveDate::from_ymd(year, 12, 31))
+            .filter(|date| !self.is_weekend(**date))
+            .copied()
+            .collect()
+    }
+
+    /// Classify the date as business day, weekend, holiday, or holiday on a weekend
+    pub fn day_info(&self, date: NaiveDate) -> DayInfo {
+        let name = || self.names.get(&date).cloned();
+        match (self.is_weekend(date), self.is_holiday(date)) {
+            (false, false) => DayInfo::BusinessDay,
+            (true, false) => DayInfo::Weekend,
+            (false, true) => DayInfo::Holiday { name: name() },
+            (true, true) => DayInfo::WeekendAndHoliday { name: name() },
+        }
+    }
+
+    /// Returns true if the date falls on a weekend
+    pub fn is_weekend(&self, day: NaiveDate) -> bool {
+        self.weekdays.contains(&day.weekday())
+    }
+
+    /// Returns true if the specified day is a bank holiday
+    pub fn is_holiday(&self, date: NaiveDate) -> bool {
+        self.holidays.contains(&date)
+    }
+
+    /// Returns true if the specified day is a business day
+    pub fn is_business_day(&self, date: NaiveDate) -> bool {
+        !self.is_weekend(date) && !self.is_holiday(date)
+    }
+
+    /// Add trading hours from `open` (inclusively) to `close` (exclusively) on all
+    /// business days. Without trading hours, the market is open all day on business days.
+    pub fn with_sessions(mut self, open: NaiveTime, close: NaiveTime) -> Calendar {
+        let early_closes = match self.sessions {
+            Some(sessions) => sessions.early_closes,
+            None => BTreeMap::new(),
+        };
+        self.sessions = Some(Sessions {
+            open,
+            close,
+            early_closes,
+        });
+        self
+    }
+
+    /// Close trading early at `close` on `date`, e.g. on a half trading day.
+    /// Early closes take effect only if trading hours have been set by `with_sessions`.
+    pub fn with_early_close(mut self, date: NaiveDate, close: NaiveTime) -> Calendar {
+        if let Some(sessions) = &mut self.sessions {
+            sessions.early_closes.insert(date, close);
+        }
+        self
+    }
+
+    /// Opening and closing time at a business day, or the whole day without trading hours
+    fn session_at(&self, date: NaiveDate) -> (NaiveDateTime, NaiveDateTime) {
+        let start_of_day = date.and_hms(0, 0, 0);
+        match &self.sessions {
+            None => (start_of_day, date.succ().and_hms(0, 0, 0)),
+            Some(sessions) => {
+                let close = sessions.early_closes.get(&date).unwrap_or(&sessions.close);
+                (date.and_time(sessions.open), date.and_time(*close))
+            }
+        }
+    }
+
+    /// Returns true if the market is open at the given time
+    pub fn is_open_at(&self, datetime: NaiveDateTime) -> bool {
+        let date = datetime.date();
+        if !self.is_business_day(date) {
+            return false;
+        }
+        let (open, close) = self.session_at(date);
+        datetime >= open && datetime < close
+    }
+
+    /// The earliest time at or after `datetime` the market is open, i.e. `datetime` itself
+    /// if the market is open, or the next opening time otherwise.
+    pub fn next_open(&self, datetime: NaiveDateTime) -> NaiveDateTime {
+        if self.is_open_at(datetime) {
+            return datetime;
+        }
+        let date = datetime.date();
+        if self.is_business_day(date) {
+            let (open, _) = self.session_at(date);
+            if datetime < open {
+                return open;
+            }
+        }
+        self.session_at(self.next_bday(date)).0
+    }
+}
+
+/// Key of calendars in the registry
+type CalendarKey = (Vec<Holiday>, i32, i32);
+
+/// Registry of calendars, which calculates calendars with identical rules and
+/// range of years only once and shares them. In addition, calendars could be
+/// registered by name, e.g. "NYSE" or "TARGET". Clones of the registry share the
+/// same calendars, including named ones, and could be used from multiple threads.
+#[derive(Debug, Clone, Default)]
+pub struct CalendarRegistry {
+    calendars: Arc<Mutex<HashMap<CalendarKey, Arc<Calendar>>>>,
+    named: Arc<Mutex<HashMap<String, Arc<Calendar>>>>,
+}
+
+impl CalendarRegistry {
+    pub fn new() -> CalendarRegistry {
+        CalendarRegistry::default()
+    }
+
+    /// Get the calendar for the given rules and range of years, which is calculated
+    /// only if no such calendar is in the registry yet
+    pub fn get_or_insert(
+        &self,
+        rules: &[Holiday],
+        start: i32,
+        end: i32,
+    ) -> Result<Arc<Calendar>, CalendarError> {
+        let key = (rules.to_vec(), start, end);
+        let mut calendars = self.calendars.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(cal) = calendars.get(&key) {
+            return Ok(cal.clone());
+        }
+        let cal = Arc::new(Calendar::calc_calendar(&key.0, start, end)?);
+        calendars.insert(key, cal.clone());
+        Ok(cal)
+    }
+
+    /// Number of distinct calendars in the registry
+    pub fn len(&self) -> usize {
+        self.calendars
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Register a calendar by name, replacing any calendar registered before by this name
+    pub fn register(&self, name: &str, cal: Calendar) {
+        self.named
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(name.to_string(), Arc::new(cal));
+    }
+
+    /// Get the calendar registered by `name`
+    pub fn get(&self, name: &str) -> Option<Arc<Calendar>> {
+        self.named
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(name)
+            .cloned()
+    }
+
+    /// Combine the calendars registered by `names` to a calendar where a day is a holiday
+    /// (or weekend day) if it is a holiday (or weekend day) in any of these calendars.
+    /// The range of years is restricted to the years covered by all calendars, and
+    /// trading hours are not taken over. Returns `None` if any name is unknown, no
+    /// name is given, or the ranges of years of the calendars don't overlap.
+    pub fn combined(&self, names: &[&str]) -> Option<Calendar> {
+        let (first, others) = names.split_first()?;
+        let mut combined = self.get(first)?.as_ref().clone();
+        combined.sessions = None;
+        for name in others {
+            let cal = self.get(name)?;
+            combined.holidays.extend(cal.holidays.iter().copied());
+            for weekday in &cal.weekdays {
+                if !combined.weekdays.contains(weekday) {
+                    combined.weekdays.push(*weekday);
+                }
+            }
+            for (date, original) in &cal.substitutes {
+                combined.substitutes.entry(*date).or_insert(*original);
+            }
+            for (date, name) in &cal.names {
+                combined.names.entry(*date).or_insert_with(|| name.clone());
+            }
+            for rule in &cal.rules {
+                if !combined.rules.contains(rule) {
+                    combined.rules.push(rule.clone());
+                }
+            }
+            combined.start = std::cmp::max(combined.start, cal.start);
+            combined.end = std::cmp::min(combined.end, cal.end);
+        }
+        if combined.start > combined.end {
+            return None;
+        }
+        Some(combined)
+    }
+}
+
+/// Returns true if the specified year is a leap year (i.e. Feb 29th exists for this year)
+pub fn is_leap_year(year: i32) -> bool {
+    NaiveDate::from_ymd_opt(year, 2, 29).is_some()
+}
+
+/// Calculate the last day of a given month in a given year
+pub fn last_day_of_month(year: i32, month: u32) -> u32 {
+    NaiveDate::from_ymd_opt(year, month + 1, 1)
+        .unwrap_or(NaiveDate::from_ymd(year + 1, 1, 1))
+        .pred()
+        .day()
+}
+
+/// Statistics of the calendar day lag between trade and settlement dates
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct LagStats {
+    /// Number of trades considered
+    pub count: usize,
+    pub min: i64,
+    pub max: i64,
+    pub mean: f64,
+}
+
+/// Settlement date of a trade `offset` business days after the trade date (T+offset).
+/// For T+0, the settlement date is the trade date if it is a business day and the
+/// next business day otherwise. Negative offsets count backwards.
+fn settlement_date(trade: NaiveDate, cal: &Calendar, offset: i64) -> NaiveDate {
+    let mut date = trade;
+    if offset >= 0 {
+        if !cal.is_business_day(date) {
+            date = cal.next_bday(date);
+        }
+        for _ in 0..offset {
+            date = cal.next_bday(date);
+        }
+    } else {
+        for _ in 0..-offset {
+            date = cal.prev_bday(date);
+        }
+    }
+    date
+}
+
+/// Calculate minimum, maximum and mean number of calendar days between the trade dates
+/// and their T+offset settlement dates. Weekends and holidays between trade and
+/// settlement increase the calendar day lag. All values are zero if no trade is given.
+pub fn settlement_lag_stats(trades: &[NaiveDate], cal: &Calendar, offset: i64) -> LagStats {
+    if trades.is_empty() {
+        return LagStats::default();
+    }
+    let lags: Vec<i64> = trades
+        .iter()
+        .map(|trade| (settlement_date(*trade, cal, offset) - *trade).num_days())
+        .collect();
+    LagStats {
+        count: lags.len(),
+        min: *lags.iter().min().unwrap(),
+        max: *lags.iter().max().unwrap(),
+        mean: lags.iter().sum::<i64>() as f64 / lags.len() as f64,
+    }
+}
+
+/// Standard settlement lags per currency, in business days after the trade date
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SettlementConventions {
+    offsets: HashMap<Currency, i64>,
+}
+
+impl SettlementConventions {
+    /// Settlement lag of currencies without explicit convention (T+2)
+    pub const DEFAULT_OFFSET: i64 = 2;
+
+    pub fn new() -> SettlementConventions {
+        SettlementConventions::default()
+    }
+
+    /// Set the settlement lag of trades in `currency` to T+`offset`
+    pub fn set_offset(&mut self, currency: Currency, offset: i64) {
+        self.offsets.insert(currency, offset);
+    }
+
+    /// Number of business days between trade and settlement of trades in `currency`
+    pub fn settlement_offset(&self, currency: &Currency) -> i64 {
+        *self
+            .offsets
+            .get(currency)
+            .unwrap_or(&SettlementConventions::DEFAULT_OFFSET)
+    }
+
+    /// Settlement date of a trade in `currency` on the trade date `trade`
+    pub fn settlement_date(
+        &self,
+        trade: NaiveDate,
+        currency: &Currency,
+        cal: &Calendar,
+    ) -> NaiveDate {
+        settlement_date(trade, cal, self.settlement_offset(currency))
+    }
+}
+
+pub mod io;
+pub mod presets;
+pub mod validation;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn fixed_dates_calendar() {
+        let holidays = vec![
+            Holiday::SingularDay(NaiveDate::from_ymd(2019, 11, 20)),
+            Holiday::SingularDay(NaiveDate::from_ymd(2019, 11, 24)),
+            Holiday::SingularDay(NaiveDate::from_ymd(2019, 11, 25)),
+            Holiday::WeekDay(Weekday::Sat),
+            Holiday::WeekDay(Weekday::Sun),
+        ];
+        let cal = Calendar::calc_calendar(&holidays, 2019, 2019).unwrap();
+
+        assert_eq!(
+            false,
+            cal.is_business_day(NaiveDate::from_ymd(2019, 11, 20))
+        );
+        assert_eq!(true, cal.is_business_day(NaiveDate::from_ymd(2019, 11, 21)));
+        assert_eq!(true, cal.is_business_day(NaiveDate::from_ymd(2019, 11, 22)));
+        // weekend
+        assert_eq!(
+            false,
+            cal.is_business_day(NaiveDate::from_ymd(2019, 11, 23))
+        );
+        assert_eq!(true, cal.is_weekend(NaiveDate::from_ymd(2019, 11, 23)));
+        assert_eq!(false, cal.is_holiday(NaiveDate::from_ymd(2019, 11, 23)));
+        // weekend and holiday
+        assert_eq!(
+            false,
+            cal.is_business_day(NaiveDate::from_ymd(2019, 11, 24))
+        );
+        assert_eq!(true, cal.is_weekend(NaiveDate::from_ymd(2019, 11, 24)));
+        assert_eq!(true, cal.is_holiday(NaiveDate::from_ymd(2019, 11, 24)));
+        assert_eq!(
+            false,
+            cal.is_business_day(NaiveDate::from_ymd(2019, 11, 25))
+        );
+        assert_eq!(true, cal.is_business_day(NaiveDate::from_ymd(2019, 11, 26)));
+    }
+
+    #[test]
+    fn test_yearly_day() {
+        let holidays = vec![
+            Holiday::YearlyDay {
+                month: 11,
+                day: 1,
+                first: None,
+                last: None,
+            },
+            Holiday::YearlyDay {
+                month: 11,
+                day: 2,
+                first: Some(2019),
+                last: None,
+            },
+            Holiday::YearlyDay {
+                month: 11,
+                day: 3,
+                first: None,
+                last: Some(2019),
+            },
+            Holiday::YearlyDay {
+                month: 11,
+                day: 4,
+                first: Some(2019),
+                last: Some(2019),
+            },
+        ];
+        let cal = Calendar::calc_calendar(&holidays, 2018, 2020).unwrap();
+
+        assert_eq!(true, cal.is_holiday(NaiveDate::from_ymd(2018, 11, 1)));
+        assert_eq!(true, cal.is_holiday(NaiveDate::from_ymd(2019, 11, 1)));
+        assert_eq!(true, cal.is_holiday(NaiveDate::from_ymd(2020, 11, 1)));
+
+        assert_eq!(false, cal.is_holiday(NaiveDate::from_ymd(2018, 11, 2)));
+        assert_eq!(true, cal.is_holiday(NaiveDate::from_ymd(2019, 11, 2)));
+        assert_eq!(true, cal.is_holiday(NaiveDate::from_ymd(2020, 11, 2)));
+
+        assert_eq!(true, cal.is_holiday(NaiveDate::from_ymd(2018, 11, 3)));
+        assert_eq!(true, cal.is_holiday(NaiveDate::from_ymd(2019, 11, 3)));
+        assert_eq!(false, cal.is_holiday(NaiveDate::from_ymd(2020, 11, 3)));
+
+        assert_eq!(false, cal.is_holiday(NaiveDate::from_ymd(2018, 11, 4)));
+        assert_eq!(true, cal.is_holiday(NaiveDate::from_ymd(2019, 11, 4)));
+        assert_eq!(false, cal.is_holiday(NaiveDate::from_ymd(2020, 11, 4)));
+    }
+
+    #[test]
+    fn test_movable_yearly_day() {
+        let holidays = vec![
+            Holiday::MovableYearlyDay {
+                month: 11,
+                day: 1,
+                first: None,
+                last: None,
+                direction: None,
+            },
+            Holiday::MovableYearlyDay {
+                month: 11,
+                day: 2,
+                first: None,
+                last: None,
+                direction: None,
+            },
+            Holiday::MovableYearlyDay {
+                month: 11,
+                day: 10,
+                first: None,
+                last: Some(2019),
+                direction: None,
+            },
+            Holiday::MovableYearlyDay {
+                month: 11,
+                day: 17,
+                first: Some(2019),
+                last: None,
+                direction: None,
+            },
+            Holiday::MovableYearlyDay {
+                month: 11,
+                day: 24,
+                first: Some(2019),
+                last: Some(2019),
+                direction: None,
+            },
+        ];
+        let cal = Calendar::calc_calendar(&holidays, 2018, 2020).unwrap();
+        assert_eq!(true, cal.is_holiday(NaiveDate::from_ymd(2018, 11, 1)));
+        assert_eq!(true, cal.is_holiday(NaiveDate::from_ymd(2018, 11, 2)));
+        assert_eq!(true, cal.is_holiday(NaiveDate::from_ymd(2019, 11, 1)));
+        assert_eq!(true, cal.is_holiday(NaiveDate::from_ymd(2019, 11, 4)));
+        assert_eq!(true, cal.is_holiday(NaiveDate::from_ymd(2020, 11, 2)));
+        assert_eq!(true, cal.is_holiday(NaiveDate::from_ymd(2020, 11, 3)));
+
+        assert_eq!(true, cal.is_holiday(NaiveDate::from_ymd(2018, 11, 12)));
+        assert_eq!(true, cal.is_holiday(NaiveDate::from_ymd(2019, 11, 11)));
+        assert_eq!(false, cal.is_holiday(NaiveDate::from_ymd(2020, 11, 10)));
+        assert_eq!(false, cal.is_holiday(NaiveDate::from_ymd(2018, 11, 19)));
+        assert_eq!(true, cal.is_holiday(NaiveDate::from_ymd(2019, 11, 18)));
+        assert_eq!(true, cal.is_holiday(NaiveDate::from_ymd(2020, 11, 17)));
+        assert_eq!(false, cal.is_holiday(NaiveDate::from_ymd(2018, 11, 26)));
+        assert_eq!(true, cal.is_holiday(NaiveDate::from_ymd(2019, 11, 25)));
+        assert_eq!(false, cal.is_holiday(NaiveDate::from_ymd(2020, 11, 24)));
+    }
+
+    #[test]
+    /// Good Friday example
+    fn test_easter_offset() {
+        let holidays = vec![Holiday::EasterOffset {
+            offset: -2,
+            first: None,
+            last: None,
+        }];
+        let cal = Calendar::calc_calendar(&holidays, 2019, 2020).unwrap();
+        assert_eq!(false, cal.is_business_day(NaiveDate::from_ymd(2019, 4, 19)));
+        assert_eq!(false, cal.is_business_day(NaiveDate::from_ymd(2020, 4, 10)));
+    }
+
+    #[test]
+    fn test_month_weekday() {
+        let holidays = vec![
+            Holiday::MonthWeekday {
+                month: 11,
+                weekday: Weekday::Mon,
+                nth: NthWeek::First,
+                first: None,
+                last: None,
+            },
+            Holiday::MonthWeekday {
+                month: 11,
+                weekday: Weekday::Tue,
+                nth: NthWeek::Second,
+                first: None,
+                last: None,
+            },
+            Holiday::MonthWeekday {
+                month: 11,
+                weekday: Weekday::Wed,
+                nth: NthWeek::Third,
+                first: None,
+                last: None,
+            },
+            Holiday::MonthWeekday {
+                month: 11,
+                weekday: Weekday::Thu,
+                nth: NthWeek::Fourth,
+                first: None,
+                last: None,
+            },
+            Holiday::MonthWeekday {
+                month: 11,
+                weekday: Weekday::Fri,
+                nth: NthWeek::Last,
+                first: None,
+                last: None,
+            },
+            Holiday::MonthWeekday {
+                month: 11,
+                weekday: Weekday::Sat,
+                nth: NthWeek::First,
+                first: None,
+                last: Some(2018),
+            },
+            Holiday::MonthWeekday {
+                month: 11,
+                weekday: Weekday::Sun,
+                nth: NthWeek::Last,
+                first: Some(2020),
+                last: None,
+            },
+        ];
+        let cal = Calendar::calc_calendar(&holidays, 2018, 2020).unwrap();
+        assert_eq!(true, cal.is_holiday(NaiveDate::from_ymd(2019, 11, 4)));
+        assert_eq!(true, cal.is_holiday(NaiveDate::from_ymd(2019, 11, 12)));
+        assert_eq!(true, cal.is_holiday(NaiveDate::from_ymd(2019, 11, 20)));
+        assert_eq!(true, cal.is_holiday(NaiveDate::from_ymd(2019, 11, 28)));
+        assert_eq!(true, cal.is_holiday(NaiveDate::from_ymd(2019, 11, 29)));
+
+        assert_eq!(true, cal.is_holiday(NaiveDate::from_ymd(2018, 11, 3)));
+        assert_eq!(false, cal.is_holiday(NaiveDate::from_ymd(2019, 11, 2)));
+        assert_eq!(false, cal.is_holiday(NaiveDate::from_ymd(2020, 11, 7)));
+        assert_eq!(false, cal.is_holiday(NaiveDate::from_ymd(2018, 11, 25)));
+        assert_eq!(false, cal.is_holiday(NaiveDate::from_ymd(2019, 11, 24)));
+        assert_eq!(true, cal.is_holiday(NaiveDate::from_ymd(2020, 11, 29)));
+    }
+
+    #[test]
+    /// Testing serialization and deserialization of holidays definitions
+    fn serialize_cal_definition() {
+        let holidays = vec![
+            Holiday::MonthWeekday {
+                month: 11,
+                weekday: Weekday::Mon,
+                nth: NthWeek::First,
+                first: None,
+                last: None,
+            },
+            Holiday::MovableYearlyDay {
+                month: 11,
+                day: 1,
+                first: Some(2016),
+                last: None,
+                direction: None,
+            },
+            Holiday::YearlyDay {
+                month: 11,
+                day: 3,
+                first: None,
+                last: Some(2019),
+            },
+            Holiday::SingularDay(NaiveDate::from_ymd(2019, 11, 25)),
+            Holiday::WeekDay(Weekday::Sat),
+            Holiday::EasterOffset {
+                offset: -2,
+                first: None,
+                last: None,
+            },
+        ];
+        let json = serde_json::to_string_pretty(&holidays).unwrap();
+        assert_eq!(
+            json,
+            r#"[
+  {
+    "MonthWeekday": {
+      "month": 11,
+      "weekday": "Mon",
+      "nth": "First",
+      "first": null,
+      "last": null
+    }
+  },
+  {
+    "MovableYearlyDay": {
+      "month": 11,
+      "day": 1,
+      "first": 2016,
+      "last": null
+    }
+  },
+  {
+    "YearlyDay": {
+      "month": 11,
+      "day": 3,
+      "first": null,
+      "last": 2019
+    }
+  },
+  {
+    "SingularDay": "2019-11-25"
+  },
+  {
+    "WeekDay": "Sat"
+  },
+  {
+    "EasterOffset": {
+      "offset": -2,
+      "first": null,
+      "last": null
+    }
+  }
+]"#
+        );
+        let holidays2: Vec<Holiday> = serde_json::from_str(&json).unwrap();
+        assert_eq!(holidays.len(), holidays2.len());
+        for i in 0..holidays.len() {
+            assert_eq!(holidays[i], holidays2[i]);
+        }
+    }
+
+    #[test]
+    fn movable_yearly_day_directions() {
+        // 2020-07-04 is a Saturday
+        let rule = |direction| Holiday::MovableYearlyDay {
+            month: 7,
+            day: 4,
+            first: None,
+            last: None,
+            direction,
+        };
+        let holiday = |holidays: &Vec<Holiday>| {
+            let cal = Calendar::calc_calendar(holidays, 2020, 2020).unwrap();
+            cal.holidays.iter().cloned().collect::<Vec<NaiveDate>>()
+        };
+        assert_eq!(
+            holiday(&vec![rule(None)]),
+            vec![NaiveDate::from_ymd(2020, 7, 6)]
+        );
+        assert_eq!(
+            holiday(&vec![rule(Some(RollDirection::Forward))]),
+            vec![NaiveDate::from_ymd(2020, 7, 6)]
+        );
+        assert_eq!(
+            holiday(&vec![rule(Some(RollDirection::Backward))]),
+            vec![NaiveDate::from_ymd(2020, 7, 3)]
+        );
+        assert_eq!(
+            holiday(&vec![rule(Some(RollDirection::Nearest))]),
+            vec![NaiveDate::from_ymd(2020, 7, 3)]
+        );
+        // Friday is already a holiday, Thursday and Monday are equally far away
+        let holidays = vec![
+            Holiday::SingularDay(NaiveDate::from_ymd(2020, 7, 3)),
+            rule(Some(RollDirection::Nearest)),
+        ];
+        assert_eq!(
+            holiday(&holidays),
+            vec![
+                NaiveDate::from_ymd(2020, 7, 3),
+                NaiveDate::from_ymd(2020, 7, 6)
+            ]
+        );
+        let holidays = vec![
+            Holiday::SingularDay(NaiveDate::from_ymd(2020, 7, 3)),
+            rule(Some(RollDirection::Backward)),
+        ];
+        assert_eq!(
+            holiday(&holidays),
+            vec![
+                NaiveDate::from_ymd(2020, 7, 2),
+                NaiveDate::from_ymd(2020, 7, 3)
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_year_range() {
+        let holidays = vec![
+            Holiday::WeekDay(Weekday::Sat),
+            Holiday::WeekDay(Weekday::Sun),
+        ];
+        assert_eq!(
+            Calendar::calc_calendar(&holidays, 2020, 2019).unwrap_err(),
+            CalendarError::InvalidYearRange {
+                start: 2020,
+                end: 2019
+            }
+        );
+        assert_eq!(
+            Calendar::calc_calendar(&holidays, 0, 2019).unwrap_err(),
+            CalendarError::InvalidYearRange {
+                start: 0,
+                end: 2019
+            }
+        );
+        assert!(Calendar::calc_calendar(&holidays, 1, 2019).is_ok());
+    }
+
+    #[test]
+    fn easter_out_of_range() {
+        let holidays = vec![Holiday::EasterOffset {
+            offset: -2,
+            first: None,
+            last: None,
+        }];
+        assert_eq!(
+            Calendar::calc_calendar(&holidays, 1500, 2019).unwrap_err(),
+            CalendarError::EasterOutOfRange(1500)
+        );
+        // rule is restricted to supported years
+        let holidays = vec![Holiday::EasterOffset {
+            offset: -2,
+            first: Some(2000),
+            last: None,
+        }];
+        let cal = Calendar::calc_calendar(&holidays, 1500, 2019).unwrap();
+        assert!(cal.is_holiday(NaiveDate::from_ymd(2019, 4, 19)));
+    }
+
+    #[test]
+    fn substitute_day_for_colliding_holidays() {
+        // In 2011, Easter Monday and Anzac Day both fall on April 25th
+        let holidays = vec![
+            Holiday::WeekDay(Weekday::Sat),
+            Holiday::WeekDay(Weekday::Sun),
+            Holiday::EasterOffset {
+                offset: 1,
+                first: None,
+                last: None,
+            },
+            Holiday::YearlyDay {
+                month: 4,
+                day: 25,
+                first: None,
+                last: None,
+            },
+        ];
+        let cal = Calendar::calc_calendar(&holidays, 2011, 2011).unwrap();
+        assert!(cal.is_holiday(NaiveDate::from_ymd(2011, 4, 25)));
+        assert!(cal.is_business_day(NaiveDate::from_ymd(2011, 4, 26)));
+        assert_eq!(cal.substitute_for(NaiveDate::from_ymd(2011, 4, 26)), None);
+
+        let cal = Calendar::calc_calendar_with_substitution(
+            &holidays,
+            2011,
+            2011,
+            Substitution::NextBusinessDay,
+        )
+        .unwrap();
+        assert!(cal.is_holiday(NaiveDate::from_ymd(2011, 4, 25)));
+        assert!(cal.is_holiday(NaiveDate::from_ymd(2011, 4, 26)));
+        assert_eq!(
+            cal.substitute_for(NaiveDate::from_ymd(2011, 4, 26)),
+            Some(NaiveDate::from_ymd(2011, 4, 25))
+        );
+        assert_eq!(cal.substitute_for(NaiveDate::from_ymd(2011, 4, 25)), None);
+        // no collision in 2012
+        let cal = Calendar::calc_calendar_with_substitution(
+            &holidays,
+            2012,
+            2012,
+            Substitution::NextBusinessDay,
+        )
+        .unwrap();
+        assert!(cal.is_business_day(NaiveDate::from_ymd(2012, 4, 26)));
+    }
+
+    #[test]
+    fn business_and_calendar_days_in_interval() {
+        // 2020-05-01 (Friday) is a holiday
+        let holidays = vec![
+            Holiday::WeekDay(Weekday::Sat),
+            Holiday::WeekDay(Weekday::Sun),
+            Holiday::SingularDay(NaiveDate::from_ymd(2020, 5, 1)),
+            Holiday::SingularDay(NaiveDate::from_ymd(2020, 5, 9)),
+        ];
+        let cal = Calendar::calc_calendar(&holidays, 2020, 2020).unwrap();
+        let d = |day| NaiveDate::from_ymd(2020, 5, day);
+        // (start, end, business days, calendar days)
+        let cases = vec![
+            // same day
+            (d(4), d(4), 0, 0),
+            (d(2), d(2), 0, 0),
+            // adjacent days, only the start counts
+            (d(4), d(5), 1, 1),
+            (d(3), d(4), 0, 1),
+            (d(1), d(2), 0, 1),
+            // spanning a weekend
+            (d(8), d(11), 1, 3),
+            (d(8), d(12), 2, 4),
+            (d(9), d(11), 0, 2),
+            // spanning a holiday and a weekend, and a holiday on a weekend
+            (d(1), d(11), 5, 10),
+            (d(4), d(18), 10, 14),
+            (d(1), d(29), 19, 28),
+            // reversed order
+            (d(11), d(4), 0, 0),
+        ];
+        for (start, end, bdays, days) in cases {
+            assert_eq!(cal.business_days_in(start, end), bdays, "{} {}", start, end);
+            assert_eq!(Calendar::calendar_days_in(start, end), days);
+            // business days match stepping from business day to business day
+            let mut steps = 0;
+            let mut date = start;
+            if !cal.is_business_day(date) {
+                date = cal.next_bday(date);
+            }
+            while date < end {
+                steps += 1;
+                date = cal.next_bday(date);
+            }
+            assert_eq!(bdays, steps);
+        }
+        // adjacent intervals add up
+        assert_eq!(
+            cal.business_days_in(d(1), d(8)) + cal.business_days_in(d(8), d(29)),
+            cal.business_days_in(d(1), d(29))
+        );
+
+        // duplicate weekend rules count once
+        let mut duplicates = holidays.clone();
+        duplicates.push(Holiday::WeekDay(Weekday::Sat));
+        duplicates.push(Holiday::WeekDay(Weekday::Sun));
+        let cal_duplicates = Calendar::calc_calendar(&duplicates, 2020, 2020).unwrap();
+        assert_eq!(cal_duplicates.weekend_days(), &[Weekday::Sat, Weekday::Sun]);
+        assert_eq!(cal_duplicates.business_days_in(d(1), d(29)), 19);
+        // a week of weekend days only has no business days, even with duplicate rules
+        let week = [
+            Weekday::Mon,
+            Weekday::Tue,
+            Weekday::Wed,
+            Weekday::Thu,
+            Weekday::Fri,
+            Weekday::Sat,
+            Weekday::Sun,
+        ];
+        let all_days: Vec<Holiday> = week
+            .iter()
+            .cycle()
+            .take(15)
+            .map(|weekday| Holiday::WeekDay(*weekday))
+            .collect();
+        let closed = Calendar::calc_calendar(&all_days, 2020, 2020).unwrap();
+        assert_eq!(closed.business_days_in(d(1), d(29)), 0);
+    }
+
+    #[test]
+    fn calendar_registry_shares_calendars() {
+        let rules = vec![
+            Holiday::WeekDay(Weekday::Sat),
+            Holiday::WeekDay(Weekday::Sun),
+            Holiday::YearlyDay {
+                month: 12,
+                day: 25,
+                first: None,
+                last: None,
+            },
+        ];
+        let registry = CalendarRegistry::new();
+        let cal1 = registry.get_or_insert(&rules, 2020, 2021).unwrap();
+        let cal2 = registry.clone().get_or_insert(&rules, 2020, 2021).unwrap();
+        assert!(Arc::ptr_eq(&cal1, &cal2));
+        assert_eq!(registry.len(), 1);
+
+        let cal3 = registry.get_or_insert(&rules, 2020, 2022).unwrap();
+        assert!(!Arc::ptr_eq(&cal1, &cal3));
+        assert_ne!(*cal1, *cal3);
+        assert_eq!(registry.len(), 2);
+        assert!(registry.get_or_insert(&rules, 2021, 2020).is_err());
+        assert_eq!(registry.len(), 2);
+
+        // equal calendars are calculated from equal rules, even if not shared
+        let cal4 = Calendar::calc_calendar(&rules, 2020, 2021).unwrap();
+        assert_eq!(*cal1, cal4);
+        let mut set = std::collections::HashSet::new();
+        set.insert(cal4);
+        assert!(set.contains(&*cal1));
+        assert!(!set.contains(&*cal3));
+
+        // usable from multiple threads
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let registry = registry.clone();
+                let rules = rules.clone();
+                std::thread::spawn(move || registry.get_or_insert(&rules, 2020, 2021).unwrap())
+            })
+            .collect();
+        for handle in handles {
+            assert!(Arc::ptr_eq(&cal1, &handle.join().unwrap()));
+        }
+    }
+
+    #[test]
+    fn combined_named_calendars() {
+        let registry = CalendarRegistry::new();
+        let rules = vec![
+            Holiday::WeekDay(Weekday::Sat),
+            Holiday::WeekDay(Weekday::Sun),
+            Holiday::YearlyDay {
+                month: 12,
+                day: 24,
+                first: None,
+                last: None,
+            },
+        ];
+        registry.register("US", Calendar::calc_calendar(&rules, 2020, 2022).unwrap());
+        let rules = vec![
+            Holiday::WeekDay(Weekday::Fri),
+            Holiday::WeekDay(Weekday::Sat),
+            Holiday::SingularDay(NaiveDate::from_ymd(2021, 5, 13)),
+            Holiday::Tabulated {
+                name: Some("Arafat Day".to_string()),
+                dates: vec![NaiveDate::from_ymd(2021, 7, 19)],
+            },
+        ];
+        // named calendars are shared with clones of the registry
+        registry
+            .clone()
+            .register("AE", Calendar::calc_calendar(&rules, 2021, 2023).unwrap());
+        assert!(registry.get("AE").is_some());
+        assert!(registry.clone().get("US").is_some());
+        assert!(registry.get("UK").is_none());
+        assert!(registry.combined(&["US", "UK"]).is_none());
+        assert!(registry.combined(&[]).is_none());
+
+        let cal = registry.combined(&["US", "AE"]).unwrap();
+        // Thursday 2021-05-13 is a holiday in AE, Friday 2021-12-24 in US
+        assert!(cal.is_holiday(NaiveDate::from_ymd(2021, 5, 13)));
+        assert!(cal.is_holiday(NaiveDate::from_ymd(2021, 12, 24)));
+        assert_eq!(
+            cal.day_info(NaiveDate::from_ymd(2021, 7, 19))
+                .holiday_name(),
+            Some("Arafat Day")
+        );
+        assert!(cal.is_weekend(NaiveDate::from_ymd(2021, 5, 14)));
+        assert!(cal.is_weekend(NaiveDate::from_ymd(2021, 5, 16)));
+        assert!(cal.is_business_day(NaiveDate::from_ymd(2021, 5, 12)));
+        assert_eq!(
+            cal.next_bday(NaiveDate::from_ymd(2021, 5, 13)),
+            NaiveDate::from_ymd(2021, 5, 17)
+        );
+        assert_eq!((cal.start, cal.end), (2021, 2022));
+
+        // no common years
+        let rules = vec![Holiday::WeekDay(Weekday::Sun)];
+        registry.register("OLD", Calendar::calc_calendar(&rules, 2000, 2019).unwrap());
+        assert!(registry.combined(&["US", "OLD"]).is_none());
+        assert!(registry.combined(&["OLD"]).is_some());
+    }
+
+    #[test]
+    fn weekday_relative_to_anchor() {
+        let rule = |month, day, weekday, direction| Holiday::WeekdayRelativeTo {
+            month,
+            day,
+            weekday,
+            direction,
+            first: None,
+            last: None,
+        };
+        let holiday = |rule: Holiday| {
+            let cal = Calendar::calc_calendar(&vec![rule], 2020, 2020).unwrap();
+            cal.holidays.iter().cloned().collect::<Vec<NaiveDate>>()
+        };
+        let d = |month, day| vec![NaiveDate::from_ymd(2020, month, day)];
+        // 2020-05-13 is a Wednesday
+        assert_eq!(
+            holiday(rule(5, 13, Weekday::Mon, WeekdayDirection::OnOrBefore)),
+            d(5, 11)
+        );
+        assert_eq!(
+            holiday(rule(5, 13, Weekday::Mon, WeekdayDirection::OnOrAfter)),
+            d(5, 18)
+        );
+        assert_eq!(
+            holiday(rule(5, 13, Weekday::Mon, WeekdayDirection::Nearest)),
+            d(5, 11)
+        );
+        assert_eq!(
+            holiday(rule(5, 13, Weekday::Sat, WeekdayDirection::Nearest)),
+            d(5, 16)
+        );
+        // the anchor itself is on the requested weekday
+        for direction in &[
+            WeekdayDirection::OnOrBefore,
+            WeekdayDirection::OnOrAfter,
+            WeekdayDirection::Nearest,
+        ] {
+            assert_eq!(holiday(rule(5, 13, Weekday::Wed, *direction)), d(5, 13));
+        }
+        // 2020-06-01 is a Monday, the result may leave the anchor's month
+        assert_eq!(
+            holiday(rule(6, 1, Weekday::Fri, WeekdayDirection::OnOrBefore)),
+            d(5, 29)
+        );
+        assert_eq!(
+            holiday(rule(6, 1, Weekday::Fri, WeekdayDirection::OnOrAfter)),
+            d(6, 5)
+        );
+        assert_eq!(
+            holiday(rule(6, 1, Weekday::Fri, WeekdayDirection::Nearest)),
+            d(5, 29)
+        );
+        assert_eq!(
+            holiday(rule(6, 1, Weekday::Thu, WeekdayDirection::Nearest)),
+            d(6, 4)
+        );
+        // 2020-01-01 is a Wednesday, the holiday may even fall into the previous year
+        let cal = Calendar::calc_calendar(
+            &vec![rule(1, 1, Weekday::Mon, WeekdayDirection::OnOrBefore)],
+            2020,
+            2020,
+        )
+        .unwrap();
+        assert!(cal.is_holiday(NaiveDate::from_ymd(2019, 12, 30)));
+    }
+
+    #[test]
+    fn business_day_index_in_month() {
+        // 2020-05-01 (Friday) is a holiday
+        let holidays = vec![
+            Holiday::WeekDay(Weekday::Sat),
+            Holiday::WeekDay(Weekday::Sun),
+            Holiday::SingularDay(NaiveDate::from_ymd(2020, 5, 1)),
+        ];
+        let cal = Calendar::calc_calendar(&holidays, 2020, 2020).unwrap();
+        assert_eq!(
+            cal.business_day_index(NaiveDate::from_ymd(2020, 5, 4)),
+            Some(1)
+        );
+        assert_eq!(
+            cal.business_day_index(NaiveDate::from_ymd(2020, 5, 15)),
+            Some(10)
+        );
+        assert_eq!(
+            cal.business_day_index(NaiveDate::from_ymd(2020, 5, 29)),
+            Some(20)
+        );
+        assert_eq!(
+            cal.business_day_index(NaiveDate::from_ymd(2020, 6, 1)),
+            Some(1)
+        );
+        assert_eq!(
+            cal.business_day_index(NaiveDate::from_ymd(2020, 5, 16)),
+            None
+        );
+        assert_eq!(
+            cal.business_day_index(NaiveDate::from_ymd(2020, 5, 1)),
+            None
+        );
+    }
+
+    #[test]
+    fn clean_settlement_window() {
+        // 2020-05-01 (Friday) is a holiday
+        let holidays = vec![
+            Holiday::WeekDay(Weekday::Sat),
+            Holiday::WeekDay(Weekday::Sun),
+            Holiday::SingularDay(NaiveDate::from_ymd(2020, 5, 1)),
+        ];
+        let cal = Calendar::calc_calendar(&holidays, 2020, 2020).unwrap();
+        // window from Wednesday to Friday contains the holiday
+        assert!(!cal.clean_window(NaiveDate::from_ymd(2020, 4, 29), 3, true));
+        assert!(cal.clean_window(NaiveDate::from_ymd(2020, 4, 29), 2, true));
+        // window from Monday to Sunday is free of holidays, but contains a weekend
+        assert!(cal.clean_window(NaiveDate::from_ymd(2020, 5, 4), 7, true));
+        assert!(!cal.clean_window(NaiveDate::from_ymd(2020, 5, 4), 7, false));
+        assert!(cal.clean_window(NaiveDate::from_ymd(2020, 5, 4), 5, false));
+        assert!(cal.clean_window(NaiveDate::from_ymd(2020, 5, 1), 0, false));
+    }
+
+    #[test]
+    fn extend_years_equals_full_calculation() {
+        let holidays = vec![
+            Holiday::WeekDay(Weekday::Sat),
+            Holiday::WeekDay(Weekday::Sun),
+            Holiday::MovableYearlyDay {
+                month: 1,
+                day: 1,
+                first: None,
+                last: None,
+                direction: None,
+            },
+            Holiday::EasterOffset {
+                offset: -2,
+                first: None,
+                last: None,
+            },
+            Holiday::EasterOffset {
+                offset: 1,
+                first: None,
+                last: None,
+            },
+            Holiday::YearlyDay {
+                month: 4,
+                day: 25,
+                first: Some(2000),
+                last: None,
+            },
+            Holiday::MonthWeekday {
+                month: 5,
+                weekday: Weekday::Mon,
+                nth: NthWeek::Last,
+                first: None,
+                last: None,
+            },
+            Holiday::WeekdayRelativeTo {
+                month: 5,
+                day: 24,
+                weekday: Weekday::Mon,
+                direction: WeekdayDirection::OnOrBefore,
+                first: None,
+                last: None,
+            },
+            Holiday::MovableYearlyDay {
+                month: 12,
+                day: 25,
+                first: None,
+                last: None,
+                direction: None,
+            },
+            Holiday::MovableYearlyDay {
+                month: 12,
+                day: 26,
+                first: None,
+                last: None,
+                direction: None,
+            },
+            Holiday::SingularDay(NaiveDate::from_ymd(2011, 4, 29)),
+        ];
+        let full = Calendar::calc_calendar_with_substitution(
+            &holidays,
+            1990,
+            2030,
+            Substitution::NextBusinessDay,
+        )
+        .unwrap();
+        let mut extended = Calendar::calc_calendar_with_substitution(
+            &holidays,
+            1990,
+            2000,
+            Substitution::NextBusinessDay,
+        )
+        .unwrap();
+        extended.extend_years(2010).unwrap();
+        extended.extend_years(2030).unwrap();
+        // nothing to do
+        extended.extend_years(2020).unwrap();
+        assert_eq!(extended.holidays, full.holidays);
+        assert_eq!(extended.substitutes, full.substitutes);
+        assert_eq!(extended, full);
+        assert!(extended.is_holiday(NaiveDate::from_ymd(2011, 4, 29)));
+        assert_eq!(
+            extended.extend_years(10_000),
+            Err(CalendarError::EasterOutOfRange(10_000))
+        );
+        assert_eq!(extended, full);
+    }
+
+    #[test]
+    fn trading_sessions() {
+        let holidays = vec![
+            Holiday::WeekDay(Weekday::Sat),
+            Holiday::WeekDay(Weekday::Sun),
+            // Good Friday, 2020-04-10
+            Holiday::EasterOffset {
+                offset: -2,
+                first: None,
+                last: None,
+            },
+        ];
+        let cal = Calendar::calc_calendar(&holidays, 2020, 2020).unwrap();
+        // without sessions, business days are open all day
+        let thursday = NaiveDate::from_ymd(2020, 4, 9);
+        assert!(cal.is_open_at(thursday.and_hms(23, 59, 59)));
+        assert!(!cal.is_open_at(NaiveDate::from_ymd(2020, 4, 10).and_hms(12, 0, 0)));
+        assert_eq!(
+            cal.next_open(NaiveDate::from_ymd(2020, 4, 10).and_hms(12, 0, 0)),
+            NaiveDate::from_ymd(2020, 4, 13).and_hms(0, 0, 0)
+        );
+
+        let cal = cal
+            .with_sessions(NaiveTime::from_hms(9, 30, 0), NaiveTime::from_hms(16, 0, 0))
+            .with_early_close(
+                NaiveDate::from_ymd(2020, 4, 8),
+                NaiveTime::from_hms(13, 0, 0),
+            );
+        // date only functions are not affected
+        assert!(cal.is_business_day(thursday));
+        assert!(!cal.is_open_at(thursday.and_hms(9, 29, 59)));
+        assert!(cal.is_open_at(thursday.and_hms(9, 30, 0)));
+        assert!(cal.is_open_at(thursday.and_hms(15, 59, 59)));
+        assert!(!cal.is_open_at(thursday.and_hms(16, 0, 0)));
+        assert_eq!(
+            cal.next_open(thursday.and_hms(8, 0, 0)),
+            thursday.and_hms(9, 30, 0)
+        );
+        assert_eq!(
+            cal.next_open(thursday.and_hms(11, 0, 0)),
+            thursday.and_hms(11, 0, 0)
+        );
+        // after close before Good Friday and the weekend
+        assert_eq!(
+            cal.next_open(thursday.and_hms(16, 30, 0)),
+            NaiveDate::from_ymd(2020, 4, 13).and_hms(9, 30, 0)
+        );
+        assert_eq!(
+            cal.next_open(NaiveDate::from_ymd(2020, 4, 11).and_hms(10, 0, 0)),
+            NaiveDate::from_ymd(2020, 4, 13).and_hms(9, 30, 0)
+        );
+        // half trading day
+        let wednesday = NaiveDate::from_ymd(2020, 4, 8);
+        assert!(cal.is_open_at(wednesday.and_hms(12, 59, 0)));
+        assert!(!cal.is_open_at(wednesday.and_hms(13, 0, 0)));
+        assert_eq!(
+            cal.next_open(wednesday.and_hms(14, 0, 0)),
+            thursday.and_hms(9, 30, 0)
+        );
+    }
+
+    #[test]
+    fn recompute_year_range() {
+        let holidays = vec![
+            Holiday::WeekDay(Weekday::Sat),
+            Holiday::WeekDay(Weekday::Sun),
+            Holiday::YearlyDay {
+                month: 1,
+                day: 1,
+                first: None,
+                last: None,
+            },
+            Holiday::EasterOffset {
+                offset: -2,
+                first: None,
+                last: None,
+            },
+            Holiday::MonthWeekday {
+                month: 5,
+                weekday: Weekday::Mon,
+                nth: NthWeek::Last,
+                first: None,
+                last: None,
+            },
+            Holiday::SingularDay(NaiveDate::from_ymd(2019, 6, 3)),
+        ];
+        let mut cal = Calendar::calc_calendar(&holidays, 2020, 2020).unwrap();
+        assert_eq!(cal.holidays.len(), 3);
+
+        // growth
+        cal.recompute(2018, 2021).unwrap();
+        assert_eq!(cal, Calendar::calc_calendar(&holidays, 2018, 2021).unwrap());
+        assert_eq!(cal.holidays.len(), 13);
+        assert!(cal.is_holiday(NaiveDate::from_ymd(2018, 3, 30)));
+        assert!(cal.is_holiday(NaiveDate::from_ymd(2019, 6, 3)));
+        assert!(cal.is_holiday(NaiveDate::from_ymd(2021, 5, 31)));
+        assert_eq!(
+            cal.holidays,
+            Calendar::calc_calendar(&holidays, 2018, 2021)
+                .unwrap()
+                .holidays
+        );
+
+        // shrinkage
+        cal.recompute(2021, 2021).unwrap();
+        assert_eq!(cal.holidays.len(), 3);
+        assert!(!cal.is_holiday(NaiveDate::from_ymd(2019, 6, 3)));
+        assert!(!cal.is_holiday(NaiveDate::from_ymd(2020, 1, 1)));
+        assert!(cal.is_holiday(NaiveDate::from_ymd(2021, 4, 2)));
+
+        // invalid ranges leave the calendar unchanged
+        assert!(cal.recompute(2022, 2021).is_err());
+        assert!(cal.recompute(1000, 2021).is_err());
+        assert_eq!(cal, Calendar::calc_calendar(&holidays, 2021, 2021).unwrap());
+    }
+
+    #[test]
+    fn rule_out_of_date_range() {
+        let holidays = vec![
+            Holiday::YearlyDay {
+                month: 1,
+                day: 1,
+                first: None,
+                last: None,
+            },
+            Holiday::EasterOffset {
+                offset: i32::MAX,
+                first: Some(2020),
+                last: None,
+            },
+        ];
+        assert_eq!(
+            Calendar::calc_calendar(&holidays, 2019, 2020),
+            Err(CalendarError::DateOutOfRange(2020))
+        );
+        let mut cal = Calendar::calc_calendar(&holidays, 2018, 2019).unwrap();
+        let unchanged = cal.clone();
+        assert_eq!(
+            cal.extend_years(2021),
+            Err(CalendarError::DateOutOfRange(2020))
+        );
+        assert_eq!(
+            cal.recompute(2019, 2020),
+            Err(CalendarError::DateOutOfRange(2020))
+        );
+        assert_eq!(cal, unchanged);
+        assert_eq!(cal.holidays.len(), 2);
+    }
+
+    #[test]
+    fn classify_days() {
+        let holidays = vec![
+            Holiday::SingularDay(NaiveDate::from_ymd(2019, 11, 20)),
+            Holiday::SingularDay(NaiveDate::from_ymd(2019, 11, 24)),
+            Holiday::SingularDay(NaiveDate::from_ymd(2019, 11, 25)),
+            Holiday::WeekDay(Weekday::Sat),
+            Holiday::WeekDay(Weekday::Sun),
+        ];
+        let cal = Calendar::calc_calendar(&holidays, 2019, 2019).unwrap();
+        assert_eq!(
+            cal.day_info(NaiveDate::from_ymd(2019, 11, 21)),
+            DayInfo::BusinessDay
+        );
+        assert_eq!(
+            cal.day_info(NaiveDate::from_ymd(2019, 11, 23)),
+            DayInfo::Weekend
+        );
+        assert_eq!(
+            cal.day_info(NaiveDate::from_ymd(2019, 11, 20)),
+            DayInfo::Holiday { name: None }
+        );
+        let info = cal.day_info(NaiveDate::from_ymd(2019, 11, 24));
+        assert_eq!(info, DayInfo::WeekendAndHoliday { name: None });
+        assert_eq!(info.holiday_name(), None);
+    }
+
+    #[test]
+    fn business_days_remaining_in_quarter() {
+        let holidays = vec![
+            Holiday::YearlyDay {
+                month: 12,
+                day: 25,
+                first: None,
+                last: None,
+            },
+            Holiday::YearlyDay {
+                month: 12,
+                day: 26,
+                first: None,
+                last: None,
+            },
+            Holiday::WeekDay(Weekday::Sat),
+            Holiday::WeekDay(Weekday::Sun),
+        ];
+        let cal = Calendar::calc_calendar(&holidays, 2019, 2020).unwrap();
+        // 18 business days left in April, 21 in May and 22 in June 2020
+        assert_eq!(
+            cal.business_days_remaining_in_quarter(NaiveDate::from_ymd(2020, 4, 6)),
+            61
+        );
+        // last business day of the quarter, with a weekend following
+        assert_eq!(
+            cal.business_days_remaining_in_quarter(NaiveDate::from_ymd(2020, 6, 30)),
+            0
+        );
+        // Christmas holidays and the year end are taken into account
+        assert_eq!(
+            cal.business_days_remaining_in_quarter(NaiveDate::from_ymd(2019, 12, 20)),
+            5
+        );
+        assert_eq!(
+            cal.business_days_remaining_in_quarter(NaiveDate::from_ymd(2019, 12, 31)),
+            0
+        );
+    }
+
+    #[test]
+    fn effective_holidays_exclude_weekends() {
+        let holidays = vec![
+            Holiday::YearlyDay {
+                month: 1,
+                day: 1,
+                first: None,
+                last: None,
+            },
+            Holiday::YearlyDay {
+                month: 12,
+                day: 25,
+                first: None,
+                last: None,
+            },
+            Holiday::WeekDay(Weekday::Sat),
+            Holiday::WeekDay(Weekday::Sun),
+        ];
+        let cal = Calendar::calc_calendar(&holidays, 2022, 2023).unwrap();
+        // New Year's Day 2023 is a Sunday
+        assert!(cal.is_holiday(NaiveDate::from_ymd(2023, 1, 1)));
+        assert_eq!(
+            cal.effective_holidays(2023),
+            vec![NaiveDate::from_ymd(2023, 12, 25)]
+        );
+        // both holidays fall on a weekend in 2022
+        assert!(cal.effective_holidays(2022).is_empty());
+        // out of the calendar's range of years
+        assert!(cal.effective_holidays(2024).is_empty());
+    }
+
+    #[test]
+    fn tabulated_holidays() {
+        let d = NaiveDate::from_ymd;
+        let chinese_new_year = Holiday::Tabulated {
+            name: Some("Chinese New Year".to_string()),
+            dates: vec![
+                d(2020, 1, 25),
+                d(2021, 2, 12),
+                d(2022, 2, 1),
+                d(2023, 1, 22),
+            ],
+        };
+        let holidays = vec![
+            Holiday::WeekDay(Weekday::Sat),
+            Holiday::WeekDay(Weekday::Sun),
+            chinese_new_year,
+        ];
+        let cal = Calendar::calc_calendar(&holidays, 2020, 2022).unwrap();
+        for date in &[d(2020, 1, 25), d(2021, 2, 12), d(2022, 2, 1)] {
+            assert!(cal.is_holiday(*date));
+            assert_eq!(cal.day_info(*date).holiday_name(), Some("Chinese New Year"));
+        }
+        assert_eq!(cal.effective_holidays(2021), vec![d(2021, 2, 12)]);
+        // outside of the calendar's range of years
+        assert!(!cal.is_holiday(d(2023, 1, 22)));
+        assert!(cal.is_business_day(d(2021, 2, 11)));
+    }
+
+    #[test]
+    fn settlement_lag_around_holiday() {
+        let d = NaiveDate::from_ymd;
+        let weekends = vec![
+            Holiday::WeekDay(Weekday::Sat),
+            Holiday::WeekDay(Weekday::Sun),
+        ];
+        let mut holidays = weekends.clone();
+        // Friday 2nd of April 2021 and Monday 5th of April 2021
+        holidays.push(Holiday::SingularDay(d(2021, 4, 2)));
+        holidays.push(Holiday::SingularDay(d(2021, 4, 5)));
+        let plain = Calendar::calc_calendar(&weekends, 2021, 2021).unwrap();
+        let easter = Calendar::calc_calendar(&holidays, 2021, 2021).unwrap();
+
+        // Monday to Thursday before Easter
+        let trades = vec![
+            d(2021, 3, 29),
+            d(2021, 3, 30),
+            d(2021, 3, 31),
+            d(2021, 4, 1),
+        ];
+        let stats = settlement_lag_stats(&trades, &plain, 2);
+        assert_eq!(stats.count, 4);
+        assert_eq!(stats.min, 2);
+        assert_eq!(stats.max, 4);
+        assert_fuzzy_eq!(stats.mean, 2.5, 1e-12);
+
+        // settlement of Wednesday's and Thursday's trades is deferred to the 6th and 7th
+        let stats = settlement_lag_stats(&trades, &easter, 2);
+        assert_eq!(stats.min, 2);
+        assert_eq!(stats.max, 6);
+        assert_fuzzy_eq!(stats.mean, 4.0, 1e-12);
+
+        // T+0 settles trades on non-business days at the next business day
+        let stats = settlement_lag_stats(&[d(2021, 4, 2), d(2021, 4, 6)], &easter, 0);
+        assert_eq!((stats.min, stats.max), (0, 4));
+        assert_eq!(settlement_lag_stats(&[], &easter, 2), LagStats::default());
+    }
+
+    #[test]
+    fn read_holidays_and_weekend_days() {
+        let d = NaiveDate::from_ymd;
+        let holidays = vec![
+            Holiday::WeekDay(Weekday::Fri),
+            Holiday::WeekDay(Weekday::Sat),
+            Holiday::YearlyDay {
+                month: 12,
+                day: 2,
+                first: None,
+                last: None,
+            },
+            Holiday::SingularDay(d(2021, 5, 13)),
+        ];
+        let cal = Calendar::calc_calendar(&holidays, 2020, 2021).unwrap();
+        assert_eq!(cal.weekend_days(), &[Weekday::Fri, Weekday::Sat]);
+        let dates: Vec<NaiveDate> = cal.holidays().iter().copied().collect();
+        assert_eq!(dates, vec![d(2020, 12, 2), d(2021, 5, 13), d(2021, 12, 2)]);
+    }
+
+    #[test]
+    fn joint_business_days() {
+        let d = NaiveDate::from_ymd;
+        let weekends = vec![
+            Holiday::WeekDay(Weekday::Sat),
+            Holiday::WeekDay(Weekday::Sun),
+        ];
+        let mut us_holidays = weekends.clone();
+        // Independence Day, observed on Monday 5th of July 2021
+        us_holidays.push(Holiday::SingularDay(d(2021, 7, 5)));
+        let mut uk_holidays = weekends;
+        // Summer bank holiday, Monday 30th of August 2021
+        uk_holidays.push(Holiday::SingularDay(d(2021, 8, 30)));
+        let us = Calendar::calc_calendar(&us_holidays, 2021, 2021).unwrap();
+        let uk = Calendar::calc_calendar(&uk_holidays, 2021, 2021).unwrap();
+
+        // Monday 5th of July is a business day in the UK only
+        assert_eq!(uk.next_bday(d(2021, 7, 2)), d(2021, 7, 5));
+        assert_eq!(uk.next_bday_joint(d(2021, 7, 2), &us), d(2021, 7, 6));
+        assert_eq!(us.next_bday_joint(d(2021, 7, 2), &uk), d(2021, 7, 6));
+        assert_eq!(us.prev_bday_joint(d(2021, 7, 6), &uk), d(2021, 7, 2));
+        // Monday 30th of August is a business day in the US only
+        assert_eq!(us.next_bday_joint(d(2021, 8, 27), &uk), d(2021, 8, 31));
+        assert_eq!(us.prev_bday_joint(d(2021, 8, 31), &uk), d(2021, 8, 27));
+        assert_eq!(us.next_bday_joint(d(2021, 8, 30), &uk), d(2021, 8, 31));
+    }
+
+    #[test]
+    fn weekends_only_calendar() {
+        let d = NaiveDate::from_ymd;
+        let cal = Calendar::weekends_only();
+        assert_eq!(cal.weekend_days(), &[Weekday::Sat, Weekday::Sun]);
+        assert!(cal.holidays().is_empty());
+        // Fridays far beyond any calculated range of years
+        for friday in &[d(2021, 12, 31), d(9999, 12, 24), d(15000, 6, 6)] {
+            assert_eq!(friday.weekday(), Weekday::Fri);
+            let monday = cal.next_bday(*friday);
+            assert_eq!(monday, *friday + Duration::days(3));
+            assert_eq!(cal.prev_bday(monday), *friday);
+        }
+        assert!(cal.covers(d(1, 1, 1), d(15000, 1, 1)));
+        let cal =
+            Calendar::calc_calendar(&[Holiday::SingularDay(d(2021, 1, 1))], 2021, 2021).unwrap();
+        assert!(!cal.covers(d(2021, 1, 1), d(2022, 1, 1)));
+    }
+
+    #[test]
+    fn always_open_calendar() {
+        let d = NaiveDate::from_ymd;
+        let cal = Calendar::always_open();
+        assert!(cal.weekend_days().is_empty());
+        assert!(cal.holidays().is_empty());
+        let saturday = d(2021, 1, 2);
+        assert!(cal.is_business_day(saturday));
+        assert_eq!(cal.next_bday(saturday), d(2021, 1, 3));
+        assert_eq!(cal.prev_bday(saturday), d(2021, 1, 1));
+        let start = d(2020, 12, 24);
+        let end = d(2021, 3, 1);
+        assert_eq!(
+            cal.business_days_in(start, end) as i64,
+            (end - start).num_days()
+        );
+        assert!(cal.covers(d(1, 1, 1), d(15000, 1, 1)));
+    }
+
+    #[test]
+    fn settlement_offsets_per_currency() {
+        let d = NaiveDate::from_ymd;
+        let usd = Currency::from_str("USD").unwrap();
+        let chf = Currency::from_str("CHF").unwrap();
+        let cal = Calendar::weekends_only();
+        let mut conventions = SettlementConventions::new();
+        conventions.set_offset(chf, 1);
+        assert_eq!(conventions.settlement_offset(&usd), 2);
+        assert_eq!(conventions.settlement_offset(&chf), 1);
+        // US equity traded on Thursday settles on Monday
+        assert_eq!(
+            conventions.settlement_date(d(2021, 6, 3), &usd, &cal),
+            d(2021, 6, 7)
+        );
+        assert_eq!(
+            conventions.settlement_date(d(2021, 6, 3), &chf, &cal),
+            d(2021, 6, 4)
+        );
+    }
+
+    #[test]
+    fn iterate_business_days() {
+        let d = NaiveDate::from_ymd;
+        let cal = Calendar::calc_calendar(
+            &[
+                Holiday::WeekDay(Weekday::Sat),
+                Holiday::WeekDay(Weekday::Sun),
+                Holiday::SingularDay(d(2021, 6, 8)),
+            ],
+            2021,
+            2021,
+        )
+        .unwrap();
+        // from Thursday across the weekend and a holiday on Tuesday
+        let days: Vec<NaiveDate> = cal.business_days_from(d(2021, 6, 3)).take(5).collect();
+        assert_eq!(
+            days,
+            vec![
+                d(2021, 6, 3),
+                d(2021, 6, 4),
+                d(2021, 6, 7),
+                d(2021, 6, 9),
+                d(2021, 6, 10)
+            ]
+        );
+        // starting on a Saturday
+        let days: Vec<NaiveDate> = cal
+            .business_days_from(d(2021, 6, 5))
+            .take_while(|date| *date < d(2021, 6, 10))
+            .collect();
+        assert_eq!(days, vec![d(2021, 6, 7), d(2021, 6, 9)]);
+    }
+}
@@ -0,0 +1,509 @@
+//! Predefined holiday rules of some markets
+//! The rules could be used to calculate a calendar for any range of years, e.g.
+//! `Calendar::calc_calendar(&swiss_holidays(), 2020, 2030)`, or the ready-made
+//! calendars could be used directly, e.g. `nyse_calendar(2020, 2030)`.
+
+use super::{Calendar, CalendarError, Holiday, NthWeek, RollDirection, WeekdayDirection};
+use chrono::{Datelike, NaiveDate, Weekday};
+
+fn yearly_day(month: u32, day: u32) -> Holiday {
+    Holiday::YearlyDay {
+        month,
+        day,
+        first: None,
+        last: None,
+    }
+}
+
+/// Moved to the next business day if it falls on a weekend or another holiday
+fn movable_day(month: u32, day: u32) -> Holiday {
+    Holiday::MovableYearlyDay {
+        month,
+        day,
+        first: None,
+        last: None,
+        direction: None,
+    }
+}
+
+/// Moved to the preceding Friday if it falls on a Saturday and to the following
+/// Monday if it falls on a Sunday
+fn observed_day(month: u32, day: u32, first: Option<i32>) -> Holiday {
+    Holiday::MovableYearlyDay {
+        month,
+        day,
+        first,
+        last: None,
+        direction: Some(RollDirection::Nearest),
+    }
+}
+
+fn month_weekday(month: u32, weekday: Weekday, nth: NthWeek) -> Holiday {
+    Holiday::MonthWeekday {
+        month,
+        weekday,
+        nth,
+        first: None,
+        last: None,
+    }
+}
+
+fn easter_offset(offset: i32) -> Holiday {
+    Holiday::EasterOffset {
+        offset,
+        first: None,
+        last: None,
+    }
+}
+
+/// Holidays of the Swiss stock exchange SIX
+pub fn swiss_holidays() -> Vec<Holiday> {
+    vec![
+        Holiday::WeekDay(Weekday::Sat),
+        Holiday::WeekDay(Weekday::Sun),
+        // New Year's Day
+        yearly_day(1, 1),
+        // Berchtoldstag
+        yearly_day(1, 2),
+        // Good Friday
+        easter_offset(-2),
+        // Easter Monday
+        easter_offset(1),
+        // Labour Day
+        yearly_day(5, 1),
+        // Ascension Day
+        easter_offset(39),
+        // Whit Monday
+        easter_offset(50),
+        // Swiss National Day
+        yearly_day(8, 1),
+        // Christmas Eve
+        yearly_day(12, 24),
+        // Christmas Day
+        yearly_day(12, 25),
+        // St. Stephen's Day
+        yearly_day(12, 26),
+        // New Year's Eve
+        yearly_day(12, 31),
+    ]
+}
+
+/// Holidays of the Swedish stock exchange Nasdaq Stockholm
+pub fn swedish_holidays() -> Vec<Holiday> {
+    vec![
+        Holiday::WeekDay(Weekday::Sat),
+        Holiday::WeekDay(Weekday::Sun),
+        // New Year's Day
+        yearly_day(1, 1),
+        // Epiphany
+        yearly_day(1, 6),
+        // Good Friday
+        easter_offset(-2),
+        // Easter Monday
+        easter_offset(1),
+        // Labour Day
+        yearly_day(5, 1),
+        // Ascension Day
+        easter_offset(39),
+        // National Day, a public holiday since 2005
+        Holiday::YearlyDay {
+            month: 6,
+            day: 6,
+            first: Some(2005),
+            last: None,
+        },
+        // Midsummer Eve, the Friday between 19th and 25th of June
+        Holiday::WeekdayRelativeTo {
+            month: 6,
+            day: 19,
+            weekday: Weekday::Fri,
+            direction: WeekdayDirection::OnOrAfter,
+            first: None,
+            last: None,
+        },
+        // Christmas Eve
+        yearly_day(12, 24),
+        // Christmas Day
+        yearly_day(12, 25),
+        // Boxing Day
+        yearly_day(12, 26),
+        // New Year's Eve
+        yearly_day(12, 31),
+    ]
+}
+
+/// Holidays of the Toronto Stock Exchange (TSX)
+pub fn canadian_holidays() -> Vec<Holiday> {
+    vec![
+        Holiday::WeekDay(Weekday::Sat),
+        Holiday::WeekDay(Weekday::Sun),
+        // New Year's Day
+        movable_day(1, 1),
+        // Family Day, since 2008
+        Holiday::MonthWeekday {
+            month: 2,
+            weekday: Weekday::Mon,
+            nth: NthWeek::Third,
+            first: Some(2008),
+            last: None,
+        },
+        // Good Friday
+        easter_offset(-2),
+        // Victoria Day, the Monday before 25th of May
+        Holiday::WeekdayRelativeTo {
+            month: 5,
+            day: 24,
+            weekday: Weekday::Mon,
+            direction: WeekdayDirection::OnOrBefore,
+            first: None,
+            last: None,
+        },
+        // Canada Day
+        movable_day(7, 1),
+        // Civic Holiday
+        month_weekday(8, Weekday::Mon, NthWeek::First),
+        // Labour Day
+        month_weekday(9, Weekday::Mon, NthWeek::First),
+        // Thanksgiving
+        month_weekday(10, Weekday::Mon, NthWeek::Second),
+        // Christmas Day and Boxing Day, the latter is moved once more if
+        // Christmas has been moved to the following Monday
+        movable_day(12, 25),
+        movable_day(12, 26),
+    ]
+}
+
+/// Holidays of the Australian Securities Exchange (ASX)
+pub fn australian_holidays() -> Vec<Holiday> {
+    vec![
+        Holiday::WeekDay(Weekday::Sat),
+        Holiday::WeekDay(Weekday::Sun),
+        // New Year's Day
+        movable_day(1, 1),
+        // Australia Day
+        movable_day(1, 26),
+        // Good Friday
+        easter_offset(-2),
+        // Easter Monday
+        easter_offset(1),
+        // Anzac Day, not moved if it falls on a weekend
+        yearly_day(4, 25),
+        // Queen's Birthday
+        month_weekday(6, Weekday::Mon, NthWeek::Second),
+        // National Day of Mourning for Queen Elizabeth II
+        Holiday::SingularDay(NaiveDate::from_ymd(2022, 9, 22)),
+        // Christmas Day and Boxing Day
+        movable_day(12, 25),
+        movable_day(12, 26),
+    ]
+}
+
+/// Holidays of the New York Stock Exchange (NYSE), including unscheduled closings
+/// since 2001. New Year's Day is not observed on the preceding Friday if it falls
+/// on a Saturday. The observed Monday after a New Year's Day on a Sunday can't be
+/// expressed by the rules, use `nyse_calendar` to include it.
+pub fn nyse_holidays() -> Vec<Holiday> {
+    vec![
+        Holiday::WeekDay(Weekday::Sat),
+        Holiday::WeekDay(Weekday::Sun),
+        // New Year's Day
+        yearly_day(1, 1),
+        // Martin Luther King, Jr. Day, since 1998
+        Holiday::MonthWeekday {
+            month: 1,
+            weekday: Weekday::Mon,
+            nth: NthWeek::Third,
+            first: Some(1998),
+            last: None,
+        },
+        // Washington's Birthday
+        month_weekday(2, Weekday::Mon, NthWeek::Third),
+        // Good Friday
+        easter_offset(-2),
+        // Memorial Day
+        month_weekday(5, Weekday::Mon, NthWeek::Last),
+        // Juneteenth National Independence Day, since 2022
+        observed_day(6, 19, Some(2022)),
+        // Independence Day
+        observed_day(7, 4, None),
+        // Labor Day
+        month_weekday(9, Weekday::Mon, NthWeek::First),
+        // Thanksgiving Day
+        month_weekday(11, Weekday::Thu, NthWeek::Fourth),
+        // Christmas Day
+        observed_day(12, 25, None),
+        // Closings after the attacks of September 11th
+        Holiday::SingularDay(NaiveDate::from_ymd(2001, 9, 11)),
+        Holiday::SingularDay(NaiveDate::from_ymd(2001, 9, 12)),
+        Holiday::SingularDay(NaiveDate::from_ymd(2001, 9, 13)),
+        Holiday::SingularDay(NaiveDate::from_ymd(2001, 9, 14)),
+        // National Days of Mourning for former presidents
+        Holiday::SingularDay(NaiveDate::from_ymd(2004, 6, 11)),
+        Holiday::SingularDay(NaiveDate::from_ymd(2007, 1, 2)),
+        Holiday::SingularDay(NaiveDate::from_ymd(2018, 12, 5)),
+        Holiday::SingularDay(NaiveDate::from_ymd(2025, 1, 9)),
+        // Hurricane Sandy
+        Holiday::SingularDay(NaiveDate::from_ymd(2012, 10, 29)),
+        Holiday::SingularDay(NaiveDate::from_ymd(2012, 10, 30)),
+    ]
+}
+
+/// Calendar of the New York Stock Exchange for the years from `start` to `end`.
+/// In addition to `nyse_holidays`, New Year's Day falling on a Sunday is observed
+/// on the following Monday.
+pub fn nyse_calendar(start: i32, end: i32) -> Result<Calendar, CalendarError> {
+    let mut rules = nyse_holidays();
+    for year in start.max(1)..=end {
+        let new_year =
+            NaiveDate::from_ymd_opt(year, 1, 1).ok_or(CalendarError::DateOutOfRange(year))?;
+        if new_year.weekday() == Weekday::Sun {
+            rules.push(Holiday::SingularDay(new_year.succ()));
+        }
+    }
+    Calendar::calc_calendar(&rules, start, end)
+}
+
+/// Calendar of the Swiss stock exchange SIX for the years from `start` to `end`
+pub fn swiss_calendar(start: i32, end: i32) -> Result<Calendar, CalendarError> {
+    Calendar::calc_calendar(&swiss_holidays(), start, end)
+}
+
+/// Calendar of Nasdaq Stockholm for the years from `start` to `end`
+pub fn swedish_calendar(start: i32, end: i32) -> Result<Calendar, CalendarError> {
+    Calendar::calc_calendar(&swedish_holidays(), start, end)
+}
+
+/// Calendar of the Toronto Stock Exchange for the years from `start` to `end`
+pub fn canadian_calendar(start: i32, end: i32) -> Result<Calendar, CalendarError> {
+    Calendar::calc_calendar(&canadian_holidays(), start, end)
+}
+
+/// Calendar of the Australian Securities Exchange for the years from `start` to `end`
+pub fn australian_calendar(start: i32, end: i32) -> Result<Calendar, CalendarError> {
+    Calendar::calc_calendar(&australian_holidays(), start, end)
+}
+
+impl Calendar {
+    /// Calendar of the Toronto Stock Exchange for the years from `start` to `end`
+    pub fn canada(start: i32, end: i32) -> Result<Calendar, CalendarError> {
+        canadian_calendar(start, end)
+    }
+
+    /// Calendar of the Australian Securities Exchange for the years from `start` to `end`
+    pub fn australia(start: i32, end: i32) -> Result<Calendar, CalendarError> {
+        australian_calendar(start, end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// All holidays on business days in the given year
+    fn holidays_in_year(rules: &[Holiday], year: i32) -> Vec<NaiveDate> {
+        let cal = Calendar::calc_calendar(rules, year, year).unwrap();
+        holidays_in_calendar(&cal, year)
+    }
+
+    fn holidays_in_calendar(cal: &Calendar, year: i32) -> Vec<NaiveDate> {
+        let mut date = NaiveDate::from_ymd(year, 1, 1);
+        let mut holidays = Vec::new();
+        while date.year() == year {
+            if cal.is_holiday(date) && !cal.is_weekend(date) {
+                holidays.push(date);
+            }
+            date = date.succ();
+        }
+        holidays
+    }
+
+    #[test]
+    fn swiss_holidays_2020_and_2021() {
+        let d = NaiveDate::from_ymd;
+        assert_eq!(
+            holidays_in_year(&swiss_holidays(), 2020),
+            vec![
+                d(2020, 1, 1),
+                d(2020, 1, 2),
+                d(2020, 4, 10),
+                d(2020, 4, 13),
+                d(2020, 5, 1),
+                d(2020, 5, 21),
+                d(2020, 6, 1),
+                d(2020, 12, 24),
+                d(2020, 12, 25),
+                d(2020, 12, 31),
+            ]
+        );
+        assert_eq!(
+            holidays_in_year(&swiss_holidays(), 2021),
+            vec![
+                d(2021, 1, 1),
+                d(2021, 4, 2),
+                d(2021, 4, 5),
+                d(2021, 5, 13),
+                d(2021, 5, 24),
+                d(2021, 12, 24),
+                d(2021, 12, 31),
+            ]
+        );
+    }
+
+    #[test]
+    fn swedish_holidays_2020_and_2021() {
+        let d = NaiveDate::from_ymd;
+        assert_eq!(
+            holidays_in_year(&swedish_holidays(), 2020),
+            vec![
+                d(2020, 1, 1),
+                d(2020, 1, 6),
+                d(2020, 4, 10),
+                d(2020, 4, 13),
+                d(2020, 5, 1),
+                d(2020, 5, 21),
+                d(2020, 6, 19),
+                d(2020, 12, 24),
+                d(2020, 12, 25),
+                d(2020, 12, 31),
+            ]
+        );
+        assert_eq!(
+            holidays_in_year(&swedish_holidays(), 2021),
+            vec![
+                d(2021, 1, 1),
+                d(2021, 1, 6),
+                d(2021, 4, 2),
+                d(2021, 4, 5),
+                d(2021, 5, 13),
+                d(2021, 6, 25),
+                d(2021, 12, 24),
+                d(2021, 12, 31),
+            ]
+        );
+    }
+
+    #[test]
+    fn canadian_holidays_2020_to_2022() {
+        let d = NaiveDate::from_ymd;
+        let cal = Calendar::canada(2020, 2022).unwrap();
+        assert_eq!(
+            holidays_in_calendar(&cal, 2020),
+            vec![
+                d(2020, 1, 1),
+                d(2020, 2, 17),
+                d(2020, 4, 10),
+                d(2020, 5, 18),
+                d(2020, 7, 1),
+                d(2020, 8, 3),
+                d(2020, 9, 7),
+                d(2020, 10, 12),
+                d(2020, 12, 25),
+                d(2020, 12, 28),
+            ]
+        );
+        assert_eq!(
+            holidays_in_calendar(&cal, 2021),
+            vec![
+                d(2021, 1, 1),
+                d(2021, 2, 15),
+                d(2021, 4, 2),
+                d(2021, 5, 24),
+                d(2021, 7, 1),
+                d(2021, 8, 2),
+                d(2021, 9, 6),
+                d(2021, 10, 11),
+                d(2021, 12, 27),
+                d(2021, 12, 28),
+            ]
+        );
+        assert_eq!(
+            holidays_in_calendar(&cal, 2022),
+            vec![
+                d(2022, 1, 3),
+                d(2022, 2, 21),
+                d(2022, 4, 15),
+                d(2022, 5, 23),
+                d(2022, 7, 1),
+                d(2022, 8, 1),
+                d(2022, 9, 5),
+                d(2022, 10, 10),
+                d(2022, 12, 26),
+                d(2022, 12, 27),
+            ]
+        );
+    }
+
+    #[test]
+    fn australian_holidays_2020_to_2022() {
+        let d = NaiveDate::from_ymd;
+        let cal = Calendar::australia(2020, 2022).unwrap();
+        assert_eq!(
+            holidays_in_calendar(&cal, 2020),
+            vec![
+                d(2020, 1, 1),
+                d(2020, 1, 27),
+                d(2020, 4, 10),
+                d(2020, 4, 13),
+                d(2020, 6, 8),
+                d(2020, 12, 25),
+                d(2020, 12, 28),
+            ]
+        );
+        assert_eq!(
+            holidays_in_calendar(&cal, 2021),
+            vec![
+                d(2021, 1, 1),
+                d(2021, 1, 26),
+                d(2021, 4, 2),
+                d(2021, 4, 5),
+                d(2021, 6, 14),
+                d(2021, 12, 27),
+                d(2021, 12, 28),
+            ]
+        );
+        assert_eq!(
+            holidays_in_calendar(&cal, 2022),
+            vec![
+                d(2022, 1, 3),
+                d(2022, 1, 26),
+                d(2022, 4, 15),
+                d(2022, 4, 18),
+                d(2022, 4, 25),
+                d(2022, 6, 13),
+                d(2022, 9, 22),
+                d(2022, 12, 26),
+                d(2022, 12, 27),
+            ]
+        );
+    }
+
+    #[test]
+    fn nyse_calendar_2021_to_2023() {
+        let d = NaiveDate::from_ymd;
+        let cal = nyse_calendar(2021, 2023).unwrap();
+        // Thanksgiving and the following Friday, a half trading day
+        assert!(cal.is_holiday(d(2021, 11, 25)));
+        assert!(cal.is_business_day(d(2021, 11, 26)));
+        assert_eq!(
+            holidays_in_calendar(&cal, 2022),
+            vec![
+                d(2022, 1, 17),
+                d(2022, 2, 21),
+                d(2022, 4, 15),
+                d(2022, 5, 30),
+                d(2022, 6, 20),
+                d(2022, 7, 4),
+                d(2022, 9, 5),
+                d(2022, 11, 24),
+                d(2022, 12, 26),
+            ]
+        );
+        // New Year's Day on a Saturday is not observed, but on a Sunday
+        assert!(cal.is_business_day(d(2021, 12, 31)));
+        assert!(cal.is_holiday(d(2023, 1, 2)));
+        assert!(swiss_calendar(2020, 2020)
+            .unwrap()
+            .is_holiday(d(2020, 8, 1)));
+        assert!(nyse_calendar(2023, 2021).is_err());
+    }
+}
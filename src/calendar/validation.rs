@@ -0,0 +1,417 @@
+//! Validation of holiday rules
+//! All problems of a set of rules are reported at once, which is useful if many calendar
+//! definitions are loaded from configuration files.
+
+use super::{Calendar, CalendarError, Holiday, Weekday};
+use chrono::{Datelike, NaiveDate};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+/// Range of years outside of which rules are most likely misconfigured
+const TYPICAL_YEARS: (i32, i32) = (1900, 2200);
+
+/// Severity of a problem found in a holiday rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The rule is most likely not what was intended, but a calendar could be calculated
+    Warning,
+    /// The rule is invalid, calculating a calendar would fail or give wrong results
+    Error,
+}
+
+/// Problem found in a holiday rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleIssue {
+    /// Position of the rule in the list of rules
+    pub index: usize,
+    pub severity: Severity,
+    pub description: String,
+}
+
+impl fmt::Display for RuleIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(
+            f,
+            "{} in rule {}: {}",
+            severity, self.index, self.description
+        )
+    }
+}
+
+/// Collects the issues of all rules
+struct Issues(Vec<RuleIssue>);
+
+impl Issues {
+    fn add(&mut self, index: usize, severity: Severity, description: String) {
+        self.0.push(RuleIssue {
+            index,
+            severity,
+            description,
+        });
+    }
+
+    fn check_month(&mut self, index: usize, month: u32) -> bool {
+        if (1..=12).contains(&month) {
+            true
+        } else {
+            self.add(index, Severity::Error, format!("invalid month {}", month));
+            false
+        }
+    }
+
+    /// Check that the day exists every year
+    fn check_yearly_day(&mut self, index: usize, month: u32, day: u32) {
+        if !self.check_month(index, month) {
+            return;
+        }
+        if month == 2 && day == 29 {
+            self.add(
+                index,
+                Severity::Error,
+                "29th of February does not exist every year".to_string(),
+            );
+        } else if NaiveDate::from_ymd_opt(2000, month, day).is_none() {
+            self.add(
+                index,
+                Severity::Error,
+                format!("invalid day {} of month {}", day, month),
+            );
+        }
+    }
+
+    fn check_years(&mut self, index: usize, first: &Option<i32>, last: &Option<i32>) {
+        if let (Some(first), Some(last)) = (first, last) {
+            if first > last {
+                self.add(
+                    index,
+                    Severity::Error,
+                    format!("first year {} is after last year {}", first, last),
+                );
+                return;
+            }
+        }
+        let before = matches!(last, Some(last) if *last < TYPICAL_YEARS.0);
+        let after = matches!(first, Some(first) if *first > TYPICAL_YEARS.1);
+        if before || after {
+            self.add(
+                index,
+                Severity::Warning,
+                format!(
+                    "rule applies only to years outside of {} to {}",
+                    TYPICAL_YEARS.0, TYPICAL_YEARS.1
+                ),
+            );
+        }
+    }
+}
+
+impl Calendar {
+    /// Check all rules and report every problem found, without calculating a calendar
+    pub fn validate_rules(rules: &[Holiday]) -> Vec<RuleIssue> {
+        let mut issues = Issues(Vec::new());
+        let mut singular_days = HashSet::new();
+        let mut weekdays: HashSet<Weekday> = HashSet::new();
+        for (index, rule) in rules.iter().enumerate() {
+            match rule {
+                Holiday::WeekDay(weekday) => {
+                    if !weekdays.insert(*weekday) {
+                        issues.add(
+                            index,
+                            Severity::Warning,
+                            format!("duplicate weekend day {:?}", weekday),
+                        );
+                    }
+                }
+                Holiday::SingularDay(date) => {
+                    if !singular_days.insert(*date) {
+                        issues.add(
+                            index,
+                            Severity::Warning,
+                            format!("duplicate singular day {}", date),
+                        );
+                    }
+                    let year = Some(date.year());
+                    issues.check_years(index, &year, &year);
+                }
+                Holiday::Tabulated { dates, .. } => {
+                    let mut tabulated = HashSet::new();
+                    for date in dates {
+                        if !tabulated.insert(*date) {
+                            issues.add(
+                                index,
+                                Severity::Warning,
+                                format!("duplicate tabulated day {}", date),
+                            );
+                        }
+                        let year = Some(date.year());
+                        issues.check_years(index, &year, &year);
+                    }
+                }
+                Holiday::YearlyDay {
+                    month,
+                    day,
+                    first,
+                    last,
+                }
+                | Holiday::MovableYearlyDay {
+                    month,
+                    day,
+                    first,
+                    last,
+                    ..
+                }
+                | Holiday::WeekdayRelativeTo {
+                    month,
+                    day,
+                    first,
+                    last,
+                    ..
+                } => {
+                    issues.check_yearly_day(index, *month, *day);
+                    issues.check_years(index, first, last);
+                }
+                Holiday::EasterOffset { first, last, .. } => {
+                    issues.check_years(index, first, last);
+                }
+                Holiday::MonthWeekday {
+                    month, first, last, ..
+                } => {
+                    issues.check_month(index, *month);
+                    issues.check_years(index, first, last);
+                }
+            }
+        }
+        issues.0
+    }
+
+    /// Report rules that are most likely copy-paste mistakes, by applying each rule on its
+    /// own to the years from `start` to `end`: rules that produce a date another rule
+    /// produces as well (one warning per pair of rules, for the later rule), and rules
+    /// whose holidays always fall on a weekend day. Rules that could not be applied are
+    /// skipped, see `validate_rules` to find these.
+    pub fn lint_rules(rules: &[Holiday], start: i32, end: i32) -> Vec<RuleIssue> {
+        let mut issues = Issues(Vec::new());
+        let weekend: Vec<Weekday> = rules
+            .iter()
+            .filter_map(|rule| match rule {
+                Holiday::WeekDay(weekday) => Some(*weekday),
+                _ => None,
+            })
+            .collect();
+        // first rule producing a date
+        let mut first_rule: BTreeMap<NaiveDate, usize> = BTreeMap::new();
+        for (index, rule) in rules.iter().enumerate() {
+            if let Holiday::WeekDay(_) = rule {
+                continue;
+            }
+            let dates = match Calendar::calc_calendar(std::slice::from_ref(rule), start, end) {
+                Ok(cal) => cal.holidays,
+                Err(_) => continue,
+            };
+            // colliding dates per other rule
+            let mut collisions: BTreeMap<usize, Vec<NaiveDate>> = BTreeMap::new();
+            for date in &dates {
+                match first_rule.get(date) {
+                    Some(other) => collisions.entry(*other).or_default().push(*date),
+                    None => {
+                        first_rule.insert(*date, index);
+                    }
+                }
+            }
+            for (other, dates) in collisions {
+                issues.add(
+                    index,
+                    Severity::Warning,
+                    format!(
+                        "rule produces {} date(s) of rule {}, first on {}",
+                        dates.len(),
+                        other,
+                        dates[0]
+                    ),
+                );
+            }
+            if !dates.is_empty() && dates.iter().all(|date| weekend.contains(&date.weekday())) {
+                issues.add(
+                    index,
+                    Severity::Warning,
+                    format!(
+                        "holiday always falls on a weekend from {} to {}",
+                        start, end
+                    ),
+                );
+            }
+        }
+        issues.0
+    }
+
+    /// Like `calc_calendar`, but the rules are validated first. If any rule has an
+    /// issue of severity `Error`, all issues are returned as error.
+    pub fn try_calc_calendar(
+        rules: &[Holiday],
+        start: i32,
+        end: i32,
+    ) -> Result<Calendar, CalendarError> {
+        let issues = Self::validate_rules(rules);
+        if issues.iter().any(|issue| issue.severity == Severity::Error) {
+            return Err(CalendarError::InvalidRules(issues));
+        }
+        Calendar::calc_calendar(rules, start, end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calendar::{NthWeek, WeekdayDirection};
+
+    #[test]
+    fn report_all_rule_issues() {
+        let rules = vec![
+            Holiday::WeekDay(Weekday::Sat),
+            Holiday::WeekDay(Weekday::Sun),
+            Holiday::WeekDay(Weekday::Sat),
+            Holiday::YearlyDay {
+                month: 13,
+                day: 1,
+                first: None,
+                last: None,
+            },
+            Holiday::YearlyDay {
+                month: 4,
+                day: 31,
+                first: None,
+                last: None,
+            },
+            Holiday::MovableYearlyDay {
+                month: 2,
+                day: 29,
+                first: None,
+                last: None,
+                direction: None,
+            },
+            Holiday::EasterOffset {
+                offset: -2,
+                first: Some(2020),
+                last: Some(2010),
+            },
+            Holiday::SingularDay(NaiveDate::from_ymd(2020, 5, 8)),
+            Holiday::SingularDay(NaiveDate::from_ymd(2020, 5, 8)),
+            Holiday::MonthWeekday {
+                month: 0,
+                weekday: Weekday::Mon,
+                nth: NthWeek::First,
+                first: None,
+                last: Some(1850),
+            },
+            Holiday::WeekdayRelativeTo {
+                month: 6,
+                day: 19,
+                weekday: Weekday::Fri,
+                direction: WeekdayDirection::OnOrAfter,
+                first: Some(2500),
+                last: None,
+            },
+            // valid rule
+            Holiday::YearlyDay {
+                month: 12,
+                day: 25,
+                first: None,
+                last: None,
+            },
+        ];
+        let issues = Calendar::validate_rules(&rules);
+        let summary: Vec<(usize, Severity)> = issues
+            .iter()
+            .map(|issue| (issue.index, issue.severity))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (2, Severity::Warning),
+                (3, Severity::Error),
+                (4, Severity::Error),
+                (5, Severity::Error),
+                (6, Severity::Error),
+                (8, Severity::Warning),
+                (9, Severity::Error),
+                (9, Severity::Warning),
+                (10, Severity::Warning),
+            ]
+        );
+        assert_eq!(issues[1].description, "invalid month 13");
+        assert_eq!(issues[2].description, "invalid day 31 of month 4");
+        assert_eq!(
+            issues[3].description,
+            "29th of February does not exist every year"
+        );
+        assert_eq!(
+            issues[4].to_string(),
+            "error in rule 6: first year 2020 is after last year 2010"
+        );
+        assert_eq!(issues[5].description, "duplicate singular day 2020-05-08");
+
+        match Calendar::try_calc_calendar(&rules, 2020, 2020) {
+            Err(CalendarError::InvalidRules(errors)) => assert_eq!(errors, issues),
+            _ => panic!("invalid rules must be rejected"),
+        }
+        // warnings only
+        let rules = vec![
+            Holiday::WeekDay(Weekday::Sat),
+            Holiday::WeekDay(Weekday::Sat),
+            Holiday::SingularDay(NaiveDate::from_ymd(2020, 5, 8)),
+        ];
+        assert_eq!(Calendar::validate_rules(&rules).len(), 1);
+        let cal = Calendar::try_calc_calendar(&rules, 2020, 2020).unwrap();
+        assert!(cal.is_holiday(NaiveDate::from_ymd(2020, 5, 8)));
+    }
+
+    #[test]
+    fn lint_colliding_and_ineffective_rules() {
+        let d = NaiveDate::from_ymd;
+        let yearly_day = |month, day| Holiday::YearlyDay {
+            month,
+            day,
+            first: None,
+            last: None,
+        };
+        let rules = vec![
+            Holiday::WeekDay(Weekday::Sat),
+            Holiday::WeekDay(Weekday::Sun),
+            yearly_day(1, 1),
+            // Christmas Eve added twice
+            yearly_day(12, 24),
+            Holiday::SingularDay(d(2021, 12, 24)),
+            // Boxing Day falls on Sunday in 2021
+            yearly_day(12, 26),
+            Holiday::MovableYearlyDay {
+                month: 12,
+                day: 26,
+                first: None,
+                last: None,
+                direction: None,
+            },
+        ];
+        let issues = Calendar::lint_rules(&rules, 2021, 2021);
+        let summary: Vec<usize> = issues.iter().map(|issue| issue.index).collect();
+        assert_eq!(summary, vec![4, 5]);
+        assert!(issues
+            .iter()
+            .all(|issue| issue.severity == Severity::Warning));
+        assert_eq!(
+            issues[0].to_string(),
+            "warning in rule 4: rule produces 1 date(s) of rule 3, first on 2021-12-24"
+        );
+        assert_eq!(
+            issues[1].description,
+            "holiday always falls on a weekend from 2021 to 2021"
+        );
+        // Boxing Day is effective in 2022, but then both rules for it collide
+        let issues = Calendar::lint_rules(&rules, 2021, 2022);
+        let summary: Vec<usize> = issues.iter().map(|issue| issue.index).collect();
+        assert_eq!(summary, vec![4, 6]);
+    }
+}
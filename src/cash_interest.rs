@@ -0,0 +1,181 @@
+//! Accrual of interest on cash balances, e.g. interest paid by a broker on uninvested cash.
+//! Balances and interest rates are given as step functions, i.e. each value holds from its
+//! date until the date of the next value. Interest accrues daily on the end-of-day balance
+//! and is paid at the end of each payment period.
+
+use crate::currency::Currency;
+use crate::day_count_conv::{DayCountConv, DayCountConvError};
+use crate::fixed_income::CashFlow;
+use crate::time_period::TimePeriod;
+use crate::transaction::{Transaction, TransactionType};
+use chrono::NaiveDate;
+
+/// Value of the step function `schedule` at `date`, if `date` is not before its first date
+fn step_value(schedule: &[(NaiveDate, f64)], date: NaiveDate) -> Option<f64> {
+    schedule
+        .iter()
+        .take_while(|(d, _)| *d <= date)
+        .last()
+        .map(|(_, value)| *value)
+}
+
+/// Copy of `schedule` sorted by date
+fn sorted(schedule: &[(NaiveDate, f64)]) -> Vec<(NaiveDate, f64)> {
+    let mut schedule = schedule.to_vec();
+    schedule.sort_by_key(|(date, _)| *date);
+    schedule
+}
+
+/// Interest payments on the end-of-day balances `balance_series` from its first to its last
+/// date, in `currency`. Positive balances accrue at the rates of `rate_schedule`, negative
+/// balances at the rates of `debit_rate_schedule` or, if `None`, of `rate_schedule` as well.
+/// No interest accrues before the first date of a rate schedule. Interest is paid at the end
+/// of each period of `payment_frequency` starting at the first balance date, and on the last
+/// balance date for the remaining accrual; with a non-positive frequency, everything is paid
+/// on the last balance date. Periods without accrual don't generate a payment.
+pub fn accrue(
+    balance_series: &[(NaiveDate, f64)],
+    rate_schedule: &[(NaiveDate, f64)],
+    debit_rate_schedule: Option<&[(NaiveDate, f64)]>,
+    day_count: DayCountConv,
+    payment_frequency: TimePeriod,
+    currency: Currency,
+) -> Result<Vec<CashFlow>, DayCountConvError> {
+    let balances = sorted(balance_series);
+    let credit_rates = sorted(rate_schedule);
+    let debit_rates = debit_rate_schedule.map(sorted);
+    let (start, end) = match (balances.first(), balances.last()) {
+        (Some((start, _)), Some((end, _))) => (*start, *end),
+        _ => return Ok(Vec::new()),
+    };
+
+    // payment dates are offsets from the start to avoid drifting month ends
+    let mut payment_dates = Vec::new();
+    if payment_frequency.is_positive() {
+        let mut n = 1;
+        while let Some(period) = payment_frequency.checked_mul(n) {
+            let date = period.add_to(start, None);
+            if date >= end {
+                break;
+            }
+            payment_dates.push(date);
+            n += 1;
+        }
+    }
+    payment_dates.push(end);
+
+    // all dates at which the balance or rate may change
+    let mut dates: Vec<NaiveDate> = balances
+        .iter()
+        .chain(credit_rates.iter())
+        .chain(debit_rates.iter().flatten())
+        .map(|(date, _)| *date)
+        .filter(|date| *date > start && *date < end)
+        .chain(payment_dates.iter().copied())
+        .collect();
+    dates.sort();
+    dates.dedup();
+
+    let mut flows = Vec::new();
+    let mut period_start = start;
+    let mut accrued = 0.;
+    let mut segment_start = start;
+    let mut yf_start = 0.;
+    for date in dates {
+        let yf_end = day_count.year_fraction(
+            period_start,
+            date,
+            Some(period_start),
+            Some(payment_frequency),
+        )?;
+        let balance = step_value(&balances, segment_start).unwrap_or(0.);
+        let rates = if balance < 0. {
+            debit_rates.as_deref().unwrap_or(&credit_rates)
+        } else {
+            &credit_rates
+        };
+        if let Some(rate) = step_value(rates, segment_start) {
+            accrued += balance * rate * (yf_end - yf_start);
+        }
+        segment_start = date;
+        yf_start = yf_end;
+        if payment_dates.binary_search(&date).is_ok() {
+            if accrued != 0. {
+                flows.push(CashFlow::new(accrued, currency, date));
+            }
+            accrued = 0.;
+            period_start = date;
+            yf_start = 0.;
+        }
+    }
+    Ok(flows)
+}
+
+/// Interest transaction of an interest payment on the cash account represented by the asset
+/// `asset_id`, ready to be inserted into a transaction store
+pub fn interest_transaction(flow: CashFlow, asset_id: u64) -> Transaction {
+    Transaction {
+        note: Some("interest on cash".to_string()),
+        ..Transaction::new(TransactionType::Interest { asset_id }, flow)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn accrue_interest_on_cash() {
+        let eur = Currency::from_str("EUR").unwrap();
+        let start = NaiveDate::from_ymd(2021, 1, 1);
+        let end = NaiveDate::from_ymd(2021, 4, 1);
+        let quarterly = TimePeriod::from_str("3M").unwrap();
+        let rates = vec![(start, 0.02)];
+
+        // constant balance at a constant rate over a quarter
+        let balances = vec![(start, 10_000.), (end, 10_000.)];
+        let dc = DayCountConv::Act365;
+        let flows = accrue(&balances, &rates, None, dc, quarterly, eur).unwrap();
+        assert_eq!(flows.len(), 1);
+        assert_eq!(flows[0].date, end);
+        assert_eq!(flows[0].amount.currency, eur);
+        let yf = dc.year_fraction(start, end, None, None).unwrap();
+        assert_fuzzy_eq!(flows[0].amount.value(), 10_000. * 0.02 * yf, 1e-10);
+
+        // monthly payments of the same interest
+        let monthly = TimePeriod::from_str("1M").unwrap();
+        let flows = accrue(&balances, &rates, None, dc, monthly, eur).unwrap();
+        assert_eq!(flows.len(), 3);
+        assert_eq!(flows[1].date, NaiveDate::from_ymd(2021, 3, 1));
+        assert_fuzzy_eq!(flows[1].amount.value(), 10_000. * 0.02 * 28. / 365., 1e-10);
+        let total: f64 = flows.iter().map(|f| f.amount.value()).sum();
+        assert_fuzzy_eq!(total, 10_000. * 0.02 * yf, 1e-10);
+
+        // rate change and overdraft at a debit rate in February
+        let feb = NaiveDate::from_ymd(2021, 2, 1);
+        let mar = NaiveDate::from_ymd(2021, 3, 1);
+        let balances = vec![(start, 10_000.), (feb, -1_000.), (mar, 5_000.), (end, 0.)];
+        let rates = vec![(start, 0.02), (mar, 0.01)];
+        let debit_rates = vec![(start, 0.1)];
+        let flows = accrue(
+            &balances,
+            &rates,
+            Some(&debit_rates),
+            DayCountConv::D30_360,
+            quarterly,
+            eur,
+        )
+        .unwrap();
+        assert_eq!(flows.len(), 1);
+        let expected = (10_000. * 0.02 - 1_000. * 0.1 + 5_000. * 0.01) / 12.;
+        assert_fuzzy_eq!(flows[0].amount.value(), expected, 1e-10);
+
+        let transaction = interest_transaction(flows[0], 1);
+        assert!(matches!(
+            transaction.transaction_type,
+            TransactionType::Interest { asset_id: 1 }
+        ));
+        assert_eq!(transaction.cash_flow.date, end);
+    }
+}
@@ -0,0 +1,316 @@
+//! Credit default swaps, i.e. protection against the default of a reference entity in
+//! exchange for a regular premium. Premiums are paid on standard CDS dates, the legs are
+//! valued with a flat hazard rate of the reference entity.
+
+use crate::currency::Currency;
+use crate::day_count_conv::{DayCountConv, DayCountConvError};
+use crate::fixed_income::CashFlow;
+use crate::math::bisect;
+use crate::rates::{DiscountError, Discounter};
+use crate::schedule::next_cds_date;
+use crate::time_period::TimePeriod;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+
+/// Number of steps per premium period to integrate the protection leg over
+const INTEGRATION_STEPS: i64 = 12;
+
+/// Upper bound of the hazard rates searched for by `Cds::implied_hazard_rate`
+const MAX_HAZARD_RATE: f64 = 10.;
+
+/// Error related to credit default swaps
+#[derive(Debug)]
+pub enum CdsError {
+    /// The CDS matures on or before the valuation date
+    Expired,
+    /// No hazard rate between zero and `MAX_HAZARD_RATE` reproduces the spread
+    NoHazardRate {
+        spread: f64,
+    },
+    /// The premium frequency must move dates forward in time
+    InvalidFrequency(TimePeriod),
+    DayCountError(DayCountConvError),
+    DiscountingFailure(DiscountError),
+}
+
+impl fmt::Display for CdsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CdsError::Expired => write!(f, "the CDS has expired"),
+            CdsError::NoHazardRate { spread } => {
+                write!(f, "no hazard rate implies the CDS spread {}", spread)
+            }
+            CdsError::InvalidFrequency(period) => {
+                write!(f, "invalid premium frequency {}", period)
+            }
+            CdsError::DayCountError(_) => {
+                write!(f, "invalid day count convention in this context")
+            }
+            CdsError::DiscountingFailure(_) => write!(f, "discounting cash flows failed"),
+        }
+    }
+}
+
+impl Error for CdsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CdsError::DayCountError(err) => Some(err),
+            CdsError::DiscountingFailure(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<DayCountConvError> for CdsError {
+    fn from(error: DayCountConvError) -> Self {
+        CdsError::DayCountError(error)
+    }
+}
+
+impl From<DiscountError> for CdsError {
+    fn from(error: DiscountError) -> Self {
+        CdsError::DiscountingFailure(error)
+    }
+}
+
+fn default_day_count() -> DayCountConv {
+    DayCountConv::Act360
+}
+
+/// Terms of a credit default swap from the view of the protection buyer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cds {
+    pub notional: f64,
+    pub currency: Currency,
+    /// Annual premium as decimal fraction of the notional, e.g. 0.01 for 100bp
+    pub spread: f64,
+    /// Start of the protection and of the first premium period
+    pub start: NaiveDate,
+    pub maturity: NaiveDate,
+    /// Period between premium payments, the first premium is paid on the next CDS date
+    pub premium_frequency: TimePeriod,
+    /// Fraction of the notional recovered in case of default
+    pub recovery_rate: f64,
+    /// Day count convention of the premium periods, Act/360 by default
+    #[serde(default = "default_day_count")]
+    pub day_count: DayCountConv,
+    /// If true, the premium accrued since the last payment is paid on default
+    #[serde(default)]
+    pub accrued_on_default: bool,
+}
+
+impl Cds {
+    /// Check that the terms can be rolled out, i.e. that the premium frequency is positive
+    pub fn validate(&self) -> Result<(), CdsError> {
+        if self.premium_frequency.is_positive() {
+            Ok(())
+        } else {
+            Err(CdsError::InvalidFrequency(self.premium_frequency))
+        }
+    }
+
+    /// Unadjusted premium periods as pairs of start and end date. The first period ends
+    /// on the first CDS date after the start, the following periods are rolled by the
+    /// premium frequency and the last one ends at maturity. If the premium frequency is
+    /// not positive, the periods stop after the first one.
+    pub fn premium_periods(&self) -> Vec<(NaiveDate, NaiveDate)> {
+        let mut periods = Vec::new();
+        let mut start = self.start;
+        let mut end = next_cds_date(self.start);
+        while start < self.maturity && end > start {
+            end = end.min(self.maturity);
+            periods.push((start, end));
+            start = end;
+            end = self.premium_frequency.add_to(end, None);
+        }
+        periods
+    }
+
+    /// Premium payments at the end of each premium period
+    pub fn premium_cash_flows(&self) -> Result<Vec<CashFlow>, CdsError> {
+        self.validate()?;
+        let mut cash_flows = Vec::new();
+        for (start, end) in self.premium_periods() {
+            let year_fraction = self.day_count.year_fraction(start, end, None, None)?;
+            cash_flows.push(CashFlow::new(
+                self.notional * self.spread * year_fraction,
+                self.currency,
+                end,
+            ));
+        }
+        Ok(cash_flows)
+    }
+
+    /// Present values at `valuation` of a premium of 1 per unit of notional (the risky
+    /// annuity) and of the protection per unit of notional, given a flat `hazard_rate`.
+    /// The protection leg is integrated over `INTEGRATION_STEPS` steps per premium period,
+    /// with defaults assumed in the middle of each step.
+    fn legs(
+        &self,
+        discounter: &dyn Discounter,
+        hazard_rate: f64,
+        valuation: NaiveDate,
+    ) -> Result<(f64, f64), CdsError> {
+        self.validate()?;
+        if self.maturity <= valuation {
+            return Err(CdsError::Expired);
+        }
+        if discounter.currency() != self.currency {
+            return Err(CdsError::DiscountingFailure(DiscountError));
+        }
+        let survival = |date: NaiveDate| {
+            let time = (date - valuation).num_days().max(0) as f64 / 365.;
+            (-hazard_rate * time).exp()
+        };
+        let mut annuity = 0.;
+        let mut protection = 0.;
+        for (start, end) in self.premium_periods() {
+            if end <= valuation {
+                continue;
+            }
+            let year_fraction = self.day_count.year_fraction(start, end, None, None)?;
+            annuity += year_fraction * discounter.discount_factor(valuation, end) * survival(end);
+
+            let protection_start = start.max(valuation);
+            let days = (end - protection_start).num_days();
+            let steps = INTEGRATION_STEPS.min(days);
+            for step in 0..steps {
+                let step_start = protection_start + chrono::Duration::days(days * step / steps);
+                let step_end = protection_start + chrono::Duration::days(days * (step + 1) / steps);
+                let mid = step_start + (step_end - step_start) / 2;
+                let default_prob = survival(step_start) - survival(step_end);
+                let df = discounter.discount_factor(valuation, mid);
+                protection += df * default_prob;
+                if self.accrued_on_default {
+                    let accrued = self.day_count.year_fraction(start, mid, None, None)?;
+                    annuity += accrued * df * default_prob;
+                }
+            }
+        }
+        Ok((annuity, (1. - self.recovery_rate) * protection))
+    }
+
+    /// Value at `valuation` for the protection buyer, i.e. the present value of the
+    /// protection less the present value of the premiums, given a flat `hazard_rate`
+    pub fn npv(
+        &self,
+        discounter: &dyn Discounter,
+        hazard_rate: f64,
+        valuation: NaiveDate,
+    ) -> Result<f64, CdsError> {
+        let (annuity, protection) = self.legs(discounter, hazard_rate, valuation)?;
+        Ok(self.notional * (protection - self.spread * annuity))
+    }
+
+    /// Spread for which the CDS has zero value at `valuation`, given a flat `hazard_rate`
+    pub fn par_spread(
+        &self,
+        discounter: &dyn Discounter,
+        hazard_rate: f64,
+        valuation: NaiveDate,
+    ) -> Result<f64, CdsError> {
+        let (annuity, protection) = self.legs(discounter, hazard_rate, valuation)?;
+        Ok(protection / annuity)
+    }
+
+    /// Flat hazard rate for which the par spread at `valuation` equals `market_spread`.
+    /// By the credit triangle, the result is close to `market_spread / (1 - recovery_rate)`.
+    pub fn implied_hazard_rate(
+        &self,
+        discounter: &dyn Discounter,
+        market_spread: f64,
+        valuation: NaiveDate,
+    ) -> Result<f64, CdsError> {
+        // check for errors independent of the hazard rate before solving
+        self.legs(discounter, 0., valuation)?;
+        let excess = |hazard_rate: f64| match self.par_spread(discounter, hazard_rate, valuation) {
+            Ok(spread) => spread - market_spread,
+            Err(_) => f64::NAN,
+        };
+        bisect(excess, 0., MAX_HAZARD_RATE, 1e-14).ok_or(CdsError::NoHazardRate {
+            spread: market_spread,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rates::DiscountCurve;
+    use std::str::FromStr;
+
+    fn five_year_cds(spread: f64) -> Cds {
+        Cds {
+            notional: 1_000_000.,
+            currency: Currency::from_str("EUR").unwrap(),
+            spread,
+            start: NaiveDate::from_ymd(2021, 3, 22),
+            maturity: NaiveDate::from_ymd(2026, 6, 20),
+            premium_frequency: TimePeriod::from_str("3M").unwrap(),
+            recovery_rate: 0.4,
+            day_count: DayCountConv::Act360,
+            accrued_on_default: false,
+        }
+    }
+
+    #[test]
+    fn premium_leg_on_cds_dates() {
+        let cds = five_year_cds(0.01);
+        let cash_flows = cds.premium_cash_flows().unwrap();
+        assert_eq!(cash_flows.len(), 21);
+        assert_eq!(cash_flows[0].date, NaiveDate::from_ymd(2021, 6, 20));
+        assert_fuzzy_eq!(cash_flows[0].amount.value(), 10_000. * 90. / 360., 1e-8);
+        assert_eq!(cash_flows[1].date, NaiveDate::from_ymd(2021, 9, 20));
+        assert_eq!(cash_flows[20].date, NaiveDate::from_ymd(2026, 6, 20));
+
+        // a premium frequency which doesn't roll forward is rejected
+        for frequency in &["0M", "-3M"] {
+            let cds = Cds {
+                premium_frequency: TimePeriod::from_str(frequency).unwrap(),
+                ..five_year_cds(0.01)
+            };
+            assert_eq!(cds.premium_periods().len(), 1);
+            assert!(matches!(
+                cds.premium_cash_flows(),
+                Err(CdsError::InvalidFrequency(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn par_spread_from_implied_hazard_rate() {
+        let valuation = NaiveDate::from_ymd(2021, 3, 22);
+        let eur = Currency::from_str("EUR").unwrap();
+        let curve = DiscountCurve::flat(valuation, 0.01, DayCountConv::Act365, eur);
+        let mut cds = five_year_cds(0.012);
+
+        let hazard_rate = cds.implied_hazard_rate(&curve, 0.012, valuation).unwrap();
+        let par_spread = cds.par_spread(&curve, hazard_rate, valuation).unwrap();
+        assert_fuzzy_eq!(par_spread, 0.012, 1e-10);
+        assert_fuzzy_eq!(cds.npv(&curve, hazard_rate, valuation).unwrap(), 0., 1e-6);
+        // credit triangle
+        assert_fuzzy_eq!(hazard_rate, 0.012 / 0.6, 5e-4);
+        assert!(cds.npv(&curve, 2. * hazard_rate, valuation).unwrap() > 0.);
+
+        // paying the accrued premium on default lowers the par spread
+        cds.accrued_on_default = true;
+        assert!(cds.par_spread(&curve, hazard_rate, valuation).unwrap() < par_spread);
+        let hazard_rate = cds.implied_hazard_rate(&curve, 0.012, valuation).unwrap();
+        assert_fuzzy_eq!(
+            cds.par_spread(&curve, hazard_rate, valuation).unwrap(),
+            0.012,
+            1e-10
+        );
+
+        assert!(matches!(
+            cds.implied_hazard_rate(&curve, -0.01, valuation),
+            Err(CdsError::NoHazardRate { .. })
+        ));
+        assert!(matches!(
+            cds.npv(&curve, hazard_rate, NaiveDate::from_ymd(2026, 6, 20)),
+            Err(CdsError::Expired)
+        ));
+    }
+}
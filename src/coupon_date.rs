@@ -7,7 +7,7 @@ use std::str::FromStr;
 
 /// Month and day that serves as a reference for rolling out the cash flows
 /// This should equal the (unadjusted) first coupon's end date
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct CouponDate {
     day: u32,
     month: u32,
@@ -41,11 +41,103 @@ impl de::Error for CurrencyError {
 }
 
 /// Special type for currencies
-#[derive(Debug, Clone, PartialEq, Copy)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy)]
 pub struct Currency {
     iso_code: [char; 3],
 }
 
+impl Currency {
+    /// Number of digits of the currency's minor unit according to ISO 4217,
+    /// e.g. 2 for EUR (cents) or 0 for JPY
+    pub fn minor_units(&self) -> u32 {
+        match self.to_string().as_str() {
+            "BIF" | "CLP" | "DJF" | "GNF" | "ISK" | "JPY" | "KMF" | "KRW" | "PYG" | "RWF"
+            | "UGX" | "UYI" | "VND" | "VUV" | "XAF" | "XOF" | "XPF" => 0,
+            "BHD" | "IQD" | "JOD" | "KWD" | "LYD" | "OMR" | "TND" => 3,
+            "CLF" | "UYW" => 4,
+            _ => 2,
+        }
+    }
+
+    /// Common symbol of the currency, if there is an unambiguous one
+    pub fn symbol(&self) -> Option<&'static str> {
+        match self.to_string().as_str() {
+            "EUR" => Some("€"),
+            "USD" => Some("$"),
+            "GBP" => Some("£"),
+            "JPY" => Some("¥"),
+            "INR" => Some("₹"),
+            "KRW" => Some("₩"),
+            _ => None,
+        }
+    }
+}
+
+/// Rules to round monetary amounts to the minor unit of their currency
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum RoundingRule {
+    /// Round to nearest, ties away from zero
+    #[serde(rename = "half up")]
+    HalfUp,
+    /// Round to nearest, ties to even (banker's rounding)
+    #[serde(rename = "half even")]
+    HalfEven,
+    /// Cut off all digits beyond the minor unit, i.e. round towards zero
+    #[serde(rename = "truncate")]
+    #[serde(alias = "down")]
+    Truncate,
+    /// Round away from zero, i.e. any digit beyond the minor unit rounds up the absolute amount
+    #[serde(rename = "up")]
+    Up,
+    /// Leave amounts unchanged
+    #[serde(rename = "none")]
+    #[default]
+    None,
+}
+
+impl RoundingRule {
+    /// Round `amount` to the minor unit of `currency`
+    pub fn round(&self, amount: f64, currency: Currency) -> f64 {
+        let scale = 10_f64.powi(currency.minor_units() as i32);
+        let scaled = amount * scale;
+        // Decimal amounts like 2.675 are not exactly representable as floats,
+        // therefore values within this tolerance are treated as ties or integers.
+        let tol = 1e-9 + 1e-12 * scaled.abs();
+        let abs = scaled.abs();
+        let rounded = match self {
+            RoundingRule::None => return amount,
+            RoundingRule::HalfUp => (abs + 0.5 + tol).floor(),
+            RoundingRule::HalfEven => {
+                let floor = abs.floor();
+                if (abs - floor - 0.5).abs() <= tol {
+                    if floor % 2.0 == 0.0 {
+                        floor
+                    } else {
+                        floor + 1.0
+                    }
+                } else {
+                    abs.round()
+                }
+            }
+            RoundingRule::Truncate => {
+                if (abs - abs.round()).abs() <= tol {
+                    abs.round()
+                } else {
+                    abs.trunc()
+                }
+            }
+            RoundingRule::Up => {
+                if (abs - abs.round()).abs() <= tol {
+                    abs.round()
+                } else {
+                    abs.ceil()
+                }
+            }
+        };
+        scaled.signum() * rounded / scale
+    }
+}
+
 impl fmt::Display for Currency {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
@@ -151,6 +243,48 @@ mod tests {
         assert_eq!(currency, Err(CurrencyError::InvalidCharacter));
     }
 
+    #[test]
+    fn currency_rounding() {
+        let eur = Currency::from_str("EUR").unwrap();
+        let jpy = Currency::from_str("JPY").unwrap();
+        let kwd = Currency::from_str("KWD").unwrap();
+        assert_eq!(eur.minor_units(), 2);
+        assert_eq!(jpy.minor_units(), 0);
+        assert_eq!(kwd.minor_units(), 3);
+
+        let tol = 1e-12;
+        assert_fuzzy_eq!(RoundingRule::HalfUp.round(2.675, eur), 2.68, tol);
+        assert_fuzzy_eq!(RoundingRule::HalfEven.round(2.675, eur), 2.68, tol);
+        assert_fuzzy_eq!(RoundingRule::HalfEven.round(2.665, eur), 2.66, tol);
+        assert_fuzzy_eq!(RoundingRule::HalfUp.round(-2.665, eur), -2.67, tol);
+        assert_fuzzy_eq!(RoundingRule::Truncate.round(0.29, eur), 0.29, tol);
+        assert_fuzzy_eq!(RoundingRule::Truncate.round(-1.239, eur), -1.23, tol);
+        assert_fuzzy_eq!(RoundingRule::HalfEven.round(12.5, jpy), 12., tol);
+        assert_fuzzy_eq!(RoundingRule::HalfUp.round(12.5, jpy), 13., tol);
+        assert_fuzzy_eq!(RoundingRule::HalfUp.round(1.2345, kwd), 1.235, tol);
+        assert_fuzzy_eq!(RoundingRule::None.round(1.2345, eur), 1.2345, tol);
+        assert_fuzzy_eq!(RoundingRule::Up.round(0.281, eur), 0.29, tol);
+        assert_fuzzy_eq!(RoundingRule::Up.round(-1.231, eur), -1.24, tol);
+        assert_fuzzy_eq!(RoundingRule::Up.round(0.29, eur), 0.29, tol);
+        let rule: RoundingRule = serde_json::from_str(r#""down""#).unwrap();
+        assert_eq!(rule, RoundingRule::Truncate);
+    }
+
+    #[test]
+    fn half_even_rounding_by_minor_units() {
+        let usd = Currency::from_str("USD").unwrap();
+        let jpy = Currency::from_str("JPY").unwrap();
+        let tol = 1e-12;
+        assert_fuzzy_eq!(RoundingRule::HalfEven.round(0.125, usd), 0.12, tol);
+        assert_fuzzy_eq!(RoundingRule::HalfEven.round(0.135, usd), 0.14, tol);
+        assert_fuzzy_eq!(RoundingRule::HalfEven.round(-0.125, usd), -0.12, tol);
+        assert_fuzzy_eq!(RoundingRule::HalfEven.round(1.0051, usd), 1.01, tol);
+        assert_fuzzy_eq!(RoundingRule::HalfEven.round(1234.5, jpy), 1234., tol);
+        assert_fuzzy_eq!(RoundingRule::HalfEven.round(1235.5, jpy), 1236., tol);
+        assert_fuzzy_eq!(RoundingRule::HalfEven.round(-0.5, jpy), 0., tol);
+        assert_fuzzy_eq!(RoundingRule::HalfEven.round(99.49, jpy), 99., tol);
+    }
+
     #[test]
     fn deserialize_currency() {
         let input = r#""EUR""#;
@@ -1,6 +1,11 @@
 ///! Implementation of a data handler trait to deal with global data
+use chrono::{DateTime, Utc};
+use std::any::Any;
 use std::fmt;
 
+/// Default number of transactions fetched at once when iterating over all transactions
+pub const DEFAULT_PAGE_SIZE: usize = 1000;
+
 #[derive(Debug)]
 pub enum DataError {
     DataAccessFailure(String),
@@ -9,6 +14,15 @@ pub enum DataError {
     DeleteFailed(String),
     InsertFailed(String),
     InvalidTransaction(String),
+    InvalidAsset(String),
+    /// Reading or writing a file failed
+    Io(String),
+    /// Data could not be (de)serialized
+    Serialization(String),
+    /// The operation is not supported by the data handler
+    Unsupported(String),
+    /// An id is out of the range of ids supported by the data handler
+    InvalidId(String),
 }
 
 impl std::error::Error for DataError {
@@ -26,15 +40,70 @@ impl fmt::Display for DataError {
             Self::DeleteFailed(err) => write!(f, "removing object from database failed: {}", err),
             Self::InsertFailed(err) => write!(f, "inserting object to database failed: {}", err),
             Self::InvalidTransaction(err) => write!(f, "invalid transaction type: {}", err),
+            Self::InvalidAsset(err) => write!(f, "invalid asset definition: {}", err),
+            Self::Io(err) => write!(f, "file access failed: {}", err),
+            Self::Serialization(err) => write!(f, "serialization of data failed: {}", err),
+            Self::Unsupported(err) => write!(f, "operation not supported: {}", err),
+            Self::InvalidId(id) => write!(f, "id out of supported range: {}", id),
         }
     }
 }
 
+impl From<std::io::Error> for DataError {
+    fn from(err: std::io::Error) -> DataError {
+        DataError::Io(err.to_string())
+    }
+}
+
+impl From<serde_json::Error> for DataError {
+    fn from(err: serde_json::Error) -> DataError {
+        DataError::Serialization(err.to_string())
+    }
+}
+
 pub trait DataItem {
     // get id or return error if id hasn't been set yet
-    fn get_id(&self) -> Result<usize, DataError>;
+    fn get_id(&self) -> Result<u64, DataError>;
     // set id or return error if id has already been set
-    fn set_id(&mut self, id: usize) -> Result<(), DataError>;
+    fn set_id(&mut self, id: u64) -> Result<(), DataError>;
+}
+
+/// State of a data handler at some point in time, which could be restored later.
+/// The content is specific to the data handler that created the snapshot.
+pub struct DataSnapshot {
+    data: Box<dyn Any>,
+}
+
+impl DataSnapshot {
+    pub fn new<T: Any>(data: T) -> DataSnapshot {
+        DataSnapshot {
+            data: Box::new(data),
+        }
+    }
+
+    /// Get the content of the snapshot, or an error if the snapshot
+    /// has been created by a different kind of data handler
+    pub fn into_inner<T: Any>(self) -> Result<T, DataError> {
+        self.data.downcast::<T>().map(|data| *data).map_err(|_| {
+            DataError::Unsupported("snapshot was created by another data handler".to_string())
+        })
+    }
+}
+
+/// Source of the current time, e.g. for audit time stamps.
+/// Data handlers use the system clock by default, but another clock
+/// may be injected, e.g. for deterministic tests.
+pub trait Clock {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// Clock returning the current system time
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
 }
 
 pub mod quote_handler;
@@ -1,33 +1,131 @@
 ///! Data handler trait for market quotes
-use super::DataError;
+use super::{DataError, DataHandler};
+use crate::calendar::Calendar;
 use crate::currency::Currency;
 use crate::quote::{MarketDataSource, Quote, Ticker};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
+use std::collections::HashSet;
 
 /// Handler for globally available market quotes data
-pub trait QuoteHandler {
+/// Since tickers refer to assets, any quote handler must also be a `DataHandler`.
+pub trait QuoteHandler: DataHandler {
     // insert, get, update and delete for market data sources
-    fn insert_md_source(&mut self, source: &MarketDataSource) -> Result<usize, DataError>;
-    fn get_md_source_by_id(&mut self, id: usize) -> Result<MarketDataSource, DataError>;
+    fn insert_md_source(&mut self, source: &MarketDataSource) -> Result<u64, DataError>;
+    fn get_md_source_by_id(&mut self, id: u64) -> Result<MarketDataSource, DataError>;
     fn get_all_md_sources(&mut self) -> Result<Vec<MarketDataSource>, DataError>;
     fn update_md_source(&mut self, source: &MarketDataSource) -> Result<(), DataError>;
-    fn delete_md_source(&mut self, id: usize) -> Result<(), DataError>;
+    fn delete_md_source(&mut self, id: u64) -> Result<(), DataError>;
 
     // insert, get, update and delete for market data sources
-    fn insert_ticker(&mut self, ticker: &Ticker) -> Result<usize, DataError>;
-    fn get_ticker_by_id(&mut self, id: usize) -> Result<Ticker, DataError>;
-    fn get_all_ticker_for_source(&mut self, source_id: usize) -> Result<Vec<Ticker>, DataError>;
+    fn insert_ticker(&mut self, ticker: &Ticker) -> Result<u64, DataError>;
+    fn get_ticker_by_id(&mut self, id: u64) -> Result<Ticker, DataError>;
+    fn get_all_ticker_for_source(&mut self, source_id: u64) -> Result<Vec<Ticker>, DataError>;
+    /// Get all tickers of a source which should be considered for quote updates,
+    /// i.e. tickers of inactive assets are skipped.
+    fn get_tickers_to_update(&mut self, source_id: u64) -> Result<Vec<Ticker>, DataError> {
+        let active: Vec<u64> = self
+            .get_active_assets()?
+            .iter()
+            .filter_map(|a| a.id)
+            .collect();
+        let tickers = self.get_all_ticker_for_source(source_id)?;
+        Ok(tickers
+            .into_iter()
+            .filter(|t| active.contains(&t.asset))
+            .collect())
+    }
+    /// Get all tickers of all sources which should be updated at `now` according to
+    /// their update policy and the time of their latest quote before `now`.
+    /// Business days are defined by `cal`, tickers of inactive assets are skipped.
+    fn tickers_due_for_update(
+        &mut self,
+        now: DateTime<Utc>,
+        cal: &Calendar,
+    ) -> Result<Vec<Ticker>, DataError> {
+        let mut due = Vec::new();
+        for source in self.get_all_md_sources()? {
+            let source_id = match source.id {
+                Some(id) => id,
+                None => continue,
+            };
+            for ticker in self.get_tickers_to_update(source_id)? {
+                let ticker_id = match ticker.id {
+                    Some(id) => id,
+                    None => continue,
+                };
+                let last = match self.get_last_quote_before(ticker_id, now) {
+                    Ok((quote, _)) => Some(quote.time),
+                    Err(DataError::NotFound(_)) => None,
+                    Err(err) => return Err(err),
+                };
+                if ticker.update_policy.is_due(last, now, cal) {
+                    due.push(ticker);
+                }
+            }
+        }
+        Ok(due)
+    }
     fn update_ticker(&mut self, ticker: &Ticker) -> Result<(), DataError>;
-    fn delete_ticker(&mut self, id: usize) -> Result<(), DataError>;
+    fn delete_ticker(&mut self, id: u64) -> Result<(), DataError>;
 
     // insert, get, update and delete for market data sources
-    fn insert_quote(&mut self, quote: &Quote) -> Result<usize, DataError>;
+    fn insert_quote(&mut self, quote: &Quote) -> Result<u64, DataError>;
     fn get_last_quote_before(
         &mut self,
-        ticker_id: usize,
+        ticker_id: u64,
+        time: DateTime<Utc>,
+    ) -> Result<(Quote, Currency), DataError>;
+    /// Get the last quote before `time` of any ticker related to the given asset
+    fn get_last_quote_before_by_id(
+        &mut self,
+        asset_id: u64,
         time: DateTime<Utc>,
     ) -> Result<(Quote, Currency), DataError>;
-    fn get_all_quotes_for_ticker(&mut self, ticker_id: usize) -> Result<Vec<Quote>, DataError>;
+    fn get_all_quotes_for_ticker(&mut self, ticker_id: u64) -> Result<Vec<Quote>, DataError>;
     fn update_quote(&mut self, quote: &Quote) -> Result<(), DataError>;
-    fn delete_quote(&mut self, id: usize) -> Result<(), DataError>;
+    fn delete_quote(&mut self, id: u64) -> Result<(), DataError>;
+
+    /// Get all quotes of all tickers related to the given asset, ordered by time
+    fn get_all_quotes_for_asset(&mut self, asset_id: u64) -> Result<Vec<Quote>, DataError> {
+        let mut quotes = Vec::new();
+        for source in self.get_all_md_sources()? {
+            let source_id = match source.id {
+                Some(id) => id,
+                None => continue,
+            };
+            for ticker in self.get_all_ticker_for_source(source_id)? {
+                if let (true, Some(ticker_id)) = (ticker.asset == asset_id, ticker.id) {
+                    quotes.extend(self.get_all_quotes_for_ticker(ticker_id)?);
+                }
+            }
+        }
+        quotes.sort_by_key(|quote| quote.time);
+        Ok(quotes)
+    }
+
+    /// Get all business days of `cal` from `from` to `to` (inclusively) without any quote
+    /// for the given asset by any of its tickers, e.g. to detect missing price data.
+    /// The date of a quote is the date of its time stamp in UTC.
+    fn quote_gaps(
+        &mut self,
+        asset_id: u64,
+        from: NaiveDate,
+        to: NaiveDate,
+        cal: &Calendar,
+    ) -> Result<Vec<NaiveDate>, DataError> {
+        let quoted: HashSet<NaiveDate> = self
+            .get_all_quotes_for_asset(asset_id)?
+            .iter()
+            .map(|quote| quote.time.naive_utc().date())
+            .collect();
+        let mut gaps = Vec::new();
+        let mut date = from;
+        while date <= to {
+            if cal.is_business_day(date) && !quoted.contains(&date) {
+                gaps.push(date);
+            }
+            date = date.succ();
+        }
+        Ok(gaps)
+    }
 }
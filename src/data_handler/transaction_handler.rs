@@ -1,20 +1,141 @@
-use super::DataError;
-use crate::asset::Asset;
-use crate::transaction::Transaction;
+use super::{DataError, DataSnapshot, DEFAULT_PAGE_SIZE};
+use crate::asset::{rank_search_results, Asset, InstrumentType};
+use crate::transaction::{check_transfer_legs, Transaction, TransactionFilter, TransactionType};
 
 /// Handler for globally available data of transactions and related data
 pub trait DataHandler {
     // insert, get, update and delete for assets
-    fn insert_asset(&mut self, asset: &Asset) -> Result<usize, DataError>;
-    fn get_asset_by_id(&mut self, id: usize) -> Result<Asset, DataError>;
+    fn insert_asset(&mut self, asset: &Asset) -> Result<u64, DataError>;
+    fn get_asset_by_id(&mut self, id: u64) -> Result<Asset, DataError>;
+    fn get_asset_by_name(&mut self, name: &str) -> Result<Asset, DataError>;
     fn get_all_assets(&mut self) -> Result<Vec<Asset>, DataError>;
+    /// Get all assets with an instrument definition of the given kind
+    fn get_assets_by_instrument_type(
+        &mut self,
+        kind: InstrumentType,
+    ) -> Result<Vec<Asset>, DataError>;
+    /// Search assets by substring matching on name and ISIN, case-insensitive for ASCII characters.
+    /// Results are ordered by match quality, prefix matches first, followed by
+    /// matches ordered by position of the match.
+    fn search_assets(&mut self, query: &str, limit: usize) -> Result<Vec<Asset>, DataError>;
+    /// Like `search_assets`, but assets with a name or ISIN within an edit distance
+    /// of 2 to the query are found as well, ranked after all substring matches.
+    fn search_assets_fuzzy(&mut self, query: &str, limit: usize) -> Result<Vec<Asset>, DataError> {
+        let assets = self.get_all_assets()?;
+        Ok(rank_search_results(assets, query, limit, true))
+    }
+    /// Get all assets labeled with `tag`
+    fn get_assets_by_tag(&mut self, tag: &str) -> Result<Vec<Asset>, DataError> {
+        let assets = self.get_all_assets()?;
+        Ok(assets.into_iter().filter(|a| a.has_tag(tag)).collect())
+    }
+    /// Get all assets which are still active, e.g. to be considered for quote updates
+    fn get_active_assets(&mut self) -> Result<Vec<Asset>, DataError> {
+        let assets = self.get_all_assets()?;
+        Ok(assets.into_iter().filter(|a| a.active).collect())
+    }
     fn update_asset(&mut self, asset: &Asset) -> Result<(), DataError>;
-    fn delete_asset(&mut self, id: usize) -> Result<(), DataError>;
+    fn delete_asset(&mut self, id: u64) -> Result<(), DataError>;
 
     // insert, get, update and delete for transactions
-    fn insert_transaction(&mut self, transaction: &Transaction) -> Result<usize, DataError>;
-    fn get_transaction_by_id(&mut self, id: usize) -> Result<Transaction, DataError>;
+    fn insert_transaction(&mut self, transaction: &Transaction) -> Result<u64, DataError>;
+    fn get_transaction_by_id(&mut self, id: u64) -> Result<Transaction, DataError>;
     fn get_all_transactions(&mut self) -> Result<Vec<Transaction>, DataError>;
+    /// Get up to `limit` transactions with id strictly greater than `after`, ordered by id.
+    /// Paging by the last id received is stable even if new transactions are inserted.
+    fn get_transactions_after_id(
+        &mut self,
+        after: u64,
+        limit: usize,
+    ) -> Result<Vec<Transaction>, DataError>;
+    /// Number of transactions fetched at once when iterating over all transactions
+    fn transaction_page_size(&self) -> usize {
+        DEFAULT_PAGE_SIZE
+    }
+    /// Call `f` for each transaction ordered by id, without loading all transactions
+    /// at once. Transactions are fetched in pages of `transaction_page_size`.
+    /// Iteration stops at the first error, which is returned.
+    fn for_each_transaction(
+        &mut self,
+        f: &mut dyn FnMut(&Transaction) -> Result<(), DataError>,
+    ) -> Result<(), DataError> {
+        let page_size = self.transaction_page_size();
+        let mut after = 0;
+        loop {
+            let page = self.get_transactions_after_id(after, page_size)?;
+            for transaction in &page {
+                f(transaction)?;
+            }
+            match page.last() {
+                Some(last) if page.len() == page_size => after = last.id.unwrap_or(u64::MAX),
+                _ => return Ok(()),
+            }
+        }
+    }
+    /// Get all transactions labeled with `tag`, which is normalized before comparison
+    fn get_transactions_by_tag(&mut self, tag: &str) -> Result<Vec<Transaction>, DataError> {
+        let mut transactions = Vec::new();
+        self.for_each_transaction(&mut |transaction| {
+            if transaction.has_tag(tag) {
+                transactions.push(transaction.clone());
+            }
+            Ok(())
+        })?;
+        Ok(transactions)
+    }
+    /// Get all transactions with a note containing `text`, ignoring case
+    fn search_transactions(&mut self, text: &str) -> Result<Vec<Transaction>, DataError> {
+        let mut transactions = Vec::new();
+        self.for_each_transaction(&mut |transaction| {
+            if transaction.note_contains(text) {
+                transactions.push(transaction.clone());
+            }
+            Ok(())
+        })?;
+        Ok(transactions)
+    }
+    /// Get all transactions matching `filter`, ordered by id
+    fn get_transactions_matching(
+        &mut self,
+        filter: &TransactionFilter,
+    ) -> Result<Vec<Transaction>, DataError> {
+        let mut transactions = Vec::new();
+        self.for_each_transaction(&mut |transaction| {
+            if transaction.matches(filter) {
+                transactions.push(transaction.clone());
+            }
+            Ok(())
+        })?;
+        Ok(transactions)
+    }
     fn update_transaction(&mut self, transaction: &Transaction) -> Result<(), DataError>;
-    fn delete_transaction(&mut self, id: usize) -> Result<(), DataError>;
+    /// Check that a transfer referring to another leg matches this stored leg, see
+    /// `check_transfer_legs`. Called before transactions are inserted or updated.
+    fn validate_transfer(&mut self, transaction: &Transaction) -> Result<(), DataError> {
+        if let TransactionType::Transfer {
+            transaction_ref: Some(id),
+        } = transaction.transaction_type
+        {
+            let other = self
+                .get_transaction_by_id(id)
+                .map_err(|e| DataError::InvalidTransaction(e.to_string()))?;
+            check_transfer_legs(transaction, &other)?;
+        }
+        Ok(())
+    }
+    fn delete_transaction(&mut self, id: u64) -> Result<(), DataError>;
+
+    /// Take a snapshot of all stored data, e.g. before a bulk import, to be able to roll back
+    /// to this state via `restore`. Not supported by default.
+    fn snapshot(&self) -> Result<DataSnapshot, DataError> {
+        Err(DataError::Unsupported(
+            "data handler does not support snapshots".to_string(),
+        ))
+    }
+    /// Restore the state of a snapshot taken before, discarding all changes made since then
+    fn restore(&mut self, _snapshot: DataSnapshot) -> Result<(), DataError> {
+        Err(DataError::Unsupported(
+            "data handler does not support snapshots".to_string(),
+        ))
+    }
 }
@@ -5,7 +5,7 @@ use serde::{Deserialize, Serialize};
 /// Rules to adjust dates to business days
 /// The rule "Modified Preceding" commonly referred to in text books
 /// was intentionally left out since
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default)]
 pub enum DayAdjust {
     #[serde(rename = "none")]
     None,
@@ -16,6 +16,7 @@ pub enum DayAdjust {
     /// Next business day, if it falls in the same month, otherwise preceding business day
     #[serde(rename = "modified")]
     #[serde(alias = "modified following")]
+    #[default]
     Modified,
 }
 
@@ -64,7 +65,7 @@ mod tests {
             Holiday::SingularDay(NaiveDate::from_ymd(2019, 10, 10)),
             Holiday::SingularDay(NaiveDate::from_ymd(2019, 10, 31)),
         ];
-        let cal = Calendar::calc_calendar(&holidays, 2019, 2019);
+        let cal = Calendar::calc_calendar(&holidays, 2019, 2019).unwrap();
         let rule = DayAdjust::None;
         assert_eq!(
             rule.adjust_date(NaiveDate::from_ymd(2019, 10, 1), &cal),
@@ -7,12 +7,13 @@ use std::error::Error;
 use std::fmt::{self, Display, Formatter};
 
 /// Specify a day count method
-#[derive(Deserialize, Serialize, Clone, Copy, Debug)]
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default)]
 pub enum DayCountConv {
     #[serde(rename = "icma")]
     #[serde(alias = "act/act icma")]
     #[serde(alias = "Act/Act")]
     #[serde(alias = "Act/Act ICMA")]
+    #[default]
     ActActICMA,
     #[serde(rename = "act/365")]
     #[serde(alias = "Act/365f")]
@@ -121,24 +122,47 @@ impl DayCountConv {
             / DayCountConv::days_in_year(start.year()) as f64
     }
 
+    /// Number of days between `period_start` and `settlement` as counted by the day count
+    /// convention, i.e. the numerator of the year fraction. This is the actual number of
+    /// days for Act/xxx conventions and the number of adjusted days for 30/360 conventions.
+    pub fn accrued_days(&self, period_start: NaiveDate, settlement: NaiveDate) -> i64 {
+        match self {
+            DayCountConv::D30_360 => DayCountConv::days_30_360(period_start, settlement),
+            DayCountConv::D30E360 => DayCountConv::days_30_e_360(period_start, settlement),
+            _ => settlement.signed_duration_since(period_start).num_days(),
+        }
+    }
+
     /// Implementation of 30/360 day count method
     fn calc_30_360(start: NaiveDate, end: NaiveDate) -> f64 {
-        let yf = (end.year() - start.year()) as f64 + (end.month() - start.month()) as f64 / 12.;
-        let start_day = std::cmp::min(start.day(), 30) as i32;
+        DayCountConv::days_30_360(start, end) as f64 / 360.
+    }
+
+    /// Number of days between two dates according to 30/360
+    fn days_30_360(start: NaiveDate, end: NaiveDate) -> i64 {
+        let start_day = std::cmp::min(start.day(), 30) as i64;
         let end_day = if start_day == 30 && end.day() == 31 {
             30
         } else {
-            end.day()
-        } as i32;
-        yf + (end_day - start_day) as f64 / 360.
+            end.day() as i64
+        };
+        DayCountConv::days_360(start, end) + end_day - start_day
     }
 
     /// Implementation of 30E/360 day count method
     fn calc_30_e_360(start: NaiveDate, end: NaiveDate) -> f64 {
-        (end.year() - start.year()) as f64
-            + (end.month() - start.month()) as f64 / 12.
-            + (std::cmp::min(end.day(), 30) as i32 - std::cmp::min(start.day(), 30) as i32) as f64
-                / 360.
+        DayCountConv::days_30_e_360(start, end) as f64 / 360.
+    }
+
+    /// Number of days between two dates according to 30E/360
+    fn days_30_e_360(start: NaiveDate, end: NaiveDate) -> i64 {
+        DayCountConv::days_360(start, end) + std::cmp::min(end.day(), 30) as i64
+            - std::cmp::min(start.day(), 30) as i64
+    }
+
+    /// Number of days of full years and months between two dates in 30/360 style
+    fn days_360(start: NaiveDate, end: NaiveDate) -> i64 {
+        360 * (end.year() - start.year()) as i64 + 30 * (end.month() as i64 - start.month() as i64)
     }
 
     fn calc_act_act_icma(
@@ -1069,4 +1093,35 @@ mod tests {
             tol
         );
     }
+
+    #[test]
+    fn accrued_days_act_vs_30_360() {
+        // settlement at month end, 31 days in January and 29 days in February 2020
+        let start = NaiveDate::from_ymd(2020, 1, 15);
+        let settlement = NaiveDate::from_ymd(2020, 2, 29);
+        assert_eq!(DayCountConv::Act365.accrued_days(start, settlement), 45);
+        assert_eq!(DayCountConv::D30_360.accrued_days(start, settlement), 44);
+        assert_eq!(DayCountConv::D30E360.accrued_days(start, settlement), 44);
+
+        let start = NaiveDate::from_ymd(2019, 12, 31);
+        let settlement = NaiveDate::from_ymd(2020, 3, 31);
+        assert_eq!(DayCountConv::Act365.accrued_days(start, settlement), 91);
+        assert_eq!(DayCountConv::Act360.accrued_days(start, settlement), 91);
+        assert_eq!(DayCountConv::D30_360.accrued_days(start, settlement), 90);
+        assert_eq!(DayCountConv::D30E360.accrued_days(start, settlement), 90);
+
+        // the numerator is consistent with the year fraction
+        let dcc = DayCountConv::Act365;
+        assert_fuzzy_eq!(
+            dcc.year_fraction(start, settlement, None, None).unwrap(),
+            dcc.accrued_days(start, settlement) as f64 / 365.,
+            1e-12
+        );
+        let dcc = DayCountConv::D30_360;
+        assert_fuzzy_eq!(
+            dcc.year_fraction(start, settlement, None, None).unwrap(),
+            dcc.accrued_days(start, settlement) as f64 / 360.,
+            1e-12
+        );
+    }
 }
@@ -0,0 +1,395 @@
+//! Fixed point decimal numbers for exact amounts of money
+//!
+//! With the `decimal` feature, `CashAmount` is a `Decimal` with `DECIMAL_PLACES` digits,
+//! i.e. amounts of cash flows and transactions are stored, rolled out and aggregated
+//! exactly, such that e.g. adding up many cents yields exact results. This applies to
+//! the in-memory data handler, rolled out cash flows of bonds, cash balances and external
+//! cash flows, the monthly sums of rolled out cash flows, the netting of trades, the cash
+//! balances of the P&L attribution and income reports. Pricing and curve calculations
+//! stay in `f64`, as do the amount columns of the SQL data handlers; amounts are converted
+//! with `amount_to_f64` and `amount_from_f64` at these boundaries.
+
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Neg, Sub, SubAssign};
+use std::str::FromStr;
+
+/// Number of decimal places stored
+pub const DECIMAL_PLACES: u32 = 10;
+
+const SCALE: i128 = 10_i128.pow(DECIMAL_PLACES);
+
+/// Error parsing a decimal number
+#[derive(Debug, PartialEq)]
+pub struct ParseDecimalError(String);
+
+impl fmt::Display for ParseDecimalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid decimal number '{}'", self.0)
+    }
+}
+
+impl std::error::Error for ParseDecimalError {}
+
+/// Decimal number with a fixed number of decimal places
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Decimal {
+    /// Value multiplied by 10^DECIMAL_PLACES
+    mantissa: i128,
+}
+
+impl Decimal {
+    pub const ZERO: Decimal = Decimal { mantissa: 0 };
+
+    /// Convert a floating point number, rounded to `DECIMAL_PLACES` digits
+    pub fn from_f64(value: f64) -> Decimal {
+        Decimal {
+            mantissa: (value * SCALE as f64).round() as i128,
+        }
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.mantissa as f64 / SCALE as f64
+    }
+
+    pub fn abs(self) -> Decimal {
+        Decimal {
+            mantissa: self.mantissa.abs(),
+        }
+    }
+}
+
+impl From<f64> for Decimal {
+    fn from(value: f64) -> Decimal {
+        Decimal::from_f64(value)
+    }
+}
+
+impl Add for Decimal {
+    type Output = Decimal;
+    fn add(self, other: Decimal) -> Decimal {
+        Decimal {
+            mantissa: self.mantissa + other.mantissa,
+        }
+    }
+}
+
+impl AddAssign for Decimal {
+    fn add_assign(&mut self, other: Decimal) {
+        self.mantissa += other.mantissa;
+    }
+}
+
+impl Sub for Decimal {
+    type Output = Decimal;
+    fn sub(self, other: Decimal) -> Decimal {
+        Decimal {
+            mantissa: self.mantissa - other.mantissa,
+        }
+    }
+}
+
+impl SubAssign for Decimal {
+    fn sub_assign(&mut self, other: Decimal) {
+        self.mantissa -= other.mantissa;
+    }
+}
+
+impl Neg for Decimal {
+    type Output = Decimal;
+    fn neg(self) -> Decimal {
+        Decimal {
+            mantissa: -self.mantissa,
+        }
+    }
+}
+
+impl Sum for Decimal {
+    fn sum<I: Iterator<Item = Decimal>>(iter: I) -> Decimal {
+        iter.fold(Decimal::ZERO, |a, b| a + b)
+    }
+}
+
+impl fmt::Display for Decimal {
+    /// Display the number without trailing zeros, or rounded to the given precision
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut abs = self.mantissa.abs();
+        let mut places = DECIMAL_PLACES as usize;
+        if let Some(precision) = f.precision() {
+            if precision < places {
+                let factor = 10_i128.pow((places - precision) as u32);
+                abs = (abs + factor / 2) / factor;
+                places = precision;
+            }
+        }
+        let sign = if self.mantissa < 0 && abs != 0 {
+            "-"
+        } else {
+            ""
+        };
+        let scale = 10_i128.pow(places as u32);
+        let frac = if places > 0 {
+            format!("{:0width$}", abs % scale, width = places)
+        } else {
+            String::new()
+        };
+        let frac = match f.precision() {
+            Some(precision) => format!("{:0<width$}", frac, width = precision),
+            None => frac.trim_end_matches('0').to_string(),
+        };
+        let digits = if frac.is_empty() {
+            (abs / scale).to_string()
+        } else {
+            format!("{}.{}", abs / scale, frac)
+        };
+        f.pad_integral(sign.is_empty(), "", &digits)
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = ParseDecimalError;
+    fn from_str(s: &str) -> Result<Decimal, ParseDecimalError> {
+        let err = || ParseDecimalError(s.to_string());
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s),
+        };
+        let (int_part, frac_part) = match digits.find('.') {
+            Some(pos) => (&digits[..pos], &digits[pos + 1..]),
+            None => (digits, ""),
+        };
+        if int_part.is_empty()
+            || frac_part.len() > DECIMAL_PLACES as usize
+            || !int_part.chars().all(|c| c.is_ascii_digit())
+            || !frac_part.chars().all(|c| c.is_ascii_digit())
+        {
+            return Err(err());
+        }
+        let int_part: i128 = int_part.parse().map_err(|_| err())?;
+        let frac = format!("{:0<width$}", frac_part, width = DECIMAL_PLACES as usize);
+        let frac: i128 = frac.parse().map_err(|_| err())?;
+        let mantissa = int_part
+            .checked_mul(SCALE)
+            .and_then(|m| m.checked_add(frac))
+            .ok_or_else(err)?;
+        Ok(Decimal {
+            mantissa: if negative { -mantissa } else { mantissa },
+        })
+    }
+}
+
+impl Serialize for Decimal {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+/// Decimals are read from strings, or from numbers as written for `f64` amounts
+struct DecimalVisitor;
+
+impl<'de> Visitor<'de> for DecimalVisitor {
+    type Value = Decimal;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a decimal number as string or number")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Decimal, E> {
+        Decimal::from_str(value).map_err(E::custom)
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<Decimal, E> {
+        Ok(Decimal::from_f64(value))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Decimal, E> {
+        Ok(Decimal {
+            mantissa: i128::from(value) * SCALE,
+        })
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Decimal, E> {
+        Ok(Decimal {
+            mantissa: i128::from(value) * SCALE,
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for Decimal {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Decimal, D::Error> {
+        deserializer.deserialize_any(DecimalVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "persistence")]
+    fn exact_sum_of_cents() {
+        use crate::currency::Currency;
+        use crate::fixed_income::{AmountSum, CashFlow};
+        use crate::portfolio::{cash_balance_history, external_cash_flows};
+        use crate::transaction::{Transaction, TransactionType};
+        use chrono::NaiveDate;
+
+        let eur = Currency::from_str("EUR").unwrap();
+        let date = NaiveDate::from_ymd(2020, 1, 15);
+        let transactions: Vec<_> = (0..10_000)
+            .map(|_| Transaction::new(TransactionType::Cash, CashFlow::new(0.01, eur, date)))
+            .collect();
+        // summing with floating point numbers accumulates errors
+        let float_sum: f64 = transactions
+            .iter()
+            .map(|t| t.cash_flow.amount.value())
+            .sum();
+        assert_ne!(float_sum, 100.);
+
+        let sum: Decimal = transactions.iter().map(|t| t.cash_flow.amount.amount).sum();
+        assert_eq!(sum, Decimal::from_str("100.00").unwrap());
+        assert_eq!(format!("{:.2}", sum), "100.00");
+        assert_eq!(
+            cash_balance_history(&transactions, &eur),
+            vec![(date, 100.)]
+        );
+        assert_eq!(
+            external_cash_flows(&transactions, None, &eur),
+            vec![(date, 100.)]
+        );
+        let sum: AmountSum = transactions.iter().map(|t| t.cash_flow.amount.amount).sum();
+        assert_eq!(sum.value(), 100.);
+    }
+
+    #[test]
+    #[cfg(all(feature = "decimal", feature = "persistence"))]
+    fn decimal_amounts_in_store_and_rollout() {
+        use crate::bond::Bond;
+        use crate::currency::Currency;
+        use crate::data_handler::DataHandler;
+        use crate::fixed_income::{Amount, CashFlow, FixedIncome};
+        use crate::market::Market;
+        use crate::memory_handler::InMemoryDB;
+        use crate::transaction::{Transaction, TransactionType};
+        use chrono::NaiveDate;
+
+        let eur = Currency::from_str("EUR").unwrap();
+        let amount = Decimal::from_str("1234567.0123456789").unwrap();
+        let cash_flow = CashFlow {
+            amount: Amount {
+                amount,
+                currency: eur,
+            },
+            date: NaiveDate::from_ymd(2020, 1, 15),
+        };
+        let mut db = InMemoryDB::new();
+        let id = db
+            .insert_transaction(&Transaction::new(TransactionType::Cash, cash_flow))
+            .unwrap();
+        assert_eq!(
+            db.get_transaction_by_id(id)
+                .unwrap()
+                .cash_flow
+                .amount
+                .amount,
+            amount
+        );
+        let path =
+            std::env::temp_dir().join(format!("finql_decimal_db_{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        db.save_to_file(path).unwrap();
+        let mut loaded = InMemoryDB::load_from_file(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(
+            loaded
+                .get_transaction_by_id(id)
+                .unwrap()
+                .cash_flow
+                .amount
+                .amount,
+            amount
+        );
+
+        let data = r#"{
+            "bond_type": "bond",
+            "currency": "EUR",
+            "coupon" : {
+                "coupon_type": "fixed",
+                "rate": 5,
+                "coupon_date": "01.10",
+                "period": "1Y",
+                "day_count_convention": "icma"
+            },
+            "business_day_rule": "none",
+            "calendar": "TARGET",
+            "issue_date": "2019-10-01",
+            "maturity": "2022-10-01",
+            "denomination": 1000,
+            "amortization": "linear",
+            "rounding": "half up"
+        }"#;
+        let bond: Bond = serde_json::from_str(data).unwrap();
+        let cash_flows = bond.rollout_cash_flows(1., &Market::new()).unwrap();
+        let principal: Vec<Decimal> = [1, 3, 5]
+            .iter()
+            .map(|&i| cash_flows[i].amount.amount)
+            .collect();
+        assert_eq!(
+            principal,
+            vec![
+                Decimal::from_str("333.33").unwrap(),
+                Decimal::from_str("333.33").unwrap(),
+                Decimal::from_str("333.34").unwrap(),
+            ]
+        );
+        let repaid: Decimal = principal.into_iter().sum();
+        assert_eq!(repaid, Decimal::from_str("1000").unwrap());
+
+        let ids: Vec<u64> = cash_flows
+            .iter()
+            .map(|cf| {
+                loaded
+                    .insert_transaction(&Transaction::new(TransactionType::Cash, *cf))
+                    .unwrap()
+            })
+            .collect();
+        for (id, cf) in ids.into_iter().zip(cash_flows.iter()) {
+            assert_eq!(
+                loaded
+                    .get_transaction_by_id(id)
+                    .unwrap()
+                    .cash_flow
+                    .amount
+                    .amount,
+                cf.amount.amount
+            );
+        }
+    }
+
+    #[test]
+    fn parse_and_display_decimals() {
+        let x = Decimal::from_str("-1234.5").unwrap();
+        assert_eq!(x.to_string(), "-1234.5");
+        assert_eq!(format!("{:.2}", x), "-1234.50");
+        assert_eq!(format!("{:.0}", x), "-1235");
+        assert_eq!(format!("{:.2}", Decimal::from(0.125)), "0.13");
+        assert_eq!(x + Decimal::from(0.5), Decimal::from_str("-1234").unwrap());
+        assert_eq!(
+            Decimal::from(0.1) + Decimal::from(0.2),
+            Decimal::from_str("0.3").unwrap()
+        );
+        assert!(Decimal::from_str("1.2.3").is_err());
+        assert!(Decimal::from_str("abc").is_err());
+        let json = serde_json::to_string(&x).unwrap();
+        assert_eq!(json, "\"-1234.5\"");
+        assert_eq!(serde_json::from_str::<Decimal>(&json).unwrap(), x);
+        assert_eq!(serde_json::from_str::<Decimal>("-1234.5").unwrap(), x);
+        assert_eq!(
+            serde_json::from_str::<Decimal>("42").unwrap(),
+            Decimal::from_str("42").unwrap()
+        );
+        assert_eq!(format!("{:10.2}", x), "  -1234.50");
+    }
+}
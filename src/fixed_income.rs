@@ -1,7 +1,9 @@
 use crate::currency::Currency;
-use crate::day_count_conv::DayCountConv;
+use crate::day_count_conv::{DayCountConv, DayCountConvError};
+#[cfg(feature = "decimal")]
+use crate::decimal::Decimal;
 use crate::market::Market;
-use crate::rates::{Compounding, DiscountError, Discounter, FlatRate};
+use crate::rates::{Compounding, DiscountCurve, DiscountError, Discounter, FlatRate};
 use argmin::prelude::*;
 use argmin::solver::brent::Brent;
 use chrono::NaiveDate;
@@ -10,10 +12,42 @@ use std::f64;
 use std::fmt;
 use std::fmt::{Display, Formatter};
 
+/// Number type of amounts of money. With the `decimal` feature, amounts are stored as
+/// `Decimal`, such that they are stored, rolled out and aggregated exactly, otherwise
+/// as `f64`. Pricing and curve calculations convert amounts to `f64` at their boundary.
+#[cfg(feature = "decimal")]
+pub type CashAmount = Decimal;
+#[cfg(not(feature = "decimal"))]
+pub type CashAmount = f64;
+
+/// Convert a floating point number, e.g. a calculated price, to an amount of money
+pub fn amount_from_f64(value: f64) -> CashAmount {
+    #[cfg(feature = "decimal")]
+    {
+        Decimal::from_f64(value)
+    }
+    #[cfg(not(feature = "decimal"))]
+    {
+        value
+    }
+}
+
+/// Convert an amount of money to `f64`, e.g. for pricing
+pub fn amount_to_f64(amount: CashAmount) -> f64 {
+    #[cfg(feature = "decimal")]
+    {
+        amount.to_f64()
+    }
+    #[cfg(not(feature = "decimal"))]
+    {
+        amount
+    }
+}
+
 /// Container for an amount of money in some currency
 #[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
 pub struct Amount {
-    pub amount: f64,
+    pub amount: CashAmount,
     pub currency: Currency,
 }
 
@@ -23,6 +57,128 @@ impl Display for Amount {
     }
 }
 
+/// Separators of thousands and decimals used to format numbers
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum NumberStyle {
+    /// E.g. 1,234.56
+    #[default]
+    English,
+    /// E.g. 1.234,56
+    German,
+}
+
+/// How to display the currency of an amount
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum CurrencyStyle {
+    /// ISO code after the number, e.g. 1,234.56 EUR
+    #[default]
+    IsoCode,
+    /// Symbol before the number, e.g. €1,234.56; falls back to the ISO code
+    /// for currencies without a symbol
+    Symbol,
+}
+
+/// How to display negative amounts
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum NegativeStyle {
+    /// Leading minus sign, e.g. -1,234.56 EUR
+    #[default]
+    MinusSign,
+    /// Enclosed in parentheses as common in accounting, e.g. (1,234.56 EUR)
+    Parentheses,
+}
+
+/// Format specification of amounts in reports
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct AmountFormat {
+    pub number: NumberStyle,
+    pub currency: CurrencyStyle,
+    pub negative: NegativeStyle,
+}
+
+impl Amount {
+    /// The amount as floating point number, e.g. for pricing
+    pub fn value(&self) -> f64 {
+        amount_to_f64(self.amount)
+    }
+
+    /// Format the amount with thousands separators and as many decimals as
+    /// the currency's minor unit
+    pub fn format(&self, style: AmountFormat) -> String {
+        let digits = self.currency.minor_units() as usize;
+        let number = format!("{:.*}", digits, self.amount.abs());
+        let (int_part, frac_part) = match number.find('.') {
+            Some(pos) => (&number[..pos], Some(&number[pos + 1..])),
+            None => (number.as_str(), None),
+        };
+        let (thousands, decimal) = match style.number {
+            NumberStyle::English => (',', '.'),
+            NumberStyle::German => ('.', ','),
+        };
+        let mut formatted = String::new();
+        for (i, c) in int_part.chars().enumerate() {
+            if i > 0 && (int_part.len() - i) % 3 == 0 {
+                formatted.push(thousands);
+            }
+            formatted.push(c);
+        }
+        if let Some(frac_part) = frac_part {
+            formatted.push(decimal);
+            formatted.push_str(frac_part);
+        }
+        let formatted = match (style.currency, self.currency.symbol()) {
+            (CurrencyStyle::Symbol, Some(symbol)) => format!("{}{}", symbol, formatted),
+            _ => format!("{} {}", formatted, self.currency),
+        };
+        // a negative amount might be rounded to zero
+        let is_negative = self.value() < 0. && number.chars().any(|c| c != '0' && c != '.');
+        match (is_negative, style.negative) {
+            (false, _) => formatted,
+            (true, NegativeStyle::MinusSign) => format!("-{}", formatted),
+            (true, NegativeStyle::Parentheses) => format!("({})", formatted),
+        }
+    }
+}
+
+/// Running sum of amounts of money, e.g. a cash balance. With the `decimal` feature,
+/// amounts are added as decimal numbers, such that the sum is exact (up to the decimal
+/// places of `Decimal`), otherwise as floating point numbers.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AmountSum {
+    sum: CashAmount,
+}
+
+impl AmountSum {
+    /// Add an amount to the sum
+    pub fn add(&mut self, amount: CashAmount) {
+        self.sum += amount;
+    }
+
+    /// Current sum as amount of money
+    pub fn amount(&self) -> CashAmount {
+        self.sum
+    }
+
+    /// Current value of the sum as floating point number, e.g. for reports
+    pub fn value(&self) -> f64 {
+        amount_to_f64(self.sum)
+    }
+}
+
+impl From<CashAmount> for AmountSum {
+    fn from(amount: CashAmount) -> AmountSum {
+        AmountSum { sum: amount }
+    }
+}
+
+impl std::iter::Sum<CashAmount> for AmountSum {
+    fn sum<I: Iterator<Item = CashAmount>>(iter: I) -> AmountSum {
+        let mut sum = AmountSum::default();
+        iter.for_each(|amount| sum.add(amount));
+        sum
+    }
+}
+
 /// Container for a single cash flow
 #[derive(Deserialize, Serialize, Debug, Clone, Copy)]
 pub struct CashFlow {
@@ -31,10 +187,13 @@ pub struct CashFlow {
 }
 
 impl CashFlow {
-    /// Construct new cash flow
+    /// Construct new cash flow, converting the amount to `CashAmount`
     pub fn new(amount: f64, currency: Currency, date: NaiveDate) -> CashFlow {
         CashFlow {
-            amount: Amount { amount, currency },
+            amount: Amount {
+                amount: amount_from_f64(amount),
+                currency,
+            },
             date,
         }
     }
@@ -53,9 +212,9 @@ impl CashFlow {
     pub fn fuzzy_cash_flows_cmp_eq(&self, cf: &CashFlow, tol: f64) -> bool {
         if !self.aggregatable(cf) {
             false
-        } else if self.amount.amount.is_nan()
-            || cf.amount.amount.is_nan()
-            || (self.amount.amount - cf.amount.amount).abs() > tol
+        } else if self.amount.value().is_nan()
+            || cf.amount.value().is_nan()
+            || (self.amount.value() - cf.amount.value()).abs() > tol
         {
             false
         } else {
@@ -81,6 +240,93 @@ pub fn get_cash_flows_after(cash_flows: &Vec<CashFlow>, date: NaiveDate) -> Vec<
     new_cash_flows
 }
 
+/// Price of a fixed income product
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BondPrice {
+    /// Present value of all future cash flows
+    pub dirty: f64,
+    /// Dirty price without accrued interest
+    pub clean: f64,
+    pub accrued: f64,
+}
+
+/// Calculate the price of a bond at the settlement date by discounting all cash flows
+/// after the settlement date with the given curve. The clean price is derived from the
+/// dirty price by subtracting the accrued interest.
+pub fn price_bond_curve(
+    flows: &[CashFlow],
+    curve: &DiscountCurve,
+    settlement: NaiveDate,
+    accrued: f64,
+) -> Result<BondPrice, DiscountError> {
+    let mut dirty = 0.;
+    for cf in flows.iter().filter(|cf| cf.date > settlement) {
+        dirty += curve.discount_cash_flow(cf, settlement)?.value();
+    }
+    Ok(BondPrice {
+        dirty,
+        clean: dirty - accrued,
+        accrued,
+    })
+}
+
+/// Calculate the weighted average life in years, i.e. the average time until repayment
+/// weighted by the repaid principal. Only principal repayments must be given, interest
+/// payments have to be excluded, e.g. by using `Bond::principal_cash_flows`.
+/// Repayments on or before the `valuation` date are ignored; if there are none left,
+/// the weighted average life is zero. The day count convention must not require
+/// a roll date or time period, i.e. Act/Act ICMA is not supported.
+pub fn weighted_average_life(
+    principal_flows: &[CashFlow],
+    valuation: NaiveDate,
+    dc: DayCountConv,
+) -> Result<f64, DayCountConvError> {
+    let mut weighted_time = 0.;
+    let mut principal = 0.;
+    for cf in principal_flows.iter().filter(|cf| cf.date > valuation) {
+        let time = dc.year_fraction(valuation, cf.date, None, None)?;
+        weighted_time += time * cf.amount.value();
+        principal += cf.amount.value();
+    }
+    if principal == 0. {
+        Ok(0.)
+    } else {
+        Ok(weighted_time / principal)
+    }
+}
+
+/// Coupon payments on `notional` for the periods between consecutive dates of `schedule`,
+/// paid at the end of each period, with a coupon rate that changes over time.
+/// `coupon_steps` are pairs of the date from which on a rate applies and the rate as decimal
+/// fraction (e.g. 0.02 for 2%), sorted by date. Each coupon uses the rate effective at the
+/// start of its period, periods starting before the first step use the first rate.
+/// Without any step, all coupons are zero.
+/// As for `weighted_average_life`, Act/Act ICMA is not supported.
+pub fn stepped_coupon_cash_flows(
+    schedule: &[NaiveDate],
+    notional: f64,
+    coupon_steps: &[(NaiveDate, f64)],
+    dc: DayCountConv,
+    currency: Currency,
+) -> Result<Vec<CashFlow>, DayCountConvError> {
+    let mut cash_flows = Vec::new();
+    for period in schedule.windows(2) {
+        let (start, end) = (period[0], period[1]);
+        let steps_started = coupon_steps.partition_point(|(date, _)| *date <= start);
+        let rate = match coupon_steps.get(steps_started.saturating_sub(1)) {
+            Some((_, rate)) => *rate,
+            None => 0.,
+        };
+        let year_fraction = dc.year_fraction(start, end, None, None)?;
+        cash_flows.push(CashFlow::new(
+            notional * rate * year_fraction,
+            currency,
+            end,
+        ));
+    }
+    Ok(cash_flows)
+}
+
 pub trait FixedIncome {
     type Error: std::convert::From<DiscountError>;
 
@@ -156,11 +402,11 @@ impl<'a> ArgminOp for FlatRateDiscounter<'a> {
     fn apply(&self, p: &Self::Param) -> Result<Self::Output, Error> {
         let mut discount_rate = self.rate.clone();
         discount_rate.rate = *p;
-        let mut sum = self.init_cash_flow.amount.amount;
+        let mut sum = self.init_cash_flow.amount.value();
         let today = self.init_cash_flow.date;
         for cf in self.cash_flows.clone() {
             if cf.date > today {
-                sum += discount_rate.discount_cash_flow(&cf, today)?.amount;
+                sum += discount_rate.discount_cash_flow(&cf, today)?.value();
             }
         }
         Ok(sum)
@@ -206,4 +452,144 @@ mod tests {
         let ytm = calculate_cash_flows_ytm(&cash_flows, &init_cash_flow).unwrap();
         assert_fuzzy_eq!(ytm, 0.05, tol);
     }
+
+    #[test]
+    fn curve_and_yield_price_agree() {
+        let curr = Currency::from_str("EUR").unwrap();
+        let mut cash_flows = Vec::new();
+        for year in 2020..2025 {
+            cash_flows.push(CashFlow::new(5., curr, NaiveDate::from_ymd(year, 3, 1)));
+        }
+        cash_flows.push(CashFlow::new(100., curr, NaiveDate::from_ymd(2024, 3, 1)));
+        let settlement = NaiveDate::from_ymd(2020, 6, 15);
+        let accrued = 5. * 106. / 365.;
+
+        // flat curve equivalent to an annually compounded yield of 4%
+        let curve = DiscountCurve::flat(settlement, f64::ln(1.04), DayCountConv::Act365, curr);
+        let price = price_bond_curve(&cash_flows, &curve, settlement, accrued).unwrap();
+        assert_fuzzy_eq!(price.clean, price.dirty - accrued, 1e-12);
+
+        let ytm =
+            calculate_cash_flows_ytm(&cash_flows, &CashFlow::new(-price.dirty, curr, settlement))
+                .unwrap();
+        assert_fuzzy_eq!(ytm, 0.04, 1e-8);
+        let flat_rate = FlatRate::new(ytm, DayCountConv::Act365, Compounding::Annual, curr);
+        let future_cash_flows = get_cash_flows_after(&cash_flows, settlement);
+        let yield_price = flat_rate
+            .discount_cash_flow_stream(&future_cash_flows, settlement)
+            .unwrap();
+        assert_fuzzy_eq!(price.dirty, yield_price.value(), 1e-6);
+    }
+
+    #[test]
+    fn format_amounts() {
+        let english_iso = AmountFormat::default();
+        let english_symbol = AmountFormat {
+            currency: CurrencyStyle::Symbol,
+            ..AmountFormat::default()
+        };
+        let german_iso = AmountFormat {
+            number: NumberStyle::German,
+            ..AmountFormat::default()
+        };
+        let accounting = AmountFormat {
+            negative: NegativeStyle::Parentheses,
+            ..AmountFormat::default()
+        };
+        let cases = vec![
+            ("EUR", 1234.56, english_iso, "1,234.56 EUR"),
+            ("EUR", 1234.56, english_symbol, "€1,234.56"),
+            ("EUR", 1234.56, german_iso, "1.234,56 EUR"),
+            ("EUR", 1234.56, accounting, "1,234.56 EUR"),
+            ("EUR", -1234.56, english_iso, "-1,234.56 EUR"),
+            ("EUR", -1234.56, english_symbol, "-€1,234.56"),
+            ("EUR", -1234.56, german_iso, "-1.234,56 EUR"),
+            ("EUR", -1234.56, accounting, "(1,234.56 EUR)"),
+            ("USD", 1234567.891, english_iso, "1,234,567.89 USD"),
+            ("USD", 1234567.891, english_symbol, "$1,234,567.89"),
+            ("USD", 1234567.891, german_iso, "1.234.567,89 USD"),
+            ("USD", -999.999, accounting, "(1,000.00 USD)"),
+            ("JPY", 1234567., english_iso, "1,234,567 JPY"),
+            ("JPY", 1234567., english_symbol, "¥1,234,567"),
+            ("JPY", 1234567., german_iso, "1.234.567 JPY"),
+            ("JPY", -123., accounting, "(123 JPY)"),
+            ("CHF", 12.5, english_symbol, "12.50 CHF"),
+            ("EUR", -0.001, english_iso, "0.00 EUR"),
+        ];
+        for (currency, amount, style, expected) in cases {
+            let amount = Amount {
+                amount: amount_from_f64(amount),
+                currency: Currency::from_str(currency).unwrap(),
+            };
+            assert_eq!(amount.format(style), expected);
+        }
+    }
+
+    #[test]
+    fn weighted_average_life_of_two_repayments() {
+        let curr = Currency::from_str("EUR").unwrap();
+        let valuation = NaiveDate::from_ymd(2020, 1, 1);
+        let principal_flows = vec![
+            // already repaid
+            CashFlow::new(20., curr, NaiveDate::from_ymd(2019, 7, 1)),
+            CashFlow::new(30., curr, NaiveDate::from_ymd(2021, 1, 1)),
+            CashFlow::new(50., curr, NaiveDate::from_ymd(2023, 1, 1)),
+        ];
+        let wal = weighted_average_life(&principal_flows, valuation, DayCountConv::Act365).unwrap();
+        // 366 and 1096 days until repayment
+        let expected = (30. * 366. / 365. + 50. * 1096. / 365.) / 80.;
+        assert_fuzzy_eq!(wal, expected, 1e-12);
+
+        let wal = weighted_average_life(
+            &principal_flows,
+            NaiveDate::from_ymd(2023, 1, 1),
+            DayCountConv::Act365,
+        )
+        .unwrap();
+        assert_eq!(wal, 0.);
+        assert!(
+            weighted_average_life(&principal_flows, valuation, DayCountConv::ActActICMA).is_err()
+        );
+    }
+
+    #[test]
+    fn step_up_coupons() {
+        let eur = Currency::from_str("EUR").unwrap();
+        let schedule: Vec<NaiveDate> = (2020..=2025)
+            .map(|year| NaiveDate::from_ymd(year, 1, 15))
+            .collect();
+        let steps = vec![
+            (NaiveDate::from_ymd(2020, 1, 15), 0.02),
+            (NaiveDate::from_ymd(2023, 1, 15), 0.03),
+        ];
+        let cash_flows =
+            stepped_coupon_cash_flows(&schedule, 10000., &steps, DayCountConv::D30_360, eur)
+                .unwrap();
+        assert_eq!(cash_flows.len(), 5);
+        for (cf, (year, amount)) in cash_flows.iter().zip(&[
+            (2021, 200.),
+            (2022, 200.),
+            (2023, 200.),
+            (2024, 300.),
+            (2025, 300.),
+        ]) {
+            assert_eq!(cf.date, NaiveDate::from_ymd(*year, 1, 15));
+            assert_eq!(cf.amount.currency, eur);
+            assert_fuzzy_eq!(cf.amount.value(), *amount, 1e-8);
+        }
+        // a step within a period applies from the next period on
+        let steps = vec![
+            (NaiveDate::from_ymd(2019, 1, 1), 0.02),
+            (NaiveDate::from_ymd(2023, 6, 1), 0.03),
+        ];
+        let cash_flows =
+            stepped_coupon_cash_flows(&schedule, 10000., &steps, DayCountConv::D30_360, eur)
+                .unwrap();
+        assert_fuzzy_eq!(cash_flows[3].amount.value(), 200., 1e-8);
+        assert_fuzzy_eq!(cash_flows[4].amount.value(), 300., 1e-8);
+        assert!(
+            stepped_coupon_cash_flows(&schedule, 1., &steps, DayCountConv::ActActICMA, eur)
+                .is_err()
+        );
+    }
 }
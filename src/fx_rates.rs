@@ -0,0 +1,261 @@
+//! Foreign exchange rates
+//! Foreign exchange rates are stored like any other market quote. The quoted asset
+//! is named after the currency pair, e.g. the asset "EUR/USD" is quoted in USD and
+//! gives the price of one EUR in USD.
+
+use crate::asset::Asset;
+use crate::calendar::{Calendar, CalendarError};
+use crate::currency::Currency;
+use crate::data_handler::{DataError, DataItem, QuoteHandler};
+use crate::quote::{Quote, Ticker, UpdatePeriod};
+use chrono::{DateTime, NaiveDate, Utc};
+
+/// Name of the asset representing the currency pair `foreign`/`base`
+pub fn fx_asset_name(foreign: Currency, base: Currency) -> String {
+    format!("{}/{}", foreign, base)
+}
+
+/// Get the last foreign exchange rate before `time` that converts an amount
+/// in currency `foreign` into currency `base`. If only the inverse currency pair
+/// is quoted, the inverse of that rate is returned.
+pub fn get_fx_rate(
+    foreign: Currency,
+    base: Currency,
+    time: DateTime<Utc>,
+    fx: &mut dyn QuoteHandler,
+) -> Result<f64, DataError> {
+    if foreign == base {
+        return Ok(1.0);
+    }
+    if let Some(price) = last_price_by_name(&fx_asset_name(foreign, base), time, fx)? {
+        return Ok(price);
+    }
+    if let Some(price) = last_price_by_name(&fx_asset_name(base, foreign), time, fx)? {
+        if price != 0.0 {
+            return Ok(1.0 / price);
+        }
+    }
+    Err(DataError::NotFound(format!(
+        "no fx rate found for {}/{} before {}",
+        foreign, base, time
+    )))
+}
+
+/// Last price before `time` of the asset named `name`, or `None` if either the asset or
+/// a quote could not be found. Any other error of the quote handler is returned.
+fn last_price_by_name(
+    name: &str,
+    time: DateTime<Utc>,
+    fx: &mut dyn QuoteHandler,
+) -> Result<Option<f64>, DataError> {
+    let asset = match fx.get_asset_by_name(name) {
+        Ok(asset) => asset,
+        Err(DataError::NotFound(_)) => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    match fx.get_last_quote_before_by_id(asset.get_id()?, time) {
+        Ok((quote, _)) => Ok(Some(quote.price)),
+        Err(DataError::NotFound(_)) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Store a foreign exchange rate as quote for the currency pair `foreign`/`base`.
+/// The asset and ticker representing the currency pair are created if necessary.
+pub fn insert_fx_quote(
+    fx_rate: f64,
+    foreign: Currency,
+    base: Currency,
+    source: u64,
+    time: DateTime<Utc>,
+    fx: &mut dyn QuoteHandler,
+) -> Result<u64, DataError> {
+    let name = fx_asset_name(foreign, base);
+    let asset_id = match fx.get_asset_by_name(&name) {
+        Ok(asset) => asset.get_id()?,
+        Err(_) => fx.insert_asset(&Asset::new(None, &name, None, None, None))?,
+    };
+    let ticker = fx
+        .get_all_ticker_for_source(source)?
+        .into_iter()
+        .find(|ticker| ticker.asset == asset_id);
+    let ticker_id = match ticker {
+        Some(ticker) => ticker.get_id()?,
+        None => fx.insert_ticker(&Ticker {
+            id: None,
+            name: name.clone(),
+            asset: asset_id,
+            currency: base,
+            source,
+            update_policy: UpdatePeriod::Daily,
+        })?,
+    };
+    fx.insert_quote(&Quote {
+        id: None,
+        ticker: ticker_id,
+        price: fx_rate,
+        time,
+        volume: None,
+    })
+}
+
+/// Maximal number of consecutive days without business day searched for a spot date
+const MAX_NON_BUSINESS_DAYS: usize = 30;
+
+/// First day at or after `date` for which `is_business_day` holds, if any within
+/// `MAX_NON_BUSINESS_DAYS`
+fn next_business_day(
+    date: NaiveDate,
+    is_business_day: impl Fn(NaiveDate) -> bool,
+) -> Result<NaiveDate, CalendarError> {
+    let mut next = date;
+    for _ in 0..MAX_NON_BUSINESS_DAYS {
+        if is_business_day(next) {
+            return Ok(next);
+        }
+        next = next.succ();
+    }
+    Err(CalendarError::NoBusinessDay(date))
+}
+
+/// Calculate the spot date of a FX trade, i.e. two business days after the `trade` date.
+/// Both days must be business days in the calendars `cal1` and `cal2` of the two
+/// currencies. For cross currency pairs, a USD holiday is allowed as the first day,
+/// but the spot date itself must not be a holiday in `usd_cal` as well, otherwise it
+/// is moved to the next day which is a business day in all calendars.
+/// Fails if the calendars have no common business day within a month.
+pub fn fx_spot_date(
+    trade: NaiveDate,
+    cal1: &Calendar,
+    cal2: &Calendar,
+    usd_cal: Option<&Calendar>,
+) -> Result<NaiveDate, CalendarError> {
+    let is_business_day = |date| cal1.is_business_day(date) && cal2.is_business_day(date);
+    let mut date = trade;
+    for _ in 0..2 {
+        date = next_business_day(date.succ(), is_business_day)?;
+    }
+    if let Some(usd_cal) = usd_cal {
+        date = next_business_day(date, |date| {
+            is_business_day(date) && usd_cal.is_business_day(date)
+        })?;
+    }
+    Ok(date)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calendar::Holiday;
+    use crate::helpers::make_time;
+    use crate::market::Market;
+    use crate::memory_handler::InMemoryDB;
+    use crate::quote::MarketDataSource;
+    use chrono::Weekday;
+    use std::str::FromStr;
+
+    #[test]
+    fn direct_and_inverse_fx_rates() {
+        let mut db = InMemoryDB::new();
+        let source = db
+            .insert_md_source(&MarketDataSource {
+                id: None,
+                name: "manual".to_string(),
+            })
+            .unwrap();
+        let eur = Currency::from_str("EUR").unwrap();
+        let usd = Currency::from_str("USD").unwrap();
+        let jpy = Currency::from_str("JPY").unwrap();
+        let time = make_time(2020, 1, 15, 18, 0, 0).unwrap();
+        insert_fx_quote(1.1, eur, usd, source, time, &mut db).unwrap();
+
+        let later = make_time(2020, 1, 16, 18, 0, 0).unwrap();
+        assert_fuzzy_eq!(get_fx_rate(eur, usd, later, &mut db).unwrap(), 1.1, 1e-12);
+        assert_fuzzy_eq!(
+            get_fx_rate(usd, eur, later, &mut db).unwrap(),
+            1. / 1.1,
+            1e-12
+        );
+        assert_fuzzy_eq!(get_fx_rate(eur, eur, later, &mut db).unwrap(), 1.0, 1e-12);
+        // no rate available before the first quote
+        let earlier = make_time(2020, 1, 14, 18, 0, 0).unwrap();
+        assert!(get_fx_rate(eur, usd, earlier, &mut db).is_err());
+        // unknown currency pair
+        assert!(get_fx_rate(jpy, usd, later, &mut db).is_err());
+    }
+
+    #[test]
+    fn fx_spot_date_across_uk_bank_holiday() {
+        let market = Market::new();
+        let target = market.get_calendar("TARGET").unwrap();
+        let uk = market.get_calendar("uk").unwrap();
+        // 2020-05-25 is the UK spring bank holiday, but no TARGET holiday
+        let trade = NaiveDate::from_ymd(2020, 5, 21);
+        assert_eq!(
+            fx_spot_date(trade, target, uk, None),
+            Ok(NaiveDate::from_ymd(2020, 5, 26))
+        );
+        let trade = NaiveDate::from_ymd(2020, 5, 22);
+        assert_eq!(
+            fx_spot_date(trade, target, uk, None),
+            Ok(NaiveDate::from_ymd(2020, 5, 27))
+        );
+
+        // Independence Day is observed on Friday, 2020-07-03
+        let usd = Calendar::calc_calendar(
+            &vec![
+                Holiday::WeekDay(Weekday::Sat),
+                Holiday::WeekDay(Weekday::Sun),
+                Holiday::SingularDay(NaiveDate::from_ymd(2020, 7, 3)),
+            ],
+            2020,
+            2020,
+        )
+        .unwrap();
+        let trade = NaiveDate::from_ymd(2020, 7, 1);
+        assert_eq!(
+            fx_spot_date(trade, target, uk, None),
+            Ok(NaiveDate::from_ymd(2020, 7, 3))
+        );
+        assert_eq!(
+            fx_spot_date(trade, target, uk, Some(&usd)),
+            Ok(NaiveDate::from_ymd(2020, 7, 6))
+        );
+        // a USD holiday as first day does not delay the spot date
+        let trade = NaiveDate::from_ymd(2020, 7, 2);
+        assert_eq!(
+            fx_spot_date(trade, target, uk, Some(&usd)),
+            Ok(NaiveDate::from_ymd(2020, 7, 6))
+        );
+    }
+
+    #[test]
+    fn fx_spot_date_without_business_days() {
+        let closed = Calendar::calc_calendar(
+            &[
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+                Weekday::Sat,
+                Weekday::Sun,
+            ]
+            .iter()
+            .map(|day| Holiday::WeekDay(*day))
+            .collect::<Vec<_>>(),
+            2020,
+            2020,
+        )
+        .unwrap();
+        let market = Market::new();
+        let target = market.get_calendar("TARGET").unwrap();
+        let trade = NaiveDate::from_ymd(2020, 7, 1);
+        assert_eq!(
+            fx_spot_date(trade, target, &closed, None),
+            Err(CalendarError::NoBusinessDay(NaiveDate::from_ymd(
+                2020, 7, 2
+            )))
+        );
+    }
+}
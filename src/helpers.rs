@@ -1,43 +1,43 @@
+#[cfg(feature = "persistence")]
 use crate::currency::Currency;
+#[cfg(feature = "persistence")]
 use crate::data_handler::DataError;
+#[cfg(feature = "persistence")]
 use crate::fixed_income::CashFlow;
 ///! Useful helper functions that do not belong to any other module
 use chrono::offset::TimeZone;
 use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, Utc};
+#[cfg(feature = "persistence")]
+use std::convert::TryFrom;
+#[cfg(feature = "persistence")]
 use std::str::FromStr;
 
-/// Transform optional `usize` to optional `i64`
-pub fn usize_to_i64(val: Option<usize>) -> Option<i64> {
-    match val {
-        Some(v) => Some(v as i64),
-        None => None,
-    }
+/// Transform id to `i64` as used by database backends, fails for ids beyond `i64::MAX`
+#[cfg(feature = "persistence")]
+pub fn to_db_id(id: u64) -> Result<i64, DataError> {
+    i64::try_from(id).map_err(|_| DataError::InvalidId(id.to_string()))
 }
 
-/// Transform optional `i64` to optional `usize`
-pub fn i64_to_usize(val: Option<i64>) -> Option<usize> {
-    match val {
-        Some(v) => Some(v as usize),
-        None => None,
-    }
+/// Transform `i64` from database backends to id, fails for negative values
+#[cfg(feature = "persistence")]
+pub fn from_db_id(id: i64) -> Result<u64, DataError> {
+    u64::try_from(id).map_err(|_| DataError::InvalidId(id.to_string()))
 }
 
-/// Transform optional `usize` to optional `i64`
-pub fn usize_to_i32(val: Option<usize>) -> Option<i32> {
-    match val {
-        Some(v) => Some(v as i32),
-        None => None,
-    }
+/// Transform optional id to optional `i64` as used by database backends
+#[cfg(feature = "persistence")]
+pub fn id_to_i64(val: Option<u64>) -> Result<Option<i64>, DataError> {
+    val.map(to_db_id).transpose()
 }
 
-/// Transform optional `i64` to optional `usize`
-pub fn i32_to_usize(val: Option<i32>) -> Option<usize> {
-    match val {
-        Some(v) => Some(v as usize),
-        None => None,
-    }
+/// Transform optional `i64` from database backends to optional id
+#[cfg(feature = "persistence")]
+pub fn i64_to_id(val: Option<i64>) -> Result<Option<u64>, DataError> {
+    val.map(from_db_id).transpose()
 }
+
 /// Construct cash flow from raw strings
+#[cfg(feature = "persistence")]
 pub fn raw_to_cash_flow(amount: f64, currency: &str, date: &str) -> Result<CashFlow, DataError> {
     let currency = Currency::from_str(currency).map_err(|e| DataError::NotFound(e.to_string()))?;
     let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")
@@ -46,6 +46,7 @@ pub fn raw_to_cash_flow(amount: f64, currency: &str, date: &str) -> Result<CashF
 }
 
 /// Convert string to DateTime<Utc>
+#[cfg(feature = "persistence")]
 pub fn to_time(time: &str) -> Result<DateTime<Utc>, DataError> {
     let time =
         DateTime::parse_from_rfc3339(time).map_err(|e| DataError::NotFound(e.to_string()))?;
@@ -70,3 +71,52 @@ pub fn make_time(
         None => None,
     }
 }
+
+/// Convert a date into the UTC time at the very end of that day,
+/// e.g. to find the last quote available on that date
+pub fn end_of_day(date: NaiveDate) -> DateTime<Utc> {
+    Utc.from_utc_datetime(&date.and_hms(23, 59, 59))
+}
+
+/// Enclose a field in double quotes if it contains commas or double quotes
+pub(crate) fn quote_csv_field(field: &str) -> String {
+    if field.contains(',') || field.contains('"') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Split a line into its fields, removing surrounding white space and quotes
+pub(crate) fn split_csv_line(line: &str) -> Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted => {
+                if chars.peek() == Some(&'"') {
+                    field.push('"');
+                    chars.next();
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if field.trim().is_empty() => {
+                field.clear();
+                quoted = true;
+            }
+            ',' if !quoted => {
+                fields.push(field.trim().to_string());
+                field.clear();
+            }
+            _ => field.push(c),
+        }
+    }
+    if quoted {
+        return Err("unterminated quoted field".to_string());
+    }
+    fields.push(field.trim().to_string());
+    Ok(fields)
+}
@@ -0,0 +1,219 @@
+//! Reports of dividend income, e.g. for tax returns. Dividends are booked with their gross
+//! amount, taxes withheld at source are booked as tax transactions of kind
+//! `TaxKind::Withholding` referring to the dividend. The source country of a dividend is derived from the ISIN of the asset.
+
+use crate::currency::Currency;
+use crate::data_handler::{DataError, DataHandler};
+use crate::fixed_income::AmountSum;
+use crate::transaction::{TaxKind, Transaction, TransactionType};
+use std::collections::{BTreeMap, HashMap};
+
+/// Dividend income from a single source country in a single currency
+#[derive(Debug, Clone, PartialEq)]
+pub struct CountryIncome {
+    /// Country code of the source country, or `None` for assets without ISIN
+    pub country: Option<String>,
+    pub currency: Currency,
+    /// Dividends before withholding tax
+    pub gross: f64,
+    /// Tax withheld at source
+    pub withheld: f64,
+}
+
+impl CountryIncome {
+    /// Dividends after withholding tax
+    pub fn net(&self) -> f64 {
+        self.gross - self.withheld
+    }
+}
+
+/// Country code of an ISIN, i.e. its first two letters, if valid
+pub fn isin_country(isin: &str) -> Option<String> {
+    let code: String = isin.chars().take(2).collect();
+    if code.len() == 2 && code.chars().all(|c| c.is_ascii_uppercase()) {
+        Some(code)
+    } else {
+        None
+    }
+}
+
+/// Gross dividends, withheld taxes and net dividends of `transactions` per source country
+/// and currency, ordered by country and currency. Withholding taxes are the tax transactions
+/// of kind `TaxKind::Withholding` referring to one of the dividends, which must be in the
+/// same currency as the dividend; other taxes, e.g. domestic taxes on the dividend, are
+/// ignored.
+/// Assets are resolved with `db` to get their ISIN.
+pub fn dividend_income_by_country(
+    transactions: &[Transaction],
+    db: &mut dyn DataHandler,
+) -> Result<Vec<CountryIncome>, DataError> {
+    let mut countries: HashMap<u64, Option<String>> = HashMap::new();
+    // source country and currency of each dividend, by transaction id
+    let mut dividends: HashMap<u64, (Option<String>, Currency)> = HashMap::new();
+    // income with running sums of gross dividends and withheld taxes
+    let mut income: BTreeMap<(Option<String>, String), (CountryIncome, AmountSum, AmountSum)> =
+        BTreeMap::new();
+    for transaction in transactions {
+        if let TransactionType::Dividend { asset_id } = transaction.transaction_type {
+            let country = match countries.get(&asset_id) {
+                Some(country) => country.clone(),
+                None => {
+                    let asset = db.get_asset_by_id(asset_id)?;
+                    let country = asset.isin.as_deref().and_then(isin_country);
+                    countries.insert(asset_id, country.clone());
+                    country
+                }
+            };
+            let currency = transaction.cash_flow.amount.currency;
+            let entry = income
+                .entry((country.clone(), currency.to_string()))
+                .or_insert_with(|| {
+                    let income = CountryIncome {
+                        country: country.clone(),
+                        currency,
+                        gross: 0.,
+                        withheld: 0.,
+                    };
+                    (income, AmountSum::default(), AmountSum::default())
+                });
+            entry.1.add(transaction.cash_flow.amount.amount);
+            if let Some(id) = transaction.id {
+                dividends.insert(id, (country, currency));
+            }
+        }
+    }
+    for transaction in transactions {
+        if let TransactionType::Tax {
+            transaction_ref: Some(dividend_id),
+            kind: TaxKind::Withholding,
+        } = transaction.transaction_type
+        {
+            if let Some((country, currency)) = dividends.get(&dividend_id) {
+                if transaction.cash_flow.amount.currency != *currency {
+                    return Err(DataError::InvalidTransaction(format!(
+                        "withholding tax in {} for dividend in {}",
+                        transaction.cash_flow.amount.currency, currency
+                    )));
+                }
+                let entry = income
+                    .get_mut(&(country.clone(), currency.to_string()))
+                    .unwrap();
+                entry.2.add(-transaction.cash_flow.amount.amount);
+            }
+        }
+    }
+    Ok(income
+        .into_values()
+        .map(|(mut income, gross, withheld)| {
+            income.gross = gross.value();
+            income.withheld = withheld.value();
+            income
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::Asset;
+    use crate::memory_handler::InMemoryDB;
+    use crate::transaction_csv::store_transactions_csv;
+
+    #[test]
+    fn us_dividend_with_withholding_tax() {
+        assert_eq!(isin_country("US0378331005").as_deref(), Some("US"));
+        assert_eq!(isin_country("x"), None);
+
+        let mut db = InMemoryDB::new();
+        for (name, isin) in &[("Apple", "US0378331005"), ("BASF AG", "DE000BASF111")] {
+            db.insert_asset(&Asset::new(None, name, None, Some(isin.to_string()), None))
+                .unwrap();
+        }
+        db.insert_asset(&Asset::new(None, "Private Co", None, None, None))
+            .unwrap();
+        let csv = "date,asset_name,kind,quantity,price,fee,currency,note,withholding_tax
+2021-02-11,Apple,dividend,100,0.205,,USD,,3.075
+2021-05-13,Apple,dividend,100,0.22,,USD,,3.30
+2021-04-30,BASF AG,dividend,1,33,,EUR
+2021-06-01,Private Co,dividend,1,10,,EUR,,
+";
+        store_transactions_csv(csv.as_bytes(), &mut db, false).unwrap();
+        let transactions = db.get_all_transactions().unwrap();
+        assert_eq!(transactions.len(), 6);
+        assert!(matches!(
+            transactions[1].transaction_type,
+            TransactionType::Tax {
+                transaction_ref: Some(1),
+                kind: TaxKind::Withholding,
+            }
+        ));
+
+        let report = dividend_income_by_country(&transactions, &mut db).unwrap();
+        assert_eq!(report.len(), 3);
+        assert_eq!(report[0].country, None);
+        assert_eq!(report[0].gross, 10.);
+        assert_eq!(report[1].country.as_deref(), Some("DE"));
+        assert_eq!(report[1].withheld, 0.);
+        assert_eq!(report[1].net(), 33.);
+        let us = &report[2];
+        assert_eq!(us.country.as_deref(), Some("US"));
+        assert_eq!(us.currency.to_string(), "USD");
+        assert_fuzzy_eq!(us.gross, 42.5, 1e-10);
+        // 15% withholding tax
+        assert_fuzzy_eq!(us.withheld, 0.15 * 42.5, 1e-10);
+        assert_fuzzy_eq!(us.net(), us.gross - us.withheld, 1e-10);
+        assert_fuzzy_eq!(us.net(), 36.125, 1e-10);
+
+        // withholding tax must not exceed the dividend
+        let csv = "date,asset_name,kind,quantity,price,fee,currency,note,withholding_tax
+2021-08-12,Apple,dividend,100,0.22,,USD,,30
+";
+        assert!(store_transactions_csv(csv.as_bytes(), &mut db, false).is_err());
+        let csv = "date,asset_name,kind,quantity,price,fee,currency,note,withholding_tax
+2021-08-12,Apple,buy,100,150,,USD,,3
+";
+        assert!(store_transactions_csv(csv.as_bytes(), &mut db, false).is_err());
+    }
+
+    #[test]
+    fn domestic_tax_on_dividend_is_not_withheld() {
+        use crate::fixed_income::CashFlow;
+        use chrono::NaiveDate;
+        use std::str::FromStr;
+
+        let mut db = InMemoryDB::new();
+        let asset_id = db
+            .insert_asset(&Asset::new(
+                None,
+                "Apple",
+                None,
+                Some("US0378331005".to_string()),
+                None,
+            ))
+            .unwrap();
+        let usd = Currency::from_str("USD").unwrap();
+        let date = NaiveDate::from_ymd(2021, 2, 11);
+        let dividend_id = db
+            .insert_transaction(&Transaction::new(
+                TransactionType::Dividend { asset_id },
+                CashFlow::new(100., usd, date),
+            ))
+            .unwrap();
+        for (kind, amount) in &[(TaxKind::Withholding, -15.), (TaxKind::Domestic, -21.25)] {
+            let tax = TransactionType::Tax {
+                transaction_ref: Some(dividend_id),
+                kind: *kind,
+            };
+            db.insert_transaction(&Transaction::new(tax, CashFlow::new(*amount, usd, date)))
+                .unwrap();
+        }
+
+        let transactions = db.get_all_transactions().unwrap();
+        let report = dividend_income_by_country(&transactions, &mut db).unwrap();
+        assert_eq!(report.len(), 1);
+        assert_eq!(report[0].country.as_deref(), Some("US"));
+        assert_eq!(report[0].gross, 100.);
+        assert_eq!(report[0].withheld, 15.);
+        assert_eq!(report[0].net(), 85.);
+    }
+}
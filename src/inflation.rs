@@ -0,0 +1,346 @@
+//! Inflation indices and inflation-linked bonds, i.e. bonds whose coupons and redemption
+//! are scaled by the ratio of the reference index at payment to the reference index at
+//! the bond's base date. Reference index values are calculated from monthly fixings
+//! with a lag of three months and interpolated linearly within the month, as for
+//! TIPS or euro area linkers.
+
+use crate::bond::{Bond, BondError};
+use crate::calendar::last_day_of_month;
+#[cfg(feature = "persistence")]
+use crate::data_handler::{DataError, QuoteHandler};
+use crate::fixed_income::{CashFlow, FixedIncome};
+use crate::market::Market;
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+
+/// Number of months the reference index lags behind the fixing month
+pub const REFERENCE_LAG_MONTHS: i32 = 3;
+
+/// Error related to inflation indices and inflation-linked bonds
+#[derive(Debug)]
+pub enum InflationError {
+    /// The fixing of the given year and month is required, but not available
+    MissingFixing { year: i32, month: u32 },
+    /// The given year and month do not form a valid date
+    InvalidMonth { year: i32, month: u32 },
+    /// Rolling out the real cash flows of the bond failed
+    BondError(BondError),
+}
+
+impl fmt::Display for InflationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InflationError::MissingFixing { year, month } => {
+                write!(
+                    f,
+                    "missing inflation index fixing for {}-{:02}",
+                    year, month
+                )
+            }
+            InflationError::InvalidMonth { year, month } => {
+                write!(f, "invalid fixing month {}-{:02}", year, month)
+            }
+            InflationError::BondError(_) => write!(f, "rolling out bond cash flows failed"),
+        }
+    }
+}
+
+impl Error for InflationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            InflationError::BondError(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<BondError> for InflationError {
+    fn from(error: BondError) -> Self {
+        InflationError::BondError(error)
+    }
+}
+
+/// Month `n` months after the month of `date` (before, if `n` is negative)
+fn shift_month(date: NaiveDate, n: i32) -> NaiveDate {
+    let months = date.year() * 12 + date.month0() as i32 + n;
+    NaiveDate::from_ymd(months.div_euclid(12), months.rem_euclid(12) as u32 + 1, 1)
+}
+
+/// Monthly fixings of an inflation index, e.g. a consumer price index
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InflationIndex {
+    pub name: String,
+    /// Fixings by the first day of their month
+    fixings: BTreeMap<NaiveDate, f64>,
+}
+
+impl InflationIndex {
+    pub fn new(name: &str) -> InflationIndex {
+        InflationIndex {
+            name: name.to_string(),
+            fixings: BTreeMap::new(),
+        }
+    }
+
+    /// Load the fixings from the quotes of a ticker, where the month of the quote's time
+    /// is the fixing month. Of several quotes in a month, the last one is taken.
+    #[cfg(feature = "persistence")]
+    pub fn from_quotes(
+        name: &str,
+        db: &mut dyn QuoteHandler,
+        ticker_id: u64,
+    ) -> Result<InflationIndex, DataError> {
+        let mut index = InflationIndex::new(name);
+        let mut quotes = db.get_all_quotes_for_ticker(ticker_id)?;
+        quotes.sort_by_key(|quote| quote.time);
+        for quote in quotes {
+            let month = shift_month(quote.time.naive_utc().date(), 0);
+            index.fixings.insert(month, quote.price);
+        }
+        Ok(index)
+    }
+
+    /// Set the fixing of the given year and month, replacing any previous fixing.
+    /// Fails if `month` is not in the range 1 to 12 or the year is out of range.
+    pub fn add_fixing(&mut self, year: i32, month: u32, value: f64) -> Result<(), InflationError> {
+        let date = NaiveDate::from_ymd_opt(year, month, 1)
+            .ok_or(InflationError::InvalidMonth { year, month })?;
+        self.fixings.insert(date, value);
+        Ok(())
+    }
+
+    /// Fixing of the given year and month, if available
+    pub fn fixing(&self, year: i32, month: u32) -> Option<f64> {
+        NaiveDate::from_ymd_opt(year, month, 1).and_then(|date| self.fixings.get(&date).copied())
+    }
+
+    fn fixing_of_month(&self, month: NaiveDate) -> Result<f64, InflationError> {
+        self.fixings
+            .get(&month)
+            .copied()
+            .ok_or(InflationError::MissingFixing {
+                year: month.year(),
+                month: month.month(),
+            })
+    }
+
+    /// Copy of the index with fixings after the last known one projected up to the month of
+    /// `until`, assuming the given annual inflation `rate` (e.g. 0.02 for 2%).
+    /// An index without fixings could not be projected and is returned unchanged.
+    pub fn projected(&self, rate: f64, until: NaiveDate) -> InflationIndex {
+        let mut index = self.clone();
+        if let Some((&last_month, &last_value)) = self.fixings.iter().next_back() {
+            let mut months = 1;
+            let mut month = shift_month(last_month, 1);
+            while month <= until {
+                let value = last_value * (1. + rate).powf(months as f64 / 12.);
+                index.fixings.insert(month, value);
+                months += 1;
+                month = shift_month(month, 1);
+            }
+        }
+        index
+    }
+
+    /// Reference index at `date`, interpolated linearly between the fixings of three and two
+    /// months before the month of `date`, such that the first day of the month gets the
+    /// fixing of three months before
+    pub fn reference_index(&self, date: NaiveDate) -> Result<f64, InflationError> {
+        let first = self.fixing_of_month(shift_month(date, -REFERENCE_LAG_MONTHS))?;
+        let second = self.fixing_of_month(shift_month(date, 1 - REFERENCE_LAG_MONTHS))?;
+        let days = last_day_of_month(date.year(), date.month()) as f64;
+        Ok(first + (date.day() - 1) as f64 / days * (second - first))
+    }
+
+    /// Ratio of the reference index at `date` to the reference index at `base_date`
+    pub fn index_ratio(
+        &self,
+        date: NaiveDate,
+        base_date: NaiveDate,
+    ) -> Result<f64, InflationError> {
+        Ok(self.reference_index(date)? / self.reference_index(base_date)?)
+    }
+}
+
+/// Bond whose cash flows are linked to an inflation index. The terms of `bond` define the
+/// real cash flows, which are scaled by the index ratio relative to `base_date`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InflationLinkedBond {
+    pub bond: Bond,
+    /// Name of the inflation index, e.g. "CPURNSA" or "HICP ex tobacco"
+    pub index: String,
+    /// Date of the base reference index, usually the issue or dated date
+    pub base_date: NaiveDate,
+    /// If true, the redemption is paid at least at par in case of deflation
+    #[serde(default)]
+    pub deflation_floor: bool,
+}
+
+impl InflationLinkedBond {
+    /// Real cash flows of a position in the bond, i.e. without indexation
+    pub fn real_cash_flows(
+        &self,
+        position: f64,
+        market: &Market,
+    ) -> Result<Vec<CashFlow>, InflationError> {
+        Ok(self.bond.rollout_cash_flows(position, market)?)
+    }
+
+    /// Cash flows of a position in the bond, each scaled by the index ratio at its payment
+    /// date. Fixings which are not yet known are projected with the `assumed_inflation`
+    /// rate, if given, and otherwise a `MissingFixing` error is returned. With deflation
+    /// floor, principal repayments are never scaled below their real amount.
+    pub fn indexed_cash_flows(
+        &self,
+        position: f64,
+        index: &InflationIndex,
+        assumed_inflation: Option<f64>,
+        market: &Market,
+    ) -> Result<Vec<CashFlow>, InflationError> {
+        let (interest_cfs, principal_cfs) =
+            self.bond.interest_and_principal_flows(position, market)?;
+        let projected;
+        let index = match (assumed_inflation, principal_cfs.last()) {
+            (Some(rate), Some(last)) => {
+                projected = index.projected(rate, last.date);
+                &projected
+            }
+            _ => index,
+        };
+        let mut cash_flows = Vec::with_capacity(interest_cfs.len() + principal_cfs.len());
+        for cf in &interest_cfs {
+            let ratio = index.index_ratio(cf.date, self.base_date)?;
+            cash_flows.push(CashFlow::new(
+                cf.amount.value() * ratio,
+                cf.amount.currency,
+                cf.date,
+            ));
+        }
+        for cf in &principal_cfs {
+            let mut ratio = index.index_ratio(cf.date, self.base_date)?;
+            if self.deflation_floor {
+                ratio = ratio.max(1.);
+            }
+            cash_flows.push(CashFlow::new(
+                cf.amount.value() * ratio,
+                cf.amount.currency,
+                cf.date,
+            ));
+        }
+        cash_flows.sort_by_key(|cf| cf.date);
+        Ok(cash_flows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::currency::Currency;
+    use std::str::FromStr;
+
+    /// US CPI-U (not seasonally adjusted) from October 2020 to February 2021
+    fn cpi() -> InflationIndex {
+        let mut cpi = InflationIndex::new("CPURNSA");
+        cpi.add_fixing(2020, 10, 260.388).unwrap();
+        cpi.add_fixing(2020, 11, 260.229).unwrap();
+        cpi.add_fixing(2020, 12, 260.474).unwrap();
+        cpi.add_fixing(2021, 1, 261.582).unwrap();
+        cpi.add_fixing(2021, 2, 263.014).unwrap();
+        cpi
+    }
+
+    #[test]
+    fn invalid_fixing_month() {
+        let mut index = cpi();
+        assert!(matches!(
+            index.add_fixing(2021, 13, 264.877),
+            Err(InflationError::InvalidMonth {
+                year: 2021,
+                month: 13
+            })
+        ));
+        assert_eq!(index, cpi());
+    }
+
+    #[test]
+    fn index_ratio_with_lag_and_interpolation() {
+        let cpi = cpi();
+        // April 15th: January fixing plus 14/30 of the change to February
+        let reference = cpi
+            .reference_index(NaiveDate::from_ymd(2021, 4, 15))
+            .unwrap();
+        assert_fuzzy_eq!(reference, 262.25026667, 1e-8);
+        // January 15th: October fixing plus 14/31 of the change to November
+        let base = cpi
+            .reference_index(NaiveDate::from_ymd(2021, 1, 15))
+            .unwrap();
+        assert_fuzzy_eq!(base, 260.31619355, 1e-8);
+        let ratio = cpi
+            .index_ratio(
+                NaiveDate::from_ymd(2021, 4, 15),
+                NaiveDate::from_ymd(2021, 1, 15),
+            )
+            .unwrap();
+        assert_fuzzy_eq!(ratio, 1.00742971, 1e-8);
+        // first of month uses the fixing three months before
+        assert_eq!(
+            cpi.reference_index(NaiveDate::from_ymd(2021, 4, 1))
+                .unwrap(),
+            261.582
+        );
+        assert!(matches!(
+            cpi.reference_index(NaiveDate::from_ymd(2021, 5, 1)),
+            Err(InflationError::MissingFixing {
+                year: 2021,
+                month: 3
+            })
+        ));
+    }
+
+    #[test]
+    fn indexed_and_projected_cash_flows() {
+        let usd = Currency::from_str("USD").unwrap();
+        let market = Market::new();
+        let bond = Bond::builder()
+            .issue_date(NaiveDate::from_ymd(2021, 1, 15))
+            .maturity(NaiveDate::from_ymd(2023, 1, 15))
+            .coupon(0.01)
+            .currency(usd)
+            .business_day_rule(crate::day_adjust::DayAdjust::None)
+            .build(&market)
+            .unwrap();
+        let mut linker = InflationLinkedBond {
+            bond,
+            index: "CPURNSA".to_string(),
+            base_date: NaiveDate::from_ymd(2021, 1, 15),
+            deflation_floor: false,
+        };
+        let real = linker.real_cash_flows(1., &market).unwrap();
+        assert_eq!(real.len(), 3);
+
+        let cpi = cpi();
+        assert!(linker.indexed_cash_flows(1., &cpi, None, &market).is_err());
+        let cash_flows = linker
+            .indexed_cash_flows(1., &cpi, Some(0.02), &market)
+            .unwrap();
+        assert_eq!(cash_flows.len(), 3);
+        // fixings for October and November 2022 projected from February 2021 at 2% p.a.
+        let base = 260.388 + 14. / 31. * (260.229 - 260.388);
+        let first = 263.014 * 1.02_f64.powf(20. / 12.);
+        let second = 263.014 * 1.02_f64.powf(21. / 12.);
+        let ratio = (first + 14. / 31. * (second - first)) / base;
+        assert_fuzzy_eq!(cash_flows[2].amount.value(), 1000. * ratio, 1e-8);
+        assert_fuzzy_eq!(cash_flows[1].amount.value(), 10. * ratio, 1e-8);
+
+        // deflation floors the redemption, but not the coupons
+        linker.deflation_floor = true;
+        let cash_flows = linker
+            .indexed_cash_flows(1., &cpi, Some(-0.05), &market)
+            .unwrap();
+        assert_eq!(cash_flows[2].amount.value(), 1000.);
+        assert!(cash_flows[1].amount.value() < 10.);
+    }
+}
@@ -23,28 +23,73 @@
 //! defining data handler traits for various data categories, with concrete implementations supporting
 //! storage in memory or in a databases (supporting `sqlite3` and `postgreSQL`).
 //!
+//! Data handlers and everything built on stored assets, transactions and quotes (portfolio
+//! analysis, P&L, taxes, risk figures and market quote updates) are enabled by the default
+//! feature `persistence`, the database handlers by the default features `sqlite` and
+//! `postgresql`. Without default features, the library is checked to compile to WebAssembly
+//! with `cargo check --target wasm32-unknown-unknown --no-default-features`, e.g. to run
+//! calendar, day count and bond calculations in the browser.
+//!
 
 // macro exports
 #[macro_use]
 pub mod macros;
 
 // module exports
+#[cfg(feature = "persistence")]
 pub mod asset;
 pub mod bond;
 pub mod calendar;
+#[cfg(feature = "persistence")]
+pub mod cash_interest;
+pub mod cds;
 pub mod coupon_date;
 pub mod currency;
+#[cfg(feature = "persistence")]
 pub mod data_handler;
 pub mod day_adjust;
 pub mod day_count_conv;
+#[cfg(feature = "decimal")]
+pub mod decimal;
 pub mod fixed_income;
+#[cfg(feature = "persistence")]
+pub mod fx_rates;
 pub mod helpers;
+#[cfg(feature = "persistence")]
+pub mod income;
+pub mod inflation;
 pub mod market;
+#[cfg(feature = "persistence")]
+pub mod market_quotes;
+pub mod math;
+#[cfg(feature = "persistence")]
 pub mod memory_handler;
+pub mod optimize;
+pub mod options;
+#[cfg(feature = "persistence")]
+pub mod pnl;
+#[cfg(feature = "persistence")]
 pub mod portfolio;
+#[cfg(feature = "postgresql")]
 pub mod postgres_handler;
+pub mod projection;
+#[cfg(feature = "persistence")]
 pub mod quote;
 pub mod rates;
+#[cfg(feature = "persistence")]
+pub mod rebalance;
+#[cfg(feature = "persistence")]
+pub mod risk;
+#[cfg(feature = "persistence")]
+pub mod scenario;
+pub mod schedule;
+pub mod series;
+#[cfg(feature = "sqlite")]
 pub mod sqlite_handler;
+#[cfg(feature = "persistence")]
+pub mod tax;
 pub mod time_period;
+#[cfg(feature = "persistence")]
 pub mod transaction;
+#[cfg(feature = "persistence")]
+pub mod transaction_csv;
@@ -65,6 +65,7 @@ fn generate_calendars() -> HashMap<String, Calendar> {
             day: 1,
             first: None,
             last: None,
+            direction: None,
         },
         // Good Friday
         Holiday::EasterOffset {
@@ -138,6 +139,7 @@ fn generate_calendars() -> HashMap<String, Calendar> {
             day: 25,
             first: None,
             last: None,
+            direction: None,
         },
         // Boxing Day
         Holiday::MovableYearlyDay {
@@ -145,6 +147,7 @@ fn generate_calendars() -> HashMap<String, Calendar> {
             day: 26,
             first: None,
             last: None,
+            direction: None,
         },
         // Golden Jubilee
         Holiday::SingularDay(NaiveDate::from_ymd(2002, 6, 3)),
@@ -159,7 +162,7 @@ fn generate_calendars() -> HashMap<String, Calendar> {
         // Introduction of EUR
         Holiday::SingularDay(NaiveDate::from_ymd(1999, 12, 31)),
     ];
-    let uk_cal = Calendar::calc_calendar(&uk_settlement_holidays, 1990, 2050);
+    let uk_cal = Calendar::calc_calendar(&uk_settlement_holidays, 1990, 2050).unwrap();
     calendars.insert("uk".to_string(), uk_cal);
 
     let target_holidays = vec![
@@ -209,7 +212,7 @@ fn generate_calendars() -> HashMap<String, Calendar> {
         },
         Holiday::SingularDay(NaiveDate::from_ymd(1995, 5, 8)),
     ];
-    let target_cal = Calendar::calc_calendar(&target_holidays, 1990, 2050);
+    let target_cal = Calendar::calc_calendar(&target_holidays, 1990, 2050).unwrap();
     calendars.insert("TARGET".to_string(), target_cal);
 
     calendars
@@ -0,0 +1,215 @@
+//! Market quote provider serving quotes from memory, e.g. to test code using providers
+//! without network access. Errors could be injected and all calls are recorded.
+
+use super::{MarketQuoteError, MarketQuoteProvider};
+use crate::quote::{Quote, Ticker};
+use chrono::{DateTime, Utc};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+
+/// Call received by a `MockProvider`, identified by the name of the ticker
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProviderCall {
+    Latest {
+        ticker: String,
+    },
+    History {
+        ticker: String,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    },
+}
+
+/// Provider of quotes given per ticker name. The provider is configured via shared
+/// references, so it could be wrapped into an `Rc` to be inspected after it has been
+/// handed over, e.g. to `run_quote_update`.
+#[derive(Debug, Default)]
+pub struct MockProvider {
+    quotes: HashMap<String, Vec<Quote>>,
+    now: Cell<Option<DateTime<Utc>>>,
+    fail_next: Cell<usize>,
+    ticker_errors: RefCell<HashMap<String, String>>,
+    calls: RefCell<Vec<ProviderCall>>,
+}
+
+impl MockProvider {
+    /// Construct provider from quotes per ticker name, in any order
+    pub fn new(mut quotes: HashMap<String, Vec<Quote>>) -> MockProvider {
+        for series in quotes.values_mut() {
+            series.sort_by_key(|quote| quote.time);
+        }
+        MockProvider {
+            quotes,
+            ..Default::default()
+        }
+    }
+
+    /// Set the current time, the latest quote is the last quote at or before `now`.
+    /// Without current time, the last quote given is the latest one.
+    pub fn set_now(&self, now: Option<DateTime<Utc>>) {
+        self.now.set(now);
+    }
+
+    /// Let the next `n` calls fail, regardless of the ticker
+    pub fn fail_next(&self, n: usize) {
+        self.fail_next.set(n);
+    }
+
+    /// Let all calls for the ticker with the given name fail with `message`
+    pub fn fail_ticker(&self, ticker: &str, message: &str) {
+        self.ticker_errors
+            .borrow_mut()
+            .insert(ticker.to_string(), message.to_string());
+    }
+
+    /// Remove the error configured for the ticker with the given name
+    pub fn clear_ticker_error(&self, ticker: &str) {
+        self.ticker_errors.borrow_mut().remove(ticker);
+    }
+
+    /// All calls received so far, including failed ones
+    pub fn calls(&self) -> Vec<ProviderCall> {
+        self.calls.borrow().clone()
+    }
+
+    /// Number of calls received so far
+    pub fn call_count(&self) -> usize {
+        self.calls.borrow().len()
+    }
+
+    /// Record the call and return the quotes of the ticker, unless an error is injected
+    fn receive(&self, ticker: &Ticker, call: ProviderCall) -> Result<&[Quote], MarketQuoteError> {
+        self.calls.borrow_mut().push(call);
+        let fail_next = self.fail_next.get();
+        if fail_next > 0 {
+            self.fail_next.set(fail_next - 1);
+            return Err(MarketQuoteError::FetchFailed(
+                "injected failure".to_string(),
+            ));
+        }
+        if let Some(message) = self.ticker_errors.borrow().get(&ticker.name) {
+            return Err(MarketQuoteError::FetchFailed(message.clone()));
+        }
+        self.quotes
+            .get(&ticker.name)
+            .map(|quotes| quotes.as_slice())
+            .ok_or_else(|| MarketQuoteError::FetchFailed(format!("unknown ticker {}", ticker.name)))
+    }
+}
+
+impl MarketQuoteProvider for MockProvider {
+    fn fetch_latest_quote(&self, ticker: &Ticker) -> Result<Quote, MarketQuoteError> {
+        let call = ProviderCall::Latest {
+            ticker: ticker.name.clone(),
+        };
+        let quotes = self.receive(ticker, call)?;
+        let latest = match self.now.get() {
+            Some(now) => quotes.iter().rev().find(|quote| quote.time <= now),
+            None => quotes.last(),
+        };
+        latest.cloned().ok_or_else(|| {
+            MarketQuoteError::FetchFailed(format!("no quote available for {}", ticker.name))
+        })
+    }
+
+    fn fetch_quote_history(
+        &self,
+        ticker: &Ticker,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<Quote>, MarketQuoteError> {
+        let call = ProviderCall::History {
+            ticker: ticker.name.clone(),
+            start,
+            end,
+        };
+        let quotes = self.receive(ticker, call)?;
+        Ok(quotes
+            .iter()
+            .filter(|quote| quote.time >= start && quote.time <= end)
+            .cloned()
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::currency::Currency;
+    use crate::helpers::make_time;
+    use crate::quote::UpdatePeriod;
+    use std::str::FromStr;
+
+    fn ticker(name: &str) -> Ticker {
+        Ticker {
+            id: Some(1),
+            name: name.to_string(),
+            asset: 1,
+            currency: Currency::from_str("EUR").unwrap(),
+            source: 1,
+            update_policy: UpdatePeriod::Daily,
+        }
+    }
+
+    fn quote(day: u32, price: f64) -> Quote {
+        Quote {
+            id: None,
+            ticker: 1,
+            price,
+            time: make_time(2020, 6, day, 18, 0, 0).unwrap(),
+            volume: None,
+        }
+    }
+
+    #[test]
+    fn serve_quotes_and_inject_errors() {
+        let mut quotes = HashMap::new();
+        quotes.insert(
+            "BAS.DE".to_string(),
+            vec![quote(3, 10.), quote(5, 12.), quote(4, 11.)],
+        );
+        let provider = MockProvider::new(quotes);
+        let basf = ticker("BAS.DE");
+
+        assert_eq!(provider.fetch_latest_quote(&basf).unwrap().price, 12.);
+        provider.set_now(Some(make_time(2020, 6, 4, 20, 0, 0).unwrap()));
+        assert_eq!(provider.fetch_latest_quote(&basf).unwrap().price, 11.);
+        provider.set_now(Some(make_time(2020, 6, 1, 0, 0, 0).unwrap()));
+        assert!(provider.fetch_latest_quote(&basf).is_err());
+
+        let (start, end) = (quote(4, 0.).time, quote(5, 0.).time);
+        let history = provider.fetch_quote_history(&basf, start, end).unwrap();
+        let prices: Vec<f64> = history.iter().map(|q| q.price).collect();
+        assert_eq!(prices, vec![11., 12.]);
+
+        provider.fail_next(1);
+        assert!(provider.fetch_quote_history(&basf, start, end).is_err());
+        assert!(provider.fetch_quote_history(&basf, start, end).is_ok());
+        provider.fail_ticker("BAS.DE", "rate limit exceeded");
+        match provider.fetch_latest_quote(&basf) {
+            Err(MarketQuoteError::FetchFailed(message)) => {
+                assert_eq!(message, "rate limit exceeded")
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+        provider.clear_ticker_error("BAS.DE");
+        assert!(provider.fetch_quote_history(&basf, start, end).is_ok());
+        assert!(provider.fetch_latest_quote(&ticker("SIE.DE")).is_err());
+
+        assert_eq!(provider.call_count(), 9);
+        assert_eq!(
+            provider.calls()[3],
+            ProviderCall::History {
+                ticker: "BAS.DE".to_string(),
+                start,
+                end
+            }
+        );
+        assert_eq!(
+            provider.calls()[8],
+            ProviderCall::Latest {
+                ticker: "SIE.DE".to_string()
+            }
+        );
+    }
+}
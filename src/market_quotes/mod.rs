@@ -0,0 +1,330 @@
+//! Fetching quotes from market data providers and storing them in a quote database.
+//! Providers are identified by the id of the market data source their tickers belong to.
+
+use crate::calendar::Calendar;
+use crate::data_handler::{DataError, QuoteHandler};
+use crate::quote::{detect_outliers, OutlierConfig, OutlierFlag, Quote, Ticker};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::rc::Rc;
+
+/// Error related to fetching and storing market quotes
+#[derive(Debug)]
+pub enum MarketQuoteError {
+    /// The provider failed to deliver quotes
+    FetchFailed(String),
+    /// No provider is given for the market data source of the ticker
+    NoProvider(u64),
+    /// The fetched quote has been rejected as outlier
+    Outlier(OutlierFlag),
+    /// Reading or storing quotes in the database failed
+    StoreFailed(DataError),
+}
+
+impl fmt::Display for MarketQuoteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MarketQuoteError::FetchFailed(err) => write!(f, "fetching quotes failed: {}", err),
+            MarketQuoteError::NoProvider(source) => {
+                write!(f, "no provider for market data source {}", source)
+            }
+            MarketQuoteError::Outlier(flag) => {
+                write!(
+                    f,
+                    "quote {} at {} rejected as outlier",
+                    flag.price, flag.time
+                )
+            }
+            MarketQuoteError::StoreFailed(err) => write!(f, "storing quotes failed: {}", err),
+        }
+    }
+}
+
+impl Error for MarketQuoteError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MarketQuoteError::StoreFailed(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<DataError> for MarketQuoteError {
+    fn from(err: DataError) -> MarketQuoteError {
+        MarketQuoteError::StoreFailed(err)
+    }
+}
+
+/// General interface of market data providers
+pub trait MarketQuoteProvider {
+    /// Fetch the latest available quote of `ticker`
+    fn fetch_latest_quote(&self, ticker: &Ticker) -> Result<Quote, MarketQuoteError>;
+    /// Fetch all quotes of `ticker` from `start` to `end` (inclusively)
+    fn fetch_quote_history(
+        &self,
+        ticker: &Ticker,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<Quote>, MarketQuoteError>;
+}
+
+/// Shared providers, e.g. to inspect a `MockProvider` after handing it over
+impl<P: MarketQuoteProvider + ?Sized> MarketQuoteProvider for Rc<P> {
+    fn fetch_latest_quote(&self, ticker: &Ticker) -> Result<Quote, MarketQuoteError> {
+        (**self).fetch_latest_quote(ticker)
+    }
+
+    fn fetch_quote_history(
+        &self,
+        ticker: &Ticker,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<Quote>, MarketQuoteError> {
+        (**self).fetch_quote_history(ticker, start, end)
+    }
+}
+
+/// Result of a quote update run
+#[derive(Debug, Default)]
+pub struct UpdateReport {
+    /// Ids of tickers whose latest quote has been stored
+    pub updated: Vec<u64>,
+    /// Ids of tickers that could not be updated, together with the reason
+    pub errors: Vec<(u64, MarketQuoteError)>,
+}
+
+/// Fetch and store the latest quote of all tickers that are due for an update at `now`,
+/// see `QuoteHandler::tickers_due_for_update`. Failures of single tickers are collected
+/// in the report, only failing to determine the due tickers aborts the update.
+/// If `outliers` is given, fetched quotes are checked against the last stored quotes of the
+/// ticker within the outlier window and rejected as error if they are flagged as outlier.
+pub fn run_quote_update(
+    db: &mut dyn QuoteHandler,
+    providers: &HashMap<u64, Box<dyn MarketQuoteProvider>>,
+    now: DateTime<Utc>,
+    cal: &Calendar,
+    outliers: Option<&OutlierConfig>,
+) -> Result<UpdateReport, DataError> {
+    let mut report = UpdateReport::default();
+    for ticker in db.tickers_due_for_update(now, cal)? {
+        let ticker_id = match ticker.id {
+            Some(id) => id,
+            None => continue,
+        };
+        match update_ticker(db, providers, &ticker, ticker_id, now, outliers) {
+            Ok(()) => report.updated.push(ticker_id),
+            Err(err) => report.errors.push((ticker_id, err)),
+        }
+    }
+    Ok(report)
+}
+
+/// Fetch, check and store the latest quote of a single ticker
+fn update_ticker(
+    db: &mut dyn QuoteHandler,
+    providers: &HashMap<u64, Box<dyn MarketQuoteProvider>>,
+    ticker: &Ticker,
+    ticker_id: u64,
+    now: DateTime<Utc>,
+    outliers: Option<&OutlierConfig>,
+) -> Result<(), MarketQuoteError> {
+    let provider = providers
+        .get(&ticker.source)
+        .ok_or(MarketQuoteError::NoProvider(ticker.source))?;
+    let mut quote = provider.fetch_latest_quote(ticker)?;
+    quote.id = None;
+    quote.ticker = ticker_id;
+    if let Some(config) = outliers {
+        let mut series: Vec<Quote> = db
+            .get_all_quotes_for_ticker(ticker_id)?
+            .into_iter()
+            .filter(|q| q.time <= now)
+            .collect();
+        series.sort_by_key(|q| q.time);
+        let history = config.window.saturating_sub(1);
+        let mut series = series.split_off(series.len().saturating_sub(history));
+        series.push(quote.clone());
+        let new_index = series.len() - 1;
+        if let Some(flag) = detect_outliers(&series, config)
+            .into_iter()
+            .find(|flag| flag.index == new_index)
+        {
+            return Err(MarketQuoteError::Outlier(flag));
+        }
+    }
+    db.insert_quote(&quote)?;
+    Ok(())
+}
+
+pub mod mock;
+
+pub use mock::MockProvider;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::Asset;
+    use crate::calendar::Holiday;
+    use crate::currency::Currency;
+    use crate::data_handler::DataHandler;
+    use crate::helpers::make_time;
+    use crate::memory_handler::InMemoryDB;
+    use crate::quote::{MarketDataSource, UpdatePeriod};
+    use chrono::Weekday;
+    use std::str::FromStr;
+
+    /// Store a ticker with the given policy and optionally a quote at the given day of June 2020
+    fn add_ticker(
+        db: &mut InMemoryDB,
+        source: u64,
+        name: &str,
+        update_policy: UpdatePeriod,
+        quote_day: Option<u32>,
+    ) -> u64 {
+        let asset = db
+            .insert_asset(&Asset::new(None, name, None, None, None))
+            .unwrap();
+        let ticker = db
+            .insert_ticker(&Ticker {
+                id: None,
+                name: name.to_string(),
+                asset,
+                currency: Currency::from_str("EUR").unwrap(),
+                source,
+                update_policy,
+            })
+            .unwrap();
+        if let Some(day) = quote_day {
+            db.insert_quote(&Quote {
+                id: None,
+                ticker,
+                price: 10.,
+                time: make_time(2020, 6, day, 18, 0, 0).unwrap(),
+                volume: None,
+            })
+            .unwrap();
+        }
+        ticker
+    }
+
+    /// Quote database with a single source, served by a mock provider quoting `price`
+    /// on Monday 8th of June 2020 for all tickers but "FAIL"
+    fn setup(
+        price: f64,
+    ) -> (
+        InMemoryDB,
+        HashMap<u64, Box<dyn MarketQuoteProvider>>,
+        Rc<MockProvider>,
+    ) {
+        let mut db = InMemoryDB::new();
+        let source = db
+            .insert_md_source(&MarketDataSource {
+                id: None,
+                name: "test".to_string(),
+            })
+            .unwrap();
+        let quotes = ["STOCK", "FUND", "MANUAL", "NEW"]
+            .iter()
+            .map(|name| {
+                let quote = Quote {
+                    id: None,
+                    ticker: 0,
+                    price,
+                    time: make_time(2020, 6, 8, 17, 30, 0).unwrap(),
+                    volume: None,
+                };
+                (name.to_string(), vec![quote])
+            })
+            .collect();
+        let mock = Rc::new(MockProvider::new(quotes));
+        mock.fail_ticker("FAIL", "unknown ticker");
+        let mut providers: HashMap<u64, Box<dyn MarketQuoteProvider>> = HashMap::new();
+        providers.insert(source, Box::new(mock.clone()));
+        (db, providers, mock)
+    }
+
+    fn weekends() -> Calendar {
+        Calendar::calc_calendar(
+            &[
+                Holiday::WeekDay(Weekday::Sat),
+                Holiday::WeekDay(Weekday::Sun),
+            ],
+            2020,
+            2020,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn update_due_tickers_only() {
+        let (mut db, providers, mock) = setup(10.5);
+        let cal = weekends();
+        let source = *providers.keys().next().unwrap();
+        // last quotes on Friday 5th and Wednesday 3rd of June 2020
+        let stock = add_ticker(&mut db, source, "STOCK", UpdatePeriod::Daily, Some(5));
+        let fund = add_ticker(&mut db, source, "FUND", UpdatePeriod::Weekly, Some(3));
+        add_ticker(&mut db, source, "MANUAL", UpdatePeriod::Never, None);
+        let new = add_ticker(&mut db, source, "NEW", UpdatePeriod::Weekly, None);
+        let failing = add_ticker(&mut db, source, "FAIL", UpdatePeriod::Daily, Some(5));
+
+        // no business day since Friday's quotes on Sunday
+        let sunday = make_time(2020, 6, 7, 12, 0, 0).unwrap();
+        let due: Vec<u64> = db
+            .tickers_due_for_update(sunday, &cal)
+            .unwrap()
+            .iter()
+            .filter_map(|t| t.id)
+            .collect();
+        assert_eq!(due, vec![new]);
+
+        let monday = make_time(2020, 6, 8, 18, 0, 0).unwrap();
+        let report = run_quote_update(&mut db, &providers, monday, &cal, None).unwrap();
+        assert_eq!(mock.call_count(), 3);
+        for name in &["STOCK", "NEW", "FAIL"] {
+            assert!(mock.calls().contains(&mock::ProviderCall::Latest {
+                ticker: name.to_string()
+            }));
+        }
+        let mut updated = report.updated.clone();
+        updated.sort_unstable();
+        assert_eq!(updated, vec![stock, new]);
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].0, failing);
+        assert_eq!(db.get_all_quotes_for_ticker(stock).unwrap().len(), 2);
+        assert_eq!(db.get_all_quotes_for_ticker(fund).unwrap().len(), 1);
+
+        // a second run fetches the failed ticker only
+        let report = run_quote_update(&mut db, &providers, monday, &cal, None).unwrap();
+        assert_eq!(mock.call_count(), 4);
+        assert!(report.updated.is_empty());
+
+        mock.clear_ticker_error("FAIL");
+        mock.fail_next(1);
+        let report = run_quote_update(&mut db, &providers, monday, &cal, None).unwrap();
+        assert_eq!(mock.call_count(), 5);
+        assert_eq!(report.errors.len(), 1);
+    }
+
+    #[test]
+    fn reject_outliers_before_storing() {
+        let (mut db, providers, mock) = setup(1050.);
+        let cal = weekends();
+        let source = *providers.keys().next().unwrap();
+        let stock = add_ticker(&mut db, source, "STOCK", UpdatePeriod::Daily, Some(5));
+        let monday = make_time(2020, 6, 8, 18, 0, 0).unwrap();
+        let config = OutlierConfig::default();
+        let report = run_quote_update(&mut db, &providers, monday, &cal, Some(&config)).unwrap();
+        assert_eq!(mock.call_count(), 1);
+        assert!(report.updated.is_empty());
+        match &report.errors[..] {
+            [(id, MarketQuoteError::Outlier(flag))] => {
+                assert_eq!(*id, stock);
+                assert_eq!(flag.price, 1050.);
+            }
+            errors => panic!("unexpected errors {:?}", errors),
+        }
+        assert_eq!(db.get_all_quotes_for_ticker(stock).unwrap().len(), 1);
+    }
+}
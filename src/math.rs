@@ -0,0 +1,205 @@
+//! Statistical helper functions for the analysis of return series,
+//! e.g. to calculate the beta of an asset relative to a benchmark.
+
+use chrono::NaiveDate;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+/// Error related to statistical calculations
+#[derive(Debug, PartialEq)]
+pub enum MathError {
+    /// The samples of dependent and independent variable differ in length
+    LengthMismatch { x: usize, y: usize },
+    /// The number of samples is too small, the minimum required number is given
+    TooFewPoints { required: usize, found: usize },
+    /// All samples of the independent variable are equal
+    ConstantSeries,
+}
+
+impl fmt::Display for MathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MathError::LengthMismatch { x, y } => {
+                write!(f, "series lengths differ: {} x values, {} y values", x, y)
+            }
+            MathError::TooFewPoints { required, found } => write!(
+                f,
+                "at least {} points are required, but only {} are given",
+                required, found
+            ),
+            MathError::ConstantSeries => write!(f, "independent variable is constant"),
+        }
+    }
+}
+
+impl Error for MathError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
+
+/// Result of a simple linear regression `y = intercept + slope * x`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LinRegResult {
+    pub slope: f64,
+    pub intercept: f64,
+    /// Coefficient of determination
+    pub r_squared: f64,
+    /// Standard error of the slope estimate
+    pub slope_std_err: f64,
+    /// Standard error of the intercept estimate
+    pub intercept_std_err: f64,
+}
+
+/// Ordinary least squares fit of `y` against `x`. At least 3 points are required,
+/// since the standard errors are based on `n - 2` degrees of freedom.
+pub fn linreg(x: &[f64], y: &[f64]) -> Result<LinRegResult, MathError> {
+    if x.len() != y.len() {
+        return Err(MathError::LengthMismatch {
+            x: x.len(),
+            y: y.len(),
+        });
+    }
+    let n = x.len();
+    if n < 3 {
+        return Err(MathError::TooFewPoints {
+            required: 3,
+            found: n,
+        });
+    }
+    let mean_x = x.iter().sum::<f64>() / n as f64;
+    let mean_y = y.iter().sum::<f64>() / n as f64;
+    let (mut sxx, mut sxy, mut syy) = (0., 0., 0.);
+    for (xi, yi) in x.iter().zip(y) {
+        let (dx, dy) = (xi - mean_x, yi - mean_y);
+        sxx += dx * dx;
+        sxy += dx * dy;
+        syy += dy * dy;
+    }
+    if sxx == 0. {
+        return Err(MathError::ConstantSeries);
+    }
+    let slope = sxy / sxx;
+    let intercept = mean_y - slope * mean_x;
+    let sse: f64 = x
+        .iter()
+        .zip(y)
+        .map(|(xi, yi)| (yi - intercept - slope * xi).powi(2))
+        .sum();
+    let r_squared = if syy == 0. { 1. } else { 1. - sse / syy };
+    let variance = sse / (n - 2) as f64;
+    Ok(LinRegResult {
+        slope,
+        intercept,
+        r_squared,
+        slope_std_err: (variance / sxx).sqrt(),
+        intercept_std_err: (variance * (1. / n as f64 + mean_x * mean_x / sxx)).sqrt(),
+    })
+}
+
+/// Inner join of two dated series, i.e. the values of both series at all dates present
+/// in both series, ordered by date. If a date occurs multiple times in a series,
+/// the last value is taken.
+pub fn align_series(a: &[(NaiveDate, f64)], b: &[(NaiveDate, f64)]) -> (Vec<f64>, Vec<f64>) {
+    let a_values: HashMap<NaiveDate, f64> = a.iter().copied().collect();
+    let b_values: HashMap<NaiveDate, f64> = b.iter().copied().collect();
+    let mut joined: Vec<_> = a_values
+        .into_iter()
+        .filter_map(|(date, a)| b_values.get(&date).map(|b| (date, a, *b)))
+        .collect();
+    joined.sort_by_key(|(date, _, _)| *date);
+    joined.into_iter().map(|(_, a, b)| (a, b)).unzip()
+}
+
+/// Maximal number of bisection steps of `bisect`
+const MAX_BISECTIONS: usize = 200;
+
+/// Root of the continuous function `f` between `low` and `high` by bisection, until the
+/// interval containing the root is narrower than `tol`. Returns `None` if `f(low)` and
+/// `f(high)` have the same sign, i.e. no root is bracketed by the interval.
+pub fn bisect<F: Fn(f64) -> f64>(f: F, mut low: f64, mut high: f64, tol: f64) -> Option<f64> {
+    let (f_low, f_high) = (f(low), f(high));
+    if f_low == 0. {
+        return Some(low);
+    }
+    if f_high == 0. {
+        return Some(high);
+    }
+    // false for values of the same sign, but also if any value is NaN
+    let bracketed = f_low.signum() * f_high.signum() < 0.;
+    if !bracketed {
+        return None;
+    }
+    for _ in 0..MAX_BISECTIONS {
+        let mid = 0.5 * (low + high);
+        let f_mid = f(mid);
+        if f_mid == 0. {
+            return Some(mid);
+        }
+        if f_mid.signum() == f_low.signum() {
+            low = mid;
+        } else {
+            high = mid;
+        }
+        if (high - low).abs() < tol {
+            break;
+        }
+    }
+    Some(0.5 * (low + high))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn linear_regression() {
+        let x = [0.01, -0.02, 0.015, 0.03, -0.005, 0.02];
+        let y = [0.012, -0.025, 0.02, 0.033, -0.004, 0.018];
+        let result = linreg(&x, &y).unwrap();
+        // reference values calculated in exact rational arithmetic
+        let tol = 1e-12;
+        assert_fuzzy_eq!(result.slope, 1.126_530_612_244_898, tol);
+        assert_fuzzy_eq!(result.intercept, -0.000_387_755_102_040_816, tol);
+        assert_fuzzy_eq!(result.r_squared, 0.981_447_124_304_267, tol);
+        assert_fuzzy_eq!(result.slope_std_err, 0.077_443_534_534_736, tol);
+        assert_fuzzy_eq!(result.intercept_std_err, 0.001_431_483_911_571, tol);
+
+        assert_eq!(
+            linreg(&x, &y[..5]),
+            Err(MathError::LengthMismatch { x: 6, y: 5 })
+        );
+        assert_eq!(
+            linreg(&x[..2], &y[..2]),
+            Err(MathError::TooFewPoints {
+                required: 3,
+                found: 2
+            })
+        );
+        assert_eq!(
+            linreg(&[1., 1., 1.], &[1., 2., 3.]),
+            Err(MathError::ConstantSeries)
+        );
+    }
+
+    #[test]
+    fn align_dated_series() {
+        let d = |day| NaiveDate::from_ymd(2020, 3, day);
+        let a = vec![(d(2), 1.), (d(3), 2.), (d(5), 5.), (d(4), 4.), (d(5), 3.)];
+        let b = vec![(d(5), 30.), (d(1), 0.), (d(2), 10.), (d(4), 40.)];
+        let (x, y) = align_series(&a, &b);
+        assert_eq!(x, vec![1., 4., 3.]);
+        assert_eq!(y, vec![10., 40., 30.]);
+    }
+
+    #[test]
+    fn bisection() {
+        let root = bisect(|x| x * x - 2., 0., 2., 1e-14).unwrap();
+        assert_fuzzy_eq!(root, 2_f64.sqrt(), 1e-13);
+        let root = bisect(|x| 1. - x, -1., 3., 1e-14).unwrap();
+        assert_fuzzy_eq!(root, 1., 1e-13);
+        assert_eq!(bisect(|x| x, 0., 1., 1e-14), Some(0.));
+        assert_eq!(bisect(|x| x * x + 1., -1., 1., 1e-14), None);
+    }
+}
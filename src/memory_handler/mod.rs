@@ -1,24 +1,32 @@
 ///! Implementation of in-memory data handler
 use crate::asset::Asset;
-use crate::data_handler::{DataError, DataItem};
+use crate::data_handler::{Clock, DataError, DataItem, SystemClock};
 use crate::quote::{MarketDataSource, Quote, Ticker};
 use crate::transaction::Transaction;
+use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::ops::Bound::{Excluded, Unbounded};
 
+/// Container of items of the same kind; like in the database backends,
+/// the first item gets id 1 and ids are not reused after deletion. Id 0
+/// is never assigned, since keyset pagination starts after id 0.
+#[derive(Clone, Serialize, Deserialize)]
 struct InMemoryContainer<T: DataItem + Clone> {
-    next_id: usize,
-    items: BTreeMap<usize, T>,
+    next_id: u64,
+    items: BTreeMap<u64, T>,
 }
 
 impl<T: DataItem + Clone> InMemoryContainer<T> {
     fn new() -> InMemoryContainer<T> {
         InMemoryContainer {
-            next_id: 0,
+            next_id: 1,
             items: BTreeMap::new(),
         }
     }
 
-    fn insert(&mut self, item: &T) -> Result<usize, DataError> {
+    fn insert(&mut self, item: &T) -> Result<u64, DataError> {
         let id = self.next_id;
         let mut item = item.clone();
         item.set_id(id)?;
@@ -27,7 +35,7 @@ impl<T: DataItem + Clone> InMemoryContainer<T> {
         Ok(id)
     }
 
-    fn get_by_id(&self, id: usize) -> Result<T, DataError> {
+    fn get_by_id(&self, id: u64) -> Result<T, DataError> {
         let item = self.items.get(&id);
         match item {
             Some(item) => Ok(item.clone()),
@@ -45,6 +53,23 @@ impl<T: DataItem + Clone> InMemoryContainer<T> {
         Ok(items)
     }
 
+    /// Call `f` for each item ordered by id, stopping at the first error
+    fn try_for_each(
+        &self,
+        f: &mut dyn FnMut(&T) -> Result<(), DataError>,
+    ) -> Result<(), DataError> {
+        self.items.values().try_for_each(f)
+    }
+
+    /// Get up to `limit` items with id strictly greater than `after`, ordered by id
+    fn get_after_id(&self, after: u64, limit: usize) -> Vec<T> {
+        self.items
+            .range((Excluded(after), Unbounded))
+            .take(limit)
+            .map(|(_, item)| item.clone())
+            .collect()
+    }
+
     fn update(&mut self, item: &T) -> Result<(), DataError> {
         let id = item.get_id()?;
         if !self.items.contains_key(&id) {
@@ -56,7 +81,7 @@ impl<T: DataItem + Clone> InMemoryContainer<T> {
         Ok(())
     }
 
-    fn delete(&mut self, id: usize) -> Result<(), DataError> {
+    fn delete(&mut self, id: u64) -> Result<(), DataError> {
         if !self.items.contains_key(&id) {
             return Err(DataError::DeleteFailed(
                 "item id not found in database".to_string(),
@@ -67,26 +92,146 @@ impl<T: DataItem + Clone> InMemoryContainer<T> {
     }
 }
 
+/// Content of all containers, used as snapshot of the in-memory database
+#[derive(Clone)]
+struct InMemoryData {
+    assets: InMemoryContainer<Asset>,
+    transactions: InMemoryContainer<Transaction>,
+    md_sources: InMemoryContainer<MarketDataSource>,
+    ticker_map: InMemoryContainer<Ticker>,
+    quotes: InMemoryContainer<Quote>,
+}
+
 /// Struct to store data in memory
+#[derive(Serialize, Deserialize)]
 pub struct InMemoryDB {
     assets: InMemoryContainer<Asset>,
     transactions: InMemoryContainer<Transaction>,
     md_sources: InMemoryContainer<MarketDataSource>,
     ticker_map: InMemoryContainer<Ticker>,
     quotes: InMemoryContainer<Quote>,
+    #[serde(skip, default = "system_clock")]
+    clock: Box<dyn Clock>,
+}
+
+fn system_clock() -> Box<dyn Clock> {
+    Box::new(SystemClock)
 }
 
 impl InMemoryDB {
     pub fn new() -> InMemoryDB {
+        InMemoryDB::with_clock(Box::new(SystemClock))
+    }
+
+    /// Create new in-memory database whose first asset gets id `asset_start` and whose first
+    /// transaction gets id `txn_start`, e.g. to avoid collisions with ids of other systems
+    /// when merging data. Since id 0 is never assigned, start ids of 0 are treated as 1.
+    pub fn new_with_start_ids(asset_start: u64, txn_start: u64) -> InMemoryDB {
+        let mut db = InMemoryDB::new();
+        db.assets.next_id = asset_start.max(1);
+        db.transactions.next_id = txn_start.max(1);
+        db
+    }
+
+    /// Create new in-memory database using the given clock for audit time stamps
+    pub fn with_clock(clock: Box<dyn Clock>) -> InMemoryDB {
         InMemoryDB {
             assets: InMemoryContainer::new(),
             transactions: InMemoryContainer::new(),
             md_sources: InMemoryContainer::new(),
             ticker_map: InMemoryContainer::new(),
             quotes: InMemoryContainer::new(),
+            clock,
         }
     }
+
+    /// Save all data to a file in JSON format
+    pub fn save_to_file(&self, path: &str) -> Result<(), DataError> {
+        let file = File::create(path)?;
+        serde_json::to_writer(BufWriter::new(file), self)?;
+        Ok(())
+    }
+
+    /// Load data previously saved by `save_to_file`, audit time stamps of
+    /// further changes are taken from the system clock
+    pub fn load_from_file(path: &str) -> Result<InMemoryDB, DataError> {
+        let file = File::open(path)?;
+        let db = serde_json::from_reader(BufReader::new(file))?;
+        Ok(db)
+    }
+
+    /// Call `f` for each stored transaction ordered by id, borrowing the transactions
+    /// instead of cloning them into pages as `DataHandler::for_each_transaction` does
+    pub fn visit_transactions<F: FnMut(&Transaction)>(&self, mut f: F) -> Result<(), DataError> {
+        self.transactions.try_for_each(&mut |transaction| {
+            f(transaction);
+            Ok(())
+        })
+    }
 }
 
 pub mod quote_handler;
 pub mod transaction_handler;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_handler::DataHandler;
+
+    #[test]
+    fn save_and_load_file() {
+        let result = InMemoryDB::load_from_file("/nonexistent/finql/db.json");
+        assert!(matches!(result, Err(DataError::Io(_))));
+
+        let mut db = InMemoryDB::new();
+        let id = db
+            .insert_asset(&Asset::new(None, "BASF AG", None, None, None))
+            .unwrap();
+        let path =
+            std::env::temp_dir().join(format!("finql_memory_db_{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        db.save_to_file(path).unwrap();
+        let mut loaded = InMemoryDB::load_from_file(path).unwrap();
+        assert_eq!(loaded.get_asset_by_id(id).unwrap().name, "BASF AG");
+        // ids continue after the loaded ones
+        let next_id = loaded
+            .insert_asset(&Asset::new(None, "Siemens AG", None, None, None))
+            .unwrap();
+        assert_eq!(next_id, id + 1);
+
+        std::fs::write(path, "no json").unwrap();
+        let result = InMemoryDB::load_from_file(path);
+        assert!(matches!(result, Err(DataError::Serialization(_))));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn first_id_is_found_by_pagination_from_zero() {
+        let mut db = InMemoryDB::new();
+        let id = db
+            .insert_asset(&Asset::new(None, "BASF AG", None, None, None))
+            .unwrap();
+        assert_eq!(id, 1);
+        let first = db.assets.get_after_id(0, 1);
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].get_id().unwrap(), id);
+    }
+
+    #[test]
+    fn configurable_start_ids() {
+        let mut db = InMemoryDB::new_with_start_ids(100, 500);
+        let id = db
+            .insert_asset(&Asset::new(None, "BASF AG", None, None, None))
+            .unwrap();
+        assert_eq!(id, 100);
+        let id = db
+            .insert_asset(&Asset::new(None, "Siemens AG", None, None, None))
+            .unwrap();
+        assert_eq!(id, 101);
+        assert_eq!(db.transactions.next_id, 500);
+
+        let db = InMemoryDB::new_with_start_ids(0, 0);
+        assert_eq!(db.assets.next_id, 1);
+        assert_eq!(db.transactions.next_id, 1);
+    }
+}
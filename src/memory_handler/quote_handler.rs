@@ -1,17 +1,17 @@
 use super::InMemoryDB;
 use crate::currency::Currency;
-use crate::data_handler::{DataError, QuoteHandler};
+use crate::data_handler::{DataError, DataItem, QuoteHandler};
 use crate::quote::{MarketDataSource, Quote, Ticker};
 use chrono::{DateTime, Utc, MIN_DATE};
 
 /// Handler for globally available market data quotes information
 impl QuoteHandler for InMemoryDB {
     // insert, get, update and delete for market data sources
-    fn insert_md_source(&mut self, source: &MarketDataSource) -> Result<usize, DataError> {
+    fn insert_md_source(&mut self, source: &MarketDataSource) -> Result<u64, DataError> {
         self.md_sources.insert(source)
     }
 
-    fn get_md_source_by_id(&mut self, id: usize) -> Result<MarketDataSource, DataError> {
+    fn get_md_source_by_id(&mut self, id: u64) -> Result<MarketDataSource, DataError> {
         self.md_sources.get_by_id(id)
     }
 
@@ -23,20 +23,20 @@ impl QuoteHandler for InMemoryDB {
         self.md_sources.update(source)
     }
 
-    fn delete_md_source(&mut self, id: usize) -> Result<(), DataError> {
+    fn delete_md_source(&mut self, id: u64) -> Result<(), DataError> {
         self.md_sources.delete(id)
     }
 
     // insert, get, update and delete for market data ticker
-    fn insert_ticker(&mut self, asset: &Ticker) -> Result<usize, DataError> {
+    fn insert_ticker(&mut self, asset: &Ticker) -> Result<u64, DataError> {
         self.ticker_map.insert(asset)
     }
 
-    fn get_ticker_by_id(&mut self, id: usize) -> Result<Ticker, DataError> {
+    fn get_ticker_by_id(&mut self, id: u64) -> Result<Ticker, DataError> {
         self.ticker_map.get_by_id(id)
     }
 
-    fn get_all_ticker_for_source(&mut self, source_id: usize) -> Result<Vec<Ticker>, DataError> {
+    fn get_all_ticker_for_source(&mut self, source_id: u64) -> Result<Vec<Ticker>, DataError> {
         let mut all_ticker = Vec::new();
         for ticker in self.ticker_map.items.values() {
             if ticker.source == source_id {
@@ -50,18 +50,18 @@ impl QuoteHandler for InMemoryDB {
         self.ticker_map.update(asset)
     }
 
-    fn delete_ticker(&mut self, id: usize) -> Result<(), DataError> {
+    fn delete_ticker(&mut self, id: u64) -> Result<(), DataError> {
         self.ticker_map.delete(id)
     }
 
     // insert, get, update and delete for quotes
-    fn insert_quote(&mut self, quote: &Quote) -> Result<usize, DataError> {
+    fn insert_quote(&mut self, quote: &Quote) -> Result<u64, DataError> {
         self.quotes.insert(quote)
     }
 
     fn get_last_quote_before(
         &mut self,
-        ticker_id: usize,
+        ticker_id: u64,
         time: DateTime<Utc>,
     ) -> Result<(Quote, Currency), DataError> {
         let mut last_quote = Quote {
@@ -92,7 +92,39 @@ impl QuoteHandler for InMemoryDB {
         Ok((last_quote, ticker.currency))
     }
 
-    fn get_all_quotes_for_ticker(&mut self, ticker_id: usize) -> Result<Vec<Quote>, DataError> {
+    fn get_last_quote_before_by_id(
+        &mut self,
+        asset_id: u64,
+        time: DateTime<Utc>,
+    ) -> Result<(Quote, Currency), DataError> {
+        let mut ticker_ids = Vec::new();
+        for ticker in self.ticker_map.items.values() {
+            if ticker.asset == asset_id {
+                ticker_ids.push(ticker.get_id()?);
+            }
+        }
+        let mut last_quote: Option<(Quote, Currency)> = None;
+        for ticker_id in ticker_ids {
+            match self.get_last_quote_before(ticker_id, time) {
+                Ok((quote, currency)) => {
+                    let is_later = match &last_quote {
+                        Some((last, _)) => last.time < quote.time,
+                        None => true,
+                    };
+                    if is_later {
+                        last_quote = Some((quote, currency));
+                    }
+                }
+                Err(DataError::NotFound(_)) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        last_quote.ok_or_else(|| {
+            DataError::NotFound("No valid quote found before specified date".to_string())
+        })
+    }
+
+    fn get_all_quotes_for_ticker(&mut self, ticker_id: u64) -> Result<Vec<Quote>, DataError> {
         let mut all_quotes = Vec::new();
         for quote in self.quotes.items.values() {
             if quote.ticker == ticker_id {
@@ -106,7 +138,95 @@ impl QuoteHandler for InMemoryDB {
         self.quotes.update(quote)
     }
 
-    fn delete_quote(&mut self, id: usize) -> Result<(), DataError> {
+    fn delete_quote(&mut self, id: u64) -> Result<(), DataError> {
         self.quotes.delete(id)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::Asset;
+    use crate::calendar::{Calendar, Holiday};
+    use crate::data_handler::DataHandler;
+    use crate::helpers::make_time;
+    use crate::quote::UpdatePeriod;
+    use chrono::{NaiveDate, Weekday};
+    use std::str::FromStr;
+
+    #[test]
+    fn find_gaps_in_quote_history() {
+        let mut db = InMemoryDB::new();
+        let asset_id = db
+            .insert_asset(&Asset::new(None, "BASF", None, None, None))
+            .unwrap();
+        let source = db
+            .insert_md_source(&MarketDataSource {
+                id: None,
+                name: "manual".to_string(),
+            })
+            .unwrap();
+        let ticker = db
+            .insert_ticker(&Ticker {
+                id: None,
+                name: "BAS.DE".to_string(),
+                asset: asset_id,
+                currency: Currency::from_str("EUR").unwrap(),
+                source,
+                update_policy: UpdatePeriod::Daily,
+            })
+            .unwrap();
+        for day in &[6, 8, 9, 14] {
+            db.insert_quote(&Quote {
+                id: None,
+                ticker,
+                price: 40.,
+                time: make_time(2020, 4, *day, 12, 0, 0).unwrap(),
+                volume: None,
+            })
+            .unwrap();
+        }
+        let cal = Calendar::calc_calendar(
+            &[
+                Holiday::SingularDay(NaiveDate::from_ymd(2020, 4, 10)),
+                Holiday::SingularDay(NaiveDate::from_ymd(2020, 4, 13)),
+                Holiday::WeekDay(Weekday::Sat),
+                Holiday::WeekDay(Weekday::Sun),
+            ],
+            2020,
+            2020,
+        )
+        .unwrap();
+        let gaps = db
+            .quote_gaps(
+                asset_id,
+                NaiveDate::from_ymd(2020, 4, 6),
+                NaiveDate::from_ymd(2020, 4, 15),
+                &cal,
+            )
+            .unwrap();
+        assert_eq!(
+            gaps,
+            vec![
+                NaiveDate::from_ymd(2020, 4, 7),
+                NaiveDate::from_ymd(2020, 4, 15)
+            ]
+        );
+        // no quotes at all for unknown assets
+        let gaps = db
+            .quote_gaps(
+                asset_id + 1,
+                NaiveDate::from_ymd(2020, 4, 9),
+                NaiveDate::from_ymd(2020, 4, 14),
+                &cal,
+            )
+            .unwrap();
+        assert_eq!(
+            gaps,
+            vec![
+                NaiveDate::from_ymd(2020, 4, 9),
+                NaiveDate::from_ymd(2020, 4, 14)
+            ]
+        );
+    }
+}
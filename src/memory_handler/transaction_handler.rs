@@ -1,37 +1,70 @@
-use super::InMemoryDB;
-use crate::asset::Asset;
-use crate::data_handler::{DataError, DataHandler};
+use super::{InMemoryDB, InMemoryData};
+use crate::asset::{rank_search_results, Asset, InstrumentType};
+use crate::data_handler::{DataError, DataHandler, DataItem, DataSnapshot};
 use crate::transaction::Transaction;
 
 /// Handler for globally available data
 impl DataHandler for InMemoryDB {
     // insert, get, update and delete for assets
-    fn insert_asset(&mut self, asset: &Asset) -> Result<usize, DataError> {
+    fn insert_asset(&mut self, asset: &Asset) -> Result<u64, DataError> {
         self.assets.insert(asset)
     }
 
-    fn get_asset_by_id(&mut self, id: usize) -> Result<Asset, DataError> {
+    fn get_asset_by_id(&mut self, id: u64) -> Result<Asset, DataError> {
         self.assets.get_by_id(id)
     }
 
+    fn get_asset_by_name(&mut self, name: &str) -> Result<Asset, DataError> {
+        for asset in self.assets.items.values() {
+            if asset.name == name {
+                return Ok(asset.clone());
+            }
+        }
+        Err(DataError::NotFound(format!("asset '{}' not found", name)))
+    }
+
     fn get_all_assets(&mut self) -> Result<Vec<Asset>, DataError> {
         self.assets.get_all()
     }
 
+    fn get_assets_by_instrument_type(
+        &mut self,
+        kind: InstrumentType,
+    ) -> Result<Vec<Asset>, DataError> {
+        Ok(self
+            .assets
+            .items
+            .values()
+            .filter(|asset| asset.instrument_type() == Some(kind))
+            .cloned()
+            .collect())
+    }
+
+    fn search_assets(&mut self, query: &str, limit: usize) -> Result<Vec<Asset>, DataError> {
+        let assets = self.assets.get_all()?;
+        Ok(rank_search_results(assets, query, limit, false))
+    }
+
     fn update_asset(&mut self, asset: &Asset) -> Result<(), DataError> {
         self.assets.update(asset)
     }
 
-    fn delete_asset(&mut self, id: usize) -> Result<(), DataError> {
+    fn delete_asset(&mut self, id: u64) -> Result<(), DataError> {
         self.assets.delete(id)
     }
 
     // insert, get, update and delete for transactions
-    fn insert_transaction(&mut self, transaction: &Transaction) -> Result<usize, DataError> {
-        self.transactions.insert(transaction)
+    fn insert_transaction(&mut self, transaction: &Transaction) -> Result<u64, DataError> {
+        self.validate_transfer(transaction)?;
+        let mut transaction = transaction.clone();
+        transaction.tags = transaction.normalized_tags();
+        let now = self.clock.now();
+        transaction.created_at = Some(now);
+        transaction.modified_at = Some(now);
+        self.transactions.insert(&transaction)
     }
 
-    fn get_transaction_by_id(&mut self, id: usize) -> Result<Transaction, DataError> {
+    fn get_transaction_by_id(&mut self, id: u64) -> Result<Transaction, DataError> {
         self.transactions.get_by_id(id)
     }
 
@@ -39,11 +72,338 @@ impl DataHandler for InMemoryDB {
         self.transactions.get_all()
     }
 
+    fn get_transactions_after_id(
+        &mut self,
+        after: u64,
+        limit: usize,
+    ) -> Result<Vec<Transaction>, DataError> {
+        Ok(self.transactions.get_after_id(after, limit))
+    }
+
+    fn for_each_transaction(
+        &mut self,
+        f: &mut dyn FnMut(&Transaction) -> Result<(), DataError>,
+    ) -> Result<(), DataError> {
+        self.transactions.try_for_each(f)
+    }
+
     fn update_transaction(&mut self, transaction: &Transaction) -> Result<(), DataError> {
-        self.transactions.update(transaction)
+        let id = transaction.get_id()?;
+        self.validate_transfer(transaction)?;
+        let stored = self
+            .transactions
+            .get_by_id(id)
+            .map_err(|e| DataError::UpdateFailed(e.to_string()))?;
+        let mut transaction = transaction.clone();
+        transaction.tags = transaction.normalized_tags();
+        transaction.created_at = stored.created_at;
+        transaction.modified_at = Some(self.clock.now());
+        self.transactions.update(&transaction)
     }
 
-    fn delete_transaction(&mut self, id: usize) -> Result<(), DataError> {
+    fn delete_transaction(&mut self, id: u64) -> Result<(), DataError> {
         self.transactions.delete(id)
     }
+
+    fn snapshot(&self) -> Result<DataSnapshot, DataError> {
+        Ok(DataSnapshot::new(InMemoryData {
+            assets: self.assets.clone(),
+            transactions: self.transactions.clone(),
+            md_sources: self.md_sources.clone(),
+            ticker_map: self.ticker_map.clone(),
+            quotes: self.quotes.clone(),
+        }))
+    }
+
+    fn restore(&mut self, snapshot: DataSnapshot) -> Result<(), DataError> {
+        let data: InMemoryData = snapshot.into_inner()?;
+        self.assets = data.assets;
+        self.transactions = data.transactions;
+        self.md_sources = data.md_sources;
+        self.ticker_map = data.ticker_map;
+        self.quotes = data.quotes;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::currency::Currency;
+    use crate::data_handler::Clock;
+    use crate::fixed_income::CashFlow;
+    use crate::helpers::make_time;
+    use crate::transaction::{TransactionFilter, TransactionKind, TransactionType};
+    use chrono::{DateTime, Duration, NaiveDate, Utc};
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::str::FromStr;
+
+    /// Clock that returns a manually controlled time
+    struct FakeClock {
+        time: Rc<Cell<DateTime<Utc>>>,
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> DateTime<Utc> {
+            self.time.get()
+        }
+    }
+
+    #[test]
+    fn transaction_audit_time_stamps() {
+        let time = Rc::new(Cell::new(make_time(2020, 1, 15, 10, 0, 0).unwrap()));
+        let mut db = InMemoryDB::with_clock(Box::new(FakeClock { time: time.clone() }));
+        let eur = Currency::from_str("EUR").unwrap();
+        let transaction = Transaction::new(
+            TransactionType::Cash,
+            CashFlow::new(1000., eur, NaiveDate::from_ymd(2020, 1, 15)),
+        );
+        let id = db.insert_transaction(&transaction).unwrap();
+        let created = time.get();
+        let mut transaction = db.get_transaction_by_id(id).unwrap();
+        assert_eq!(transaction.created_at, Some(created));
+        assert_eq!(transaction.modified_at, Some(created));
+
+        time.set(created + Duration::hours(2));
+        transaction.note = Some("initial deposit".to_string());
+        transaction.created_at = None;
+        db.update_transaction(&transaction).unwrap();
+        let transaction = db.get_transaction_by_id(id).unwrap();
+        assert_eq!(transaction.created_at, Some(created));
+        assert_eq!(transaction.modified_at, Some(created + Duration::hours(2)));
+    }
+
+    #[test]
+    fn paginate_transactions_by_id() {
+        let mut db = InMemoryDB::new();
+        let eur = Currency::from_str("EUR").unwrap();
+        let mut ids = Vec::new();
+        for i in 0..5 {
+            let transaction = Transaction::new(
+                TransactionType::Cash,
+                CashFlow::new(100. * i as f64, eur, NaiveDate::from_ymd(2020, 1, 15)),
+            );
+            ids.push(db.insert_transaction(&transaction).unwrap());
+        }
+
+        let mut seen = Vec::new();
+        let mut after = 0;
+        loop {
+            let page = db.get_transactions_after_id(after, 2).unwrap();
+            if page.is_empty() {
+                break;
+            }
+            assert!(page.len() <= 2);
+            for transaction in &page {
+                seen.push(transaction.get_id().unwrap());
+            }
+            after = page.last().unwrap().get_id().unwrap();
+        }
+        assert_eq!(seen, ids);
+        assert!(db
+            .get_transactions_after_id(u64::MAX, 2)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn stream_transactions_until_error() {
+        let mut db = InMemoryDB::new();
+        let eur = Currency::from_str("EUR").unwrap();
+        for i in 1..=5 {
+            let transaction = Transaction::new(
+                TransactionType::Cash,
+                CashFlow::new(100. * i as f64, eur, NaiveDate::from_ymd(2020, 1, 15)),
+            );
+            db.insert_transaction(&transaction).unwrap();
+        }
+        let mut total = 0.;
+        db.for_each_transaction(&mut |t| {
+            total += t.cash_flow.amount.value();
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(total, 1500.);
+
+        let mut seen = Vec::new();
+        let result = db.for_each_transaction(&mut |t| {
+            if t.cash_flow.amount.value() > 250. {
+                return Err(DataError::InvalidTransaction("too large".to_string()));
+            }
+            seen.push(t.get_id().unwrap());
+            Ok(())
+        });
+        assert!(matches!(result, Err(DataError::InvalidTransaction(_))));
+        assert_eq!(seen, vec![1, 2]);
+    }
+
+    #[test]
+    fn visit_transactions_without_copies() {
+        let mut db = InMemoryDB::new();
+        let eur = Currency::from_str("EUR").unwrap();
+        let asset_id = db
+            .insert_asset(&Asset::new(None, "BASF AG", None, None, None))
+            .unwrap();
+        for i in 1..=4 {
+            let transaction = Transaction::new(
+                TransactionType::Asset {
+                    asset_id,
+                    position: 10. * i as f64,
+                },
+                CashFlow::new(-100. * i as f64, eur, NaiveDate::from_ymd(2020, 1, 15)),
+            );
+            db.insert_transaction(&transaction).unwrap();
+        }
+        let quantity = |t: &Transaction| match t.transaction_type {
+            TransactionType::Asset { position, .. } => position,
+            _ => 0.,
+        };
+        let mut total = 0.;
+        let mut count = 0;
+        db.visit_transactions(|t| {
+            total += quantity(t);
+            count += 1;
+        })
+        .unwrap();
+        let all = db.get_all_transactions().unwrap();
+        assert_eq!(count, all.len());
+        assert_eq!(total, all.iter().map(quantity).sum::<f64>());
+        assert_eq!(total, 100.);
+    }
+
+    #[test]
+    fn large_ids_round_trip() {
+        let mut db = InMemoryDB::new();
+        let large_id = u32::MAX as u64 + 1;
+        db.assets.next_id = large_id;
+        db.transactions.next_id = large_id + 10;
+        let asset_id = db
+            .insert_asset(&Asset::new(None, "Big Corp", None, None, None))
+            .unwrap();
+        assert_eq!(asset_id, large_id);
+        assert_eq!(db.get_asset_by_id(asset_id).unwrap().id, Some(large_id));
+
+        let eur = Currency::from_str("EUR").unwrap();
+        let buy = Transaction::new(
+            TransactionType::Asset {
+                asset_id,
+                position: 10.,
+            },
+            CashFlow::new(-1000., eur, NaiveDate::from_ymd(2020, 1, 15)),
+        );
+        let buy_id = db.insert_transaction(&buy).unwrap();
+        assert_eq!(buy_id, large_id + 10);
+        let fee = Transaction::new(
+            TransactionType::Fee {
+                transaction_ref: Some(buy_id),
+            },
+            CashFlow::new(-5., eur, NaiveDate::from_ymd(2020, 1, 15)),
+        );
+        let fee_id = db.insert_transaction(&fee).unwrap();
+
+        let buy = db.get_transaction_by_id(buy_id).unwrap();
+        assert_eq!(buy.id, Some(buy_id));
+        match buy.transaction_type {
+            TransactionType::Asset { asset_id: id, .. } => assert_eq!(id, large_id),
+            _ => panic!("wrong transaction type"),
+        }
+        match db.get_transaction_by_id(fee_id).unwrap().transaction_type {
+            TransactionType::Fee { transaction_ref } => assert_eq!(transaction_ref, Some(buy_id)),
+            _ => panic!("wrong transaction type"),
+        }
+        let page = db.get_transactions_after_id(buy_id, 10).unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].id, Some(fee_id));
+    }
+
+    #[test]
+    fn transaction_tags_and_note_search() {
+        let mut db = InMemoryDB::new();
+        let eur = Currency::from_str("EUR").unwrap();
+        let mut add = |note: Option<&str>, tags: &[&str]| {
+            let transaction = Transaction {
+                note: note.map(|note| note.to_string()),
+                tags: tags.iter().map(|tag| tag.to_string()).collect(),
+                ..Transaction::new(
+                    TransactionType::Cash,
+                    CashFlow::new(100., eur, NaiveDate::from_ymd(2020, 1, 15)),
+                )
+            };
+            db.insert_transaction(&transaction).unwrap()
+        };
+        let harvest = add(Some("Tax-loss harvest"), &[" Tax ", "tax", ""]);
+        let plan = add(Some("employer stock plan"), &["Employer", "TAX"]);
+        let plain = add(None, &[]);
+
+        assert_eq!(db.get_transaction_by_id(harvest).unwrap().tags, vec!["tax"]);
+        let ids = |transactions: Vec<Transaction>| -> Vec<Option<u64>> {
+            transactions.iter().map(|t| t.id).collect()
+        };
+        assert_eq!(
+            ids(db.get_transactions_by_tag("tax").unwrap()),
+            vec![Some(harvest), Some(plan)]
+        );
+        assert_eq!(
+            ids(db.get_transactions_by_tag(" EMPLOYER").unwrap()),
+            vec![Some(plan)]
+        );
+        assert!(db.get_transactions_by_tag("esg").unwrap().is_empty());
+
+        assert_eq!(
+            ids(db.search_transactions("HARVEST").unwrap()),
+            vec![Some(harvest)]
+        );
+        assert_eq!(
+            ids(db.search_transactions("st").unwrap()),
+            vec![Some(harvest), Some(plan)]
+        );
+        assert!(db.search_transactions("dividend").unwrap().is_empty());
+        assert!(!db.get_transaction_by_id(plain).unwrap().note_contains(""));
+    }
+
+    #[test]
+    fn transactions_matching_filter() {
+        let mut db = InMemoryDB::new();
+        let eur = Currency::from_str("EUR").unwrap();
+        let mut add = |transaction_type: TransactionType, amount: f64, day: u32| {
+            let transaction = Transaction::new(
+                transaction_type,
+                CashFlow::new(amount, eur, NaiveDate::from_ymd(2020, 3, day)),
+            );
+            db.insert_transaction(&transaction).unwrap()
+        };
+        let buy = |asset_id: u64, position: f64| TransactionType::Asset { asset_id, position };
+        let early = add(buy(1, 10.), -1000., 2);
+        let small = add(buy(1, 1.), -100., 10);
+        let large = add(buy(1, 20.), -2000., 12);
+        add(buy(2, 10.), -500., 10);
+        add(TransactionType::Dividend { asset_id: 1 }, 50., 11);
+        add(TransactionType::Cash, 5000., 1);
+
+        let ids = |transactions: Vec<Transaction>| -> Vec<Option<u64>> {
+            transactions.iter().map(|t| t.id).collect()
+        };
+        let filter = TransactionFilter::new()
+            .asset_id(1)
+            .kind(TransactionKind::Asset)
+            .date_range(
+                NaiveDate::from_ymd(2020, 3, 5),
+                NaiveDate::from_ymd(2020, 3, 31),
+            );
+        assert_eq!(
+            ids(db.get_transactions_matching(&filter).unwrap()),
+            vec![Some(small), Some(large)]
+        );
+        let filter = filter.max_amount(-1000.);
+        assert_eq!(
+            ids(db.get_transactions_matching(&filter).unwrap()),
+            vec![Some(large)]
+        );
+        let filter = TransactionFilter::new().asset_id(1).min_amount(-1000.);
+        assert_eq!(db.get_transactions_matching(&filter).unwrap().len(), 3);
+        assert!(ids(db.get_transactions_matching(&filter).unwrap()).contains(&Some(early)));
+        let all = TransactionFilter::new();
+        assert_eq!(db.get_transactions_matching(&all).unwrap().len(), 6);
+    }
 }
@@ -0,0 +1,411 @@
+//! Long-only mean-variance optimization of portfolio weights. Weights always sum up to one
+//! and are bounded per asset. The optimization problems are solved by projected gradient
+//! descent, where the projection onto the feasible weights is calculated exactly.
+
+use crate::math::bisect;
+use std::error::Error;
+use std::fmt;
+
+/// Maximal number of gradient steps of a single mean-variance optimization
+const MAX_ITERATIONS: usize = 100_000;
+
+/// Iteration stops once no weight changes by more than this
+const WEIGHT_TOLERANCE: f64 = 1e-13;
+
+/// Error related to the optimization of portfolio weights
+#[derive(Debug, PartialEq)]
+pub enum OptimizeError {
+    /// The covariance matrix is not square, it or the expected returns contain non-finite
+    /// values, or it does not fit to the number of expected returns or weight bounds
+    InvalidDimensions,
+    /// The lower weight bound of an asset is negative or above its upper bound
+    InvalidBounds {
+        asset: usize,
+        min: f64,
+        max: f64,
+    },
+    /// The weights can't sum up to one within their bounds
+    InfeasibleBounds {
+        min_sum: f64,
+        max_sum: f64,
+    },
+    /// No portfolio has an expected return above the risk free rate
+    NoExcessReturn,
+    NotConverged,
+}
+
+impl fmt::Display for OptimizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OptimizeError::InvalidDimensions => {
+                write!(
+                    f,
+                    "dimensions of covariance matrix and constraints don't match"
+                )
+            }
+            OptimizeError::InvalidBounds { asset, min, max } => write!(
+                f,
+                "invalid weight bounds [{}, {}] of asset {}",
+                min, max, asset
+            ),
+            OptimizeError::InfeasibleBounds { min_sum, max_sum } => write!(
+                f,
+                "weights can't sum up to one, the minimal weights sum up to {} \
+                and the maximal weights to {}",
+                min_sum, max_sum
+            ),
+            OptimizeError::NoExcessReturn => {
+                write!(f, "no expected return is above the risk free rate")
+            }
+            OptimizeError::NotConverged => write!(f, "optimization did not converge"),
+        }
+    }
+}
+
+impl Error for OptimizeError {}
+
+/// Lower and upper bounds of the weights of each asset, in addition to the weights
+/// summing up to one
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeightConstraints {
+    pub min_weights: Vec<f64>,
+    pub max_weights: Vec<f64>,
+}
+
+impl WeightConstraints {
+    /// Constraints of `n` assets with weights between zero and one
+    pub fn long_only(n: usize) -> WeightConstraints {
+        WeightConstraints {
+            min_weights: vec![0.; n],
+            max_weights: vec![1.; n],
+        }
+    }
+
+    /// Set the bounds of the weight of the asset with index `asset`
+    pub fn with_bounds(mut self, asset: usize, min: f64, max: f64) -> WeightConstraints {
+        self.min_weights[asset] = min;
+        self.max_weights[asset] = max;
+        self
+    }
+
+    fn validate(&self, n: usize) -> Result<(), OptimizeError> {
+        if self.min_weights.len() != n || self.max_weights.len() != n {
+            return Err(OptimizeError::InvalidDimensions);
+        }
+        for (asset, (&min, &max)) in self.min_weights.iter().zip(&self.max_weights).enumerate() {
+            // also fails for NaN bounds
+            let valid = min >= 0. && min <= max;
+            if !valid {
+                return Err(OptimizeError::InvalidBounds { asset, min, max });
+            }
+        }
+        let min_sum: f64 = self.min_weights.iter().sum();
+        let max_sum: f64 = self.max_weights.iter().sum();
+        if min_sum > 1. || max_sum < 1. {
+            return Err(OptimizeError::InfeasibleBounds { min_sum, max_sum });
+        }
+        Ok(())
+    }
+
+    /// Closest weights to `weights` which satisfy the constraints, i.e. the weights clamped
+    /// to their bounds after subtracting the same amount from each weight, such that they
+    /// sum up to one. The sum of the clamped weights is piecewise linear in the amount with
+    /// kinks where a weight reaches a bound, so the amount is found exactly by sweeping
+    /// through the kinks in ascending order.
+    fn project(&self, weights: &[f64]) -> Vec<f64> {
+        // amounts at which a weight leaves its upper bound (true) or reaches its lower bound
+        let mut kinks: Vec<(f64, bool)> = weights
+            .iter()
+            .zip(self.min_weights.iter().zip(&self.max_weights))
+            .flat_map(|(w, (min, max))| vec![(w - max, true), (w - min, false)])
+            .collect();
+        kinks.sort_by(|a, b| a.0.total_cmp(&b.0).then(b.1.cmp(&a.1)));
+        // sum of the clamped weights at `shift` and number of weights strictly within bounds
+        let mut sum: f64 = self.max_weights.iter().sum();
+        let mut free = 0;
+        let mut shift = kinks.first().map_or(0., |(kink, _)| *kink);
+        for (kink, leaves_max) in kinks {
+            let next_sum = sum - free as f64 * (kink - shift);
+            if next_sum <= 1. {
+                // the bounds are feasible, hence the sum is at least one up to here
+                if free > 0 {
+                    shift += (sum - 1.) / free as f64;
+                }
+                break;
+            }
+            sum = next_sum;
+            shift = kink;
+            if leaves_max {
+                free += 1;
+            } else {
+                free -= 1;
+            }
+        }
+        weights
+            .iter()
+            .zip(self.min_weights.iter().zip(&self.max_weights))
+            .map(|(w, (min, max))| (w - shift).max(*min).min(*max))
+            .collect()
+    }
+}
+
+/// Optimal portfolio weights with their risk and, if expected returns are given, return
+#[derive(Debug, Clone, PartialEq)]
+pub struct OptimalWeights {
+    pub weights: Vec<f64>,
+    /// Standard deviation of the portfolio return
+    pub volatility: f64,
+    pub expected_return: Option<f64>,
+}
+
+fn variance(cov: &[Vec<f64>], weights: &[f64]) -> f64 {
+    weights
+        .iter()
+        .zip(cov)
+        .map(|(w, row)| w * dot(row, weights))
+        .sum()
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+fn validate_covariance(cov: &[Vec<f64>]) -> Result<(), OptimizeError> {
+    let n = cov.len();
+    let valid = n > 0
+        && cov
+            .iter()
+            .all(|row| row.len() == n && row.iter().all(|x| x.is_finite()));
+    if valid {
+        Ok(())
+    } else {
+        Err(OptimizeError::InvalidDimensions)
+    }
+}
+
+/// Weights minimizing `risk_aversion / 2 * w' cov w - returns' w` subject to `constraints`
+/// by projected gradient descent, starting with equal weights. The step size is the
+/// inverse of an upper bound of the largest eigenvalue of the Hessian.
+fn mean_variance(
+    cov: &[Vec<f64>],
+    returns: &[f64],
+    risk_aversion: f64,
+    constraints: &WeightConstraints,
+) -> Result<Vec<f64>, OptimizeError> {
+    let n = cov.len();
+    let mut weights = constraints.project(&vec![1. / n as f64; n]);
+    let max_eigenvalue = cov
+        .iter()
+        .map(|row| row.iter().map(|x| x.abs()).sum::<f64>())
+        .fold(0., f64::max);
+    if max_eigenvalue == 0. && returns.iter().all(|r| *r == 0.) {
+        return Ok(weights);
+    }
+    let step = 1. / (risk_aversion * max_eigenvalue).max(f64::EPSILON);
+    for _ in 0..MAX_ITERATIONS {
+        let moved: Vec<f64> = weights
+            .iter()
+            .zip(cov.iter().zip(returns))
+            .map(|(w, (row, r))| w - step * (risk_aversion * dot(row, &weights) - r))
+            .collect();
+        let next = constraints.project(&moved);
+        let converged = next
+            .iter()
+            .zip(&weights)
+            .all(|(a, b)| (a - b).abs() <= WEIGHT_TOLERANCE);
+        weights = next;
+        if converged {
+            return Ok(weights);
+        }
+    }
+    Err(OptimizeError::NotConverged)
+}
+
+/// Weights with minimal variance of the portfolio return for the covariance matrix `cov`
+/// of the asset returns
+pub fn min_variance(
+    cov: &[Vec<f64>],
+    constraints: &WeightConstraints,
+) -> Result<OptimalWeights, OptimizeError> {
+    validate_covariance(cov)?;
+    constraints.validate(cov.len())?;
+    let weights = mean_variance(cov, &vec![0.; cov.len()], 1., constraints)?;
+    Ok(OptimalWeights {
+        volatility: variance(cov, &weights).sqrt(),
+        expected_return: None,
+        weights,
+    })
+}
+
+/// Weights with maximal Sharpe ratio, i.e. excess return over `risk_free` per volatility,
+/// for the `expected_returns` and covariance matrix `cov` of the asset returns.
+/// The optimality conditions of the Sharpe ratio equal those of the mean-variance problem
+/// with a risk aversion of the excess return per variance of the optimal weights.
+/// Therefore, this risk aversion is searched for by bisection.
+pub fn max_sharpe(
+    expected_returns: &[f64],
+    cov: &[Vec<f64>],
+    risk_free: f64,
+    constraints: &WeightConstraints,
+) -> Result<OptimalWeights, OptimizeError> {
+    validate_covariance(cov)?;
+    let valid =
+        expected_returns.len() == cov.len() && expected_returns.iter().all(|r| r.is_finite());
+    if !valid {
+        return Err(OptimizeError::InvalidDimensions);
+    }
+    constraints.validate(cov.len())?;
+    // the best excess return within the bounds is achieved by filling up the assets
+    // with the highest returns first, which must be positive for a positive Sharpe ratio
+    let mut order: Vec<usize> = (0..cov.len()).collect();
+    order.sort_by(|&a, &b| expected_returns[b].total_cmp(&expected_returns[a]));
+    let mut best = constraints.min_weights.clone();
+    let mut remaining = 1. - best.iter().sum::<f64>();
+    for asset in order {
+        let add = remaining.min(constraints.max_weights[asset] - best[asset]);
+        best[asset] += add;
+        remaining -= add;
+    }
+    if dot(expected_returns, &best) <= risk_free {
+        return Err(OptimizeError::NoExcessReturn);
+    }
+
+    let excess: Vec<f64> = expected_returns.iter().map(|r| r - risk_free).collect();
+    let mismatch =
+        |risk_aversion: f64| match mean_variance(cov, &excess, risk_aversion, constraints) {
+            Ok(w) => risk_aversion - dot(&excess, &w) / variance(cov, &w),
+            Err(_) => f64::NAN,
+        };
+    let low = 1e-8;
+    let mut high = 1.;
+    while mismatch(high) < 0. && high < 1e12 {
+        high *= 2.;
+    }
+    // with a mismatch already at the lowest risk aversion, the weights with the best
+    // excess return have the best Sharpe ratio
+    let risk_aversion = if mismatch(low) >= 0. {
+        low
+    } else {
+        bisect(mismatch, low, high, 1e-13 * high).ok_or(OptimizeError::NotConverged)?
+    };
+    let weights = mean_variance(cov, &excess, risk_aversion, constraints)?;
+    Ok(OptimalWeights {
+        volatility: variance(cov, &weights).sqrt(),
+        expected_return: Some(dot(expected_returns, &weights)),
+        weights,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Covariance matrix of assets with the given volatilities and pairwise correlation
+    fn covariance(vols: &[f64], correlation: f64) -> Vec<Vec<f64>> {
+        vols.iter()
+            .enumerate()
+            .map(|(i, a)| {
+                vols.iter()
+                    .enumerate()
+                    .map(|(j, b)| if i == j { a * a } else { correlation * a * b })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn two_asset_min_variance() {
+        // w = (0.09 - 0.015) / (0.04 + 0.09 - 2 * 0.015) = 0.75
+        let cov = covariance(&[0.2, 0.3], 0.25);
+        let constraints = WeightConstraints::long_only(2);
+        let optimal = min_variance(&cov, &constraints).unwrap();
+        assert_fuzzy_eq!(optimal.weights[0], 0.75, 1e-9);
+        assert_fuzzy_eq!(optimal.weights[1], 0.25, 1e-9);
+        let variance = 0.75_f64.powi(2) * 0.04 + 0.25_f64.powi(2) * 0.09 + 2. * 0.75 * 0.25 * 0.015;
+        assert_fuzzy_eq!(optimal.volatility, variance.sqrt(), 1e-9);
+        assert_eq!(optimal.expected_return, None);
+
+        // binding upper bound
+        let optimal = min_variance(&cov, &constraints.with_bounds(0, 0., 0.6)).unwrap();
+        assert_fuzzy_eq!(optimal.weights[0], 0.6, 1e-9);
+        assert_fuzzy_eq!(optimal.weights[1], 0.4, 1e-9);
+    }
+
+    #[test]
+    fn max_sharpe_weights() {
+        let cov = covariance(&[0.2, 0.2, 0.2], 0.3);
+        let constraints = WeightConstraints::long_only(3);
+        let optimal = max_sharpe(&[0.08, 0.08, 0.08], &cov, 0.01, &constraints).unwrap();
+        for w in &optimal.weights {
+            assert_fuzzy_eq!(*w, 1. / 3., 1e-9);
+        }
+        assert_fuzzy_eq!(optimal.expected_return.unwrap(), 0.08, 1e-9);
+
+        // tangency portfolio of uncorrelated assets is proportional to (0.04 / 0.04, 0.045 / 0.09)
+        let cov = covariance(&[0.2, 0.3], 0.);
+        let constraints = WeightConstraints::long_only(2);
+        let optimal = max_sharpe(&[0.05, 0.055], &cov, 0.01, &constraints).unwrap();
+        assert_fuzzy_eq!(optimal.weights[0], 2. / 3., 1e-7);
+        assert_fuzzy_eq!(optimal.weights[1], 1. / 3., 1e-7);
+
+        assert_eq!(
+            max_sharpe(&[0.01, 0.005], &cov, 0.01, &constraints),
+            Err(OptimizeError::NoExcessReturn)
+        );
+        assert_eq!(
+            max_sharpe(&[0.05, f64::NAN], &cov, 0.01, &constraints),
+            Err(OptimizeError::InvalidDimensions)
+        );
+    }
+
+    #[test]
+    fn projection_onto_bounded_weights() {
+        let assert_projection =
+            |constraints: &WeightConstraints, weights: &[f64], expected: &[f64]| {
+                let projected = constraints.project(weights);
+                for (w, e) in projected.iter().zip(expected) {
+                    assert_fuzzy_eq!(*w, *e, 1e-15);
+                }
+            };
+        let constraints = WeightConstraints::long_only(3);
+        assert_projection(&constraints, &[0.5, 0.5, 0.5], &[1. / 3.; 3]);
+        assert_projection(&constraints, &[2., 0.5, -1.], &[1., 0., 0.]);
+        assert_projection(&constraints, &[0.7, 0.5, 0.1], &[0.6, 0.4, 0.]);
+
+        // bounds with a fixed weight
+        let constraints = constraints
+            .with_bounds(0, 0.1, 0.4)
+            .with_bounds(2, 0.2, 0.2);
+        assert_projection(&constraints, &[0.8, 0.1, 0.], &[0.4, 0.4, 0.2]);
+        assert_projection(&constraints, &[0., 0., 0.], &[0.4, 0.4, 0.2]);
+        assert_projection(&constraints, &[0.3, 0.6, 0.3], &[0.25, 0.55, 0.2]);
+    }
+
+    #[test]
+    fn infeasible_constraints() {
+        let cov = covariance(&[0.2, 0.3], 0.25);
+        let constraints = WeightConstraints::long_only(2)
+            .with_bounds(0, 0.6, 1.)
+            .with_bounds(1, 0.5, 1.);
+        assert_eq!(
+            min_variance(&cov, &constraints),
+            Err(OptimizeError::InfeasibleBounds {
+                min_sum: 1.1,
+                max_sum: 2.
+            })
+        );
+        let constraints = WeightConstraints::long_only(2).with_bounds(1, 0.5, 0.4);
+        assert_eq!(
+            min_variance(&cov, &constraints),
+            Err(OptimizeError::InvalidBounds {
+                asset: 1,
+                min: 0.5,
+                max: 0.4
+            })
+        );
+        assert_eq!(
+            min_variance(&cov, &WeightConstraints::long_only(3)),
+            Err(OptimizeError::InvalidDimensions)
+        );
+    }
+}
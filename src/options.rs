@@ -0,0 +1,566 @@
+//! Valuation of European options on a single underlying asset with the Black-Scholes model.
+//! Prices and greeks refer to an option on one unit of the underlying; the contract size
+//! is applied when valuing positions. Theta is the change of value per year and vega and
+//! rho the change of value per unit change of volatility and interest rate, respectively.
+
+use crate::day_count_conv::{DayCountConv, DayCountConvError};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::f64::consts::PI;
+use std::fmt;
+
+/// Error related to the valuation of options
+#[derive(Debug)]
+pub enum OptionError {
+    /// Time to expiry could not be calculated with the given day count convention
+    DayCountError(DayCountConvError),
+    /// Quote of the underlying could not be retrieved
+    #[cfg(feature = "persistence")]
+    MarketDataError(crate::data_handler::DataError),
+    /// The volatility of an option before expiry is not positive
+    InvalidVolatility(f64),
+}
+
+impl fmt::Display for OptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OptionError::DayCountError(_) => write!(f, "calculation of time to expiry failed"),
+            #[cfg(feature = "persistence")]
+            OptionError::MarketDataError(_) => write!(f, "fetching quote of underlying failed"),
+            OptionError::InvalidVolatility(vol) => {
+                write!(f, "volatility must be positive, got {}", vol)
+            }
+        }
+    }
+}
+
+impl Error for OptionError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            OptionError::DayCountError(err) => Some(err),
+            #[cfg(feature = "persistence")]
+            OptionError::MarketDataError(err) => Some(err),
+            OptionError::InvalidVolatility(_) => None,
+        }
+    }
+}
+
+impl From<DayCountConvError> for OptionError {
+    fn from(error: DayCountConvError) -> Self {
+        OptionError::DayCountError(error)
+    }
+}
+
+#[cfg(feature = "persistence")]
+impl From<crate::data_handler::DataError> for OptionError {
+    fn from(error: crate::data_handler::DataError) -> Self {
+        OptionError::MarketDataError(error)
+    }
+}
+
+/// Error in the calculation of implied volatilities
+#[derive(Debug)]
+pub enum SolveError {
+    /// The price is below the lower no-arbitrage bound, i.e. the discounted intrinsic value
+    BelowIntrinsic { price: f64, lower_bound: f64 },
+    /// The price is above the upper no-arbitrage bound, i.e. the discounted spot price
+    /// for calls and the discounted strike for puts
+    AboveUpperBound { price: f64, upper_bound: f64 },
+    /// The option has expired, its price does not depend on volatility
+    Expired,
+    /// The solver did not converge within the maximal number of iterations
+    NoConvergence,
+    /// Pricing the option failed
+    ValuationError(OptionError),
+}
+
+impl fmt::Display for SolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SolveError::BelowIntrinsic { price, lower_bound } => write!(
+                f,
+                "price {} is below the discounted intrinsic value {}",
+                price, lower_bound
+            ),
+            SolveError::AboveUpperBound { price, upper_bound } => write!(
+                f,
+                "price {} is above the no-arbitrage upper bound {}",
+                price, upper_bound
+            ),
+            SolveError::Expired => write!(f, "implied volatility of expired option is undefined"),
+            SolveError::NoConvergence => {
+                write!(f, "calculation of implied volatility did not converge")
+            }
+            SolveError::ValuationError(_) => write!(f, "valuation of option failed"),
+        }
+    }
+}
+
+impl Error for SolveError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SolveError::ValuationError(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<OptionError> for SolveError {
+    fn from(error: OptionError) -> Self {
+        SolveError::ValuationError(error)
+    }
+}
+
+/// Maximal number of iterations of the implied volatility solver
+const MAX_ITERATIONS: usize = 200;
+
+/// Right of the option holder to buy (call) or sell (put) the underlying
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum PutCall {
+    #[serde(rename = "put")]
+    Put,
+    #[serde(rename = "call")]
+    Call,
+}
+
+/// European option, i.e. an option that could be exercised at expiry only
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EuropeanOption {
+    /// Id of the underlying asset
+    pub underlying_asset: u64,
+    pub strike: f64,
+    pub expiry: NaiveDate,
+    pub put_call: PutCall,
+    /// Number of units of the underlying per option contract
+    pub contract_size: f64,
+}
+
+/// Sensitivities of the option price with respect to the model parameters
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Greeks {
+    pub delta: f64,
+    pub gamma: f64,
+    pub vega: f64,
+    pub theta: f64,
+    pub rho: f64,
+}
+
+/// Cumulative distribution function of the standard normal distribution,
+/// accurate to double precision (W. J. Cody's rational approximation as given by
+/// G. West, "Better approximations to cumulative normal functions", 2005)
+pub fn normal_cdf(x: f64) -> f64 {
+    let xabs = x.abs();
+    let tail = if xabs > 37. {
+        0.
+    } else {
+        let e = (-xabs * xabs / 2.).exp();
+        if xabs < 7.071_067_811_865_47 {
+            let num = [
+                3.526_249_659_989_11e-2,
+                0.700_383_064_443_688,
+                6.373_962_203_531_65,
+                33.912_866_078_383,
+                112.079_291_497_871,
+                221.213_596_169_931,
+                220.206_867_912_376,
+            ];
+            let den = [
+                8.838_834_764_831_84e-2,
+                1.755_667_163_182_64,
+                16.064_177_579_207,
+                86.780_732_202_946_1,
+                296.564_248_779_674,
+                637.333_633_378_831,
+                793.826_512_519_948,
+                440.413_735_824_752,
+            ];
+            let num = num.iter().fold(0., |acc, c| acc * xabs + c);
+            let den = den.iter().fold(0., |acc, c| acc * xabs + c);
+            e * num / den
+        } else {
+            let mut b = xabs + 0.65;
+            for n in (1..5).rev() {
+                b = xabs + n as f64 / b;
+            }
+            e / b / (2. * PI).sqrt()
+        }
+    };
+    if x > 0. {
+        1. - tail
+    } else {
+        tail
+    }
+}
+
+/// Volatilities of options before expiry must be positive, otherwise prices are undefined
+fn check_vol(vol: f64) -> Result<(), OptionError> {
+    if vol > 0. {
+        Ok(())
+    } else {
+        Err(OptionError::InvalidVolatility(vol))
+    }
+}
+
+/// Density of the standard normal distribution
+fn normal_pdf(x: f64) -> f64 {
+    (-x * x / 2.).exp() / (2. * PI).sqrt()
+}
+
+impl EuropeanOption {
+    /// Black-Scholes price of an option on one unit of the underlying at date `as_of`, given the
+    /// `spot` price of the underlying, its volatility `vol`, the continuously compounded interest
+    /// `rate` and `dividend_yield`. The time to expiry is calculated with `day_count`, which
+    /// must not require a roll date, i.e. Act/Act ICMA is not supported.
+    /// At or after expiry, the option is valued at its intrinsic value, before expiry `vol` must
+    /// be positive.
+    pub fn price(
+        &self,
+        spot: f64,
+        vol: f64,
+        rate: f64,
+        dividend_yield: f64,
+        as_of: NaiveDate,
+        day_count: DayCountConv,
+    ) -> Result<f64, OptionError> {
+        let t = match self.time_to_expiry(as_of, day_count)? {
+            Some(t) => t,
+            None => return Ok(self.intrinsic_value(spot)),
+        };
+        check_vol(vol)?;
+        let (d1, d2) = self.d1_d2(spot, vol, rate, dividend_yield, t);
+        let spot_df = spot * (-dividend_yield * t).exp();
+        let strike_df = self.strike * (-rate * t).exp();
+        Ok(match self.put_call {
+            PutCall::Call => spot_df * normal_cdf(d1) - strike_df * normal_cdf(d2),
+            PutCall::Put => strike_df * normal_cdf(-d2) - spot_df * normal_cdf(-d1),
+        })
+    }
+
+    /// Black-Scholes greeks for the same parameters as `price`. At or after expiry,
+    /// delta is 1 (or -1 for puts) if the option is in the money and all other greeks are zero.
+    pub fn greeks(
+        &self,
+        spot: f64,
+        vol: f64,
+        rate: f64,
+        dividend_yield: f64,
+        as_of: NaiveDate,
+        day_count: DayCountConv,
+    ) -> Result<Greeks, OptionError> {
+        let sign = match self.put_call {
+            PutCall::Call => 1.,
+            PutCall::Put => -1.,
+        };
+        let t = match self.time_to_expiry(as_of, day_count)? {
+            Some(t) => t,
+            None => {
+                let delta = if self.intrinsic_value(spot) > 0. {
+                    sign
+                } else {
+                    0.
+                };
+                return Ok(Greeks {
+                    delta,
+                    gamma: 0.,
+                    vega: 0.,
+                    theta: 0.,
+                    rho: 0.,
+                });
+            }
+        };
+        check_vol(vol)?;
+        let (d1, d2) = self.d1_d2(spot, vol, rate, dividend_yield, t);
+        let spot_df = spot * (-dividend_yield * t).exp();
+        let strike_df = self.strike * (-rate * t).exp();
+        let n_d1 = normal_cdf(sign * d1);
+        let n_d2 = normal_cdf(sign * d2);
+        Ok(Greeks {
+            delta: sign * (-dividend_yield * t).exp() * n_d1,
+            gamma: (-dividend_yield * t).exp() * normal_pdf(d1) / (spot * vol * t.sqrt()),
+            vega: spot_df * normal_pdf(d1) * t.sqrt(),
+            theta: -spot_df * normal_pdf(d1) * vol / (2. * t.sqrt())
+                - sign * rate * strike_df * n_d2
+                + sign * dividend_yield * spot_df * n_d1,
+            rho: sign * strike_df * t * n_d2,
+        })
+    }
+
+    /// Volatility for which the Black-Scholes price equals the given `price`, with all other
+    /// parameters as for `price`. Newton steps are used as long as they stay within a bracket
+    /// of the solution, otherwise the bracket is bisected, which is robust for deep in or out
+    /// of the money options with vanishing vega. Prices violating the no-arbitrage bounds
+    /// result in an error.
+    pub fn implied_vol(
+        &self,
+        price: f64,
+        spot: f64,
+        rate: f64,
+        dividend_yield: f64,
+        as_of: NaiveDate,
+        day_count: DayCountConv,
+    ) -> Result<f64, SolveError> {
+        let t = self
+            .time_to_expiry(as_of, day_count)?
+            .ok_or(SolveError::Expired)?;
+        let spot_df = spot * (-dividend_yield * t).exp();
+        let strike_df = self.strike * (-rate * t).exp();
+        let (lower_bound, upper_bound) = match self.put_call {
+            PutCall::Call => ((spot_df - strike_df).max(0.), spot_df),
+            PutCall::Put => ((strike_df - spot_df).max(0.), strike_df),
+        };
+        if price < lower_bound {
+            return Err(SolveError::BelowIntrinsic { price, lower_bound });
+        }
+        if price >= upper_bound {
+            return Err(SolveError::AboveUpperBound { price, upper_bound });
+        }
+        // price and vega for given volatility, the price increases with volatility
+        let price_and_vega = |vol: f64| {
+            let (d1, d2) = self.d1_d2(spot, vol, rate, dividend_yield, t);
+            let value = match self.put_call {
+                PutCall::Call => spot_df * normal_cdf(d1) - strike_df * normal_cdf(d2),
+                PutCall::Put => strike_df * normal_cdf(-d2) - spot_df * normal_cdf(-d1),
+            };
+            (value - price, spot_df * normal_pdf(d1) * t.sqrt())
+        };
+        let (mut low, mut high) = (0., 1.);
+        while price_and_vega(high).0 < 0. {
+            low = high;
+            high *= 2.;
+            if high > 1e3 {
+                return Err(SolveError::NoConvergence);
+            }
+        }
+        let tol = 1e-12 * price.max(1e-3);
+        let mut vol = 0.5 * (low + high);
+        for _ in 0..MAX_ITERATIONS {
+            let (diff, vega) = price_and_vega(vol);
+            if diff.abs() < tol {
+                return Ok(vol);
+            }
+            if diff > 0. {
+                high = vol;
+            } else {
+                low = vol;
+            }
+            let newton = vol - diff / vega;
+            vol = if newton > low && newton < high {
+                newton
+            } else {
+                0.5 * (low + high)
+            };
+            if high - low < 1e-15 {
+                return Ok(vol);
+            }
+        }
+        Err(SolveError::NoConvergence)
+    }
+
+    /// Value of the option if exercised immediately, but not less than zero
+    pub fn intrinsic_value(&self, spot: f64) -> f64 {
+        match self.put_call {
+            PutCall::Call => (spot - self.strike).max(0.),
+            PutCall::Put => (self.strike - spot).max(0.),
+        }
+    }
+
+    /// Time to expiry in years, or `None` if the option has expired
+    fn time_to_expiry(
+        &self,
+        as_of: NaiveDate,
+        day_count: DayCountConv,
+    ) -> Result<Option<f64>, OptionError> {
+        if as_of >= self.expiry {
+            return Ok(None);
+        }
+        Ok(Some(day_count.year_fraction(
+            as_of,
+            self.expiry,
+            None,
+            None,
+        )?))
+    }
+
+    fn d1_d2(&self, spot: f64, vol: f64, rate: f64, dividend_yield: f64, t: f64) -> (f64, f64) {
+        let vol_t = vol * t.sqrt();
+        let d1 = ((spot / self.strike).ln() + (rate - dividend_yield + vol * vol / 2.) * t) / vol_t;
+        (d1, d1 - vol_t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn option(put_call: PutCall, strike: f64, expiry: NaiveDate) -> EuropeanOption {
+        EuropeanOption {
+            underlying_asset: 1,
+            strike,
+            expiry,
+            put_call,
+            contract_size: 100.,
+        }
+    }
+
+    #[test]
+    fn normal_distribution() {
+        assert_fuzzy_eq!(normal_cdf(0.), 0.5, 1e-15);
+        assert_fuzzy_eq!(normal_cdf(1.96), 0.975_002_104_851_780, 1e-14);
+        assert_fuzzy_eq!(normal_cdf(-1.), 0.158_655_253_931_457, 1e-14);
+        assert_fuzzy_eq!(normal_cdf(-8.), 6.220_960_574_271_78e-16, 1e-23);
+    }
+
+    #[test]
+    fn black_scholes_textbook_prices() {
+        // Hull, Options, Futures, and Other Derivatives, Example 15.6:
+        // S = 42, K = 40, r = 10%, vol = 20%, T = 0.5
+        let as_of = NaiveDate::from_ymd(2020, 1, 1);
+        let expiry = NaiveDate::from_ymd(2020, 6, 29);
+        let dc = DayCountConv::Act360;
+        let call = option(PutCall::Call, 40., expiry);
+        let put = option(PutCall::Put, 40., expiry);
+        let call_price = call.price(42., 0.2, 0.1, 0., as_of, dc).unwrap();
+        let put_price = put.price(42., 0.2, 0.1, 0., as_of, dc).unwrap();
+        assert_fuzzy_eq!(call_price, 4.7594, 1e-4);
+        assert_fuzzy_eq!(put_price, 0.8086, 1e-4);
+
+        // Hull, Section 19: S = 49, K = 50, r = 5%, vol = 20%, T = 20 weeks,
+        // approximated by 140 days, i.e. T = 0.3836 instead of 0.3846
+        let expiry = NaiveDate::from_ymd(2020, 5, 20);
+        let dc = DayCountConv::Act365;
+        let greeks = option(PutCall::Call, 50., expiry)
+            .greeks(49., 0.2, 0.05, 0., as_of, dc)
+            .unwrap();
+        assert_fuzzy_eq!(greeks.delta, 0.522, 2e-3);
+        assert_fuzzy_eq!(greeks.gamma, 0.066, 1e-3);
+        assert_fuzzy_eq!(greeks.vega, 12.1, 0.05);
+        assert_fuzzy_eq!(greeks.theta, -4.31, 0.01);
+        assert_fuzzy_eq!(greeks.rho, 8.91, 0.05);
+        // greeks are consistent with finite differences of the price
+        let call = option(PutCall::Call, 50., expiry);
+        let price = |spot: f64, vol: f64| call.price(spot, vol, 0.05, 0., as_of, dc).unwrap();
+        let h = 1e-4;
+        assert_fuzzy_eq!(
+            greeks.delta,
+            (price(49. + h, 0.2) - price(49. - h, 0.2)) / (2. * h),
+            1e-7
+        );
+        assert_fuzzy_eq!(
+            greeks.vega,
+            (price(49., 0.2 + h) - price(49., 0.2 - h)) / (2. * h),
+            1e-6
+        );
+    }
+
+    #[test]
+    fn put_call_parity() {
+        let as_of = NaiveDate::from_ymd(2020, 3, 2);
+        let expiry = NaiveDate::from_ymd(2021, 9, 17);
+        let dc = DayCountConv::Act365;
+        let (spot, vol, rate, q) = (95., 0.3, 0.02, 0.015);
+        let call = option(PutCall::Call, 100., expiry);
+        let put = option(PutCall::Put, 100., expiry);
+        let t = dc.year_fraction(as_of, expiry, None, None).unwrap();
+        let call_price = call.price(spot, vol, rate, q, as_of, dc).unwrap();
+        let put_price = put.price(spot, vol, rate, q, as_of, dc).unwrap();
+        assert_fuzzy_eq!(
+            call_price - put_price,
+            spot * (-q * t).exp() - 100. * (-rate * t).exp(),
+            1e-12
+        );
+        let call_greeks = call.greeks(spot, vol, rate, q, as_of, dc).unwrap();
+        let put_greeks = put.greeks(spot, vol, rate, q, as_of, dc).unwrap();
+        assert_fuzzy_eq!(call_greeks.delta - put_greeks.delta, (-q * t).exp(), 1e-12);
+        assert_fuzzy_eq!(call_greeks.gamma, put_greeks.gamma, 1e-12);
+        assert_fuzzy_eq!(call_greeks.vega, put_greeks.vega, 1e-12);
+    }
+
+    #[test]
+    fn expired_options_at_intrinsic_value() {
+        let expiry = NaiveDate::from_ymd(2020, 3, 20);
+        let after = NaiveDate::from_ymd(2020, 4, 1);
+        let dc = DayCountConv::Act365;
+        let call = option(PutCall::Call, 100., expiry);
+        let put = option(PutCall::Put, 100., expiry);
+        assert_eq!(call.price(110., 0.2, 0.01, 0., expiry, dc).unwrap(), 10.);
+        assert_eq!(put.price(110., 0.2, 0.01, 0., after, dc).unwrap(), 0.);
+        assert_eq!(put.price(90., 0.2, 0.01, 0., after, dc).unwrap(), 10.);
+        let greeks = put.greeks(90., 0.2, 0.01, 0., after, dc).unwrap();
+        assert_eq!(greeks.delta, -1.);
+        assert_eq!(greeks.gamma, 0.);
+        // the volatility only matters before expiry
+        assert_eq!(put.price(90., 0., 0.01, 0., after, dc).unwrap(), 10.);
+        let before = NaiveDate::from_ymd(2020, 1, 2);
+        for vol in &[0., -0.2, f64::NAN] {
+            assert!(matches!(
+                call.price(110., *vol, 0.01, 0., before, dc),
+                Err(OptionError::InvalidVolatility(_))
+            ));
+            assert!(matches!(
+                call.greeks(110., *vol, 0.01, 0., before, dc),
+                Err(OptionError::InvalidVolatility(_))
+            ));
+        }
+        // Act/Act ICMA can't be used without roll date
+        assert!(call
+            .price(110., 0.2, 0.01, 0., before, DayCountConv::ActActICMA)
+            .is_err());
+    }
+
+    #[test]
+    fn implied_vol_round_trip() {
+        let as_of = NaiveDate::from_ymd(2020, 3, 2);
+        let expiry = NaiveDate::from_ymd(2021, 3, 19);
+        let dc = DayCountConv::Act365;
+        let (rate, q) = (0.01, 0.02);
+        for put_call in &[PutCall::Call, PutCall::Put] {
+            for strike in &[20., 80., 100., 125., 300.] {
+                for vol in &[0.05, 0.2, 0.6, 1.5] {
+                    let option = option(*put_call, *strike, expiry);
+                    let price = option.price(100., *vol, rate, q, as_of, dc).unwrap();
+                    // prices indistinguishable from the lower bound determine no volatility
+                    if price < 1e-10 {
+                        continue;
+                    }
+                    let implied = option.implied_vol(price, 100., rate, q, as_of, dc).unwrap();
+                    let repriced = option.price(100., implied, rate, q, as_of, dc).unwrap();
+                    assert_fuzzy_eq!(repriced, price, 1e-8);
+                }
+            }
+        }
+        let call = option(PutCall::Call, 110., expiry);
+        let price = call.price(100., 0.3, rate, q, as_of, dc).unwrap();
+        let implied = call.implied_vol(price, 100., rate, q, as_of, dc).unwrap();
+        assert_fuzzy_eq!(implied, 0.3, 1e-10);
+    }
+
+    #[test]
+    fn implied_vol_out_of_bounds() {
+        let as_of = NaiveDate::from_ymd(2020, 3, 2);
+        let expiry = NaiveDate::from_ymd(2021, 3, 2);
+        let dc = DayCountConv::Act365;
+        let call = option(PutCall::Call, 80., expiry);
+        match call.implied_vol(15., 100., 0., 0., as_of, dc) {
+            Err(SolveError::BelowIntrinsic { lower_bound, .. }) => {
+                assert_fuzzy_eq!(lower_bound, 20., 1e-12)
+            }
+            _ => panic!("price below intrinsic value must be rejected"),
+        }
+        let err = call.implied_vol(101., 100., 0., 0., as_of, dc).unwrap_err();
+        assert!(matches!(err, SolveError::AboveUpperBound { .. }));
+        assert_eq!(
+            err.to_string(),
+            "price 101 is above the no-arbitrage upper bound 100"
+        );
+        let put = option(PutCall::Put, 80., expiry);
+        assert!(matches!(
+            put.implied_vol(80., 100., 0., 0., as_of, dc),
+            Err(SolveError::AboveUpperBound { .. })
+        ));
+        assert!(matches!(
+            call.implied_vol(20., 100., 0., 0., expiry, dc),
+            Err(SolveError::Expired)
+        ));
+    }
+}
@@ -0,0 +1,414 @@
+//! Attribution of the change of a portfolio's value between two dates.
+//! The change in value (converted to a base currency) is decomposed into
+//! investments and withdrawals, realized and unrealized gains from price moves,
+//! foreign exchange effects, dividends and interest, and fees and taxes.
+//! Sold positions are matched with purchases by the FIFO principle. For positions
+//! already held at the start date, the market value at the start date is used
+//! as purchase price.
+
+use crate::currency::Currency;
+use crate::data_handler::{DataError, DataHandler, QuoteHandler};
+use crate::fixed_income::AmountSum;
+use crate::fx_rates::get_fx_rate;
+use crate::helpers::end_of_day;
+use crate::transaction::{Transaction, TransactionType};
+use chrono::NaiveDate;
+use std::collections::{HashMap, VecDeque};
+
+/// Components of the change of value, all in base currency
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PnlComponents {
+    /// Investments (positive) and withdrawals (negative) of cash
+    pub flows: f64,
+    /// Gains from price moves of sold positions
+    pub realized: f64,
+    /// Gains from price moves of positions still held at the end date
+    pub unrealized: f64,
+    /// Effects of changes of foreign exchange rates
+    pub fx: f64,
+    /// Dividends and interest
+    pub income: f64,
+    /// Fees and taxes (usually negative)
+    pub costs: f64,
+}
+
+impl PnlComponents {
+    /// Sum of all components
+    pub fn sum(&self) -> f64 {
+        self.flows + self.realized + self.unrealized + self.fx + self.income + self.costs
+    }
+}
+
+/// Report on the change of a portfolio's value between two dates
+#[derive(Debug, Clone)]
+pub struct PnlReport {
+    pub currency: Currency,
+    pub start_value: f64,
+    pub end_value: f64,
+    /// Components for the total portfolio
+    pub total: PnlComponents,
+    /// Components per asset id; flows and fx effects on cash are reported in total only,
+    /// as are fees and taxes not related to an asset
+    pub per_asset: HashMap<u64, PnlComponents>,
+}
+
+impl PnlReport {
+    /// Part of the change in value not explained by the components,
+    /// which should vanish up to rounding errors
+    pub fn residual(&self) -> f64 {
+        self.end_value - self.start_value - self.total.sum()
+    }
+}
+
+/// Some units of an asset bought at a reference price
+pub(crate) struct Lot {
+    pub(crate) units: f64,
+    /// Price per unit in local currency
+    pub(crate) price: f64,
+    /// Fx rate to convert the local price to base currency
+    pub(crate) fx_rate: f64,
+    /// Id of the purchase, if the lot results from a stored transaction
+    pub(crate) purchase_id: Option<u64>,
+}
+
+/// Sell `units` of the lots of an asset by the FIFO principle, calling `f` with each lot
+/// sold from before reducing it and the number of its units sold. Fails if the lots
+/// contain less than `units` units.
+pub(crate) fn sell_fifo(
+    lots: &mut VecDeque<Lot>,
+    asset_id: u64,
+    units: f64,
+    mut f: impl FnMut(&Lot, f64),
+) -> Result<(), DataError> {
+    let mut to_sell = units;
+    while to_sell > 0. {
+        let lot = lots.front_mut().ok_or_else(|| {
+            DataError::InvalidTransaction(format!(
+                "sale of asset {} exceeds current position",
+                asset_id
+            ))
+        })?;
+        let units = lot.units.min(to_sell);
+        f(lot, units);
+        lot.units -= units;
+        to_sell -= units;
+        if lot.units <= 0. {
+            lots.pop_front();
+        }
+    }
+    Ok(())
+}
+
+/// Conversion of amounts into base currency as of some date
+struct FxConverter<'a> {
+    base: Currency,
+    fx: &'a mut dyn QuoteHandler,
+}
+
+impl FxConverter<'_> {
+    fn rate(&mut self, currency: Currency, date: NaiveDate) -> Result<f64, DataError> {
+        get_fx_rate(currency, self.base, end_of_day(date), self.fx)
+    }
+}
+
+/// Decompose the change of value of the portfolio given by all transactions in `db`
+/// between the end of `start` and the end of `end`. Transactions dated after `start`
+/// and up to `end` are considered to be within the period. Assets are valued with
+/// the last quote available, all amounts are converted to `base` currency.
+pub fn pnl_attribution(
+    db: &mut dyn DataHandler,
+    start: NaiveDate,
+    end: NaiveDate,
+    base: Currency,
+    fx: &mut dyn QuoteHandler,
+    quotes: &mut dyn QuoteHandler,
+) -> Result<PnlReport, DataError> {
+    let mut transactions = db.get_all_transactions()?;
+    transactions.sort_by(|a, b| {
+        a.cash_flow
+            .date
+            .cmp(&b.cash_flow.date)
+            .then(a.id.cmp(&b.id))
+    });
+    let mut fx = FxConverter { base, fx };
+
+    // state at start date
+    let mut positions: HashMap<u64, f64> = HashMap::new();
+    let mut cash: HashMap<Currency, AmountSum> = HashMap::new();
+    for trans in transactions.iter().filter(|t| t.cash_flow.date <= start) {
+        cash.entry(trans.cash_flow.amount.currency)
+            .or_default()
+            .add(trans.cash_flow.amount.amount);
+        if let TransactionType::Asset { asset_id, position } = trans.transaction_type {
+            *positions.entry(asset_id).or_insert(0.) += position;
+        }
+    }
+    let mut start_value = 0.;
+    let mut lots: HashMap<u64, VecDeque<Lot>> = HashMap::new();
+    for (&asset_id, &units) in &positions {
+        if units == 0. {
+            continue;
+        }
+        if units < 0. {
+            return Err(DataError::InvalidTransaction(format!(
+                "short position in asset {} is not supported",
+                asset_id
+            )));
+        }
+        let (quote, currency) = quotes.get_last_quote_before_by_id(asset_id, end_of_day(start))?;
+        let fx_rate = fx.rate(currency, start)?;
+        start_value += units * quote.price * fx_rate;
+        lots.entry(asset_id).or_default().push_back(Lot {
+            units,
+            price: quote.price,
+            fx_rate,
+            purchase_id: None,
+        });
+    }
+    let mut cash_value = HashMap::new();
+    for (&currency, amount) in &cash {
+        let value = amount.value() * fx.rate(currency, start)?;
+        start_value += value;
+        cash_value.insert(currency, value);
+    }
+
+    // replay transactions within the period
+    let by_id: HashMap<u64, &Transaction> = transactions
+        .iter()
+        .filter_map(|t| t.id.map(|id| (id, t)))
+        .collect();
+    let mut total = PnlComponents::default();
+    let mut per_asset: HashMap<u64, PnlComponents> = HashMap::new();
+    for trans in transactions
+        .iter()
+        .filter(|t| t.cash_flow.date > start && t.cash_flow.date <= end)
+    {
+        let currency = trans.cash_flow.amount.currency;
+        let amount = trans.cash_flow.amount;
+        let fx_rate = fx.rate(currency, trans.cash_flow.date)?;
+        let value = amount.value() * fx_rate;
+        cash.entry(currency).or_default().add(amount.amount);
+        *cash_value.entry(currency).or_insert(0.) += value;
+        match trans.transaction_type {
+            TransactionType::Cash | TransactionType::Transfer { .. } => total.flows += value,
+            TransactionType::Dividend { asset_id } | TransactionType::Interest { asset_id } => {
+                total.income += value;
+                per_asset.entry(asset_id).or_default().income += value;
+            }
+            TransactionType::Fee { transaction_ref }
+            | TransactionType::Tax {
+                transaction_ref, ..
+            } => {
+                total.costs += value;
+                if let Some(asset_id) = related_asset(transaction_ref, &by_id) {
+                    per_asset.entry(asset_id).or_default().costs += value;
+                }
+            }
+            TransactionType::Asset { asset_id, position } => {
+                if position == 0. {
+                    continue;
+                }
+                let price = -amount.value() / position;
+                let asset_lots = lots.entry(asset_id).or_default();
+                if position > 0. {
+                    asset_lots.push_back(Lot {
+                        units: position,
+                        price,
+                        fx_rate,
+                        purchase_id: trans.id,
+                    });
+                    continue;
+                }
+                let components = per_asset.entry(asset_id).or_default();
+                sell_fifo(asset_lots, asset_id, -position, |lot, units| {
+                    let realized = units * (price - lot.price) * lot.fx_rate;
+                    let fx_effect = units * price * (fx_rate - lot.fx_rate);
+                    components.realized += realized;
+                    components.fx += fx_effect;
+                    total.realized += realized;
+                    total.fx += fx_effect;
+                })?;
+            }
+        }
+    }
+
+    // state at end date
+    let mut end_value = 0.;
+    for (&asset_id, asset_lots) in &lots {
+        if asset_lots.is_empty() {
+            continue;
+        }
+        let (quote, currency) = quotes.get_last_quote_before_by_id(asset_id, end_of_day(end))?;
+        let fx_rate = fx.rate(currency, end)?;
+        let components = per_asset.entry(asset_id).or_default();
+        for lot in asset_lots {
+            end_value += lot.units * quote.price * fx_rate;
+            let unrealized = lot.units * (quote.price - lot.price) * lot.fx_rate;
+            let fx_effect = lot.units * quote.price * (fx_rate - lot.fx_rate);
+            components.unrealized += unrealized;
+            components.fx += fx_effect;
+            total.unrealized += unrealized;
+            total.fx += fx_effect;
+        }
+    }
+    // fx effects on cash are the difference between the value of the final cash balance
+    // and the values of all cash movements at the time they occurred
+    for (&currency, amount) in &cash {
+        let value = amount.value() * fx.rate(currency, end)?;
+        end_value += value;
+        total.fx += value - cash_value[&currency];
+    }
+
+    Ok(PnlReport {
+        currency: base,
+        start_value,
+        end_value,
+        total,
+        per_asset,
+    })
+}
+
+/// Find the asset a fee or tax is related to, if any
+fn related_asset(transaction_ref: Option<u64>, by_id: &HashMap<u64, &Transaction>) -> Option<u64> {
+    match by_id.get(&transaction_ref?)?.transaction_type {
+        TransactionType::Asset { asset_id, .. }
+        | TransactionType::Dividend { asset_id }
+        | TransactionType::Interest { asset_id } => Some(asset_id),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::Asset;
+    use crate::fixed_income::CashFlow;
+    use crate::fx_rates::insert_fx_quote;
+    use crate::helpers::make_time;
+    use crate::memory_handler::InMemoryDB;
+    use crate::quote::{MarketDataSource, Quote, Ticker, UpdatePeriod};
+    use chrono::Datelike;
+    use std::str::FromStr;
+
+    fn transaction(transaction_type: TransactionType, amount: f64, date: NaiveDate) -> Transaction {
+        let usd = Currency::from_str("USD").unwrap();
+        Transaction::new(transaction_type, CashFlow::new(amount, usd, date))
+    }
+
+    #[test]
+    fn pnl_attribution_with_trade_dividend_and_fx_move() {
+        let eur = Currency::from_str("EUR").unwrap();
+        let usd = Currency::from_str("USD").unwrap();
+        let source = MarketDataSource {
+            id: None,
+            name: "manual".to_string(),
+        };
+
+        // market data
+        let mut quotes = InMemoryDB::new();
+        let source_id = quotes.insert_md_source(&source).unwrap();
+        let asset_id = quotes
+            .insert_asset(&Asset::new(None, "Apple", None, None, None))
+            .unwrap();
+        let ticker_id = quotes
+            .insert_ticker(&Ticker {
+                id: None,
+                name: "AAPL".to_string(),
+                asset: asset_id,
+                currency: usd,
+                source: source_id,
+                update_policy: UpdatePeriod::Daily,
+            })
+            .unwrap();
+        quotes
+            .insert_quote(&Quote {
+                id: None,
+                ticker: ticker_id,
+                price: 320.,
+                time: make_time(2020, 3, 31, 18, 0, 0).unwrap(),
+                volume: None,
+            })
+            .unwrap();
+        let mut fx = InMemoryDB::new();
+        let fx_source_id = fx.insert_md_source(&source).unwrap();
+        for (day, rate) in &[
+            (NaiveDate::from_ymd(2019, 12, 31), 0.90),
+            (NaiveDate::from_ymd(2020, 1, 15), 0.91),
+            (NaiveDate::from_ymd(2020, 2, 14), 0.92),
+            (NaiveDate::from_ymd(2020, 3, 31), 0.88),
+        ] {
+            let time = make_time(day.year(), day.month(), day.day(), 18, 0, 0).unwrap();
+            insert_fx_quote(*rate, usd, eur, fx_source_id, time, &mut fx).unwrap();
+        }
+
+        // transactions
+        let mut db = InMemoryDB::new();
+        db.insert_transaction(&transaction(
+            TransactionType::Cash,
+            10_000.,
+            NaiveDate::from_ymd(2019, 12, 2),
+        ))
+        .unwrap();
+        let trade_id = db
+            .insert_transaction(&transaction(
+                TransactionType::Asset {
+                    asset_id,
+                    position: 10.,
+                },
+                -3_000.,
+                NaiveDate::from_ymd(2020, 1, 15),
+            ))
+            .unwrap();
+        db.insert_transaction(&transaction(
+            TransactionType::Fee {
+                transaction_ref: Some(trade_id),
+            },
+            -10.,
+            NaiveDate::from_ymd(2020, 1, 15),
+        ))
+        .unwrap();
+        db.insert_transaction(&transaction(
+            TransactionType::Dividend { asset_id },
+            20.,
+            NaiveDate::from_ymd(2020, 2, 14),
+        ))
+        .unwrap();
+
+        let start = NaiveDate::from_ymd(2019, 12, 31);
+        let end = NaiveDate::from_ymd(2020, 3, 31);
+        let report = pnl_attribution(&mut db, start, end, eur, &mut fx, &mut quotes).unwrap();
+        let tol = 1e-9;
+        assert_fuzzy_eq!(report.start_value, 10_000. * 0.90, tol);
+        assert_fuzzy_eq!(report.end_value, (7_010. + 10. * 320.) * 0.88, tol);
+        assert_fuzzy_eq!(report.total.flows, 0., tol);
+        assert_fuzzy_eq!(report.total.realized, 0., tol);
+        assert_fuzzy_eq!(report.total.unrealized, 10. * 20. * 0.91, tol);
+        assert_fuzzy_eq!(report.total.income, 20. * 0.92, tol);
+        assert_fuzzy_eq!(report.total.costs, -10. * 0.91, tol);
+        let asset_fx = 10. * 320. * (0.88 - 0.91);
+        let cash_fx = 7_010. * 0.88 - 10_000. * 0.90 - (-3_010. * 0.91 + 20. * 0.92);
+        assert_fuzzy_eq!(report.total.fx, asset_fx + cash_fx, tol);
+        assert_fuzzy_eq!(report.residual(), 0., tol);
+
+        let apple = report.per_asset[&asset_id];
+        assert_fuzzy_eq!(apple.unrealized, report.total.unrealized, tol);
+        assert_fuzzy_eq!(apple.fx, asset_fx, tol);
+        assert_fuzzy_eq!(apple.income, report.total.income, tol);
+        assert_fuzzy_eq!(apple.costs, report.total.costs, tol);
+
+        // selling part of the position realizes gains, measured against the FIFO purchase price
+        db.insert_transaction(&transaction(
+            TransactionType::Asset {
+                asset_id,
+                position: -4.,
+            },
+            1_240.,
+            NaiveDate::from_ymd(2020, 3, 2),
+        ))
+        .unwrap();
+        let report = pnl_attribution(&mut db, start, end, eur, &mut fx, &mut quotes).unwrap();
+        assert_fuzzy_eq!(report.total.realized, 4. * 10. * 0.91, tol);
+        assert_fuzzy_eq!(report.total.unrealized, 6. * 20. * 0.91, tol);
+        assert_fuzzy_eq!(report.residual(), 0., tol);
+    }
+}
@@ -1,5 +1,20 @@
 ///! Implementation of portfolio
+use crate::asset::Instrument;
+use crate::bond::{Bond, BondError};
+use crate::currency::Currency;
+use crate::data_handler::{DataError, DataHandler, QuoteHandler};
+use crate::day_count_conv::DayCountConv;
+use crate::fixed_income::{amount_from_f64, Amount, AmountSum, CashFlow, FixedIncome};
+use crate::fx_rates::get_fx_rate;
+use crate::helpers::end_of_day;
+use crate::market::Market;
+use crate::options::{EuropeanOption, OptionError};
+use crate::rates::Discounter;
+use crate::time_period::TimePeriod;
+use crate::transaction::{Transaction, TransactionType};
+use chrono::{Datelike, NaiveDate};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 /// Type of transaction
 #[derive(Debug, Serialize, Deserialize)]
@@ -7,3 +22,1013 @@ pub struct Portfolio {
     id: u64,
     name: String,
 }
+
+/// Position in a single asset
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Position {
+    pub asset_id: u64,
+    /// Number of units held
+    pub position: f64,
+}
+
+impl Position {
+    pub fn new(asset_id: u64, position: f64) -> Position {
+        Position { asset_id, position }
+    }
+}
+
+/// Calculate the positions held at the end of `date` from all transactions in `db`.
+/// Transactions are streamed, i.e. not loaded at once. Assets without any
+/// remaining position are omitted.
+pub fn calc_positions(
+    db: &mut dyn DataHandler,
+    date: NaiveDate,
+) -> Result<HashMap<u64, Position>, DataError> {
+    let mut positions: HashMap<u64, Position> = HashMap::new();
+    db.for_each_transaction(&mut |transaction| {
+        if let TransactionType::Asset { asset_id, position } = transaction.transaction_type {
+            if transaction.cash_flow.date <= date {
+                positions
+                    .entry(asset_id)
+                    .or_insert_with(|| Position::new(asset_id, 0.))
+                    .position += position;
+            }
+        }
+        Ok(())
+    })?;
+    positions.retain(|_, p| p.position != 0.);
+    Ok(positions)
+}
+
+/// Total value of a portfolio together with warnings about the quality of the valuation
+#[derive(Debug, Clone, PartialEq)]
+pub struct PortfolioValuation {
+    pub value: f64,
+    pub warnings: Vec<String>,
+}
+
+/// Calculate the total value of all positions in `base` currency at the given date.
+/// Each position is valued with the last quote available for the asset up to the end
+/// of `date` and converted to the base currency with the last available fx rate.
+/// Missing quotes or fx rates result in an error.
+pub fn portfolio_value(
+    positions: &HashMap<u64, Position>,
+    quotes: &mut dyn QuoteHandler,
+    fx: &mut dyn QuoteHandler,
+    base: Currency,
+    date: NaiveDate,
+) -> Result<f64, DataError> {
+    let options = OptionParameters::default();
+    Ok(portfolio_valuation(positions, quotes, fx, base, date, &options)?.value)
+}
+
+/// Parameters of the Black-Scholes valuation of option positions
+#[derive(Debug, Clone, Default)]
+pub struct OptionParameters {
+    /// Volatility per asset id of the option, options without volatility are valued by quotes
+    pub vols: HashMap<u64, f64>,
+    /// Flat continuously compounded interest rate
+    pub rate: f64,
+    /// Flat continuously compounded dividend yield of the underlyings
+    pub dividend_yield: f64,
+}
+
+/// Like `portfolio_value`, but positions in assets delisted at or before `date` are
+/// valued with the last quote before the delisting date, or zero if there is none.
+/// A warning is added for each such position. Positions in assets holding a European
+/// option with a volatility in `options` are valued by `value_option_position` instead.
+pub fn portfolio_valuation(
+    positions: &HashMap<u64, Position>,
+    quotes: &mut dyn QuoteHandler,
+    fx: &mut dyn QuoteHandler,
+    base: Currency,
+    date: NaiveDate,
+    options: &OptionParameters,
+) -> Result<PortfolioValuation, DataError> {
+    let mut value = 0.0;
+    let mut warnings = Vec::new();
+    for position in positions.values() {
+        let asset = quotes.get_asset_by_id(position.asset_id)?;
+        if let (Some(Instrument::EuropeanOption(option)), Some(vol)) =
+            (&asset.instrument, options.vols.get(&position.asset_id))
+        {
+            let amount = value_option_position(
+                option,
+                position.position,
+                date,
+                quotes,
+                *vol,
+                options.rate,
+                options.dividend_yield,
+            )
+            .map_err(|err| match err {
+                OptionError::MarketDataError(err) => err,
+                err => DataError::InvalidAsset(format!("{}: {}", asset.name, err)),
+            })?;
+            value += amount.value() * get_fx_rate(amount.currency, base, end_of_day(date), fx)?;
+            continue;
+        }
+        let delisted_at = asset.delisted_at.filter(|_| !asset.is_listed_at(date));
+        let time = match delisted_at {
+            Some(delisted_at) => end_of_day(delisted_at.pred()),
+            None => end_of_day(date),
+        };
+        let (quote, currency) = match (
+            quotes.get_last_quote_before_by_id(position.asset_id, time),
+            delisted_at,
+        ) {
+            (Ok(quote), None) => quote,
+            (Ok(quote), Some(delisted_at)) => {
+                warnings.push(format!(
+                    "{} has been delisted at {}, valued with last quote before delisting",
+                    asset.name, delisted_at
+                ));
+                quote
+            }
+            (Err(DataError::NotFound(_)), Some(delisted_at)) => {
+                warnings.push(format!(
+                    "{} has been delisted at {} without any quote before, valued with zero",
+                    asset.name, delisted_at
+                ));
+                continue;
+            }
+            (Err(err), _) => return Err(err),
+        };
+        let fx_rate = get_fx_rate(currency, base, time, fx)?;
+        value += position.position * quote.price * fx_rate;
+    }
+    Ok(PortfolioValuation { value, warnings })
+}
+
+/// Source of market data used for the valuation of a position
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ValuationMethod {
+    /// Valued by the last available market quote
+    Quote,
+    /// Valued by discounting future cash flows with a discount curve
+    Curve,
+}
+
+/// Value of a position together with the method used to calculate it
+#[derive(Debug, Clone, Copy)]
+pub struct Valuation {
+    pub value: Amount,
+    pub method: ValuationMethod,
+}
+
+/// Calculate the dirty value of a position of `units` bonds at the given date.
+/// If a market quote of the bond's asset is available, the bond is valued by the last
+/// quote up to the end of `date`. Bond quotes are expected to be clean prices in percent
+/// of the outstanding notional, i.e. of the denomination less amortized repayments, and
+/// the accrued interest is added. Otherwise, the future cash flows are discounted with
+/// `curve`. If neither is available, an error is returned.
+pub fn value_bond_position(
+    asset_id: u64,
+    bond: &Bond,
+    units: f64,
+    date: NaiveDate,
+    quotes: &mut dyn QuoteHandler,
+    curve: Option<&dyn Discounter>,
+    market: &Market,
+) -> Result<Valuation, BondError> {
+    match quotes.get_last_quote_before_by_id(asset_id, end_of_day(date)) {
+        Ok((quote, _)) => {
+            let outstanding = units * (bond.denomination as f64) * bond.outstanding_fraction(date);
+            let clean = outstanding * quote.price / 100.;
+            let accrued = units * bond.accrued_interest(date)?;
+            return Ok(Valuation {
+                value: Amount {
+                    amount: amount_from_f64(clean + accrued),
+                    currency: bond.currency(),
+                },
+                method: ValuationMethod::Quote,
+            });
+        }
+        Err(DataError::NotFound(_)) => {}
+        Err(err) => return Err(err.into()),
+    }
+    match curve {
+        Some(curve) => {
+            let cash_flows: Vec<_> = bond
+                .rollout_cash_flows(units, market)?
+                .into_iter()
+                .filter(|cf| cf.date > date)
+                .collect();
+            Ok(Valuation {
+                value: curve.discount_cash_flow_stream(&cash_flows, date)?,
+                method: ValuationMethod::Curve,
+            })
+        }
+        None => Err(BondError::NoValuationSource),
+    }
+}
+
+/// Future cash flows of the positions held, see `upcoming_cash_flows`
+#[derive(Debug, Clone, Default)]
+pub struct UpcomingCashFlows {
+    /// Cash flows in ascending order of dates, together with the id of the asset paying them
+    pub flows: Vec<(NaiveDate, Amount, u64)>,
+    /// Ids of assets held whose cash flows are not known, e.g. stocks or assets without
+    /// instrument definition
+    pub skipped: Vec<u64>,
+}
+
+impl UpcomingCashFlows {
+    /// Sum up cash flows per month and currency. The date of each aggregated cash flow is
+    /// the first day of the month; cash flows are sorted by month and currency code.
+    pub fn by_month(&self) -> Vec<CashFlow> {
+        let mut sums: Vec<(CashFlow, AmountSum)> = Vec::new();
+        for (date, amount, _) in &self.flows {
+            let month = NaiveDate::from_ymd(date.year(), date.month(), 1);
+            match sums
+                .iter_mut()
+                .find(|(cf, _)| cf.date == month && cf.amount.currency == amount.currency)
+            {
+                Some((_, sum)) => sum.add(amount.amount),
+                None => sums.push((
+                    CashFlow::new(0., amount.currency, month),
+                    AmountSum::from(amount.amount),
+                )),
+            }
+        }
+        let mut monthly: Vec<CashFlow> = sums
+            .into_iter()
+            .map(|(mut cf, sum)| {
+                cf.amount.amount = sum.amount();
+                cf
+            })
+            .collect();
+        monthly.sort_by(|a, b| {
+            (a.date, a.amount.currency.to_string()).cmp(&(b.date, b.amount.currency.to_string()))
+        });
+        monthly
+    }
+}
+
+/// Roll out the cash flows of all positions held at the end of `as_of`, scaled by the
+/// number of units held, and keep those after `as_of` up to (and including) the end of
+/// `horizon`. Only assets with a bond instrument definition have known cash flows, all
+/// other assets are listed as skipped. The calendars of bonds are taken from `market`.
+/// `horizon` must not be given in business days.
+pub fn upcoming_cash_flows(
+    db: &mut dyn DataHandler,
+    as_of: NaiveDate,
+    horizon: TimePeriod,
+    market: &Market,
+) -> Result<UpcomingCashFlows, BondError> {
+    let end = horizon.add_to(as_of, None);
+    let mut upcoming = UpcomingCashFlows::default();
+    for position in calc_positions(db, as_of)?.values() {
+        let asset = db.get_asset_by_id(position.asset_id)?;
+        let bond = match &asset.instrument {
+            Some(Instrument::Bond(bond)) => bond,
+            _ => {
+                upcoming.skipped.push(position.asset_id);
+                continue;
+            }
+        };
+        for cf in bond.rollout_cash_flows(position.position, market)? {
+            if cf.date > as_of && cf.date <= end {
+                upcoming.flows.push((cf.date, cf.amount, position.asset_id));
+            }
+        }
+    }
+    upcoming
+        .flows
+        .sort_by_key(|(date, _, asset_id)| (*date, *asset_id));
+    upcoming.skipped.sort_unstable();
+    Ok(upcoming)
+}
+
+/// Calculate the value of a position of `units` option contracts at the given date with the
+/// Black-Scholes model. The spot price of the underlying is given by the last quote of the
+/// underlying asset up to the end of `date`, the option is valued with the given volatility `vol`,
+/// and flat continuously compounded interest `rate` and `dividend_yield`, with time to expiry
+/// calculated by the Act/365 day count convention. Expired options are valued at their
+/// intrinsic value given by the last quote of the underlying up to the end of the expiry date.
+/// The value is in the currency of the underlying's quote.
+pub fn value_option_position(
+    option: &EuropeanOption,
+    units: f64,
+    date: NaiveDate,
+    quotes: &mut dyn QuoteHandler,
+    vol: f64,
+    rate: f64,
+    dividend_yield: f64,
+) -> Result<Amount, OptionError> {
+    let (quote, currency) = quotes.get_last_quote_before_by_id(
+        option.underlying_asset,
+        end_of_day(date.min(option.expiry)),
+    )?;
+    let price = option.price(
+        quote.price,
+        vol,
+        rate,
+        dividend_yield,
+        date,
+        DayCountConv::Act365,
+    )?;
+    Ok(Amount {
+        amount: amount_from_f64(units * option.contract_size * price),
+        currency,
+    })
+}
+
+/// Reconstruct the running cash balance in `currency` from a list of transactions.
+/// The signed cash flow of each transaction in this currency, e.g. positive for deposits
+/// or dividends and negative for purchases or fees, is applied in chronological order.
+/// The result contains the balance at the end of each date with at least one cash flow.
+/// The balance is aggregated as `AmountSum`, i.e. exactly with the `decimal` feature.
+pub fn cash_balance_history(txns: &[Transaction], currency: &Currency) -> Vec<(NaiveDate, f64)> {
+    let mut cash_flows: Vec<_> = txns
+        .iter()
+        .map(|t| &t.cash_flow)
+        .filter(|cf| cf.amount.currency == *currency)
+        .collect();
+    cash_flows.sort_by_key(|cf| cf.date);
+    let mut balance = AmountSum::default();
+    let mut history: Vec<(NaiveDate, f64)> = Vec::new();
+    for cf in cash_flows {
+        balance.add(cf.amount.amount);
+        match history.last_mut() {
+            Some(last) if last.0 == cf.date => last.1 = balance.value(),
+            _ => history.push((cf.date, balance.value())),
+        }
+    }
+    history
+}
+
+/// Net external cash flows in `currency` into the given set of accounts per date, in
+/// chronological order. `accounts` of `None` considers all transactions, otherwise only
+/// transactions booked in one of the accounts. Cash transactions are external flows, as are
+/// transfers unless both legs are booked within the considered accounts. Transfer legs
+/// are matched via their transaction references; a leg whose counterpart is missing in
+/// `txns` is external as well. All other transactions are part of the performance.
+pub fn external_cash_flows(
+    txns: &[Transaction],
+    accounts: Option<&[u64]>,
+    currency: &Currency,
+) -> Vec<(NaiveDate, f64)> {
+    let in_accounts = |t: &Transaction| match accounts {
+        Some(accounts) => matches!(t.account, Some(id) if accounts.contains(&id)),
+        None => true,
+    };
+    // map each transfer leg's id to its counterpart, in both directions
+    let by_id: HashMap<u64, &Transaction> =
+        txns.iter().filter_map(|t| t.id.map(|id| (id, t))).collect();
+    let mut counterparts: HashMap<u64, &Transaction> = HashMap::new();
+    for t in txns {
+        if let (
+            Some(id),
+            TransactionType::Transfer {
+                transaction_ref: Some(other_id),
+            },
+        ) = (t.id, t.transaction_type)
+        {
+            if let Some(other) = by_id.get(&other_id) {
+                counterparts.insert(id, other);
+                counterparts.insert(other_id, t);
+            }
+        }
+    }
+    let is_external = |t: &Transaction| match t.transaction_type {
+        TransactionType::Cash => true,
+        TransactionType::Transfer { .. } => match t.id.and_then(|id| counterparts.get(&id)) {
+            Some(other) => !in_accounts(other),
+            None => true,
+        },
+        _ => false,
+    };
+    let mut flows: Vec<(NaiveDate, AmountSum)> = Vec::new();
+    let mut cash_flows: Vec<&CashFlow> = txns
+        .iter()
+        .filter(|t| t.cash_flow.amount.currency == *currency && in_accounts(t) && is_external(t))
+        .map(|t| &t.cash_flow)
+        .collect();
+    cash_flows.sort_by_key(|cf| cf.date);
+    for cf in cash_flows {
+        match flows.last_mut() {
+            Some(last) if last.0 == cf.date => last.1.add(cf.amount.amount),
+            _ => flows.push((cf.date, AmountSum::from(cf.amount.amount))),
+        }
+    }
+    flows
+        .into_iter()
+        .map(|(date, sum)| (date, sum.value()))
+        .collect()
+}
+
+/// Time weighted return between the first and the last valuation date. `valuations` are
+/// the values at the end of each date in chronological order, including the external
+/// `flows` of that date (see `external_cash_flows`). The return of each period between
+/// two valuations is adjusted by the flows within the period, i.e. after the earlier and
+/// up to the later valuation date, and period returns are compounded. Flows outside of the
+/// valuation dates are ignored. Returns `None` if there are less than two valuations or
+/// any period starts with a value of zero.
+pub fn time_weighted_return(
+    valuations: &[(NaiveDate, f64)],
+    flows: &[(NaiveDate, f64)],
+) -> Option<f64> {
+    if valuations.len() < 2 {
+        return None;
+    }
+    let mut growth = 1.0;
+    for period in valuations.windows(2) {
+        let ((start, start_value), (end, end_value)) = (period[0], period[1]);
+        if start_value == 0. {
+            return None;
+        }
+        let flow: f64 = flows
+            .iter()
+            .filter(|(date, _)| *date > start && *date <= end)
+            .map(|(_, amount)| amount)
+            .sum();
+        growth *= (end_value - flow) / start_value;
+    }
+    Some(growth - 1.)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::Asset;
+    use crate::data_handler::DataHandler;
+    use crate::fx_rates::insert_fx_quote;
+    use crate::helpers::make_time;
+    use crate::memory_handler::InMemoryDB;
+    use crate::quote::{MarketDataSource, Quote, Ticker, UpdatePeriod};
+    use std::str::FromStr;
+
+    fn add_asset_with_quote(
+        db: &mut InMemoryDB,
+        name: &str,
+        currency: Currency,
+        source: u64,
+        price: f64,
+    ) -> u64 {
+        let asset_id = db
+            .insert_asset(&Asset::new(None, name, None, None, None))
+            .unwrap();
+        let ticker_id = db
+            .insert_ticker(&Ticker {
+                id: None,
+                name: name.to_string(),
+                asset: asset_id,
+                currency,
+                source,
+                update_policy: UpdatePeriod::Daily,
+            })
+            .unwrap();
+        db.insert_quote(&Quote {
+            id: None,
+            ticker: ticker_id,
+            price,
+            time: make_time(2020, 1, 10, 18, 0, 0).unwrap(),
+            volume: None,
+        })
+        .unwrap();
+        asset_id
+    }
+
+    #[test]
+    fn fx_converted_portfolio_value() {
+        let eur = Currency::from_str("EUR").unwrap();
+        let usd = Currency::from_str("USD").unwrap();
+        let source = MarketDataSource {
+            id: None,
+            name: "manual".to_string(),
+        };
+        let mut quotes = InMemoryDB::new();
+        let source_id = quotes.insert_md_source(&source).unwrap();
+        let basf = add_asset_with_quote(&mut quotes, "BASF", eur, source_id, 60.0);
+        let apple = add_asset_with_quote(&mut quotes, "Apple", usd, source_id, 300.0);
+
+        let mut fx = InMemoryDB::new();
+        let fx_source_id = fx.insert_md_source(&source).unwrap();
+        let time = make_time(2020, 1, 10, 18, 0, 0).unwrap();
+        insert_fx_quote(1.2, usd, eur, fx_source_id, time, &mut fx).unwrap();
+
+        let mut positions = HashMap::new();
+        positions.insert(basf, Position::new(basf, 10.0));
+        positions.insert(apple, Position::new(apple, 2.0));
+
+        let date = NaiveDate::from_ymd(2020, 1, 15);
+        let value = portfolio_value(&positions, &mut quotes, &mut fx, eur, date).unwrap();
+        assert_fuzzy_eq!(value, 10.0 * 60.0 + 2.0 * 300.0 * 1.2, 1e-10);
+
+        // no fx rate available to convert into a third currency
+        let gbp = Currency::from_str("GBP").unwrap();
+        assert!(portfolio_value(&positions, &mut quotes, &mut fx, gbp, date).is_err());
+
+        // no quotes available yet
+        let date = NaiveDate::from_ymd(2020, 1, 9);
+        assert!(portfolio_value(&positions, &mut quotes, &mut fx, eur, date).is_err());
+    }
+
+    #[test]
+    fn valuation_of_delisted_assets() {
+        let eur = Currency::from_str("EUR").unwrap();
+        let source = MarketDataSource {
+            id: None,
+            name: "manual".to_string(),
+        };
+        let mut quotes = InMemoryDB::new();
+        let source_id = quotes.insert_md_source(&source).unwrap();
+        let basf = add_asset_with_quote(&mut quotes, "BASF", eur, source_id, 60.0);
+        let wirecard = add_asset_with_quote(&mut quotes, "Wirecard", eur, source_id, 100.0);
+        // later quote after the delisting, e.g. from some OTC trade
+        let ticker = quotes.get_all_ticker_for_source(source_id).unwrap()[1].clone();
+        assert_eq!(ticker.asset, wirecard);
+        quotes
+            .insert_quote(&Quote {
+                id: None,
+                ticker: ticker.id.unwrap(),
+                price: 1.0,
+                time: make_time(2020, 2, 10, 18, 0, 0).unwrap(),
+                volume: None,
+            })
+            .unwrap();
+        let no_quotes = add_asset_with_quote(&mut quotes, "Unquoted", eur, source_id, 10.0);
+        let mut fx = InMemoryDB::new();
+        let options = OptionParameters::default();
+
+        // deactivate and delist via update
+        let delisted_at = NaiveDate::from_ymd(2020, 2, 1);
+        for id in &[wirecard, no_quotes] {
+            let mut asset = quotes.get_asset_by_id(*id).unwrap();
+            asset.active = false;
+            asset.delisted_at = Some(delisted_at);
+            quotes.update_asset(&asset).unwrap();
+        }
+        let active: Vec<_> = quotes
+            .get_active_assets()
+            .unwrap()
+            .iter()
+            .map(|a| a.id.unwrap())
+            .collect();
+        assert_eq!(active, vec![basf]);
+        let tickers = quotes.get_tickers_to_update(source_id).unwrap();
+        assert_eq!(tickers.len(), 1);
+        assert_eq!(tickers[0].asset, basf);
+
+        let mut positions = HashMap::new();
+        positions.insert(basf, Position::new(basf, 10.0));
+        positions.insert(wirecard, Position::new(wirecard, 5.0));
+
+        // before delisting, the latest quote is used without warnings
+        let date = NaiveDate::from_ymd(2020, 1, 31);
+        let valuation =
+            portfolio_valuation(&positions, &mut quotes, &mut fx, eur, date, &options).unwrap();
+        assert_fuzzy_eq!(valuation.value, 10.0 * 60.0 + 5.0 * 100.0, 1e-10);
+        assert!(valuation.warnings.is_empty());
+
+        // after delisting, the later quote is ignored
+        let date = NaiveDate::from_ymd(2020, 3, 1);
+        let valuation =
+            portfolio_valuation(&positions, &mut quotes, &mut fx, eur, date, &options).unwrap();
+        assert_fuzzy_eq!(valuation.value, 10.0 * 60.0 + 5.0 * 100.0, 1e-10);
+        assert_eq!(valuation.warnings.len(), 1);
+        assert!(valuation.warnings[0].starts_with("Wirecard has been delisted at 2020-02-01"));
+
+        // delisted without quotes before the delisting date is valued with zero
+        let mut asset = quotes.get_asset_by_id(no_quotes).unwrap();
+        asset.delisted_at = Some(NaiveDate::from_ymd(2020, 1, 5));
+        quotes.update_asset(&asset).unwrap();
+        positions.insert(no_quotes, Position::new(no_quotes, 3.0));
+        let valuation =
+            portfolio_valuation(&positions, &mut quotes, &mut fx, eur, date, &options).unwrap();
+        assert_fuzzy_eq!(valuation.value, 10.0 * 60.0 + 5.0 * 100.0, 1e-10);
+        assert_eq!(valuation.warnings.len(), 2);
+    }
+
+    #[test]
+    fn bond_valuation_by_quote_or_curve() {
+        use crate::day_count_conv::DayCountConv;
+        use crate::rates::{Compounding, FlatRate};
+
+        let bond: Bond = serde_json::from_str(
+            r#"{
+            "bond_type": "bond",
+            "currency": "EUR",
+            "coupon" : {
+                "coupon_type": "fixed",
+                "rate": 5,
+                "coupon_date": "01.10",
+                "period": "1Y",
+                "day_count_convention": "icma"
+            },
+            "business_day_rule": "none",
+            "issue_date": "2019-10-01",
+            "maturity": "2022-10-01",
+            "denomination": 1000
+        }"#,
+        )
+        .unwrap();
+        let eur = Currency::from_str("EUR").unwrap();
+        let market = Market::new();
+        let mut quotes = InMemoryDB::new();
+        let source_id = quotes
+            .insert_md_source(&MarketDataSource {
+                id: None,
+                name: "manual".to_string(),
+            })
+            .unwrap();
+        let quoted = add_asset_with_quote(&mut quotes, "quoted bond", eur, source_id, 102.0);
+        let unquoted = quotes
+            .insert_asset(&Asset::new(None, "unquoted bond", None, None, None))
+            .unwrap();
+        let curve = FlatRate::new(0.05, DayCountConv::Act365, Compounding::Annual, eur);
+        let date = NaiveDate::from_ymd(2020, 4, 1);
+
+        // quote is preferred to curve, accrued interest is 50 * 183/366 per bond
+        let valuation =
+            value_bond_position(quoted, &bond, 2., date, &mut quotes, Some(&curve), &market)
+                .unwrap();
+        assert_eq!(valuation.method, ValuationMethod::Quote);
+        assert_eq!(valuation.value.currency, eur);
+        assert_fuzzy_eq!(valuation.value.value(), 2. * (1020. + 25.), 1e-8);
+
+        // no quote available, fall back to curve
+        let valuation = value_bond_position(
+            unquoted,
+            &bond,
+            2.,
+            date,
+            &mut quotes,
+            Some(&curve),
+            &market,
+        )
+        .unwrap();
+        assert_eq!(valuation.method, ValuationMethod::Curve);
+        // coupons in 183, 548 and 913 days, the last one together with the notional
+        let expected = 2.
+            * (50. * 1.05_f64.powf(-183. / 365.)
+                + 50. * 1.05_f64.powf(-548. / 365.)
+                + 1050. * 1.05_f64.powf(-913. / 365.));
+        assert_fuzzy_eq!(valuation.value.value(), expected, 1e-8);
+
+        // neither quote nor curve
+        let result = value_bond_position(unquoted, &bond, 2., date, &mut quotes, None, &market);
+        assert!(matches!(result, Err(BondError::NoValuationSource)));
+    }
+
+    #[test]
+    fn amortizing_bond_valuation_by_quote() {
+        let bond: Bond = serde_json::from_str(
+            r#"{
+            "bond_type": "bond",
+            "currency": "EUR",
+            "coupon" : {
+                "coupon_type": "fixed",
+                "rate": 5,
+                "coupon_date": "01.10",
+                "period": "1Y",
+                "day_count_convention": "icma"
+            },
+            "business_day_rule": "none",
+            "issue_date": "2019-10-01",
+            "maturity": "2022-10-01",
+            "denomination": 1200,
+            "amortization": "linear"
+        }"#,
+        )
+        .unwrap();
+        let eur = Currency::from_str("EUR").unwrap();
+        let market = Market::new();
+        let mut quotes = InMemoryDB::new();
+        let source_id = quotes
+            .insert_md_source(&MarketDataSource {
+                id: None,
+                name: "manual".to_string(),
+            })
+            .unwrap();
+        let quoted = add_asset_with_quote(&mut quotes, "amortizing bond", eur, source_id, 102.0);
+
+        // before the first repayment, the full denomination is outstanding
+        let date = NaiveDate::from_ymd(2020, 4, 1);
+        let valuation =
+            value_bond_position(quoted, &bond, 2., date, &mut quotes, None, &market).unwrap();
+        assert_eq!(valuation.method, ValuationMethod::Quote);
+        assert_fuzzy_eq!(
+            valuation.value.value(),
+            2. * (1224. + 60. * 183. / 366.),
+            1e-8
+        );
+
+        // one third of the notional is repaid on 2020-10-01
+        let date = NaiveDate::from_ymd(2021, 4, 1);
+        let valuation =
+            value_bond_position(quoted, &bond, 2., date, &mut quotes, None, &market).unwrap();
+        assert_fuzzy_eq!(
+            valuation.value.value(),
+            2. * (816. + 40. * 182. / 365.),
+            1e-8
+        );
+    }
+
+    #[test]
+    fn positions_from_streamed_transactions() {
+        let eur = Currency::from_str("EUR").unwrap();
+        let mut db = InMemoryDB::new();
+        let trades = [
+            (1, 10., 1),
+            (2, 5., 2),
+            (1, -4., 3),
+            (2, -5., 4),
+            (1, 2., 20),
+        ];
+        for (asset_id, position, day) in trades.iter() {
+            db.insert_transaction(&Transaction::new(
+                TransactionType::Asset {
+                    asset_id: *asset_id,
+                    position: *position,
+                },
+                CashFlow::new(-100. * position, eur, NaiveDate::from_ymd(2020, 1, *day)),
+            ))
+            .unwrap();
+        }
+        let positions = calc_positions(&mut db, NaiveDate::from_ymd(2020, 1, 10)).unwrap();
+        assert_eq!(positions.len(), 1);
+        assert_eq!(positions[&1].position, 6.);
+        let positions = calc_positions(&mut db, NaiveDate::from_ymd(2020, 1, 31)).unwrap();
+        assert_eq!(positions[&1].position, 8.);
+    }
+
+    #[test]
+    fn cash_balance_from_transactions() {
+        let eur = Currency::from_str("EUR").unwrap();
+        let usd = Currency::from_str("USD").unwrap();
+        let transaction = |transaction_type, amount, currency, date| {
+            Transaction::new(transaction_type, CashFlow::new(amount, currency, date))
+        };
+        let txns = vec![
+            transaction(
+                TransactionType::Dividend { asset_id: 1 },
+                25.,
+                eur,
+                NaiveDate::from_ymd(2020, 6, 1),
+            ),
+            transaction(
+                TransactionType::Cash,
+                10_000.,
+                eur,
+                NaiveDate::from_ymd(2020, 1, 15),
+            ),
+            transaction(
+                TransactionType::Asset {
+                    asset_id: 1,
+                    position: 100.,
+                },
+                -4_000.,
+                eur,
+                NaiveDate::from_ymd(2020, 2, 1),
+            ),
+            transaction(
+                TransactionType::Fee {
+                    transaction_ref: None,
+                },
+                -10.,
+                eur,
+                NaiveDate::from_ymd(2020, 2, 1),
+            ),
+            transaction(
+                TransactionType::Cash,
+                500.,
+                usd,
+                NaiveDate::from_ymd(2020, 3, 1),
+            ),
+        ];
+        let history = cash_balance_history(&txns, &eur);
+        assert_eq!(
+            history,
+            vec![
+                (NaiveDate::from_ymd(2020, 1, 15), 10_000.),
+                (NaiveDate::from_ymd(2020, 2, 1), 5_990.),
+                (NaiveDate::from_ymd(2020, 6, 1), 6_015.),
+            ]
+        );
+        assert_eq!(
+            cash_balance_history(&txns, &usd),
+            vec![(NaiveDate::from_ymd(2020, 3, 1), 500.)]
+        );
+        assert!(cash_balance_history(&[], &eur).is_empty());
+    }
+
+    #[test]
+    fn value_option_from_underlying_quote() {
+        let eur = Currency::from_str("EUR").unwrap();
+        let source = MarketDataSource {
+            id: None,
+            name: "manual".to_string(),
+        };
+        let mut quotes = InMemoryDB::new();
+        let source_id = quotes.insert_md_source(&source).unwrap();
+        let basf = add_asset_with_quote(&mut quotes, "BASF", eur, source_id, 60.0);
+        let option = EuropeanOption {
+            underlying_asset: basf,
+            strike: 55.,
+            expiry: NaiveDate::from_ymd(2020, 12, 18),
+            put_call: crate::options::PutCall::Call,
+            contract_size: 100.,
+        };
+        let date = NaiveDate::from_ymd(2020, 1, 15);
+        let value = value_option_position(&option, 2., date, &mut quotes, 0.25, 0.01, 0.).unwrap();
+        let price = option
+            .price(60., 0.25, 0.01, 0., date, DayCountConv::Act365)
+            .unwrap();
+        assert_eq!(value.currency, eur);
+        assert_fuzzy_eq!(value.value(), 200. * price, 1e-10);
+        assert!(price > 5.);
+
+        // positive volatility required before expiry
+        assert!(matches!(
+            value_option_position(&option, 2., date, &mut quotes, 0., 0.01, 0.),
+            Err(OptionError::InvalidVolatility(_))
+        ));
+
+        // valued by the model in the portfolio if a volatility is given
+        let mut asset = Asset::new(None, "BASF call", None, None, None);
+        asset.instrument = Some(Instrument::EuropeanOption(option.clone()));
+        let option_id = quotes.insert_asset(&asset).unwrap();
+        let mut positions = HashMap::new();
+        positions.insert(option_id, Position::new(option_id, 2.));
+        let mut fx = InMemoryDB::new();
+        let mut options = OptionParameters {
+            rate: 0.01,
+            ..Default::default()
+        };
+        assert!(
+            portfolio_valuation(&positions, &mut quotes, &mut fx, eur, date, &options).is_err()
+        );
+        options.vols.insert(option_id, 0.25);
+        let valuation =
+            portfolio_valuation(&positions, &mut quotes, &mut fx, eur, date, &options).unwrap();
+        assert_fuzzy_eq!(valuation.value, 200. * price, 1e-10);
+        options.vols.insert(option_id, -0.25);
+        assert!(
+            portfolio_valuation(&positions, &mut quotes, &mut fx, eur, date, &options).is_err()
+        );
+
+        // expired options are valued at intrinsic value, as of the expiry date
+        let ticker = quotes.get_all_ticker_for_source(source_id).unwrap()[0].clone();
+        quotes
+            .insert_quote(&Quote {
+                id: None,
+                ticker: ticker.id.unwrap(),
+                price: 80.0,
+                time: make_time(2020, 12, 30, 18, 0, 0).unwrap(),
+                volume: None,
+            })
+            .unwrap();
+        let date = NaiveDate::from_ymd(2021, 1, 4);
+        let value = value_option_position(&option, 2., date, &mut quotes, 0.25, 0.01, 0.).unwrap();
+        assert_fuzzy_eq!(value.value(), 200. * 5., 1e-10);
+        let valuation =
+            portfolio_valuation(&positions, &mut quotes, &mut fx, eur, date, &options).unwrap();
+        assert_fuzzy_eq!(valuation.value, 200. * 5., 1e-10);
+
+        // no quote of the underlying available yet
+        let date = NaiveDate::from_ymd(2020, 1, 9);
+        assert!(value_option_position(&option, 2., date, &mut quotes, 0.25, 0.01, 0.).is_err());
+    }
+
+    #[test]
+    fn internal_transfers_in_time_weighted_return() {
+        let eur = Currency::from_str("EUR").unwrap();
+        let usd = Currency::from_str("USD").unwrap();
+        let d = |day| NaiveDate::from_ymd(2020, 1, day);
+        let transaction = |transaction_type, amount, currency, day, account| Transaction {
+            account: Some(account),
+            ..Transaction::new(transaction_type, CashFlow::new(amount, currency, d(day)))
+        };
+        let mut db = InMemoryDB::new();
+        db.insert_transaction(&transaction(TransactionType::Cash, 1000., eur, 1, 1))
+            .unwrap();
+        db.insert_transaction(&transaction(TransactionType::Cash, 1000., eur, 1, 2))
+            .unwrap();
+        // transfer of 500 EUR from account 1 to account 2
+        let out_leg = TransactionType::Transfer {
+            transaction_ref: None,
+        };
+        let out_id = db
+            .insert_transaction(&transaction(out_leg, -500., eur, 15, 1))
+            .unwrap();
+        let in_leg = TransactionType::Transfer {
+            transaction_ref: Some(out_id),
+        };
+        assert!(db
+            .insert_transaction(&transaction(in_leg, 400., eur, 15, 2))
+            .is_err());
+        assert!(db
+            .insert_transaction(&transaction(in_leg, 500., usd, 15, 2))
+            .is_err());
+        db.insert_transaction(&transaction(in_leg, 500., eur, 15, 2))
+            .unwrap();
+        let txns = db.get_all_transactions().unwrap();
+
+        // account 1 gains 10% in both periods, account 2 stays flat
+        let account_1 = [(d(1), 1000.), (d(15), 600.), (d(31), 660.)];
+        let account_2 = [(d(1), 1000.), (d(15), 1500.), (d(31), 1500.)];
+        let total: Vec<(NaiveDate, f64)> = account_1
+            .iter()
+            .zip(account_2.iter())
+            .map(|(v1, v2)| (v1.0, v1.1 + v2.1))
+            .collect();
+
+        // the transfer is internal to the portfolio
+        for accounts in &[None, Some(&[1, 2][..])] {
+            let flows = external_cash_flows(&txns, *accounts, &eur);
+            assert_eq!(flows, vec![(d(1), 2000.)]);
+            let twr = time_weighted_return(&total, &flows).unwrap();
+            assert_fuzzy_eq!(twr, 2160. / 2000. - 1., 1e-12);
+        }
+        // but external to each account
+        let flows = external_cash_flows(&txns, Some(&[1]), &eur);
+        assert_eq!(flows, vec![(d(1), 1000.), (d(15), -500.)]);
+        let twr = time_weighted_return(&account_1, &flows).unwrap();
+        assert_fuzzy_eq!(twr, 0.21, 1e-12);
+        let flows = external_cash_flows(&txns, Some(&[2]), &eur);
+        let twr = time_weighted_return(&account_2, &flows).unwrap();
+        assert_fuzzy_eq!(twr, 0., 1e-12);
+
+        // a transfer leg without counterpart is an external flow
+        let without_out_leg: Vec<Transaction> = txns
+            .iter()
+            .filter(|t| t.id != Some(out_id))
+            .cloned()
+            .collect();
+        let flows = external_cash_flows(&without_out_leg, None, &eur);
+        assert_eq!(flows, vec![(d(1), 2000.), (d(15), 500.)]);
+        assert_eq!(time_weighted_return(&total[..1], &flows), None);
+    }
+
+    #[test]
+    fn upcoming_cash_flows_of_bond_and_stock() {
+        let bond: Bond = serde_json::from_str(
+            r#"{
+            "bond_type": "bond",
+            "currency": "EUR",
+            "coupon" : {
+                "coupon_type": "fixed",
+                "rate": 5,
+                "coupon_date": "01.10",
+                "period": "1Y",
+                "day_count_convention": "icma"
+            },
+            "business_day_rule": "none",
+            "issue_date": "2019-10-01",
+            "maturity": "2022-10-01",
+            "denomination": 1000
+        }"#,
+        )
+        .unwrap();
+        let eur = Currency::from_str("EUR").unwrap();
+        let mut db = InMemoryDB::new();
+        let mut bond_asset = Asset::new(None, "bond", None, None, None);
+        bond_asset.instrument = Some(Instrument::Bond(Box::new(bond)));
+        let bond_id = db.insert_asset(&bond_asset).unwrap();
+        let mut stock = Asset::new(None, "stock", None, None, None);
+        stock.instrument = Some(Instrument::Stock);
+        let stock_id = db.insert_asset(&stock).unwrap();
+        for (asset_id, position, amount) in &[(bond_id, 2., -2040.), (stock_id, 10., -500.)] {
+            db.insert_transaction(&Transaction::new(
+                TransactionType::Asset {
+                    asset_id: *asset_id,
+                    position: *position,
+                },
+                CashFlow::new(*amount, eur, NaiveDate::from_ymd(2020, 1, 10)),
+            ))
+            .unwrap();
+        }
+
+        let market = Market::new();
+        let as_of = NaiveDate::from_ymd(2021, 6, 30);
+        let horizon = TimePeriod::from_str("2Y").unwrap();
+        let upcoming = upcoming_cash_flows(&mut db, as_of, horizon, &market).unwrap();
+        assert_eq!(upcoming.skipped, vec![stock_id]);
+        let flows: Vec<(NaiveDate, f64)> = upcoming
+            .flows
+            .iter()
+            .map(|(date, amount, asset_id)| {
+                assert_eq!(*asset_id, bond_id);
+                (*date, amount.value())
+            })
+            .collect();
+        let coupon = NaiveDate::from_ymd(2021, 10, 1);
+        let maturity = NaiveDate::from_ymd(2022, 10, 1);
+        assert_eq!(flows.len(), 3);
+        assert_eq!(flows[0].0, coupon);
+        assert_fuzzy_eq!(flows[0].1, 100., 1e-8);
+        assert!(flows[1..].iter().all(|(date, _)| *date == maturity));
+
+        let monthly = upcoming.by_month();
+        assert_eq!(monthly.len(), 2);
+        assert_eq!(monthly[1].date, NaiveDate::from_ymd(2022, 10, 1));
+        assert_fuzzy_eq!(monthly[1].amount.value(), 2100., 1e-8);
+
+        // coupons after the horizon are omitted
+        let horizon = TimePeriod::from_str("6M").unwrap();
+        let upcoming = upcoming_cash_flows(&mut db, as_of, horizon, &market).unwrap();
+        assert_eq!(upcoming.flows.len(), 1);
+    }
+}
@@ -1,4 +1,5 @@
 ///! Implemenation of PostgreSQL data handler
+use crate::data_handler::{Clock, SystemClock, DEFAULT_PAGE_SIZE};
 use postgres::{Client, NoTls};
 use tokio_postgres::error::Error;
 
@@ -8,21 +9,39 @@ pub mod transaction_handler;
 /// Struct to handle connections to sqlite3 databases
 pub struct PostgresDB {
     conn: Client,
+    /// Number of transactions fetched at once when iterating over all transactions
+    page_size: usize,
+    /// Clock used for the audit time stamps of transactions
+    clock: Box<dyn Clock>,
 }
 
 impl PostgresDB {
     pub fn connect(conn_str: &str) -> Result<PostgresDB, Error> {
         let conn = Client::connect(conn_str, NoTls)?;
-        Ok(PostgresDB { conn })
+        Ok(PostgresDB {
+            conn,
+            page_size: DEFAULT_PAGE_SIZE,
+            clock: Box::new(SystemClock),
+        })
+    }
+
+    /// Set the number of transactions fetched at once when iterating over all transactions
+    pub fn set_page_size(&mut self, page_size: usize) {
+        self.page_size = std::cmp::max(1, page_size);
+    }
+
+    /// Set the clock used for the audit time stamps of transactions
+    pub fn set_clock(&mut self, clock: Box<dyn Clock>) {
+        self.clock = clock;
     }
 
     /// Clean database by dropping all tables and than run init
     pub fn clean(&mut self) -> Result<(), Error> {
         self.conn
             .execute("DROP TABLE IF EXISTS transactions", &[])?;
-        self.conn.execute("DROP TABLE IF EXISTS assets", &[])?;
         self.conn.execute("DROP TABLE IF EXISTS quotes", &[])?;
         self.conn.execute("DROP TABLE IF EXISTS ticker", &[])?;
+        self.conn.execute("DROP TABLE IF EXISTS assets", &[])?;
         self.conn
             .execute("DROP TABLE IF EXISTS market_data_sources", &[])?;
         self.init()
@@ -32,25 +51,34 @@ impl PostgresDB {
     pub fn init(&mut self) -> Result<(), Error> {
         self.conn.execute(
             "CREATE TABLE IF NOT EXISTS assets (
-                id SERIAL PRIMARY KEY,
+                id BIGSERIAL PRIMARY KEY,
                 name TEXT NOT NULL UNIQUE,
                 wkn TEXT UNIQUE,
                 isin TEXT UNIQUE,
-                note TEXT
+                note TEXT,
+                instrument_type TEXT,
+                instrument TEXT,
+                tags TEXT,
+                active BOOLEAN NOT NULL DEFAULT TRUE,
+                delisted_at DATE
             )",
             &[],
         )?;
         self.conn.execute(
             "CREATE TABLE IF NOT EXISTS transactions (
-                id SERIAL PRIMARY KEY,
+                id BIGSERIAL PRIMARY KEY,
                 trans_type TEXT NOT NULL,
-                asset_id INTEGER,
+                asset_id BIGINT,
                 cash_amount FLOAT8 NOT NULL,
                 cash_currency TEXT NOT NULL,
                 cash_date DATE NOT NULL,
-                related_trans INTEGER,
+                related_trans BIGINT,
                 position FLOAT8,
                 note TEXT,
+                tags TEXT,
+                account_id BIGINT,
+                created_at TIMESTAMPTZ,
+                modified_at TIMESTAMPTZ,
                 FOREIGN KEY(asset_id) REFERENCES assets(id),
                 FOREIGN KEY(related_trans) REFERENCES transactions(id)
             );",
@@ -58,23 +86,26 @@ impl PostgresDB {
         )?;
         self.conn.execute(
             "CREATE TABLE IF NOT EXISTS market_data_sources (
-                id SERIAL PRIMARY KEY,
+                id BIGSERIAL PRIMARY KEY,
                 name TEXT NOT NULL UNIQUE );",
             &[],
         )?;
         self.conn.execute(
             "CREATE TABLE IF NOT EXISTS ticker (
-                id SERIAL PRIMARY KEY,
+                id BIGSERIAL PRIMARY KEY,
                 name TEXT NOT NULL,
-                source_id INTEGER NOT NULL,
+                asset_id BIGINT NOT NULL,
+                source_id BIGINT NOT NULL,
                 currency TEXT NOT NULL,
+                update_policy TEXT NOT NULL DEFAULT 'daily',
+                FOREIGN KEY(asset_id) REFERENCES assets(id),
                 FOREIGN KEY(source_id) REFERENCES market_data_sources(id) );",
             &[],
         )?;
         self.conn.execute(
             "CREATE TABLE IF NOT EXISTS quotes (
-                id SERIAL PRIMARY KEY,
-                ticker_id INTEGER NOT NULL,
+                id BIGSERIAL PRIMARY KEY,
+                ticker_id BIGINT NOT NULL,
                 price FLOAT8 NOT NULL,
                 time TIMESTAMP WITH TIME ZONE NOT NULL,
                 volume FLOAT8,
@@ -2,14 +2,15 @@
 use super::PostgresDB;
 use crate::currency::Currency;
 use crate::data_handler::{DataError, QuoteHandler};
-use crate::quote::{MarketDataSource, Quote, Ticker};
+use crate::helpers::{from_db_id, to_db_id};
+use crate::quote::{MarketDataSource, Quote, Ticker, UpdatePeriod};
 use chrono::{DateTime, Utc};
 use std::str::FromStr;
 
 /// Sqlite implementation of quote handler
 impl QuoteHandler for PostgresDB {
     // insert, get, update and delete for market data sources
-    fn insert_md_source(&mut self, source: &MarketDataSource) -> Result<usize, DataError> {
+    fn insert_md_source(&mut self, source: &MarketDataSource) -> Result<u64, DataError> {
         let row = self
             .conn
             .query_one(
@@ -17,21 +18,21 @@ impl QuoteHandler for PostgresDB {
                 &[&source.name],
             )
             .map_err(|e| DataError::InsertFailed(e.to_string()))?;
-        let id: i32 = row.get::<_, i32>(0);
-        Ok(id as usize)
+        let id: i64 = row.get::<_, i64>(0);
+        from_db_id(id)
     }
 
-    fn get_md_source_by_id(&mut self, id: usize) -> Result<MarketDataSource, DataError> {
+    fn get_md_source_by_id(&mut self, id: u64) -> Result<MarketDataSource, DataError> {
         let row = self
             .conn
             .query_one(
                 "SELECT name FROM market_data_sources WHERE id=$1;",
-                &[&(id as i32)],
+                &[&to_db_id(id)?],
             )
             .map_err(|e| DataError::NotFound(e.to_string()))?;
         let name: String = row.get(0);
         Ok(MarketDataSource {
-            id: Some(id as usize),
+            id: Some(id),
             name: name,
         })
     }
@@ -43,9 +44,9 @@ impl QuoteHandler for PostgresDB {
             .query("SELECT id, name FROM market_data_sources", &[])
             .map_err(|e| DataError::NotFound(e.to_string()))?
         {
-            let id: i32 = row.get(0);
+            let id: i64 = row.get(0);
             sources.push(MarketDataSource {
-                id: Some(id as usize),
+                id: Some(from_db_id(id)?),
                 name: row.get(1),
             });
         }
@@ -58,7 +59,7 @@ impl QuoteHandler for PostgresDB {
                 "not yet stored to database".to_string(),
             ));
         }
-        let id = source.id.unwrap() as i32;
+        let id = to_db_id(source.id.unwrap())?;
         self.conn
             .execute(
                 "UPDATE market_data_sources SET name=$2 
@@ -68,71 +69,81 @@ impl QuoteHandler for PostgresDB {
             .map_err(|e| DataError::InsertFailed(e.to_string()))?;
         Ok(())
     }
-    fn delete_md_source(&mut self, id: usize) -> Result<(), DataError> {
+    fn delete_md_source(&mut self, id: u64) -> Result<(), DataError> {
         self.conn
             .execute(
                 "DELETE FROM market_data_sources WHERE id=$1;",
-                &[&(id as i32)],
+                &[&to_db_id(id)?],
             )
             .map_err(|e| DataError::InsertFailed(e.to_string()))?;
         Ok(())
     }
 
     // insert, get, update and delete for market data sources
-    fn insert_ticker(&mut self, ticker: &Ticker) -> Result<usize, DataError> {
+    fn insert_ticker(&mut self, ticker: &Ticker) -> Result<u64, DataError> {
         let row = self
             .conn
             .query_one(
-                "INSERT INTO ticker (name, source_id, currency) VALUES ($1, $2, $3) RETURNING id",
+                "INSERT INTO ticker (name, asset_id, source_id, currency, update_policy) VALUES ($1, $2, $3, $4, $5) RETURNING id",
                 &[
                     &ticker.name,
-                    &(ticker.source as i32),
+                    &to_db_id(ticker.asset)?,
+                    &to_db_id(ticker.source)?,
                     &(ticker.currency.to_string()),
+                    &(ticker.update_policy.to_string()),
                 ],
             )
             .map_err(|e| DataError::InsertFailed(e.to_string()))?;
-        let id: i32 = row.get(0);
-        Ok(id as usize)
+        let id: i64 = row.get(0);
+        from_db_id(id)
     }
-    fn get_ticker_by_id(&mut self, id: usize) -> Result<Ticker, DataError> {
+    fn get_ticker_by_id(&mut self, id: u64) -> Result<Ticker, DataError> {
         let row = self
             .conn
             .query_one(
-                "SELECT name, source_id, currency FROM ticker WHERE id=$1;",
-                &[&(id as i32)],
+                "SELECT name, asset_id, source_id, currency, update_policy FROM ticker WHERE id=$1;",
+                &[&to_db_id(id)?],
             )
             .map_err(|e| DataError::NotFound(e.to_string()))?;
         let name: String = row.get(0);
-        let source: i32 = row.get(1);
-        let currency: String = row.get(2);
+        let asset: i64 = row.get(1);
+        let source: i64 = row.get(2);
+        let currency: String = row.get(3);
         let currency =
             Currency::from_str(&currency).map_err(|e| DataError::NotFound(e.to_string()))?;
+        let update_policy: String = row.get(4);
         Ok(Ticker {
             id: Some(id),
             name,
-            source: source as usize,
+            asset: from_db_id(asset)?,
+            source: from_db_id(source)?,
             currency,
+            update_policy: UpdatePeriod::from_str(&update_policy)?,
         })
     }
-    fn get_all_ticker_for_source(&mut self, source: usize) -> Result<Vec<Ticker>, DataError> {
+    fn get_all_ticker_for_source(&mut self, source: u64) -> Result<Vec<Ticker>, DataError> {
         let mut all_ticker = Vec::new();
         for row in self
             .conn
             .query(
-                "SELECT id, name, currency FROM ticker WHERE source_id=$1;",
-                &[&(source as i32)],
+                "SELECT id, name, asset_id, currency, update_policy FROM ticker WHERE source_id=$1;",
+                &[&to_db_id(source)?],
             )
             .map_err(|e| DataError::NotFound(e.to_string()))?
         {
-            let id: i32 = row.get(0);
-            let currency: String = row.get(2);
+            let id: i64 = row.get(0);
+            let asset: i64 = row.get(2);
+            let currency: String = row.get(3);
             let currency =
                 Currency::from_str(&currency).map_err(|e| DataError::NotFound(e.to_string()))?;
+            let update_policy: String = row.get(4);
             all_ticker.push(Ticker {
-                id: Some(id as usize),
+                id: Some(from_db_id(id)?),
                 name: row.get(1),
+                asset: from_db_id(asset)?,
                 source,
                 currency,
+                update_policy: UpdatePeriod::from_str(&update_policy)?,
             });
         }
         Ok(all_ticker)
@@ -144,51 +155,53 @@ impl QuoteHandler for PostgresDB {
                 "not yet stored to database".to_string(),
             ));
         }
-        let id = ticker.id.unwrap() as i32;
+        let id = to_db_id(ticker.id.unwrap())?;
         self.conn
             .execute(
-                "UPDATE ticker SET name=$2, source_id=$3, currency=$4
+                "UPDATE ticker SET name=$2, asset_id=$3, source_id=$4, currency=$5, update_policy=$6
                 WHERE id=$1",
                 &[
                     &id,
                     &ticker.name,
-                    &(ticker.source as i32),
+                    &to_db_id(ticker.asset)?,
+                    &to_db_id(ticker.source)?,
                     &ticker.currency.to_string(),
+                    &ticker.update_policy.to_string(),
                 ],
             )
             .map_err(|e| DataError::InsertFailed(e.to_string()))?;
         Ok(())
     }
 
-    fn delete_ticker(&mut self, id: usize) -> Result<(), DataError> {
+    fn delete_ticker(&mut self, id: u64) -> Result<(), DataError> {
         self.conn
-            .execute("DELETE FROM ticker WHERE id=$1;", &[&(id as i32)])
+            .execute("DELETE FROM ticker WHERE id=$1;", &[&to_db_id(id)?])
             .map_err(|e| DataError::InsertFailed(e.to_string()))?;
         Ok(())
     }
 
     // insert, get, update and delete for market data sources
-    fn insert_quote(&mut self, quote: &Quote) -> Result<usize, DataError> {
+    fn insert_quote(&mut self, quote: &Quote) -> Result<u64, DataError> {
         let row = self
             .conn
             .query_one(
                 "INSERT INTO quotes (ticker_id, price, time, volume) 
                 VALUES ($1, $2, $3, $4) RETURNING id",
                 &[
-                    &(quote.ticker as i32),
+                    &to_db_id(quote.ticker)?,
                     &quote.price,
                     &quote.time, //&quote.time.to_rfc3339(),
                     &quote.volume,
                 ],
             )
             .map_err(|e| DataError::InsertFailed(e.to_string()))?;
-        let id: i32 = row.get(0);
-        Ok(id as usize)
+        let id: i64 = row.get(0);
+        from_db_id(id)
     }
 
     fn get_last_quote_before(
         &mut self,
-        ticker: usize,
+        ticker: u64,
         time: DateTime<Utc>,
     ) -> Result<(Quote, Currency), DataError> {
         let row = self
@@ -198,11 +211,11 @@ impl QuoteHandler for PostgresDB {
                 FROM quotes q, ticker t 
                 WHERE t.id=$1 AND t.id=q.ticker_id AND q.time<= $2
                 ORDER BY q.time DESC LIMIT 1",
-                &[&(ticker as i32), &time],
+                &[&to_db_id(ticker)?, &time],
             )
             .map_err(|e| DataError::NotFound(e.to_string()))?;
 
-        let id: i32 = row.get(0);
+        let id: i64 = row.get(0);
         let price: f64 = row.get(1);
         let time: DateTime<Utc> = row.get(2);
         let volume: Option<f64> = row.get(3);
@@ -211,7 +224,7 @@ impl QuoteHandler for PostgresDB {
             Currency::from_str(&currency).map_err(|e| DataError::NotFound(e.to_string()))?;
         Ok((
             Quote {
-                id: Some(id as usize),
+                id: Some(from_db_id(id)?),
                 ticker,
                 price,
                 time,
@@ -221,21 +234,57 @@ impl QuoteHandler for PostgresDB {
         ))
     }
 
-    fn get_all_quotes_for_ticker(&mut self, ticker_id: usize) -> Result<Vec<Quote>, DataError> {
+    fn get_last_quote_before_by_id(
+        &mut self,
+        asset_id: u64,
+        time: DateTime<Utc>,
+    ) -> Result<(Quote, Currency), DataError> {
+        let row = self
+            .conn
+            .query_one(
+                "SELECT q.id, q.ticker_id, q.price, q.time, q.volume, t.currency 
+                FROM quotes q, ticker t 
+                WHERE t.asset_id=$1 AND t.id=q.ticker_id AND q.time<= $2
+                ORDER BY q.time DESC LIMIT 1",
+                &[&to_db_id(asset_id)?, &time],
+            )
+            .map_err(|e| DataError::NotFound(e.to_string()))?;
+
+        let id: i64 = row.get(0);
+        let ticker: i64 = row.get(1);
+        let price: f64 = row.get(2);
+        let time: DateTime<Utc> = row.get(3);
+        let volume: Option<f64> = row.get(4);
+        let currency: String = row.get(5);
+        let currency =
+            Currency::from_str(&currency).map_err(|e| DataError::NotFound(e.to_string()))?;
+        Ok((
+            Quote {
+                id: Some(from_db_id(id)?),
+                ticker: from_db_id(ticker)?,
+                price,
+                time,
+                volume,
+            },
+            currency,
+        ))
+    }
+
+    fn get_all_quotes_for_ticker(&mut self, ticker_id: u64) -> Result<Vec<Quote>, DataError> {
         let mut quotes = Vec::new();
         for row in self
             .conn
             .query(
                 "SELECT id, price, time, volume FROM quotes 
                 WHERE ticker_id=$1 ORDER BY time ASC;",
-                &[&(ticker_id as i32)],
+                &[&to_db_id(ticker_id)?],
             )
             .map_err(|e| DataError::NotFound(e.to_string()))?
         {
-            let id: i32 = row.get(0);
+            let id: i64 = row.get(0);
             let time: DateTime<Utc> = row.get(2);
             quotes.push(Quote {
-                id: Some(id as usize),
+                id: Some(from_db_id(id)?),
                 ticker: ticker_id,
                 price: row.get(1),
                 time,
@@ -251,14 +300,14 @@ impl QuoteHandler for PostgresDB {
                 "not yet stored to database".to_string(),
             ));
         }
-        let id = quote.id.unwrap() as i32;
+        let id = to_db_id(quote.id.unwrap())?;
         self.conn
             .execute(
                 "UPDATE quotes SET ticker_id=$2, price=$3, time=$4, volume=$5
                 WHERE id=$1",
                 &[
                     &id,
-                    &(quote.ticker as i32),
+                    &to_db_id(quote.ticker)?,
                     &quote.price,
                     &quote.time,
                     &quote.volume,
@@ -268,9 +317,9 @@ impl QuoteHandler for PostgresDB {
         Ok(())
     }
 
-    fn delete_quote(&mut self, id: usize) -> Result<(), DataError> {
+    fn delete_quote(&mut self, id: u64) -> Result<(), DataError> {
         self.conn
-            .execute("DELETE FROM quotes WHERE id=$1;", &[&(id as i32)])
+            .execute("DELETE FROM quotes WHERE id=$1;", &[&to_db_id(id)?])
             .map_err(|e| DataError::InsertFailed(e.to_string()))?;
         Ok(())
     }
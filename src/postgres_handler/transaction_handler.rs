@@ -1,23 +1,30 @@
 use super::PostgresDB;
-use crate::asset::Asset;
+use crate::asset::{escape_like, rank_search_results, Asset, Instrument, InstrumentType};
 use crate::currency::Currency;
 use crate::data_handler::{DataError, DataHandler};
-use crate::fixed_income::{Amount, CashFlow};
-use crate::helpers::{i32_to_usize, usize_to_i32};
-use crate::transaction::{Transaction, TransactionType};
-use chrono::NaiveDate;
+use crate::fixed_income::{amount_from_f64, Amount, CashFlow};
+use crate::helpers::{from_db_id, i64_to_id, id_to_i64, to_db_id};
+use crate::transaction::{TaxKind, Transaction, TransactionType};
+use chrono::{DateTime, NaiveDate, Utc};
+use postgres::Row;
+use std::convert::TryFrom;
 use std::str::FromStr;
 
 pub struct RawTransaction {
-    pub id: Option<i32>,
+    pub id: Option<i64>,
     pub trans_type: String,
-    pub asset: Option<i32>,
+    pub asset: Option<i64>,
     pub cash_amount: f64,
     pub cash_currency: String,
     pub cash_date: NaiveDate,
-    pub related_trans: Option<i32>,
+    pub related_trans: Option<i64>,
     pub position: Option<f64>,
     pub note: Option<String>,
+    /// Normalized tags as JSON, no tags are stored as NULL
+    pub tags: Option<String>,
+    pub account: Option<i64>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub modified_at: Option<DateTime<Utc>>,
 }
 
 /// Raw transaction type constants
@@ -26,46 +33,60 @@ const ASSET: &str = "a";
 const DIVIDEND: &str = "d";
 const INTEREST: &str = "i";
 const TAX: &str = "t";
+const WITHHOLDING_TAX: &str = "w";
 const FEE: &str = "f";
+const TRANSFER: &str = "x";
 
 impl RawTransaction {
     pub fn to_transaction(&self) -> Result<Transaction, DataError> {
         let currency = Currency::from_str(&self.cash_currency)
             .map_err(|e| DataError::InsertFailed(e.to_string()))?;
-        let id = i32_to_usize(self.id);
+        let id = i64_to_id(self.id)?;
         let cash_flow = CashFlow {
             amount: Amount {
-                amount: self.cash_amount,
+                amount: amount_from_f64(self.cash_amount),
                 currency,
             },
             date: self.cash_date,
         };
         let note = self.note.clone();
+        let tags = match &self.tags {
+            Some(json) => serde_json::from_str(json)?,
+            None => Vec::new(),
+        };
         let transaction_type = match self.trans_type.as_str() {
             CASH => TransactionType::Cash,
             ASSET => TransactionType::Asset {
-                asset_id: self.asset.ok_or(DataError::InvalidTransaction(
+                asset_id: from_db_id(self.asset.ok_or(DataError::InvalidTransaction(
                     "missing asset id".to_string(),
-                ))? as usize,
+                ))?)?,
                 position: self.position.ok_or(DataError::InvalidTransaction(
                     "missing position value".to_string(),
                 ))?,
             },
             DIVIDEND => TransactionType::Dividend {
-                asset_id: self.asset.ok_or(DataError::InvalidTransaction(
+                asset_id: from_db_id(self.asset.ok_or(DataError::InvalidTransaction(
                     "missing asset id".to_string(),
-                ))? as usize,
+                ))?)?,
             },
             INTEREST => TransactionType::Interest {
-                asset_id: self.asset.ok_or(DataError::InvalidTransaction(
+                asset_id: from_db_id(self.asset.ok_or(DataError::InvalidTransaction(
                     "missing asset id".to_string(),
-                ))? as usize,
+                ))?)?,
             },
             TAX => TransactionType::Tax {
-                transaction_ref: i32_to_usize(self.id),
+                transaction_ref: i64_to_id(self.related_trans)?,
+                kind: TaxKind::Domestic,
+            },
+            WITHHOLDING_TAX => TransactionType::Tax {
+                transaction_ref: i64_to_id(self.related_trans)?,
+                kind: TaxKind::Withholding,
             },
             FEE => TransactionType::Fee {
-                transaction_ref: i32_to_usize(self.id),
+                transaction_ref: i64_to_id(self.related_trans)?,
+            },
+            TRANSFER => TransactionType::Transfer {
+                transaction_ref: i64_to_id(self.related_trans)?,
             },
             unknown => {
                 return Err(DataError::InvalidTransaction(unknown.to_string()));
@@ -76,14 +97,24 @@ impl RawTransaction {
             transaction_type,
             cash_flow,
             note,
+            tags,
+            account: i64_to_id(self.account)?,
+            created_at: self.created_at,
+            modified_at: self.modified_at,
         })
     }
 
-    pub fn from_transaction(transaction: &Transaction) -> RawTransaction {
-        let id = usize_to_i32(transaction.id);
-        let cash_amount = transaction.cash_flow.amount.amount;
+    pub fn from_transaction(transaction: &Transaction) -> Result<RawTransaction, DataError> {
+        let id = id_to_i64(transaction.id)?;
+        let cash_amount = transaction.cash_flow.amount.value();
         let cash_currency = transaction.cash_flow.amount.currency.to_string();
         let note = transaction.note.clone();
+        let tags = transaction.normalized_tags();
+        let tags = if tags.is_empty() {
+            None
+        } else {
+            serde_json::to_string(&tags).ok()
+        };
         let mut raw_transaction = RawTransaction {
             id,
             trans_type: String::new(),
@@ -94,120 +125,239 @@ impl RawTransaction {
             related_trans: None,
             position: None,
             note,
+            tags,
+            account: id_to_i64(transaction.account)?,
+            created_at: transaction.created_at,
+            modified_at: transaction.modified_at,
         };
         match transaction.transaction_type {
             TransactionType::Cash => raw_transaction.trans_type = CASH.to_string(),
             TransactionType::Asset { asset_id, position } => {
                 raw_transaction.trans_type = ASSET.to_string();
-                raw_transaction.asset = Some(asset_id as i32);
+                raw_transaction.asset = Some(to_db_id(asset_id)?);
                 raw_transaction.position = Some(position);
             }
             TransactionType::Dividend { asset_id } => {
                 raw_transaction.trans_type = DIVIDEND.to_string();
-                raw_transaction.asset = Some(asset_id as i32);
+                raw_transaction.asset = Some(to_db_id(asset_id)?);
             }
             TransactionType::Interest { asset_id } => {
                 raw_transaction.trans_type = INTEREST.to_string();
-                raw_transaction.asset = Some(asset_id as i32);
+                raw_transaction.asset = Some(to_db_id(asset_id)?);
             }
-            TransactionType::Tax { transaction_ref } => {
-                raw_transaction.trans_type = TAX.to_string();
-                raw_transaction.related_trans = usize_to_i32(transaction_ref);
+            TransactionType::Tax {
+                transaction_ref,
+                kind,
+            } => {
+                raw_transaction.trans_type = match kind {
+                    TaxKind::Domestic => TAX.to_string(),
+                    TaxKind::Withholding => WITHHOLDING_TAX.to_string(),
+                };
+                raw_transaction.related_trans = id_to_i64(transaction_ref)?;
             }
             TransactionType::Fee { transaction_ref } => {
                 raw_transaction.trans_type = FEE.to_string();
-                raw_transaction.related_trans = usize_to_i32(transaction_ref);
+                raw_transaction.related_trans = id_to_i64(transaction_ref)?;
+            }
+            TransactionType::Transfer { transaction_ref } => {
+                raw_transaction.trans_type = TRANSFER.to_string();
+                raw_transaction.related_trans = id_to_i64(transaction_ref)?;
             }
         };
-        raw_transaction
+        Ok(raw_transaction)
     }
 }
 
+/// Build asset from a row of columns id, name, wkn, isin, note, instrument, tags,
+/// active and delisted_at
+fn asset_from_row(row: &Row) -> Result<Asset, DataError> {
+    let id: i64 = row.get(0);
+    let instrument: Option<String> = row.get(5);
+    let instrument = match instrument {
+        Some(json) => Some(Instrument::from_json(&json)?),
+        None => None,
+    };
+    let tags: Option<String> = row.get(6);
+    let tags = match tags {
+        Some(json) => {
+            serde_json::from_str(&json).map_err(|e| DataError::InvalidAsset(e.to_string()))?
+        }
+        None => Vec::new(),
+    };
+    Ok(Asset {
+        id: Some(from_db_id(id)?),
+        name: row.get(1),
+        wkn: row.get(2),
+        isin: row.get(3),
+        note: row.get(4),
+        instrument,
+        tags,
+        active: row.get(7),
+        delisted_at: row.get(8),
+    })
+}
+
+/// Serialize asset's tags to JSON for storage, no tags are stored as NULL
+fn tags_column(asset: &Asset) -> Result<Option<String>, DataError> {
+    if asset.tags.is_empty() {
+        return Ok(None);
+    }
+    serde_json::to_string(&asset.tags)
+        .map(Some)
+        .map_err(|e| DataError::InvalidAsset(e.to_string()))
+}
+
 /// Handler for globally available data
 impl DataHandler for PostgresDB {
-    fn insert_asset(&mut self, asset: &Asset) -> Result<usize, DataError> {
+    fn insert_asset(&mut self, asset: &Asset) -> Result<u64, DataError> {
+        let (instrument_type, instrument) = asset.instrument_columns()?;
+        let tags = tags_column(asset)?;
         let row = self
             .conn
             .query_one(
-                "INSERT INTO assets (name, wkn, isin, note) VALUES ($1, $2, $3, $4) RETURNING id",
-                &[&asset.name, &asset.wkn, &asset.isin, &asset.note],
+                "INSERT INTO assets (name, wkn, isin, note, instrument_type, instrument, tags,
+                active, delisted_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) RETURNING id",
+                &[
+                    &asset.name,
+                    &asset.wkn,
+                    &asset.isin,
+                    &asset.note,
+                    &instrument_type,
+                    &instrument,
+                    &tags,
+                    &asset.active,
+                    &asset.delisted_at,
+                ],
             )
             .map_err(|e| DataError::InsertFailed(e.to_string()))?;
-        let id: i32 = row.get(0);
-        Ok(id as usize)
+        let id: i64 = row.get(0);
+        from_db_id(id)
     }
 
-    fn get_asset_by_id(&mut self, id: usize) -> Result<Asset, DataError> {
+    fn get_asset_by_id(&mut self, id: u64) -> Result<Asset, DataError> {
         let row = self
             .conn
             .query_one(
-                "SELECT name, wkn, isin, note FROM assets WHERE id=$1",
-                &[&(id as i32)],
+                "SELECT id, name, wkn, isin, note, instrument, tags, active, delisted_at FROM assets WHERE id=$1",
+                &[&to_db_id(id)?],
             )
             .map_err(|e| DataError::NotFound(e.to_string()))?;
-        Ok(Asset {
-            id: Some(id),
-            name: row.get(0),
-            wkn: row.get(1),
-            isin: row.get(2),
-            note: row.get(3),
-        })
+        asset_from_row(&row)
+    }
+
+    fn get_asset_by_name(&mut self, name: &str) -> Result<Asset, DataError> {
+        let row = self
+            .conn
+            .query_one(
+                "SELECT id, name, wkn, isin, note, instrument, tags, active, delisted_at FROM assets WHERE name=$1",
+                &[&name],
+            )
+            .map_err(|e| DataError::NotFound(e.to_string()))?;
+        asset_from_row(&row)
     }
 
     fn get_all_assets(&mut self) -> Result<Vec<Asset>, DataError> {
         let mut assets = Vec::new();
         for row in self
             .conn
-            .query("SELECT id, name, wkn, isin, note FROM assets", &[])
+            .query(
+                "SELECT id, name, wkn, isin, note, instrument, tags, active, delisted_at FROM assets",
+                &[],
+            )
             .map_err(|e| DataError::NotFound(e.to_string()))?
         {
-            let id: i32 = row.get(0);
-            let id = Some(id as usize);
-            assets.push(Asset {
-                id,
-                name: row.get(1),
-                wkn: row.get(2),
-                isin: row.get(3),
-                note: row.get(4),
-            });
+            assets.push(asset_from_row(&row)?);
         }
         Ok(assets)
     }
 
+    fn get_assets_by_instrument_type(
+        &mut self,
+        kind: InstrumentType,
+    ) -> Result<Vec<Asset>, DataError> {
+        let mut assets = Vec::new();
+        for row in self
+            .conn
+            .query(
+                "SELECT id, name, wkn, isin, note, instrument, tags, active, delisted_at FROM assets
+                WHERE instrument_type=$1",
+                &[&kind.to_string()],
+            )
+            .map_err(|e| DataError::NotFound(e.to_string()))?
+        {
+            assets.push(asset_from_row(&row)?);
+        }
+        Ok(assets)
+    }
+
+    fn search_assets(&mut self, query: &str, limit: usize) -> Result<Vec<Asset>, DataError> {
+        let pattern = format!("%{}%", escape_like(query));
+        let mut assets = Vec::new();
+        for row in self
+            .conn
+            .query(
+                "SELECT id, name, wkn, isin, note, instrument, tags, active, delisted_at FROM assets
+                WHERE name ILIKE $1 OR isin ILIKE $1",
+                &[&pattern],
+            )
+            .map_err(|e| DataError::NotFound(e.to_string()))?
+        {
+            assets.push(asset_from_row(&row)?);
+        }
+        Ok(rank_search_results(assets, query, limit, false))
+    }
+
     fn update_asset(&mut self, asset: &Asset) -> Result<(), DataError> {
         if asset.id.is_none() {
             return Err(DataError::NotFound(
                 "not yet stored to database".to_string(),
             ));
         }
-        let id = asset.id.unwrap() as i32;
+        let id = to_db_id(asset.id.unwrap())?;
+        let (instrument_type, instrument) = asset.instrument_columns()?;
+        let tags = tags_column(asset)?;
         self.conn
             .execute(
-                "UPDATE assets SET name=$2, wkn=$3, isin=$4, note=$5 
+                "UPDATE assets SET name=$2, wkn=$3, isin=$4, note=$5, instrument_type=$6,
+                instrument=$7, tags=$8, active=$9, delisted_at=$10
                 WHERE id=$1;",
-                &[&id, &asset.name, &asset.wkn, &asset.isin, &asset.note],
+                &[
+                    &id,
+                    &asset.name,
+                    &asset.wkn,
+                    &asset.isin,
+                    &asset.note,
+                    &instrument_type,
+                    &instrument,
+                    &tags,
+                    &asset.active,
+                    &asset.delisted_at,
+                ],
             )
             .map_err(|e| DataError::InsertFailed(e.to_string()))?;
         Ok(())
     }
 
-    fn delete_asset(&mut self, id: usize) -> Result<(), DataError> {
+    fn delete_asset(&mut self, id: u64) -> Result<(), DataError> {
         self.conn
-            .execute("DELETE FROM assets WHERE id=$1;", &[&(id as i32)])
+            .execute("DELETE FROM assets WHERE id=$1;", &[&to_db_id(id)?])
             .map_err(|e| DataError::InsertFailed(e.to_string()))?;
         Ok(())
     }
 
     // insert, get, update and delete for transactions
-    fn insert_transaction(&mut self, transaction: &Transaction) -> Result<usize, DataError> {
-        let transaction = RawTransaction::from_transaction(transaction);
+    fn insert_transaction(&mut self, transaction: &Transaction) -> Result<u64, DataError> {
+        self.validate_transfer(transaction)?;
+        let transaction = RawTransaction::from_transaction(transaction)?;
+        let now = self.clock.now();
         let row = self
             .conn
             .query_one(
                 "INSERT INTO transactions (trans_type, asset_id, cash_amount, 
                 cash_currency, cash_date, related_trans, position,
-                note) 
-                VALUES ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING id",
+                note, tags, account_id, created_at, modified_at) 
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $11) RETURNING id",
                 &[
                     &transaction.trans_type,
                     &transaction.asset,
@@ -217,26 +367,29 @@ impl DataHandler for PostgresDB {
                     &transaction.related_trans,
                     &transaction.position,
                     &transaction.note,
+                    &transaction.tags,
+                    &transaction.account,
+                    &now,
                 ],
             )
             .map_err(|e| DataError::InsertFailed(e.to_string()))?;
-        let id: i32 = row.get(0);
-        Ok(id as usize)
+        let id: i64 = row.get(0);
+        from_db_id(id)
     }
 
-    fn get_transaction_by_id(&mut self, id: usize) -> Result<Transaction, DataError> {
+    fn get_transaction_by_id(&mut self, id: u64) -> Result<Transaction, DataError> {
         let row = self
             .conn
             .query_one(
                 "SELECT trans_type, asset_id, 
-        cash_amount, cash_currency, cash_date, related_trans, position, note 
-        FROM transactions
+        cash_amount, cash_currency, cash_date, related_trans, position, note, tags,
+        account_id, created_at, modified_at FROM transactions
         WHERE id=$1",
-                &[&(id as i32)],
+                &[&to_db_id(id)?],
             )
             .map_err(|e| DataError::NotFound(e.to_string()))?;
         let transaction = RawTransaction {
-            id: Some(id as i32),
+            id: Some(to_db_id(id)?),
             trans_type: row.get(0),
             asset: row.get(1),
             cash_amount: row.get(2),
@@ -245,6 +398,10 @@ impl DataHandler for PostgresDB {
             related_trans: row.get(5),
             position: row.get(6),
             note: row.get(7),
+            tags: row.get(8),
+            account: row.get(9),
+            created_at: row.get(10),
+            modified_at: row.get(11),
         };
         Ok(transaction.to_transaction()?)
     }
@@ -255,8 +412,8 @@ impl DataHandler for PostgresDB {
             .conn
             .query(
                 "SELECT id, trans_type, asset_id, 
-        cash_amount, cash_currency, cash_date, related_trans, position, note 
-        FROM transactions",
+        cash_amount, cash_currency, cash_date, related_trans, position, note, tags,
+        account_id, created_at, modified_at FROM transactions",
                 &[],
             )
             .map_err(|e| DataError::NotFound(e.to_string()))?
@@ -271,6 +428,57 @@ impl DataHandler for PostgresDB {
                 related_trans: row.get(6),
                 position: row.get(7),
                 note: row.get(8),
+                tags: row.get(9),
+                account: row.get(10),
+                created_at: row.get(11),
+                modified_at: row.get(12),
+            };
+            transactions.push(transaction.to_transaction()?);
+        }
+        Ok(transactions)
+    }
+
+    fn transaction_page_size(&self) -> usize {
+        self.page_size
+    }
+
+    fn get_transactions_after_id(
+        &mut self,
+        after: u64,
+        limit: usize,
+    ) -> Result<Vec<Transaction>, DataError> {
+        // ids are stored as signed integers, larger ones can't exist
+        let after = match i64::try_from(after) {
+            Ok(after) => after,
+            Err(_) => return Ok(Vec::new()),
+        };
+        let limit = i64::try_from(limit).unwrap_or(i64::MAX);
+        let mut transactions = Vec::new();
+        for row in self
+            .conn
+            .query(
+                "SELECT id, trans_type, asset_id, 
+        cash_amount, cash_currency, cash_date, related_trans, position, note, tags,
+        account_id, created_at, modified_at FROM transactions
+        WHERE id>$1 ORDER BY id LIMIT $2",
+                &[&(after), &(limit)],
+            )
+            .map_err(|e| DataError::NotFound(e.to_string()))?
+        {
+            let transaction = RawTransaction {
+                id: row.get(0),
+                trans_type: row.get(1),
+                asset: row.get(2),
+                cash_amount: row.get(3),
+                cash_currency: row.get(4),
+                cash_date: row.get(5),
+                related_trans: row.get(6),
+                position: row.get(7),
+                note: row.get(8),
+                tags: row.get(9),
+                account: row.get(10),
+                created_at: row.get(11),
+                modified_at: row.get(12),
             };
             transactions.push(transaction.to_transaction()?);
         }
@@ -283,19 +491,24 @@ impl DataHandler for PostgresDB {
                 "not yet stored to database".to_string(),
             ));
         }
-        let id = transaction.id.unwrap() as i32;
-        let transaction = RawTransaction::from_transaction(transaction);
+        let id = to_db_id(transaction.id.unwrap())?;
+        self.validate_transfer(transaction)?;
+        let transaction = RawTransaction::from_transaction(transaction)?;
+        let now = self.clock.now();
         self.conn
             .execute(
                 "UPDATE transactions SET 
                 trans_type=$2, 
                 asset_id=$3, 
-                cash_value=$4, 
+                cash_amount=$4, 
                 cash_currency=$5,
                 cash_date=$6,
                 related_trans=$7,
                 position=$8,
-                note=$9
+                note=$9,
+                tags=$10,
+                account_id=$11,
+                modified_at=$12
             WHERE id=$1",
                 &[
                     &id,
@@ -307,15 +520,18 @@ impl DataHandler for PostgresDB {
                     &transaction.related_trans,
                     &transaction.position,
                     &transaction.note,
+                    &transaction.tags,
+                    &transaction.account,
+                    &now,
                 ],
             )
             .map_err(|e| DataError::InsertFailed(e.to_string()))?;
         Ok(())
     }
 
-    fn delete_transaction(&mut self, id: usize) -> Result<(), DataError> {
+    fn delete_transaction(&mut self, id: u64) -> Result<(), DataError> {
         self.conn
-            .execute("DELETE FROM transactions WHERE id=$1;", &[&(id as i32)])
+            .execute("DELETE FROM transactions WHERE id=$1;", &[&to_db_id(id)?])
             .map_err(|e| DataError::InsertFailed(e.to_string()))?;
         Ok(())
     }
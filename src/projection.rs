@@ -0,0 +1,348 @@
+//! Projection of the future value of a savings plan, i.e. an initial investment
+//! plus recurring contributions growing with an assumed annual return.
+//! Optionally, the uncertainty of the returns is taken into account by simulating
+//! lognormal returns, resulting in percentile bands of the future value.
+
+use crate::calendar::Calendar;
+use crate::day_adjust::DayAdjust;
+use crate::fixed_income::{amount_from_f64, Amount, CashFlow};
+use crate::time_period::TimePeriod;
+use chrono::NaiveDate;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rand_distr::StandardNormal;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// Error related to projections
+#[derive(Debug, PartialEq)]
+pub enum ProjectionError {
+    /// Initial and recurring investment must be in the same currency
+    CurrencyMismatch,
+    /// Contribution period must have a frequency per year, e.g. "1M" or "3M"
+    NoFrequency,
+    /// Volatility must not be negative
+    NegativeVolatility,
+    /// Contribution period must move dates forward in time
+    NonPositivePeriod,
+}
+
+impl fmt::Display for ProjectionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProjectionError::CurrencyMismatch => write!(
+                f,
+                "initial and recurring investment must be in the same currency"
+            ),
+            ProjectionError::NoFrequency => write!(
+                f,
+                "period of contributions must be convertible to a frequency per year"
+            ),
+            ProjectionError::NegativeVolatility => write!(f, "volatility must not be negative"),
+            ProjectionError::NonPositivePeriod => {
+                write!(f, "period of contributions must be positive")
+            }
+        }
+    }
+}
+
+impl Error for ProjectionError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
+
+/// Definition of a savings plan
+#[derive(Debug, Clone)]
+pub struct SavingsPlan {
+    /// Initial investment at the start date of the plan
+    pub initial: CashFlow,
+    /// Contribution at the end of each period
+    pub recurring: Amount,
+    pub period: TimePeriod,
+    /// Assumed annual return, e.g. 0.05 for 5%
+    pub annual_return: f64,
+    pub horizon_years: u32,
+}
+
+/// Settings for the Monte Carlo simulation
+#[derive(Debug, Clone, Copy)]
+pub struct MonteCarloSettings {
+    /// Number of simulated paths
+    pub paths: usize,
+    /// Seed of the random number generator, the same seed gives the same results
+    pub seed: u64,
+}
+
+impl Default for MonteCarloSettings {
+    fn default() -> MonteCarloSettings {
+        MonteCarloSettings {
+            paths: 1000,
+            seed: 0,
+        }
+    }
+}
+
+/// Percentiles of the simulated value at some date
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PercentileBand {
+    pub p5: f64,
+    pub p50: f64,
+    pub p95: f64,
+}
+
+/// Projected values at the start date and after each contribution
+#[derive(Debug, Clone)]
+pub struct ProjectionResult {
+    pub dates: Vec<NaiveDate>,
+    /// Value assuming constant returns
+    pub values: Vec<Amount>,
+    /// Percentile bands, if a volatility has been given
+    pub bands: Option<Vec<PercentileBand>>,
+}
+
+/// Project the value of an initial investment plus recurring contributions every `period`
+/// over `horizon_years` with constant `annual_return`. If a volatility is given, the
+/// percentiles of simulated lognormal returns are calculated as well, using default
+/// Monte Carlo settings. If a calendar is given, contribution dates are adjusted to
+/// business days.
+pub fn project(
+    initial: CashFlow,
+    recurring: Amount,
+    period: TimePeriod,
+    annual_return: f64,
+    volatility: Option<f64>,
+    horizon_years: u32,
+    calendar: Option<&Calendar>,
+) -> Result<ProjectionResult, ProjectionError> {
+    let plan = SavingsPlan {
+        initial,
+        recurring,
+        period,
+        annual_return,
+        horizon_years,
+    };
+    plan.project(volatility, calendar, &MonteCarloSettings::default())
+}
+
+impl SavingsPlan {
+    /// Unadjusted contribution dates after the start date up to the end of the horizon.
+    /// The horizon is added in months, such that a plan starting on February 29 ends
+    /// on February 28 if the last year is not a leap year. The period must be positive.
+    fn contribution_dates(&self) -> Vec<NaiveDate> {
+        let start = self.initial.date;
+        let end = TimePeriod::from_str(&format!("{}M", 12 * self.horizon_years))
+            .unwrap()
+            .add_to(start, None);
+        let mut dates = Vec::new();
+        let mut date = self.period.add_to(start, None);
+        while date <= end {
+            dates.push(date);
+            date = self.period.add_to(date, None);
+        }
+        dates
+    }
+
+    /// Project the plan's value, see `project` for details
+    pub fn project(
+        &self,
+        volatility: Option<f64>,
+        calendar: Option<&Calendar>,
+        settings: &MonteCarloSettings,
+    ) -> Result<ProjectionResult, ProjectionError> {
+        let currency = self.initial.amount.currency;
+        if self.recurring.currency != currency {
+            return Err(ProjectionError::CurrencyMismatch);
+        }
+        if !self.period.is_positive() {
+            return Err(ProjectionError::NonPositivePeriod);
+        }
+        let frequency = self
+            .period
+            .frequency()
+            .map_err(|_| ProjectionError::NoFrequency)?;
+        let dt = 1. / frequency as f64;
+        let growth = (1. + self.annual_return).powf(dt);
+
+        let mut dates = vec![self.initial.date];
+        for date in self.contribution_dates() {
+            dates.push(match calendar {
+                Some(cal) => DayAdjust::Modified.adjust_date(date, cal),
+                None => date,
+            });
+        }
+        let mut value = self.initial.amount.value();
+        let mut values = vec![value];
+        for _ in 1..dates.len() {
+            value = value * growth + self.recurring.value();
+            values.push(value);
+        }
+
+        let bands = match volatility {
+            None => None,
+            Some(vol) if vol < 0. => return Err(ProjectionError::NegativeVolatility),
+            Some(vol) => Some(self.simulate(vol, dt, dates.len(), settings)),
+        };
+        Ok(ProjectionResult {
+            dates,
+            values: values
+                .into_iter()
+                .map(|amount| Amount {
+                    amount: amount_from_f64(amount),
+                    currency,
+                })
+                .collect(),
+            bands,
+        })
+    }
+
+    /// Simulate paths with lognormal returns whose expectation equals the annual return
+    fn simulate(
+        &self,
+        volatility: f64,
+        dt: f64,
+        steps: usize,
+        settings: &MonteCarloSettings,
+    ) -> Vec<PercentileBand> {
+        let mut rng = StdRng::seed_from_u64(settings.seed);
+        let drift = ((1. + self.annual_return).ln() - 0.5 * volatility * volatility) * dt;
+        let diffusion = volatility * dt.sqrt();
+        let mut paths = vec![vec![self.initial.amount.value(); settings.paths]; steps];
+        for path in 0..settings.paths {
+            let mut value = self.initial.amount.value();
+            for step in paths.iter_mut().skip(1) {
+                let z: f64 = rng.sample(StandardNormal);
+                value = value * (drift + diffusion * z).exp() + self.recurring.value();
+                step[path] = value;
+            }
+        }
+        paths
+            .iter_mut()
+            .map(|values| {
+                values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                PercentileBand {
+                    p5: percentile(values, 0.05),
+                    p50: percentile(values, 0.5),
+                    p95: percentile(values, 0.95),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Percentile of sorted values, interpolating linearly between closest ranks
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return f64::NAN;
+    }
+    let rank = p * (sorted.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    sorted[lower] + (rank - lower as f64) * (sorted[upper] - sorted[lower])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::currency::Currency;
+    use crate::market::Market;
+
+    #[test]
+    fn deterministic_projection_equals_annuity() {
+        let eur = Currency::from_str("EUR").unwrap();
+        let initial = CashFlow::new(10_000., eur, NaiveDate::from_ymd(2020, 1, 15));
+        let recurring = Amount {
+            amount: amount_from_f64(200.),
+            currency: eur,
+        };
+        let period = TimePeriod::from_str("1M").unwrap();
+        let result = project(initial, recurring, period, 0.06, None, 10, None).unwrap();
+        assert!(result.bands.is_none());
+        assert_eq!(result.dates.len(), 121);
+        assert_eq!(result.dates[120], NaiveDate::from_ymd(2030, 1, 15));
+
+        let i = 1.06_f64.powf(1. / 12.) - 1.;
+        for (n, value) in result.values.iter().enumerate() {
+            let growth = (1. + i).powi(n as i32);
+            let annuity = 10_000. * growth + 200. * (growth - 1.) / i;
+            assert_fuzzy_eq!(value.value(), annuity, 1e-8);
+        }
+
+        // zero volatility gives the deterministic path in all percentiles
+        let result = project(initial, recurring, period, 0.06, Some(0.), 10, None).unwrap();
+        for (band, value) in result.bands.unwrap().iter().zip(result.values.iter()) {
+            assert_fuzzy_eq!(band.p5, value.value(), 1e-8);
+            assert_fuzzy_eq!(band.p50, value.value(), 1e-8);
+            assert_fuzzy_eq!(band.p95, value.value(), 1e-8);
+        }
+
+        // contribution dates are adjusted to business days, 2020-02-15 is a Saturday
+        let market = Market::new();
+        let cal = market.get_calendar("TARGET").unwrap();
+        let result = project(initial, recurring, period, 0.06, None, 1, Some(cal)).unwrap();
+        assert_eq!(result.dates[1], NaiveDate::from_ymd(2020, 2, 17));
+    }
+
+    #[test]
+    fn reproducible_monte_carlo_bands() {
+        let eur = Currency::from_str("EUR").unwrap();
+        let plan = SavingsPlan {
+            initial: CashFlow::new(10_000., eur, NaiveDate::from_ymd(2020, 1, 15)),
+            recurring: Amount {
+                amount: amount_from_f64(200.),
+                currency: eur,
+            },
+            period: TimePeriod::from_str("3M").unwrap(),
+            annual_return: 0.06,
+            horizon_years: 5,
+        };
+        let settings = MonteCarloSettings {
+            paths: 500,
+            seed: 42,
+        };
+        let result1 = plan.project(Some(0.2), None, &settings).unwrap();
+        let result2 = plan.project(Some(0.2), None, &settings).unwrap();
+        let bands1 = result1.bands.unwrap();
+        assert_eq!(bands1, result2.bands.unwrap());
+        let last = bands1.last().unwrap();
+        assert!(last.p5 < last.p50 && last.p50 < last.p95);
+        assert_eq!(
+            plan.project(Some(-0.2), None, &settings).unwrap_err(),
+            ProjectionError::NegativeVolatility
+        );
+    }
+
+    #[test]
+    fn plan_starting_on_leap_day() {
+        let eur = Currency::from_str("EUR").unwrap();
+        let initial = CashFlow::new(10_000., eur, NaiveDate::from_ymd(2020, 2, 29));
+        let recurring = Amount {
+            amount: amount_from_f64(200.),
+            currency: eur,
+        };
+        let period = TimePeriod::from_str("1Y").unwrap();
+        let result = project(initial, recurring, period, 0.06, None, 3, None).unwrap();
+        assert_eq!(
+            result.dates,
+            vec![
+                NaiveDate::from_ymd(2020, 2, 29),
+                NaiveDate::from_ymd(2021, 2, 28),
+                NaiveDate::from_ymd(2022, 2, 28),
+                NaiveDate::from_ymd(2023, 2, 28),
+            ]
+        );
+        let period = TimePeriod::from_str("3M").unwrap();
+        let result = project(initial, recurring, period, 0.06, None, 1, None).unwrap();
+        assert_eq!(result.dates.len(), 5);
+        assert_eq!(result.dates[4], NaiveDate::from_ymd(2021, 2, 28));
+
+        for period in &["-1M", "-1Y"] {
+            let period = TimePeriod::from_str(period).unwrap();
+            assert_eq!(
+                project(initial, recurring, period, 0.06, None, 3, None).unwrap_err(),
+                ProjectionError::NonPositivePeriod
+            );
+        }
+    }
+}
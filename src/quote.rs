@@ -1,27 +1,94 @@
 ///! Implementation of a container for basic asset data
+use crate::calendar::Calendar;
 use crate::currency::Currency;
 use crate::data_handler::{DataError, DataItem};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MarketDataSource {
-    pub id: Option<usize>,
+    pub id: Option<u64>,
     pub name: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Ticker {
-    pub id: Option<usize>,
+    pub id: Option<u64>,
     pub name: String,
+    /// Id of the asset this ticker provides quotes for
+    pub asset: u64,
     pub currency: Currency,
-    pub source: usize,
+    pub source: u64,
+    /// How often quotes of this ticker should be fetched
+    #[serde(default)]
+    pub update_policy: UpdatePeriod,
+}
+
+/// Frequency of quote updates of a ticker
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum UpdatePeriod {
+    /// Update on every business day
+    #[default]
+    #[serde(rename = "daily")]
+    Daily,
+    /// Update once a week, i.e. if the last quote is at least seven days old
+    #[serde(rename = "weekly")]
+    Weekly,
+    /// Never update automatically
+    #[serde(rename = "never")]
+    Never,
+}
+
+impl fmt::Display for UpdatePeriod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UpdatePeriod::Daily => write!(f, "daily"),
+            UpdatePeriod::Weekly => write!(f, "weekly"),
+            UpdatePeriod::Never => write!(f, "never"),
+        }
+    }
+}
+
+impl FromStr for UpdatePeriod {
+    type Err = DataError;
+    fn from_str(period: &str) -> Result<UpdatePeriod, DataError> {
+        match period {
+            "daily" => Ok(UpdatePeriod::Daily),
+            "weekly" => Ok(UpdatePeriod::Weekly),
+            "never" => Ok(UpdatePeriod::Never),
+            _ => Err(DataError::Serialization(format!(
+                "unknown update period '{}'",
+                period
+            ))),
+        }
+    }
+}
+
+impl UpdatePeriod {
+    /// Check whether a ticker whose latest quote is from `last` should be updated at `now`.
+    /// Daily updates are due if there is a business day of `cal` after the date of `last`
+    /// up to the date of `now`. Tickers without any quote are always due, unless they
+    /// are never updated.
+    pub fn is_due(&self, last: Option<DateTime<Utc>>, now: DateTime<Utc>, cal: &Calendar) -> bool {
+        let last = match (self, last) {
+            (UpdatePeriod::Never, _) => return false,
+            (_, None) => return true,
+            (_, Some(last)) => last,
+        };
+        match self {
+            UpdatePeriod::Daily => cal.next_bday(last.naive_utc().date()) <= now.naive_utc().date(),
+            UpdatePeriod::Weekly => now - last >= Duration::days(7),
+            UpdatePeriod::Never => false,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Quote {
-    pub id: Option<usize>,
-    pub ticker: usize,
+    pub id: Option<u64>,
+    pub ticker: u64,
     pub price: f64,
     pub time: DateTime<Utc>,
     pub volume: Option<f64>,
@@ -29,7 +96,7 @@ pub struct Quote {
 
 impl DataItem for MarketDataSource {
     // get id or return error if id hasn't been set yet
-    fn get_id(&self) -> Result<usize, DataError> {
+    fn get_id(&self) -> Result<u64, DataError> {
         match self.id {
             Some(id) => Ok(id),
             None => Err(DataError::DataAccessFailure(
@@ -38,7 +105,7 @@ impl DataItem for MarketDataSource {
         }
     }
     // set id or return error if id has already been set
-    fn set_id(&mut self, id: usize) -> Result<(), DataError> {
+    fn set_id(&mut self, id: u64) -> Result<(), DataError> {
         match self.id {
             Some(_) => Err(DataError::DataAccessFailure(
                 "tried to change valid asset id".to_string(),
@@ -53,7 +120,7 @@ impl DataItem for MarketDataSource {
 
 impl DataItem for Quote {
     // get id or return error if id hasn't been set yet
-    fn get_id(&self) -> Result<usize, DataError> {
+    fn get_id(&self) -> Result<u64, DataError> {
         match self.id {
             Some(id) => Ok(id),
             None => Err(DataError::DataAccessFailure(
@@ -62,7 +129,7 @@ impl DataItem for Quote {
         }
     }
     // set id or return error if id has already been set
-    fn set_id(&mut self, id: usize) -> Result<(), DataError> {
+    fn set_id(&mut self, id: u64) -> Result<(), DataError> {
         match self.id {
             Some(_) => Err(DataError::DataAccessFailure(
                 "tried to change valid quote id".to_string(),
@@ -77,7 +144,7 @@ impl DataItem for Quote {
 
 impl DataItem for Ticker {
     // get id or return error if id hasn't been set yet
-    fn get_id(&self) -> Result<usize, DataError> {
+    fn get_id(&self) -> Result<u64, DataError> {
         match self.id {
             Some(id) => Ok(id),
             None => Err(DataError::DataAccessFailure(
@@ -86,7 +153,7 @@ impl DataItem for Ticker {
         }
     }
     // set id or return error if id has already been set
-    fn set_id(&mut self, id: usize) -> Result<(), DataError> {
+    fn set_id(&mut self, id: u64) -> Result<(), DataError> {
         match self.id {
             Some(_) => Err(DataError::DataAccessFailure(
                 "tried to change valid ticker id".to_string(),
@@ -98,3 +165,256 @@ impl DataItem for Ticker {
         }
     }
 }
+
+/// Reason for flagging a quote as outlier
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutlierReason {
+    /// The price is zero or negative
+    NonPositive,
+    /// The log-return relative to the median of the surrounding quotes exceeds the threshold
+    Jump { log_return: f64 },
+}
+
+/// Quote flagged as outlier, identified by its position in the series
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OutlierFlag {
+    pub index: usize,
+    pub time: DateTime<Utc>,
+    pub price: f64,
+    pub reason: OutlierReason,
+}
+
+/// Configuration of outlier detection
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OutlierConfig {
+    /// Maximal absolute log-return relative to the median of the surrounding quotes, e.g.
+    /// `100f64.ln()` to detect prices off by a factor of 100
+    pub max_log_return: f64,
+    /// Number of quotes the median is taken of, including the quote itself. The window is
+    /// centered around the quote and shifted at the beginning and end of the series.
+    pub window: usize,
+}
+
+impl Default for OutlierConfig {
+    /// Prices deviating by more than a factor of e (about 2.7) from the median of 5 quotes
+    /// are flagged
+    fn default() -> OutlierConfig {
+        OutlierConfig {
+            max_log_return: 1.,
+            window: 5,
+        }
+    }
+}
+
+/// Treatment of outliers when cleaning quotes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CleanMode {
+    /// Remove outliers from the series
+    Drop,
+    /// Replace the price of outliers by interpolating linearly in time between the neighbouring
+    /// valid quotes, or by the nearest valid quote at the beginning or end of the series
+    Interpolate,
+}
+
+/// Modification of a quote by cleaning, for audit purposes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuoteModification {
+    pub flag: OutlierFlag,
+    /// New price of the quote, or `None` if the quote has been dropped
+    pub replacement: Option<f64>,
+}
+
+/// Flag quotes with a non-positive price or a log-return relative to the rolling median of the
+/// positive prices within the configured window exceeding the configured threshold. Quotes are
+/// expected to be ordered by time. Since the median is robust, isolated spikes are flagged,
+/// including a bad first quote, while a lasting shift of the price level is not.
+pub fn detect_outliers(series: &[Quote], config: &OutlierConfig) -> Vec<OutlierFlag> {
+    let window = config.window.max(1).min(series.len());
+    let mut flags = Vec::new();
+    for (index, quote) in series.iter().enumerate() {
+        let reason = if quote.price <= 0. {
+            Some(OutlierReason::NonPositive)
+        } else {
+            let start = index.saturating_sub(window / 2).min(series.len() - window);
+            let log_return = quote.price.ln() - log_median(&series[start..start + window]);
+            if log_return.abs() > config.max_log_return {
+                Some(OutlierReason::Jump { log_return })
+            } else {
+                None
+            }
+        };
+        if let Some(reason) = reason {
+            flags.push(OutlierFlag {
+                index,
+                time: quote.time,
+                price: quote.price,
+                reason,
+            });
+        }
+    }
+    flags
+}
+
+/// Median of the logarithms of the positive prices in `quotes`, i.e. the mean of the two
+/// middle values for an even number of prices; at least one price must be positive
+fn log_median(quotes: &[Quote]) -> f64 {
+    let mut logs: Vec<f64> = quotes
+        .iter()
+        .filter(|quote| quote.price > 0.)
+        .map(|quote| quote.price.ln())
+        .collect();
+    logs.sort_by(f64::total_cmp);
+    let mid = logs.len() / 2;
+    if logs.len() % 2 == 1 {
+        logs[mid]
+    } else {
+        (logs[mid - 1] + logs[mid]) / 2.
+    }
+}
+
+/// Detect outliers as in `detect_outliers` and remove or replace them according to `mode`.
+/// Returns the cleaned series together with the list of modifications.
+pub fn clean_quotes(
+    series: &[Quote],
+    config: &OutlierConfig,
+    mode: CleanMode,
+) -> (Vec<Quote>, Vec<QuoteModification>) {
+    let flags = detect_outliers(series, config);
+    let is_outlier = |idx: usize| flags.iter().any(|f| f.index == idx);
+    let valid: Vec<&Quote> = series
+        .iter()
+        .enumerate()
+        .filter(|(idx, _)| !is_outlier(*idx))
+        .map(|(_, q)| q)
+        .collect();
+    let mut cleaned = Vec::with_capacity(series.len());
+    let mut modifications = Vec::with_capacity(flags.len());
+    for (idx, quote) in series.iter().enumerate() {
+        let flag = match flags.iter().find(|f| f.index == idx) {
+            Some(flag) => *flag,
+            None => {
+                cleaned.push(quote.clone());
+                continue;
+            }
+        };
+        let replacement = match mode {
+            CleanMode::Drop => None,
+            CleanMode::Interpolate => interpolate_price(&valid, quote.time),
+        };
+        if let Some(price) = replacement {
+            let mut quote = quote.clone();
+            quote.price = price;
+            cleaned.push(quote);
+        }
+        modifications.push(QuoteModification { flag, replacement });
+    }
+    (cleaned, modifications)
+}
+
+/// Price at `time` interpolated linearly between the valid quotes before and after,
+/// or `None` if there are no valid quotes
+fn interpolate_price(valid: &[&Quote], time: DateTime<Utc>) -> Option<f64> {
+    let next = valid.iter().position(|q| q.time >= time);
+    match next {
+        None => valid.last().map(|q| q.price),
+        Some(0) => Some(valid[0].price),
+        Some(idx) => {
+            let (q0, q1) = (valid[idx - 1], valid[idx]);
+            let span = (q1.time - q0.time).num_seconds() as f64;
+            if span == 0. {
+                return Some(q1.price);
+            }
+            let w = (time - q0.time).num_seconds() as f64 / span;
+            Some(q0.price + w * (q1.price - q0.price))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::make_time;
+
+    fn quotes(prices: &[f64]) -> Vec<Quote> {
+        prices
+            .iter()
+            .enumerate()
+            .map(|(i, price)| Quote {
+                id: None,
+                ticker: 1,
+                price: *price,
+                time: make_time(2020, 3, 2 + i as u32, 18, 0, 0).unwrap(),
+                volume: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn detect_spike_and_zero() {
+        let series = quotes(&[10.2, 10.4, 1040., 10.5, 0., 10.8, 10.6]);
+        let flags = detect_outliers(&series, &OutlierConfig::default());
+        assert_eq!(flags.len(), 2);
+        assert_eq!(flags[0].index, 2);
+        // the median of 10.2, 10.4, 1040 and 10.5 is the mean of 10.4 and 10.5 in log terms
+        let median = (10.4f64.ln() + 10.5f64.ln()) / 2.;
+        match flags[0].reason {
+            OutlierReason::Jump { log_return } => {
+                assert_fuzzy_eq!(log_return, 1040f64.ln() - median, 1e-12)
+            }
+            _ => panic!("jump expected"),
+        }
+        assert_eq!(flags[1].index, 4);
+        assert_eq!(flags[1].reason, OutlierReason::NonPositive);
+        assert_eq!(flags[1].time, series[4].time);
+
+        // a tight threshold flags regular moves as well
+        let config = OutlierConfig {
+            max_log_return: 0.02,
+            ..Default::default()
+        };
+        let flags = detect_outliers(&series, &config);
+        assert_eq!(flags.len(), 3);
+        assert_eq!(flags[0].index, 0);
+    }
+
+    #[test]
+    fn level_shift_and_bad_first_quote() {
+        let config = OutlierConfig::default();
+
+        // a lasting shift of the price level, e.g. by a missed split, is not an outlier
+        let series = quotes(&[10.2, 10.4, 10.3, 10.5, 1050., 1040., 1060., 1055.]);
+        assert!(detect_outliers(&series, &config).is_empty());
+
+        // a bad first quote is flagged, but not the quotes after it
+        let series = quotes(&[1020., 10.4, 10.3, 10.5, 10.6, 10.4]);
+        let flags = detect_outliers(&series, &config);
+        assert_eq!(flags.len(), 1);
+        assert_eq!(flags[0].index, 0);
+
+        // with two quotes only, the median is the geometric mean and both deviate from it
+        let series = quotes(&[10.5, 1050.]);
+        let flags = detect_outliers(&series, &config);
+        assert_eq!(flags.len(), 2);
+    }
+
+    #[test]
+    fn clean_spike_and_zero() {
+        let series = quotes(&[10.2, 10.4, 1040., 10.5, 0., 10.8, 10.6]);
+        let config = OutlierConfig::default();
+
+        let (cleaned, modifications) = clean_quotes(&series, &config, CleanMode::Drop);
+        let prices: Vec<f64> = cleaned.iter().map(|q| q.price).collect();
+        assert_eq!(prices, vec![10.2, 10.4, 10.5, 10.8, 10.6]);
+        assert_eq!(modifications.len(), 2);
+        assert_eq!(modifications[0].flag.price, 1040.);
+        assert_eq!(modifications[0].replacement, None);
+
+        let (cleaned, modifications) = clean_quotes(&series, &config, CleanMode::Interpolate);
+        assert_eq!(cleaned.len(), series.len());
+        assert_fuzzy_eq!(cleaned[2].price, 10.45, 1e-12);
+        assert_fuzzy_eq!(cleaned[4].price, 10.65, 1e-12);
+        assert_eq!(cleaned[4].time, series[4].time);
+        assert_eq!(modifications[1].flag.index, 4);
+        assert_fuzzy_eq!(modifications[1].replacement.unwrap(), 10.65, 1e-12);
+    }
+}
@@ -1,8 +1,9 @@
 use crate::currency::Currency;
 use crate::day_count_conv::DayCountConv;
-use crate::fixed_income::{Amount, CashFlow};
+use crate::fixed_income::{amount_from_f64, Amount, CashFlow};
 use chrono::NaiveDate;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
+use std::convert::TryFrom;
 
 /// Methods for compounding interest rates
 #[derive(Deserialize, Serialize, Clone, Copy, Debug)]
@@ -53,9 +54,9 @@ pub trait Discounter {
     /// Discount given cash flow
     fn discount_cash_flow(&self, cf: &CashFlow, today: NaiveDate) -> Result<Amount, DiscountError> {
         if self.currency() == cf.amount.currency {
-            let amount = self.discount_factor(today, cf.date) * cf.amount.amount;
+            let amount = self.discount_factor(today, cf.date) * cf.amount.value();
             Ok(Amount {
-                amount,
+                amount: amount_from_f64(amount),
                 currency: cf.amount.currency,
             })
         } else {
@@ -69,18 +70,18 @@ pub trait Discounter {
         cf_stream: &Vec<CashFlow>,
         today: NaiveDate,
     ) -> Result<Amount, DiscountError> {
-        let mut amount = Amount {
-            amount: 0.0,
-            currency: self.currency(),
-        };
+        let mut amount = 0.0;
         for cf in cf_stream {
             if self.currency() == cf.amount.currency {
-                amount.amount += self.discount_factor(today, cf.date) * cf.amount.amount;
+                amount += self.discount_factor(today, cf.date) * cf.amount.value();
             } else {
                 return Err(DiscountError);
             }
         }
-        Ok(amount)
+        Ok(Amount {
+            amount: amount_from_f64(amount),
+            currency: self.currency(),
+        })
     }
 }
 
@@ -130,6 +131,352 @@ impl Discounter for FlatRate {
     }
 }
 
+/// Interpolation of continuously compounded zero rates `y` given at the times `x` of the pillars
+/// (in years, strictly ascending) at time `xi`. The discount curve only requests values strictly
+/// between the first and last pillar and extrapolates flat otherwise. Implementations must
+/// reproduce the given values at the pillars.
+pub trait Interpolator {
+    /// Name of the interpolation method, identifying it in serialized curves
+    fn name(&self) -> &'static str;
+
+    /// Coefficients depending only on the pillars, e.g. of a spline, which are calculated
+    /// once when the curve is constructed and passed to each call of `interpolate`
+    fn coefficients(&self, _x: &[f64], _y: &[f64]) -> Vec<f64> {
+        Vec::new()
+    }
+
+    fn interpolate(&self, x: &[f64], y: &[f64], coefficients: &[f64], xi: f64) -> f64;
+}
+
+/// Index `i` of the interval `[x[i-1], x[i]]` containing `xi`
+fn interval(x: &[f64], xi: f64) -> usize {
+    x.iter()
+        .position(|xj| *xj >= xi)
+        .unwrap_or(x.len() - 1)
+        .max(1)
+}
+
+/// Linear interpolation of zero rates
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default)]
+pub struct Linear;
+
+impl Interpolator for Linear {
+    fn name(&self) -> &'static str {
+        "linear"
+    }
+
+    fn interpolate(&self, x: &[f64], y: &[f64], _coefficients: &[f64], xi: f64) -> f64 {
+        let i = interval(x, xi);
+        let w = (xi - x[i - 1]) / (x[i] - x[i - 1]);
+        y[i - 1] + w * (y[i] - y[i - 1])
+    }
+}
+
+/// Linear interpolation of the logarithm of discount factors, i.e. of zero rates times time,
+/// resulting in constant forward rates between pillars
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default)]
+pub struct LogLinear;
+
+impl Interpolator for LogLinear {
+    fn name(&self) -> &'static str {
+        "log-linear"
+    }
+
+    fn interpolate(&self, x: &[f64], y: &[f64], _coefficients: &[f64], xi: f64) -> f64 {
+        let i = interval(x, xi);
+        let w = (xi - x[i - 1]) / (x[i] - x[i - 1]);
+        let log_df = x[i - 1] * y[i - 1] + w * (x[i] * y[i] - x[i - 1] * y[i - 1]);
+        log_df / xi
+    }
+}
+
+/// Natural cubic spline interpolation of zero rates
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default)]
+pub struct CubicSpline;
+
+impl Interpolator for CubicSpline {
+    fn name(&self) -> &'static str {
+        "cubic-spline"
+    }
+
+    /// Second derivatives of the natural spline at the pillars
+    fn coefficients(&self, x: &[f64], y: &[f64]) -> Vec<f64> {
+        let n = x.len();
+        let mut m = vec![0.; n];
+        if n < 3 {
+            return m;
+        }
+        // solve the tridiagonal system by forward elimination and back substitution
+        let mut diag = vec![0.; n];
+        let mut rhs = vec![0.; n];
+        for i in 1..n - 1 {
+            let h0 = x[i] - x[i - 1];
+            let h1 = x[i + 1] - x[i];
+            diag[i] = 2. * (h0 + h1);
+            rhs[i] = 6. * ((y[i + 1] - y[i]) / h1 - (y[i] - y[i - 1]) / h0);
+            if i > 1 {
+                let factor = h0 / diag[i - 1];
+                diag[i] -= factor * h0;
+                rhs[i] -= factor * rhs[i - 1];
+            }
+        }
+        for i in (1..n - 1).rev() {
+            let h1 = x[i + 1] - x[i];
+            m[i] = (rhs[i] - h1 * m[i + 1]) / diag[i];
+        }
+        m
+    }
+
+    fn interpolate(&self, x: &[f64], y: &[f64], m: &[f64], xi: f64) -> f64 {
+        let i = interval(x, xi);
+        let h = x[i] - x[i - 1];
+        let a = (x[i] - xi) / h;
+        let b = (xi - x[i - 1]) / h;
+        a * y[i - 1] + b * y[i] + ((a * a * a - a) * m[i - 1] + (b * b * b - b) * m[i]) * h * h / 6.
+    }
+}
+
+/// Discount curve given by continuously compounded zero rates at pillar dates.
+/// Zero rates are interpolated between pillars by the interpolator `I`, linearly by default,
+/// and extrapolated flat before the first and after the last pillar. The name of the
+/// interpolator is serialized with the curve, a curve can only be deserialized with the same
+/// interpolator; curves without an interpolation name are accepted by any interpolator.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(
+    try_from = "CurveTerms",
+    bound(
+        serialize = "I: Interpolator",
+        deserialize = "I: Interpolator + Default"
+    )
+)]
+pub struct DiscountCurve<I = Linear> {
+    reference_date: NaiveDate,
+    pillars: Vec<(NaiveDate, f64)>,
+    day_count_conv: DayCountConv,
+    currency: Currency,
+    #[serde(rename = "interpolation", serialize_with = "serialize_name")]
+    interpolator: I,
+    #[serde(skip)]
+    cache: InterpolationCache,
+}
+
+/// Pillars as times and rates together with the interpolator's coefficients,
+/// calculated whenever the pillars change
+#[derive(Clone, Debug, Default)]
+struct InterpolationCache {
+    x: Vec<f64>,
+    y: Vec<f64>,
+    coefficients: Vec<f64>,
+}
+
+fn serialize_name<I: Interpolator, S: Serializer>(
+    interpolator: &I,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(interpolator.name())
+}
+
+/// Serialized terms of a discount curve
+#[derive(Deserialize)]
+struct CurveTerms {
+    reference_date: NaiveDate,
+    pillars: Vec<(NaiveDate, f64)>,
+    day_count_conv: DayCountConv,
+    currency: Currency,
+    interpolation: Option<String>,
+}
+
+impl<I: Interpolator + Default> TryFrom<CurveTerms> for DiscountCurve<I> {
+    type Error = String;
+
+    fn try_from(terms: CurveTerms) -> Result<Self, Self::Error> {
+        let interpolator = I::default();
+        match terms.interpolation {
+            Some(name) if name != interpolator.name() => Err(format!(
+                "curve with {} interpolation can't be read as {} interpolated curve",
+                name,
+                interpolator.name()
+            )),
+            _ => Ok(DiscountCurve::with_interpolator(
+                terms.reference_date,
+                terms.pillars,
+                terms.day_count_conv,
+                terms.currency,
+                interpolator,
+            )),
+        }
+    }
+}
+
+impl DiscountCurve {
+    /// Construct discount curve from zero rates at pillar dates
+    pub fn new(
+        reference_date: NaiveDate,
+        pillars: Vec<(NaiveDate, f64)>,
+        day_count_conv: DayCountConv,
+        currency: Currency,
+    ) -> DiscountCurve {
+        DiscountCurve::with_interpolator(reference_date, pillars, day_count_conv, currency, Linear)
+    }
+
+    /// Construct discount curve with the same zero rate for all dates
+    pub fn flat(
+        reference_date: NaiveDate,
+        rate: f64,
+        day_count_conv: DayCountConv,
+        currency: Currency,
+    ) -> DiscountCurve {
+        DiscountCurve::new(
+            reference_date,
+            vec![(reference_date, rate)],
+            day_count_conv,
+            currency,
+        )
+    }
+}
+
+impl<I: Interpolator> DiscountCurve<I> {
+    /// Construct discount curve from zero rates at pillar dates, interpolated by `interpolator`
+    pub fn with_interpolator(
+        reference_date: NaiveDate,
+        mut pillars: Vec<(NaiveDate, f64)>,
+        day_count_conv: DayCountConv,
+        currency: Currency,
+        interpolator: I,
+    ) -> DiscountCurve<I> {
+        pillars.sort_by_key(|(date, _)| *date);
+        let mut curve = DiscountCurve {
+            reference_date,
+            pillars,
+            day_count_conv,
+            currency,
+            interpolator,
+            cache: InterpolationCache::default(),
+        };
+        curve.update_cache();
+        curve
+    }
+
+    fn update_cache(&mut self) {
+        let x: Vec<f64> = self
+            .pillars
+            .iter()
+            .map(|(pillar, _)| self.year_fraction(*pillar))
+            .collect();
+        let y: Vec<f64> = self.pillars.iter().map(|(_, rate)| *rate).collect();
+        let coefficients = self.interpolator.coefficients(&x, &y);
+        self.cache = InterpolationCache { x, y, coefficients };
+    }
+
+    /// Copy of the curve with all zero rates shifted in parallel by `shift`,
+    /// e.g. 0.01 for an increase by 100bp
+    pub fn shifted(&self, shift: f64) -> DiscountCurve<I>
+    where
+        I: Clone,
+    {
+        let mut curve = self.clone();
+        for (_, rate) in curve.pillars.iter_mut() {
+            *rate += shift;
+        }
+        curve.update_cache();
+        curve
+    }
+
+    /// Date to which the curve's zero rates refer
+    pub fn reference_date(&self) -> NaiveDate {
+        self.reference_date
+    }
+
+    /// Day count convention of the curve's zero rates
+    pub fn day_count_conv(&self) -> DayCountConv {
+        self.day_count_conv
+    }
+
+    fn year_fraction(&self, date: NaiveDate) -> f64 {
+        self.day_count_conv
+            .year_fraction(self.reference_date, date, None, None)
+            .unwrap()
+    }
+
+    /// Interpolated zero rate at given date
+    fn interpolated_rate(&self, date: NaiveDate) -> f64 {
+        match (self.pillars.first(), self.pillars.last()) {
+            (None, _) | (_, None) => 0.,
+            (Some((first, rate)), _) if date <= *first => *rate,
+            (_, Some((last, rate))) if date >= *last => *rate,
+            _ => {
+                let cache = &self.cache;
+                self.interpolator.interpolate(
+                    &cache.x,
+                    &cache.y,
+                    &cache.coefficients,
+                    self.year_fraction(date),
+                )
+            }
+        }
+    }
+
+    /// Discount factor from `date` to the curve's reference date
+    fn reference_discount_factor(&self, date: NaiveDate) -> f64 {
+        (-self.interpolated_rate(date) * self.year_fraction(date)).exp()
+    }
+
+    /// Zero rate from the curve's reference date to `date` with respect to the day count
+    /// convention `dc` and the given `compounding`, i.e. the rate which reproduces the
+    /// curve's discount factor for `date`. At the reference date, the limit of the rate is
+    /// returned. Panics if `dc` requires a roll date or time period, e.g. `ActActICMA`.
+    pub fn zero_rate(&self, date: NaiveDate, dc: DayCountConv, compounding: Compounding) -> f64 {
+        let yf = dc
+            .year_fraction(self.reference_date, date, None, None)
+            .unwrap();
+        let df = self.reference_discount_factor(date);
+        // continuously compounded rate with respect to `dc`
+        let rate = if yf == 0. {
+            self.interpolated_rate(date)
+        } else {
+            -df.ln() / yf
+        };
+        let periodic = |n: f64| n * ((rate / n).exp() - 1.);
+        match compounding {
+            Compounding::Simple if yf == 0. => rate,
+            Compounding::Simple => (1. / df - 1.) / yf,
+            Compounding::Annual => periodic(1.),
+            Compounding::SemiAnnual => periodic(2.),
+            Compounding::Quarterly => periodic(4.),
+            Compounding::Monthly => periodic(12.),
+            Compounding::Continuous => rate,
+        }
+    }
+}
+
+impl<I: Interpolator> Discounter for DiscountCurve<I> {
+    fn discount_factor(&self, today: NaiveDate, pay_date: NaiveDate) -> f64 {
+        self.reference_discount_factor(pay_date) / self.reference_discount_factor(today)
+    }
+
+    fn currency(&self) -> Currency {
+        self.currency
+    }
+}
+
+/// Change of the present value of `flows` at the curve's reference date if all zero rates
+/// of `curve` are shifted in parallel by `shift_bp` basis points. Flows at or before the
+/// reference date are ignored. Fails if any flow is not in the currency of the curve.
+pub fn curve_pv_shift<I: Interpolator + Clone>(
+    flows: &[CashFlow],
+    curve: &DiscountCurve<I>,
+    shift_bp: f64,
+) -> Result<f64, DiscountError> {
+    let today = curve.reference_date();
+    let flows: Vec<CashFlow> = flows.iter().filter(|cf| cf.date > today).cloned().collect();
+    let base = curve.discount_cash_flow_stream(&flows, today)?.value();
+    let shifted = curve
+        .shifted(shift_bp / 10_000.)
+        .discount_cash_flow_stream(&flows, today)?
+        .value();
+    Ok(shifted - base)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -226,7 +573,12 @@ mod tests {
 
     #[test]
     fn discounting() {
-        let tol = 1e-11;
+        // discounted amounts are rounded to `DECIMAL_PLACES` with the `decimal` feature
+        let tol = if cfg!(feature = "decimal") {
+            1e-10
+        } else {
+            1e-11
+        };
         let curr = Currency::from_str("EUR").unwrap();
         let rate = FlatRate {
             rate: 0.05,
@@ -244,28 +596,28 @@ mod tests {
         assert_fuzzy_eq!(
             rate.discount_cash_flow(&cash_flows[0], today)
                 .unwrap()
-                .amount,
+                .value(),
             100. * f64::exp(-0.05 * (366. + 182.) / 365.),
             tol
         );
         assert_fuzzy_eq!(
             rate.discount_cash_flow(&cash_flows[1], today)
                 .unwrap()
-                .amount,
+                .value(),
             100. * f64::exp(-0.05 * (366. + 365.) / 365.),
             tol
         );
         assert_fuzzy_eq!(
             rate.discount_cash_flow(&cash_flows[2], today)
                 .unwrap()
-                .amount,
+                .value(),
             100. * f64::exp(-0.05 * (366. + 365. + 182.) / 365.),
             tol
         );
         assert_fuzzy_eq!(
             rate.discount_cash_flow(&cash_flows[3], today)
                 .unwrap()
-                .amount,
+                .value(),
             100. * f64::exp(-0.05 * (366. + 2. * 365. + 2.) / 365.),
             tol
         );
@@ -277,9 +629,195 @@ mod tests {
         assert_fuzzy_eq!(
             rate.discount_cash_flow_stream(&cash_flows, today)
                 .unwrap()
-                .amount,
+                .value(),
             sum,
             tol
         );
     }
+
+    #[test]
+    fn curve_interpolation_methods() {
+        let curr = Currency::from_str("EUR").unwrap();
+        let today = NaiveDate::from_ymd(2020, 1, 1);
+        let pillars = vec![
+            (NaiveDate::from_ymd(2021, 1, 1), 0.01),
+            (NaiveDate::from_ymd(2022, 1, 1), 0.02),
+            (NaiveDate::from_ymd(2025, 1, 1), 0.025),
+            (NaiveDate::from_ymd(2030, 1, 1), 0.03),
+        ];
+        let dc = DayCountConv::Act365;
+        let linear = DiscountCurve::new(today, pillars.clone(), dc, curr);
+        let log_linear =
+            DiscountCurve::with_interpolator(today, pillars.clone(), dc, curr, LogLinear);
+        let spline =
+            DiscountCurve::with_interpolator(today, pillars.clone(), dc, curr, CubicSpline);
+        let tol = 1e-14;
+        for (date, rate) in &pillars {
+            let t = dc.year_fraction(today, *date, None, None).unwrap();
+            let df = (-rate * t).exp();
+            assert_fuzzy_eq!(linear.discount_factor(today, *date), df, tol);
+            assert_fuzzy_eq!(log_linear.discount_factor(today, *date), df, tol);
+            assert_fuzzy_eq!(spline.discount_factor(today, *date), df, tol);
+        }
+        let date = NaiveDate::from_ymd(2023, 6, 1);
+        let df_linear = linear.discount_factor(today, date);
+        let df_log_linear = log_linear.discount_factor(today, date);
+        let df_spline = spline.discount_factor(today, date);
+        assert!((df_linear - df_log_linear).abs() > 1e-4);
+        assert!((df_linear - df_spline).abs() > 1e-5);
+
+        // log-linear interpolation results in constant forward rates between pillars
+        let t = |d: NaiveDate| dc.year_fraction(today, d, None, None).unwrap();
+        let (d0, d1) = (
+            NaiveDate::from_ymd(2022, 1, 1),
+            NaiveDate::from_ymd(2025, 1, 1),
+        );
+        let forward = |d: NaiveDate| {
+            -(log_linear.discount_factor(today, d) / log_linear.discount_factor(today, d0)).ln()
+                / (t(d) - t(d0))
+        };
+        assert_fuzzy_eq!(forward(date), forward(d1), 1e-12);
+
+        // flat extrapolation of zero rates
+        let late = NaiveDate::from_ymd(2040, 1, 1);
+        assert_fuzzy_eq!(
+            spline.discount_factor(today, late),
+            (-0.03 * t(late)).exp(),
+            tol
+        );
+    }
+
+    #[test]
+    fn serialize_curve_interpolation() {
+        let curr = Currency::from_str("EUR").unwrap();
+        let today = NaiveDate::from_ymd(2020, 1, 1);
+        let pillars = vec![
+            (NaiveDate::from_ymd(2021, 1, 1), 0.01),
+            (NaiveDate::from_ymd(2022, 1, 1), 0.02),
+            (NaiveDate::from_ymd(2025, 1, 1), 0.025),
+        ];
+        let dc = DayCountConv::Act365;
+        let spline = DiscountCurve::with_interpolator(today, pillars, dc, curr, CubicSpline);
+        let json = serde_json::to_string(&spline).unwrap();
+        assert!(json.contains(r#""interpolation":"cubic-spline""#));
+
+        let date = NaiveDate::from_ymd(2023, 6, 1);
+        let curve: DiscountCurve<CubicSpline> = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            curve.discount_factor(today, date),
+            spline.discount_factor(today, date)
+        );
+        let err = serde_json::from_str::<DiscountCurve>(&json)
+            .unwrap_err()
+            .to_string();
+        assert!(err.starts_with(
+            "curve with cubic-spline interpolation can't be read as linear interpolated curve"
+        ));
+
+        // curves without interpolation name are read with the requested interpolator
+        let json = json.replace(r#","interpolation":"cubic-spline""#, "");
+        let curve: DiscountCurve<CubicSpline> = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            curve.discount_factor(today, date),
+            spline.discount_factor(today, date)
+        );
+        let linear: DiscountCurve = serde_json::from_str(&json).unwrap();
+        assert!(
+            (linear.discount_factor(today, date) - spline.discount_factor(today, date)).abs()
+                > 1e-6
+        );
+    }
+
+    #[test]
+    fn zero_rates_from_curve() {
+        let curr = Currency::from_str("EUR").unwrap();
+        let today = NaiveDate::from_ymd(2020, 1, 1);
+        let date = NaiveDate::from_ymd(2023, 7, 1);
+        let pillars = vec![
+            (NaiveDate::from_ymd(2021, 1, 1), 0.01),
+            (NaiveDate::from_ymd(2025, 1, 1), 0.03),
+        ];
+        let curve = DiscountCurve::new(today, pillars, DayCountConv::Act365, curr);
+        let df = curve.discount_factor(today, date);
+        let tol = 1e-13;
+        // round trip via flat rates with the same compounding and day count convention
+        for dc in &[
+            DayCountConv::Act365,
+            DayCountConv::Act360,
+            DayCountConv::D30_360,
+        ] {
+            for compounding in &[
+                Compounding::Simple,
+                Compounding::Annual,
+                Compounding::SemiAnnual,
+                Compounding::Quarterly,
+                Compounding::Monthly,
+                Compounding::Continuous,
+            ] {
+                let rate = curve.zero_rate(date, *dc, *compounding);
+                let flat = FlatRate::new(rate, *dc, *compounding, curr);
+                assert_fuzzy_eq!(flat.discount_factor(today, date), df, tol);
+            }
+        }
+        // known continuously compounded rate, linearly interpolated between pillars
+        let t = 1277. / 365.;
+        let rate = 0.01 + 0.02 * (t - 366. / 365.) / (1827. / 365. - 366. / 365.);
+        assert_fuzzy_eq!(
+            curve.zero_rate(date, DayCountConv::Act365, Compounding::Continuous),
+            rate,
+            tol
+        );
+        assert_fuzzy_eq!(
+            curve.zero_rate(date, DayCountConv::Act365, Compounding::Annual),
+            rate.exp() - 1.,
+            tol
+        );
+        assert_fuzzy_eq!(
+            curve.zero_rate(today, DayCountConv::Act365, Compounding::Simple),
+            0.01,
+            tol
+        );
+    }
+
+    #[test]
+    fn pv_change_of_parallel_shift() {
+        let curr = Currency::from_str("EUR").unwrap();
+        let today = NaiveDate::from_ymd(2020, 1, 1);
+        let curve = DiscountCurve::new(
+            today,
+            vec![
+                (NaiveDate::from_ymd(2021, 1, 1), 0.01),
+                (NaiveDate::from_ymd(2025, 1, 1), 0.02),
+            ],
+            DayCountConv::Act365,
+            curr,
+        );
+        let mut flows: Vec<CashFlow> = (2020..=2025)
+            .map(|year| CashFlow::new(5., curr, NaiveDate::from_ymd(year, 1, 1)))
+            .collect();
+        flows.push(CashFlow::new(100., curr, NaiveDate::from_ymd(2025, 1, 1)));
+
+        // analytic PV01 of continuously compounded zero rates, ignoring the flow at today
+        let pv01: f64 = flows[1..]
+            .iter()
+            .map(|cf| {
+                let t = DayCountConv::Act365
+                    .year_fraction(today, cf.date, None, None)
+                    .unwrap();
+                t * cf.amount.value() * curve.discount_factor(today, cf.date) * 1e-4
+            })
+            .sum();
+        let up = curve_pv_shift(&flows, &curve, 1.).unwrap();
+        let down = curve_pv_shift(&flows, &curve, -1.).unwrap();
+        assert!(up < 0.);
+        // close up to the convexity, which cancels out in the central difference
+        assert_fuzzy_eq!(up, -pv01, 5e-5);
+        assert_fuzzy_eq!(down, pv01, 5e-5);
+        assert_fuzzy_eq!((up - down) / 2., -pv01, 1e-8);
+        assert_eq!(curve_pv_shift(&flows, &curve, 0.).unwrap(), 0.);
+
+        let usd = Currency::from_str("USD").unwrap();
+        flows.push(CashFlow::new(1., usd, NaiveDate::from_ymd(2022, 1, 1)));
+        assert!(curve_pv_shift(&flows, &curve, 1.).is_err());
+    }
 }
@@ -0,0 +1,285 @@
+//! Proposals of trades to rebalance a portfolio towards target weights, e.g. 60% equities
+//! and 40% bonds. Trades are only proposed for targets which drifted out of a tolerance band.
+
+use crate::asset::{Asset, AssetOrTag};
+use crate::currency::Currency;
+use crate::fixed_income::CashFlow;
+use crate::transaction::{Transaction, TransactionType};
+use chrono::NaiveDate;
+
+/// Current holding of an asset
+#[derive(Debug, Clone)]
+pub struct Holding {
+    pub asset: Asset,
+    /// Number of units held, which may be zero for assets to be bought
+    pub units: f64,
+    /// Latest price of one unit in the currency of the portfolio
+    pub price: f64,
+}
+
+impl Holding {
+    pub fn value(&self) -> f64 {
+        self.units * self.price
+    }
+}
+
+/// Holdings and cash of a portfolio
+#[derive(Debug, Clone)]
+pub struct PortfolioValue {
+    pub holdings: Vec<Holding>,
+    /// Cash available for buying assets
+    pub cash: f64,
+    pub currency: Currency,
+}
+
+impl PortfolioValue {
+    /// Total value of all holdings and cash
+    pub fn total(&self) -> f64 {
+        self.holdings.iter().map(Holding::value).sum::<f64>() + self.cash
+    }
+}
+
+/// Configuration of rebalancing proposals
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RebalanceConfig {
+    /// Maximal absolute difference between current and target weight without rebalancing,
+    /// e.g. 0.05 for 5 percentage points
+    pub band: f64,
+    /// Trades of a smaller value are dropped
+    pub min_trade: f64,
+    /// If true, no assets are sold, i.e. only the available cash is invested
+    pub no_sells: bool,
+    /// If true, trades are rounded down to whole units
+    pub whole_units: bool,
+}
+
+impl Default for RebalanceConfig {
+    fn default() -> RebalanceConfig {
+        RebalanceConfig {
+            band: 0.05,
+            min_trade: 0.,
+            no_sells: false,
+            whole_units: false,
+        }
+    }
+}
+
+/// Proposed trade of an asset
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProposedTrade {
+    pub asset_id: u64,
+    /// Number of units, positive for buys and negative for sells
+    pub units: f64,
+    /// Value of the trade, positive for buys and negative for sells
+    pub amount: f64,
+}
+
+impl ProposedTrade {
+    /// Transaction of the trade executed at `date` at the proposed price, to be adjusted
+    /// to the actual execution before insertion
+    pub fn to_transaction(&self, currency: Currency, date: NaiveDate) -> Transaction {
+        Transaction {
+            note: Some("rebalancing".to_string()),
+            ..Transaction::new(
+                TransactionType::Asset {
+                    asset_id: self.asset_id,
+                    position: self.units,
+                },
+                CashFlow::new(-self.amount, currency, date),
+            )
+        }
+    }
+}
+
+/// Trade of `amount` in `holding`, optionally rounded down to whole units
+fn trade(holding: &Holding, amount: f64, whole_units: bool) -> Option<ProposedTrade> {
+    let asset_id = holding.asset.id?;
+    if holding.price <= 0. {
+        return None;
+    }
+    let mut units = amount / holding.price;
+    if whole_units {
+        units = units.trunc();
+    }
+    if units == 0. {
+        return None;
+    }
+    Some(ProposedTrade {
+        asset_id,
+        units,
+        amount: units * holding.price,
+    })
+}
+
+/// Propose trades to move the weights of `targets` back to their target weights, which are
+/// fractions of the total value of the portfolio including cash. Each holding belongs to the
+/// first target selecting it, holdings without target are not traded. Targets whose weight
+/// is within `config.band` of the target weight are left as they are. The trade of a target
+/// is split among its holdings in proportion to their current values, or equally if none is
+/// held. Buys are scaled down if they exceed the available cash plus the proceeds of sells.
+/// Sells are proposed first, followed by buys, both ordered by asset id.
+pub fn propose(
+    current: &PortfolioValue,
+    targets: &[(AssetOrTag, f64)],
+    config: RebalanceConfig,
+) -> Vec<ProposedTrade> {
+    let total = current.total();
+    if total <= 0. {
+        return Vec::new();
+    }
+    let mut groups: Vec<Vec<&Holding>> = vec![Vec::new(); targets.len()];
+    for holding in &current.holdings {
+        if let Some(index) = targets.iter().position(|(t, _)| t.matches(&holding.asset)) {
+            groups[index].push(holding);
+        }
+    }
+
+    // amounts to trade per holding before rounding and cash limits
+    let mut amounts: Vec<(&Holding, f64)> = Vec::new();
+    for ((_, weight), holdings) in targets.iter().zip(&groups) {
+        let value: f64 = holdings.iter().map(|h| h.value()).sum();
+        if holdings.is_empty() || (value / total - weight).abs() <= config.band {
+            continue;
+        }
+        let difference = weight * total - value;
+        for holding in holdings {
+            let share = if value > 0. {
+                holding.value() / value
+            } else {
+                1. / holdings.len() as f64
+            };
+            amounts.push((holding, share * difference));
+        }
+    }
+    amounts.sort_by_key(|(holding, _)| holding.asset.id);
+
+    let mut trades = Vec::new();
+    let mut cash = current.cash;
+    if !config.no_sells {
+        for (holding, amount) in amounts.iter().filter(|(_, a)| *a < 0.) {
+            if let Some(sell) = trade(holding, *amount, config.whole_units) {
+                if -sell.amount >= config.min_trade {
+                    cash -= sell.amount;
+                    trades.push(sell);
+                }
+            }
+        }
+    }
+    let buys: f64 = amounts.iter().map(|(_, a)| a.max(0.)).sum();
+    let scaling = if buys > cash { cash.max(0.) / buys } else { 1. };
+    for (holding, amount) in amounts.iter().filter(|(_, a)| *a > 0.) {
+        if let Some(buy) = trade(holding, amount * scaling, config.whole_units) {
+            if buy.amount >= config.min_trade {
+                trades.push(buy);
+            }
+        }
+    }
+    trades
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn holding(id: u64, tag: &str, units: f64, price: f64) -> Holding {
+        let mut asset = Asset::new(Some(id), &format!("asset {}", id), None, None, None);
+        asset.tags.push(tag.to_string());
+        Holding {
+            asset,
+            units,
+            price,
+        }
+    }
+
+    /// Portfolio with 70% in the stock and 30% in the bond fund
+    fn drifted(cash: f64) -> PortfolioValue {
+        PortfolioValue {
+            holdings: vec![
+                holding(1, "equity", 100., 7.),
+                holding(2, "bonds", 30., 10.),
+            ],
+            cash,
+            currency: Currency::from_str("EUR").unwrap(),
+        }
+    }
+
+    #[test]
+    fn rebalance_drifted_portfolio() {
+        let targets = vec![(AssetOrTag::Asset(1), 0.6), (AssetOrTag::Asset(2), 0.4)];
+        let config = RebalanceConfig::default();
+        let trades = propose(&drifted(0.), &targets, config);
+        assert_eq!(trades.len(), 2);
+        assert_eq!(trades[0].asset_id, 1);
+        assert_fuzzy_eq!(trades[0].amount, -100., 1e-10);
+        assert_fuzzy_eq!(trades[0].units, -100. / 7., 1e-10);
+        assert_eq!(trades[1].asset_id, 2);
+        assert_fuzzy_eq!(trades[1].amount, 100., 1e-10);
+        assert_fuzzy_eq!(trades[1].units, 10., 1e-10);
+
+        // buy with the proceeds of whole units sold only
+        let whole = RebalanceConfig {
+            whole_units: true,
+            ..config
+        };
+        let trades = propose(&drifted(0.), &targets, whole);
+        assert_eq!(trades[0].units, -14.);
+        assert_eq!(trades[0].amount, -98.);
+        assert_eq!(trades[1].units, 9.);
+        assert_eq!(trades[1].amount, 90.);
+
+        // within the band
+        let trades = propose(
+            &drifted(0.),
+            &targets,
+            RebalanceConfig {
+                band: 0.1,
+                ..config
+            },
+        );
+        assert!(trades.is_empty());
+
+        // new cash only: the bond fund is underweight by 12.7 percentage points, but the
+        // purchase of 140 is limited to the cash of 100
+        let no_sells = RebalanceConfig {
+            no_sells: true,
+            ..config
+        };
+        let trades = propose(&drifted(100.), &targets, no_sells);
+        assert_eq!(trades.len(), 1);
+        assert_eq!(trades[0].asset_id, 2);
+        assert_fuzzy_eq!(trades[0].amount, 100., 1e-10);
+        let trades = propose(
+            &drifted(100.),
+            &targets,
+            RebalanceConfig {
+                min_trade: 150.,
+                ..no_sells
+            },
+        );
+        assert!(trades.is_empty());
+
+        // targets by tag give the same trades
+        let tags = vec![
+            (AssetOrTag::Tag("equity".to_string()), 0.6),
+            (AssetOrTag::Tag("bonds".to_string()), 0.4),
+        ];
+        assert_eq!(
+            propose(&drifted(0.), &tags, config),
+            propose(&drifted(0.), &targets, config)
+        );
+
+        let sell = ProposedTrade {
+            asset_id: 1,
+            units: -14.,
+            amount: -98.,
+        };
+        let date = NaiveDate::from_ymd(2021, 3, 1);
+        let transaction = sell.to_transaction(Currency::from_str("EUR").unwrap(), date);
+        assert_eq!(transaction.cash_flow.amount.value(), 98.);
+        assert!(matches!(
+            transaction.transaction_type,
+            TransactionType::Asset { asset_id: 1, position } if position == -14.
+        ));
+    }
+}
@@ -0,0 +1,570 @@
+//! Risk measures of portfolios estimated from historical market data, e.g. the
+//! value at risk by historical simulation or the covariances of asset returns.
+
+use crate::data_handler::{DataError, QuoteHandler};
+use crate::math::align_series;
+use crate::portfolio::Position;
+use crate::time_period::TimePeriod;
+use chrono::NaiveDate;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+/// Error related to the calculation of risk measures
+#[derive(Debug)]
+pub enum RiskError {
+    /// The confidence level is not strictly between zero and one
+    InvalidConfidence(f64),
+    /// The quote history of the asset does not cover the lookback period
+    MissingHistory {
+        asset_id: u64,
+    },
+    /// Fewer daily returns at dates common to all assets than required
+    InsufficientHistory {
+        required: usize,
+        available: usize,
+    },
+    /// The quote of the asset at the given date is not a positive finite number
+    InvalidQuote {
+        asset_id: u64,
+        date: NaiveDate,
+    },
+    MarketDataError(DataError),
+}
+
+impl fmt::Display for RiskError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RiskError::InvalidConfidence(confidence) => {
+                write!(f, "invalid confidence level {}", confidence)
+            }
+            RiskError::MissingHistory { asset_id } => {
+                write!(f, "missing quote history for asset {}", asset_id)
+            }
+            RiskError::InsufficientHistory {
+                required,
+                available,
+            } => write!(
+                f,
+                "{} daily returns required, but only {} available",
+                required, available
+            ),
+            RiskError::InvalidQuote { asset_id, date } => {
+                write!(f, "invalid quote of asset {} at {}", asset_id, date)
+            }
+            RiskError::MarketDataError(_) => write!(f, "fetching market data failed"),
+        }
+    }
+}
+
+impl Error for RiskError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RiskError::MarketDataError(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<DataError> for RiskError {
+    fn from(error: DataError) -> Self {
+        RiskError::MarketDataError(error)
+    }
+}
+
+/// Treatment of assets whose quote history does not cover the lookback period
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MissingHistory {
+    /// Ignore the asset, it is reported in `VarResult::dropped_assets`
+    Drop,
+    /// Fail with `RiskError::MissingHistory`
+    Fail,
+}
+
+/// Value at risk and expected shortfall of a portfolio, both given as losses,
+/// i.e. positive numbers for a loss of value
+#[derive(Debug, Clone, PartialEq)]
+pub struct VarResult {
+    pub confidence: f64,
+    pub horizon_days: u32,
+    /// Loss not exceeded with probability `confidence` within the horizon
+    pub var: f64,
+    /// Average loss within the horizon in the cases the value at risk is exceeded
+    pub expected_shortfall: f64,
+    /// Current value of all positions included
+    pub portfolio_value: f64,
+    /// Number of historical daily scenarios
+    pub scenarios: usize,
+    /// Assets ignored due to missing history, ordered by id
+    pub dropped_assets: Vec<u64>,
+}
+
+/// Value at risk by historical simulation. The current value of each position is its
+/// number of units times the quote at the last date common to all quote histories. The
+/// simple daily returns of the last `lookback_days` days common to all histories are
+/// applied to the current values, giving one daily profit or loss of the portfolio per
+/// day. The value at risk is the loss of the `ceil((1 - confidence) * n)`-th worst of
+/// these `n` scenarios, the expected shortfall the average loss of the worst scenarios up
+/// to this one. Both are scaled to the horizon by the square root of `horizon_days`.
+/// The quote histories are dated series of quotes per asset id, ordered by date. Quotes at the
+/// dates used must be positive and finite.
+pub fn historical_var(
+    positions: &HashMap<u64, Position>,
+    quote_histories: &HashMap<u64, Vec<(NaiveDate, f64)>>,
+    confidence: f64,
+    horizon_days: u32,
+    lookback_days: u32,
+    missing: MissingHistory,
+) -> Result<VarResult, RiskError> {
+    if !(confidence > 0. && confidence < 1.) {
+        return Err(RiskError::InvalidConfidence(confidence));
+    }
+    let required = lookback_days as usize;
+    let mut asset_ids: Vec<u64> = positions.keys().cloned().collect();
+    asset_ids.sort_unstable();
+
+    let mut histories = Vec::new();
+    let mut dropped_assets = Vec::new();
+    for asset_id in asset_ids {
+        match quote_histories.get(&asset_id) {
+            Some(history) if history.len() > required => {
+                let quotes: HashMap<NaiveDate, f64> = history.iter().copied().collect();
+                histories.push((asset_id, positions[&asset_id].position, quotes));
+            }
+            _ => match missing {
+                MissingHistory::Drop => dropped_assets.push(asset_id),
+                MissingHistory::Fail => return Err(RiskError::MissingHistory { asset_id }),
+            },
+        }
+    }
+
+    // dates at which quotes of all included assets are given
+    let mut dates: Vec<NaiveDate> = match histories.first() {
+        Some((_, _, quotes)) => quotes
+            .keys()
+            .filter(|date| histories.iter().all(|(_, _, q)| q.contains_key(date)))
+            .cloned()
+            .collect(),
+        None => Vec::new(),
+    };
+    dates.sort_unstable();
+    let available = dates.len().saturating_sub(1);
+    if required == 0 || available < required {
+        return Err(RiskError::InsufficientHistory {
+            required,
+            available,
+        });
+    }
+    let dates = &dates[dates.len() - required - 1..];
+    for (asset_id, _, quotes) in &histories {
+        let is_valid = |price: f64| price > 0. && price.is_finite();
+        if let Some(date) = dates.iter().find(|date| !is_valid(quotes[date])) {
+            return Err(RiskError::InvalidQuote {
+                asset_id: *asset_id,
+                date: *date,
+            });
+        }
+    }
+
+    let values: Vec<f64> = histories
+        .iter()
+        .map(|(_, units, quotes)| units * quotes[&dates[required]])
+        .collect();
+    let mut pnl: Vec<f64> = dates
+        .windows(2)
+        .map(|days| {
+            histories
+                .iter()
+                .zip(&values)
+                .map(|((_, _, quotes), value)| value * (quotes[&days[1]] / quotes[&days[0]] - 1.))
+                .sum()
+        })
+        .collect();
+    pnl.sort_by(f64::total_cmp);
+
+    // tolerance avoids an additional scenario if the product is not exact in floating point
+    let tail = (((1. - confidence) * required as f64 - 1e-9).ceil() as usize).max(1);
+    let scaling = (horizon_days as f64).sqrt();
+    Ok(VarResult {
+        confidence,
+        horizon_days,
+        var: -pnl[tail - 1] * scaling,
+        expected_shortfall: -pnl[..tail].iter().sum::<f64>() / tail as f64 * scaling,
+        portfolio_value: values.iter().sum(),
+        scenarios: required,
+        dropped_assets,
+    })
+}
+
+/// Covariances and correlations of the returns of assets
+#[derive(Debug, Clone, PartialEq)]
+pub struct CovarianceMatrix {
+    /// Ids of the assets in the order of the rows and columns of the matrices
+    pub asset_ids: Vec<u64>,
+    pub covariance: Vec<Vec<f64>>,
+    pub correlation: Vec<Vec<f64>>,
+    /// Assets with fewer returns than the minimum overlap, which are left out of the matrices
+    pub insufficient_data: Vec<u64>,
+    /// Pairs of assets with fewer returns at common dates than the minimum overlap,
+    /// their entries in the matrices are NaN
+    pub insufficient_overlap: Vec<(u64, u64)>,
+}
+
+/// Returns of an asset between sample dates, which are `start` and every `frequency`
+/// thereafter until `end` or the last quote, whatever comes first. The quote at a sample
+/// date is the last quote at or before it, but not before `start`. Sample dates without a
+/// new quote since the previous sample date are skipped, e.g. weekends in case of daily
+/// sampling, instead of producing zero returns; the next return spans the skipped dates.
+/// Returns are dated by the end of their period.
+fn sampled_returns(
+    quotes: &mut dyn QuoteHandler,
+    asset_id: u64,
+    start: NaiveDate,
+    end: NaiveDate,
+    frequency: TimePeriod,
+) -> Result<Vec<(NaiveDate, f64)>, DataError> {
+    let history: Vec<(NaiveDate, f64)> = quotes
+        .get_all_quotes_for_asset(asset_id)?
+        .iter()
+        .map(|quote| (quote.time.naive_utc().date(), quote.price))
+        .filter(|(date, _)| *date >= start && *date <= end)
+        .collect();
+    let end = match history.last() {
+        Some((last_date, _)) => end.min(*last_date),
+        None => return Ok(Vec::new()),
+    };
+    let mut returns = Vec::new();
+    let mut history = history.iter().peekable();
+    let mut previous = None;
+    let mut date = start;
+    while date <= end {
+        let mut quote = None;
+        while let Some((_, price)) = history.next_if(|(day, _)| *day <= date) {
+            quote = Some(*price);
+        }
+        if let Some(price) = quote {
+            if let Some(previous) = previous {
+                returns.push((date, price / previous - 1.));
+            }
+            previous = Some(price);
+        }
+        let next = frequency.add_to(date, None);
+        if next <= date {
+            break;
+        }
+        date = next;
+    }
+    Ok(returns)
+}
+
+/// Sample covariance of two series of equal length, i.e. normalized by the length minus one
+fn sample_covariance(x: &[f64], y: &[f64]) -> f64 {
+    let n = x.len() as f64;
+    let mean_x = x.iter().sum::<f64>() / n;
+    let mean_y = y.iter().sum::<f64>() / n;
+    x.iter()
+        .zip(y)
+        .map(|(a, b)| (a - mean_x) * (b - mean_y))
+        .sum::<f64>()
+        / (n - 1.)
+}
+
+/// Covariance and correlation matrices of the simple returns of the given assets between
+/// `start` and `end`, sampled every `frequency` (see `sampled_returns`). The quotes of all
+/// tickers of an asset are used, which should therefore be in the same currency. Each
+/// entry is calculated from the returns at the dates common to both assets, which requires
+/// at least `min_overlap` (and at least two) returns. Assets or pairs of assets without
+/// sufficient data are reported in the result.
+pub fn covariance_matrix(
+    asset_ids: &[u64],
+    quotes: &mut dyn QuoteHandler,
+    start: NaiveDate,
+    end: NaiveDate,
+    frequency: TimePeriod,
+    min_overlap: usize,
+) -> Result<CovarianceMatrix, RiskError> {
+    let min_overlap = min_overlap.max(2);
+    let mut matrix = CovarianceMatrix {
+        asset_ids: Vec::new(),
+        covariance: Vec::new(),
+        correlation: Vec::new(),
+        insufficient_data: Vec::new(),
+        insufficient_overlap: Vec::new(),
+    };
+    let mut returns = Vec::new();
+    for &asset_id in asset_ids {
+        let asset_returns = sampled_returns(quotes, asset_id, start, end, frequency)?;
+        if asset_returns.len() < min_overlap {
+            matrix.insufficient_data.push(asset_id);
+        } else {
+            matrix.asset_ids.push(asset_id);
+            returns.push(asset_returns);
+        }
+    }
+
+    let n = returns.len();
+    matrix.covariance = vec![vec![f64::NAN; n]; n];
+    matrix.correlation = vec![vec![f64::NAN; n]; n];
+    for i in 0..n {
+        for j in i..n {
+            let (x, y) = align_series(&returns[i], &returns[j]);
+            if x.len() < min_overlap {
+                let pair = (matrix.asset_ids[i], matrix.asset_ids[j]);
+                matrix.insufficient_overlap.push(pair);
+                continue;
+            }
+            let covariance = sample_covariance(&x, &y);
+            let correlation =
+                covariance / (sample_covariance(&x, &x) * sample_covariance(&y, &y)).sqrt();
+            matrix.covariance[i][j] = covariance;
+            matrix.covariance[j][i] = covariance;
+            matrix.correlation[i][j] = correlation;
+            matrix.correlation[j][i] = correlation;
+        }
+    }
+    Ok(matrix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::Asset;
+    use crate::currency::Currency;
+    use crate::data_handler::DataHandler;
+    use crate::helpers::make_time;
+    use crate::memory_handler::InMemoryDB;
+    use crate::quote::{MarketDataSource, Quote, Ticker, UpdatePeriod};
+    use std::str::FromStr;
+
+    /// Quotes ending at 100 on the last of `returns.len() + 1` consecutive days,
+    /// such that the daily returns are the given ones
+    fn history(returns: &[f64]) -> Vec<(NaiveDate, f64)> {
+        let start = NaiveDate::from_ymd(2020, 1, 1);
+        let mut quotes = vec![100.];
+        for r in returns.iter().rev() {
+            quotes.push(quotes.last().unwrap() / (1. + r));
+        }
+        quotes
+            .into_iter()
+            .rev()
+            .enumerate()
+            .map(|(i, quote)| (start + chrono::Duration::days(i as i64), quote))
+            .collect()
+    }
+
+    #[test]
+    fn two_asset_historical_var() {
+        // returns -5%, -4.9%, ..., 4.9% in shuffled order, the second asset moves half as much
+        let returns: Vec<f64> = (0..100)
+            .map(|t| ((t * 37) % 100) as f64 / 1000. - 0.05)
+            .collect();
+        let half: Vec<f64> = returns.iter().map(|r| r / 2.).collect();
+        let mut positions = HashMap::new();
+        positions.insert(1, Position::new(1, 10.));
+        positions.insert(2, Position::new(2, 10.));
+        let mut histories = HashMap::new();
+        histories.insert(1, history(&returns));
+        histories.insert(2, history(&half));
+
+        // daily profits and losses are 1500 times the returns, the worst being
+        // 75, 73.5, 72, 70.5 and 69
+        let var =
+            historical_var(&positions, &histories, 0.95, 1, 100, MissingHistory::Fail).unwrap();
+        assert_eq!(var.scenarios, 100);
+        assert_fuzzy_eq!(var.portfolio_value, 2000., 1e-10);
+        assert_fuzzy_eq!(var.var, 69., 1e-9);
+        assert_fuzzy_eq!(var.expected_shortfall, 72., 1e-9);
+
+        let var =
+            historical_var(&positions, &histories, 0.99, 10, 100, MissingHistory::Fail).unwrap();
+        assert_fuzzy_eq!(var.var, 75. * 10_f64.sqrt(), 1e-9);
+        assert_fuzzy_eq!(var.expected_shortfall, 75. * 10_f64.sqrt(), 1e-9);
+
+        // only the last 50 returns, i.e. returns (t * 37) % 100 for t = 50..99
+        let var = historical_var(&positions, &histories, 0.9, 1, 50, MissingHistory::Fail).unwrap();
+        let mut expected: Vec<f64> = returns[50..].iter().map(|r| -1500. * r).collect();
+        expected.sort_by(|a, b| b.partial_cmp(a).unwrap());
+        assert_fuzzy_eq!(var.var, expected[4], 1e-9);
+
+        // offsetting positions have no risk
+        positions.insert(2, Position::new(2, -10.));
+        histories.insert(2, history(&returns));
+        let var =
+            historical_var(&positions, &histories, 0.95, 1, 100, MissingHistory::Fail).unwrap();
+        assert_fuzzy_eq!(var.var, 0., 1e-9);
+    }
+
+    #[test]
+    fn missing_history() {
+        let returns = vec![-0.02, 0.01, -0.01, 0.03];
+        let mut positions = HashMap::new();
+        positions.insert(1, Position::new(1, 1.));
+        positions.insert(2, Position::new(2, 1.));
+        let mut histories = HashMap::new();
+        histories.insert(1, history(&returns));
+        histories.insert(2, history(&returns[..2]));
+
+        assert!(matches!(
+            historical_var(&positions, &histories, 0.75, 1, 4, MissingHistory::Fail),
+            Err(RiskError::MissingHistory { asset_id: 2 })
+        ));
+        let var = historical_var(&positions, &histories, 0.75, 1, 4, MissingHistory::Drop).unwrap();
+        assert_eq!(var.dropped_assets, vec![2]);
+        assert_fuzzy_eq!(var.var, 2., 1e-10);
+        assert!(matches!(
+            historical_var(&positions, &histories, 0.75, 1, 5, MissingHistory::Drop),
+            Err(RiskError::InsufficientHistory {
+                required: 5,
+                available: 0
+            })
+        ));
+        assert!(matches!(
+            historical_var(&positions, &histories, 1., 1, 4, MissingHistory::Drop),
+            Err(RiskError::InvalidConfidence(c)) if c == 1.
+        ));
+
+        // NaN or non-positive quotes are rejected
+        for price in &[f64::NAN, 0., f64::INFINITY] {
+            let mut quotes = history(&returns);
+            quotes[2].1 = *price;
+            histories.insert(2, quotes.clone());
+            assert!(matches!(
+                historical_var(&positions, &histories, 0.75, 1, 4, MissingHistory::Fail),
+                Err(RiskError::InvalidQuote { asset_id: 2, date }) if date == quotes[2].0
+            ));
+        }
+    }
+
+    /// Quotes on consecutive days from Monday, June 1st 2020
+    fn insert_history(db: &mut InMemoryDB, name: &str, prices: &[f64]) -> u64 {
+        let quotes: Vec<(u32, f64)> = (1..).zip(prices.iter().copied()).collect();
+        insert_quotes(db, name, &quotes)
+    }
+
+    /// Quotes at the given days of June 2020
+    fn insert_quotes(db: &mut InMemoryDB, name: &str, quotes: &[(u32, f64)]) -> u64 {
+        let asset_id = db
+            .insert_asset(&Asset::new(None, name, None, None, None))
+            .unwrap();
+        let source = db
+            .insert_md_source(&MarketDataSource {
+                id: None,
+                name: "manual".to_string(),
+            })
+            .unwrap();
+        let ticker = db
+            .insert_ticker(&Ticker {
+                id: None,
+                name: name.to_string(),
+                asset: asset_id,
+                currency: Currency::from_str("EUR").unwrap(),
+                source,
+                update_policy: UpdatePeriod::Daily,
+            })
+            .unwrap();
+        for (day, price) in quotes {
+            db.insert_quote(&Quote {
+                id: None,
+                ticker,
+                price: *price,
+                time: make_time(2020, 6, *day, 18, 0, 0).unwrap(),
+                volume: None,
+            })
+            .unwrap();
+        }
+        asset_id
+    }
+
+    #[test]
+    fn covariance_of_returns() {
+        let mut db = InMemoryDB::new();
+        let returns = [0.01, -0.02, 0.03, 0.01, -0.01, 0.02, -0.03];
+        let mut up = vec![100.];
+        let mut down = vec![50.];
+        for r in &returns {
+            up.push(up.last().unwrap() * (1. + r));
+            down.push(down.last().unwrap() * (1. - r));
+        }
+        let first = insert_history(&mut db, "UP", &up);
+        let second = insert_history(&mut db, "DOWN", &down);
+        let short = insert_history(&mut db, "SHORT", &up[..2]);
+
+        let matrix = covariance_matrix(
+            &[first, second, short],
+            &mut db,
+            NaiveDate::from_ymd(2020, 6, 1),
+            NaiveDate::from_ymd(2020, 6, 30),
+            TimePeriod::from_str("1D").unwrap(),
+            5,
+        )
+        .unwrap();
+        assert_eq!(matrix.asset_ids, vec![first, second]);
+        assert_eq!(matrix.insufficient_data, vec![short]);
+        assert!(matrix.insufficient_overlap.is_empty());
+        assert_fuzzy_eq!(matrix.correlation[0][0], 1., 1e-12);
+        assert_fuzzy_eq!(matrix.correlation[1][1], 1., 1e-12);
+        assert_fuzzy_eq!(matrix.correlation[0][1], -1., 1e-12);
+        assert_fuzzy_eq!(matrix.correlation[1][0], -1., 1e-12);
+        let mean = returns.iter().sum::<f64>() / 7.;
+        let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / 6.;
+        assert_fuzzy_eq!(matrix.covariance[0][0], variance, 1e-12);
+        assert_fuzzy_eq!(matrix.covariance[0][1], -variance, 1e-12);
+
+        // weekly returns of the first week and a half only
+        let matrix = covariance_matrix(
+            &[first, second],
+            &mut db,
+            NaiveDate::from_ymd(2020, 6, 1),
+            NaiveDate::from_ymd(2020, 6, 10),
+            TimePeriod::from_str("1W").unwrap(),
+            2,
+        )
+        .unwrap();
+        assert!(matrix.asset_ids.is_empty());
+        assert_eq!(matrix.insufficient_data, vec![first, second]);
+    }
+
+    #[test]
+    fn returns_at_quote_dates_only() {
+        let mut db = InMemoryDB::new();
+        // quotes on business days only, June 6th and 7th is a weekend
+        let weekdays = insert_quotes(
+            &mut db,
+            "WEEKDAYS",
+            &[(4, 100.), (5, 101.), (8, 103.02), (9, 102.)],
+        );
+        let daily = TimePeriod::from_str("1D").unwrap();
+        let start = NaiveDate::from_ymd(2020, 6, 1);
+        let end = NaiveDate::from_ymd(2020, 6, 30);
+        let returns = sampled_returns(&mut db, weekdays, start, end, daily).unwrap();
+        let dates: Vec<NaiveDate> = returns.iter().map(|(date, _)| *date).collect();
+        assert_eq!(
+            dates,
+            vec![
+                NaiveDate::from_ymd(2020, 6, 5),
+                NaiveDate::from_ymd(2020, 6, 8),
+                NaiveDate::from_ymd(2020, 6, 9)
+            ]
+        );
+        assert_fuzzy_eq!(returns[1].1, 0.02, 1e-12);
+
+        // assets quoted in different periods don't overlap
+        let early = insert_quotes(
+            &mut db,
+            "EARLY",
+            &[(1, 10.), (2, 11.), (3, 10.5), (4, 10.8), (5, 11.)],
+        );
+        let late = insert_quotes(
+            &mut db,
+            "LATE",
+            &[(22, 20.), (23, 21.), (24, 20.5), (25, 20.8), (26, 21.)],
+        );
+        let matrix = covariance_matrix(&[early, late], &mut db, start, end, daily, 3).unwrap();
+        assert_eq!(matrix.asset_ids, vec![early, late]);
+        assert!(matrix.insufficient_data.is_empty());
+        assert_eq!(matrix.insufficient_overlap, vec![(early, late)]);
+        assert!(matrix.covariance[0][0] > 0.);
+        assert!(matrix.covariance[0][1].is_nan());
+        assert!(matrix.correlation[1][0].is_nan());
+    }
+}
@@ -0,0 +1,362 @@
+//! Revaluation of a portfolio under shocked market data, e.g. "equities -20% and rates
+//! +100bp", to report the profit and loss of each position relative to the base case.
+
+use crate::asset::{Asset, AssetOrTag, Instrument};
+use crate::bond::{Bond, BondError};
+use crate::currency::Currency;
+use crate::data_handler::{DataError, QuoteHandler};
+use crate::fixed_income::FixedIncome;
+use crate::fx_rates::get_fx_rate;
+use crate::helpers::end_of_day;
+use crate::market::Market;
+use crate::portfolio::{value_bond_position, Position, ValuationMethod};
+use crate::rates::{DiscountCurve, Discounter};
+use chrono::NaiveDate;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+/// Error related to the valuation of scenarios
+#[derive(Debug)]
+pub enum ScenarioError {
+    MarketDataError(DataError),
+    BondError(BondError),
+}
+
+impl fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScenarioError::MarketDataError(_) => write!(f, "fetching market data failed"),
+            ScenarioError::BondError(_) => write!(f, "valuation of bond failed"),
+        }
+    }
+}
+
+impl Error for ScenarioError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ScenarioError::MarketDataError(err) => Some(err),
+            ScenarioError::BondError(err) => Some(err),
+        }
+    }
+}
+
+impl From<DataError> for ScenarioError {
+    fn from(error: DataError) -> Self {
+        ScenarioError::MarketDataError(error)
+    }
+}
+
+impl From<BondError> for ScenarioError {
+    fn from(error: BondError) -> Self {
+        ScenarioError::BondError(error)
+    }
+}
+
+/// Assets addressed by a quote shock
+pub type ShockTarget = AssetOrTag;
+
+/// Shocks of market data, all given as relative changes, e.g. -0.2 for a drop by 20%
+#[derive(Debug, Clone, Default)]
+pub struct Scenario {
+    /// Shocks of the quotes of assets, addressed by asset or by tag
+    pub equity_shocks: HashMap<ShockTarget, f64>,
+    /// Parallel shift of the zero rates of all discount curves in basis points
+    pub rate_shift_bp: f64,
+    /// Shocks of fx rates converting the first currency into the second one.
+    /// The inverse currency pair is shocked inversely.
+    pub fx_shocks: HashMap<(Currency, Currency), f64>,
+}
+
+impl Scenario {
+    /// Relative shock of the quotes of `asset`. A shock of the asset itself takes precedence,
+    /// otherwise the shocks of all tags of the asset are compounded.
+    pub fn quote_shock(&self, asset: &Asset) -> f64 {
+        if let Some(shock) = asset
+            .id
+            .and_then(|id| self.equity_shocks.get(&ShockTarget::Asset(id)))
+        {
+            return *shock;
+        }
+        let factor: f64 = asset
+            .tags
+            .iter()
+            .filter_map(|tag| self.equity_shocks.get(&ShockTarget::Tag(tag.clone())))
+            .map(|shock| 1. + shock)
+            .product();
+        factor - 1.
+    }
+
+    /// Factor applied to fx rates converting `foreign` into `base`
+    pub fn fx_factor(&self, foreign: Currency, base: Currency) -> f64 {
+        let direct = self.fx_shocks.get(&(foreign, base)).map(|s| 1. + s);
+        let inverse = self.fx_shocks.get(&(base, foreign)).map(|s| 1. / (1. + s));
+        direct.or(inverse).unwrap_or(1.)
+    }
+}
+
+/// Value of a position in the base case and in the scenario, in base currency
+#[derive(Debug, Clone, PartialEq)]
+pub struct PositionScenarioValue {
+    pub asset_id: u64,
+    pub base_value: f64,
+    pub scenario_value: f64,
+}
+
+impl PositionScenarioValue {
+    /// Profit or loss of the position in the scenario relative to the base case
+    pub fn pnl(&self) -> f64 {
+        self.scenario_value - self.base_value
+    }
+}
+
+/// Values of all positions of a portfolio in the base case and in a scenario
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ScenarioValuation {
+    /// Values per position, ordered by asset id
+    pub positions: Vec<PositionScenarioValue>,
+    pub base_value: f64,
+    pub scenario_value: f64,
+}
+
+impl ScenarioValuation {
+    /// Total profit or loss of the portfolio in the scenario relative to the base case
+    pub fn pnl(&self) -> f64 {
+        self.scenario_value - self.base_value
+    }
+}
+
+/// Value of a bond position with the curve of the bond's currency, if given, or by quote.
+/// Positions valued by quote are shifted by the change of their curve value in the scenario,
+/// i.e. the spread to the curve is kept constant.
+#[allow(clippy::too_many_arguments)]
+fn bond_values(
+    asset_id: u64,
+    bond: &Bond,
+    units: f64,
+    date: NaiveDate,
+    quotes: &mut dyn QuoteHandler,
+    curve: Option<&DiscountCurve>,
+    shift: f64,
+    market: &Market,
+) -> Result<(f64, f64), BondError> {
+    let base = value_bond_position(
+        asset_id,
+        bond,
+        units,
+        date,
+        quotes,
+        curve.map(|c| c as &dyn Discounter),
+        market,
+    )?;
+    let curve = match curve {
+        Some(curve) => curve,
+        None => return Ok((base.value.value(), base.value.value())),
+    };
+    let cash_flows: Vec<_> = bond
+        .rollout_cash_flows(units, market)?
+        .into_iter()
+        .filter(|cf| cf.date > date)
+        .collect();
+    let shifted = curve
+        .shifted(shift)
+        .discount_cash_flow_stream(&cash_flows, date)?
+        .value();
+    let scenario = match base.method {
+        ValuationMethod::Curve => shifted,
+        ValuationMethod::Quote => {
+            let unshifted = curve.discount_cash_flow_stream(&cash_flows, date)?.value();
+            base.value.value() + shifted - unshifted
+        }
+    };
+    Ok((base.value.value(), scenario))
+}
+
+/// Value all `positions` at the end of `date` in the base case and under `scenario`, in
+/// `base` currency. Bonds are valued by `value_bond_position` with the curve of their
+/// currency from `curves`, which is shifted in parallel in the scenario. All other assets
+/// are valued by their last quote, shocked by the scenario's quote shock of the asset.
+/// Values are converted to `base` with the last fx rates, shocked in the scenario.
+#[allow(clippy::too_many_arguments)]
+pub fn apply_scenario(
+    positions: &HashMap<u64, Position>,
+    scenario: &Scenario,
+    quotes: &mut dyn QuoteHandler,
+    curves: &HashMap<Currency, DiscountCurve>,
+    fx: &mut dyn QuoteHandler,
+    base: Currency,
+    date: NaiveDate,
+    market: &Market,
+) -> Result<ScenarioValuation, ScenarioError> {
+    let time = end_of_day(date);
+    let shift = scenario.rate_shift_bp / 10_000.;
+    let mut valuation = ScenarioValuation::default();
+    let mut asset_ids: Vec<u64> = positions.keys().cloned().collect();
+    asset_ids.sort_unstable();
+    for asset_id in asset_ids {
+        let units = positions[&asset_id].position;
+        let asset = quotes.get_asset_by_id(asset_id)?;
+        let (base_value, scenario_value, currency) = match &asset.instrument {
+            Some(Instrument::Bond(bond)) => {
+                let curve = curves.get(&bond.currency());
+                let (base_value, scenario_value) =
+                    bond_values(asset_id, bond, units, date, quotes, curve, shift, market)?;
+                (base_value, scenario_value, bond.currency())
+            }
+            _ => {
+                let (quote, currency) = quotes.get_last_quote_before_by_id(asset_id, time)?;
+                let value = units * quote.price;
+                let shocked = value * (1. + scenario.quote_shock(&asset));
+                (value, shocked, currency)
+            }
+        };
+        let fx_rate = get_fx_rate(currency, base, time, fx)?;
+        let scenario_fx_rate = fx_rate * scenario.fx_factor(currency, base);
+        let position = PositionScenarioValue {
+            asset_id,
+            base_value: base_value * fx_rate,
+            scenario_value: scenario_value * scenario_fx_rate,
+        };
+        valuation.base_value += position.base_value;
+        valuation.scenario_value += position.scenario_value;
+        valuation.positions.push(position);
+    }
+    Ok(valuation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_handler::DataHandler;
+    use crate::day_count_conv::DayCountConv;
+    use crate::fx_rates::insert_fx_quote;
+    use crate::helpers::make_time;
+    use crate::memory_handler::InMemoryDB;
+    use crate::quote::{MarketDataSource, Quote, Ticker, UpdatePeriod};
+    use std::str::FromStr;
+
+    #[test]
+    fn equity_rates_and_fx_shocks() {
+        let eur = Currency::from_str("EUR").unwrap();
+        let usd = Currency::from_str("USD").unwrap();
+        let market = Market::new();
+        let date = NaiveDate::from_ymd(2021, 6, 1);
+        let time = make_time(2021, 5, 31, 18, 0, 0).unwrap();
+
+        let mut quotes = InMemoryDB::new();
+        let source = quotes
+            .insert_md_source(&MarketDataSource {
+                id: None,
+                name: "manual".to_string(),
+            })
+            .unwrap();
+        let mut stock = Asset::new(None, "Apple", None, None, None);
+        stock.tags = vec!["asset_class=equity".to_string()];
+        let stock = quotes.insert_asset(&stock).unwrap();
+        let ticker = quotes
+            .insert_ticker(&Ticker {
+                id: None,
+                name: "AAPL".to_string(),
+                asset: stock,
+                currency: usd,
+                source,
+                update_policy: UpdatePeriod::Daily,
+            })
+            .unwrap();
+        quotes
+            .insert_quote(&Quote {
+                id: None,
+                ticker,
+                price: 100.,
+                time,
+                volume: None,
+            })
+            .unwrap();
+        let bond = Bond::builder()
+            .issue_date(NaiveDate::from_ymd(2020, 6, 1))
+            .maturity(NaiveDate::from_ymd(2023, 6, 1))
+            .coupon(0.02)
+            .currency(eur)
+            .build(&market)
+            .unwrap();
+        let mut bond_asset = Asset::new(None, "Bund", None, None, None);
+        bond_asset.instrument = Some(Instrument::Bond(Box::new(bond.clone())));
+        let bond_id = quotes.insert_asset(&bond_asset).unwrap();
+
+        let mut fx = InMemoryDB::new();
+        let fx_source = fx
+            .insert_md_source(&MarketDataSource {
+                id: None,
+                name: "fx".to_string(),
+            })
+            .unwrap();
+        insert_fx_quote(0.8, usd, eur, fx_source, time, &mut fx).unwrap();
+
+        let mut curves = HashMap::new();
+        curves.insert(
+            eur,
+            DiscountCurve::flat(date, 0.01, DayCountConv::Act365, eur),
+        );
+        let mut positions = HashMap::new();
+        positions.insert(stock, Position::new(stock, 10.));
+        positions.insert(bond_id, Position::new(bond_id, 2.));
+
+        let mut scenario = Scenario {
+            rate_shift_bp: 100.,
+            ..Default::default()
+        };
+        scenario
+            .equity_shocks
+            .insert(ShockTarget::Tag("asset_class=equity".to_string()), -0.2);
+        scenario.fx_shocks.insert((eur, usd), 0.25);
+        let result = apply_scenario(
+            &positions,
+            &scenario,
+            &mut quotes,
+            &curves,
+            &mut fx,
+            eur,
+            date,
+            &market,
+        )
+        .unwrap();
+
+        // stock: 10 * 100 USD at 0.8 EUR/USD, falling to 80 USD at 0.8 / 1.25 EUR/USD
+        assert_eq!(result.positions[0].asset_id, stock);
+        assert_fuzzy_eq!(result.positions[0].base_value, 800., 1e-10);
+        assert_fuzzy_eq!(result.positions[0].scenario_value, 512., 1e-10);
+        // bond: 2 * 20 EUR coupons in 2022 and 2023 and 2 * 1000 EUR redemption in 2023
+        let value = |rate: f64| {
+            bond.rollout_cash_flows(2., &market)
+                .unwrap()
+                .iter()
+                .filter(|cf| cf.date > date)
+                .map(|cf| {
+                    let t = (cf.date - date).num_days() as f64 / 365.;
+                    cf.amount.value() * (-rate * t).exp()
+                })
+                .sum::<f64>()
+        };
+        assert_eq!(result.positions[1].asset_id, bond_id);
+        assert_fuzzy_eq!(result.positions[1].base_value, value(0.01), 1e-8);
+        assert_fuzzy_eq!(result.positions[1].scenario_value, value(0.02), 1e-8);
+        assert_fuzzy_eq!(result.pnl(), 512. - 800. + value(0.02) - value(0.01), 1e-8);
+
+        // shocks addressed by asset take precedence over tags
+        scenario
+            .equity_shocks
+            .insert(ShockTarget::Asset(stock), 0.1);
+        let result = apply_scenario(
+            &positions,
+            &scenario,
+            &mut quotes,
+            &curves,
+            &mut fx,
+            eur,
+            date,
+            &market,
+        )
+        .unwrap();
+        assert_fuzzy_eq!(result.positions[0].scenario_value, 1100. * 0.64, 1e-10);
+    }
+}
@@ -0,0 +1,373 @@
+//! Tools for working with schedules of (coupon) dates,
+//! e.g. to validate a generated bond schedule against the dates of a term sheet
+
+use crate::calendar::{last_day_of_month, Calendar};
+use crate::day_count_conv::{DayCountConv, DayCountConvError};
+use chrono::{Datelike, NaiveDate};
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fmt;
+
+/// Error of an invalid schedule
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScheduleError {
+    /// The schedule contains no dates
+    Empty,
+    /// The date at `index` is not after its predecessor `previous`
+    NotIncreasing {
+        index: usize,
+        previous: NaiveDate,
+        date: NaiveDate,
+    },
+}
+
+impl fmt::Display for ScheduleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScheduleError::Empty => write!(f, "schedule contains no dates"),
+            ScheduleError::NotIncreasing {
+                index,
+                previous,
+                date,
+            } => write!(
+                f,
+                "schedule date {} at position {} is not after the preceding date {}",
+                date, index, previous
+            ),
+        }
+    }
+}
+
+impl Error for ScheduleError {}
+
+/// Differences between a generated and an expected schedule
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ScheduleDiff {
+    /// Dates contained in the generated schedule only
+    pub only_generated: Vec<NaiveDate>,
+    /// Dates contained in the expected schedule only
+    pub only_expected: Vec<NaiveDate>,
+}
+
+impl ScheduleDiff {
+    /// True if both schedules contain the same dates
+    pub fn is_empty(&self) -> bool {
+        self.only_generated.is_empty() && self.only_expected.is_empty()
+    }
+}
+
+/// Compare a generated schedule with the expected dates, e.g. from a term sheet.
+/// The order of the dates is irrelevant, both lists of differences are sorted ascending.
+pub fn schedule_diff(generated: &[NaiveDate], expected: &[NaiveDate]) -> ScheduleDiff {
+    let generated: BTreeSet<NaiveDate> = generated.iter().cloned().collect();
+    let expected: BTreeSet<NaiveDate> = expected.iter().cloned().collect();
+    ScheduleDiff {
+        only_generated: generated.difference(&expected).cloned().collect(),
+        only_expected: expected.difference(&generated).cloned().collect(),
+    }
+}
+
+/// Find the most recent coupon date on or before `date` and the next coupon date
+/// strictly after `date` in a schedule sorted in ascending order.
+pub fn surrounding_coupons(
+    schedule: &[NaiveDate],
+    date: NaiveDate,
+) -> (Option<NaiveDate>, Option<NaiveDate>) {
+    let pos = schedule.partition_point(|coupon| *coupon <= date);
+    let previous = if pos > 0 {
+        Some(schedule[pos - 1])
+    } else {
+        None
+    };
+    (previous, schedule.get(pos).cloned())
+}
+
+/// Check that the schedule is not empty and its dates are strictly increasing, e.g. to
+/// detect dates that have been rolled back past their predecessor
+pub fn validate_schedule(dates: &[NaiveDate]) -> Result<(), ScheduleError> {
+    if dates.is_empty() {
+        return Err(ScheduleError::Empty);
+    }
+    for (index, pair) in dates.windows(2).enumerate() {
+        if pair[1] <= pair[0] {
+            return Err(ScheduleError::NotIncreasing {
+                index: index + 1,
+                previous: pair[0],
+                date: pair[1],
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Next standard CDS date strictly after `date`, i.e. the 20th of March, June, September
+/// or December. CDS dates are not adjusted to business days.
+pub fn next_cds_date(date: NaiveDate) -> NaiveDate {
+    let (year, month) = (date.year(), date.month());
+    let quarter_month = month.div_ceil(3) * 3;
+    let candidate = NaiveDate::from_ymd(year, quarter_month, 20);
+    if candidate > date {
+        candidate
+    } else if quarter_month == 12 {
+        NaiveDate::from_ymd(year + 1, 3, 20)
+    } else {
+        NaiveDate::from_ymd(year, quarter_month + 3, 20)
+    }
+}
+
+/// Date `months` months before `date`, with the day clamped to the end of the month
+fn months_before(date: NaiveDate, months: u32) -> NaiveDate {
+    let total = date.year() * 12 + date.month0() as i32 - months as i32;
+    let (year, month) = (total.div_euclid(12), total.rem_euclid(12) as u32 + 1);
+    NaiveDate::from_ymd(year, month, date.day().min(last_day_of_month(year, month)))
+}
+
+/// Number of coupon dates from `from` to `to` (inclusively) of a regular schedule with
+/// `frequency` coupons per year, rolled back from `maturity` to (excluding) `issue`.
+/// Coupon dates are unadjusted and counted without generating the whole schedule.
+/// Returns 0 if `frequency` is zero or does not divide 12.
+pub fn coupons_between(
+    issue: NaiveDate,
+    maturity: NaiveDate,
+    frequency: u32,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> usize {
+    if frequency == 0 || 12 % frequency != 0 {
+        return 0;
+    }
+    let step = 12 / frequency;
+    let start = issue.max(from.pred());
+    let end = maturity.min(to);
+    if end <= start {
+        return 0;
+    }
+    // first coupon at or before `end`, estimated by the number of months in between
+    let months = |date: NaiveDate| date.year() * 12 + date.month0() as i32;
+    let mut k = ((months(maturity) - months(end)).max(0) as u32) / step;
+    while months_before(maturity, k * step) > end {
+        k += 1;
+    }
+    let mut count = 0;
+    while months_before(maturity, (k + count) * step) > start {
+        count += 1;
+    }
+    count as usize
+}
+
+/// Shift each date of a schedule by `n` business days of `cal`, e.g. to model a delayed
+/// settlement of all payments. Negative `n` shifts backwards, in the same way as a time
+/// period of `n` business days. The order of the dates is preserved.
+pub fn shift_schedule(dates: &[NaiveDate], n: i64, cal: &Calendar) -> Vec<NaiveDate> {
+    dates
+        .iter()
+        .map(|&date| {
+            (0..n.abs()).fold(date, |date, _| {
+                if n < 0 {
+                    cal.prev_bday(date)
+                } else {
+                    cal.next_bday(date)
+                }
+            })
+        })
+        .collect()
+}
+
+/// Year fractions of the periods between consecutive dates of a schedule, e.g. to calculate
+/// the coupons of the periods. Conventions requiring a roll date and time period, i.e.
+/// `ActActICMA`, are not supported and result in an error.
+pub fn schedule_year_fractions(
+    dates: &[NaiveDate],
+    dc: DayCountConv,
+) -> Result<Vec<f64>, DayCountConvError> {
+    dates
+        .windows(2)
+        .map(|period| dc.year_fraction(period[0], period[1], None, None))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calendar::Holiday;
+    use chrono::Weekday;
+
+    #[test]
+    fn schedule_with_one_wrongly_rolled_date() {
+        let expected = vec![
+            NaiveDate::from_ymd(2020, 4, 1),
+            NaiveDate::from_ymd(2020, 10, 1),
+            NaiveDate::from_ymd(2021, 4, 1),
+            NaiveDate::from_ymd(2021, 10, 1),
+        ];
+        assert!(schedule_diff(&expected, &expected).is_empty());
+
+        // 2021-10-01 is a Friday, but the schedule was rolled to the following Monday
+        let generated = vec![
+            NaiveDate::from_ymd(2020, 4, 1),
+            NaiveDate::from_ymd(2020, 10, 1),
+            NaiveDate::from_ymd(2021, 4, 1),
+            NaiveDate::from_ymd(2021, 10, 4),
+        ];
+        let diff = schedule_diff(&generated, &expected);
+        assert!(!diff.is_empty());
+        assert_eq!(diff.only_generated, vec![NaiveDate::from_ymd(2021, 10, 4)]);
+        assert_eq!(diff.only_expected, vec![NaiveDate::from_ymd(2021, 10, 1)]);
+    }
+
+    #[test]
+    fn coupons_around_date() {
+        let schedule = vec![
+            NaiveDate::from_ymd(2020, 4, 1),
+            NaiveDate::from_ymd(2020, 10, 1),
+            NaiveDate::from_ymd(2021, 4, 1),
+        ];
+        assert_eq!(
+            surrounding_coupons(&schedule, NaiveDate::from_ymd(2020, 6, 15)),
+            (
+                Some(NaiveDate::from_ymd(2020, 4, 1)),
+                Some(NaiveDate::from_ymd(2020, 10, 1))
+            )
+        );
+        // a coupon date itself is the previous coupon
+        assert_eq!(
+            surrounding_coupons(&schedule, NaiveDate::from_ymd(2020, 10, 1)),
+            (
+                Some(NaiveDate::from_ymd(2020, 10, 1)),
+                Some(NaiveDate::from_ymd(2021, 4, 1))
+            )
+        );
+        assert_eq!(
+            surrounding_coupons(&schedule, NaiveDate::from_ymd(2020, 1, 1)),
+            (None, Some(NaiveDate::from_ymd(2020, 4, 1)))
+        );
+        assert_eq!(
+            surrounding_coupons(&schedule, NaiveDate::from_ymd(2021, 4, 2)),
+            (Some(NaiveDate::from_ymd(2021, 4, 1)), None)
+        );
+        assert_eq!(
+            surrounding_coupons(&[], NaiveDate::from_ymd(2021, 4, 2)),
+            (None, None)
+        );
+    }
+
+    #[test]
+    fn validate_monotonic_schedule() {
+        let d = NaiveDate::from_ymd;
+        let schedule = vec![d(2020, 4, 1), d(2020, 10, 1), d(2021, 4, 1)];
+        assert_eq!(validate_schedule(&schedule), Ok(()));
+        assert_eq!(validate_schedule(&schedule[..1]), Ok(()));
+        assert_eq!(validate_schedule(&[]), Err(ScheduleError::Empty));
+
+        let duplicate = vec![d(2020, 4, 1), d(2020, 10, 1), d(2020, 10, 1)];
+        assert_eq!(
+            validate_schedule(&duplicate),
+            Err(ScheduleError::NotIncreasing {
+                index: 2,
+                previous: d(2020, 10, 1),
+                date: d(2020, 10, 1),
+            })
+        );
+        // short stub rolled back past the preceding date
+        let out_of_order = vec![d(2020, 4, 1), d(2020, 3, 30), d(2020, 10, 1)];
+        assert!(matches!(
+            validate_schedule(&out_of_order),
+            Err(ScheduleError::NotIncreasing { index: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn cds_dates() {
+        let d = NaiveDate::from_ymd;
+        assert_eq!(next_cds_date(d(2021, 1, 5)), d(2021, 3, 20));
+        assert_eq!(next_cds_date(d(2021, 3, 19)), d(2021, 3, 20));
+        assert_eq!(next_cds_date(d(2021, 3, 20)), d(2021, 6, 20));
+        assert_eq!(next_cds_date(d(2021, 11, 30)), d(2021, 12, 20));
+        assert_eq!(next_cds_date(d(2021, 12, 20)), d(2022, 3, 20));
+    }
+
+    #[test]
+    fn count_coupons_in_window() {
+        let d = NaiveDate::from_ymd;
+        // semiannual coupons on 31st of March and 30th of September
+        let (issue, maturity) = (d(2020, 3, 31), d(2030, 3, 31));
+        assert_eq!(coupons_between(issue, maturity, 2, issue, maturity), 20);
+        assert_eq!(
+            coupons_between(issue, maturity, 2, d(2022, 1, 1), d(2023, 12, 31)),
+            4
+        );
+        // window boundaries on coupon dates are inclusive
+        assert_eq!(
+            coupons_between(issue, maturity, 2, d(2022, 3, 31), d(2023, 9, 30)),
+            4
+        );
+        assert_eq!(
+            coupons_between(issue, maturity, 2, d(2022, 4, 1), d(2023, 9, 29)),
+            2
+        );
+        assert_eq!(
+            coupons_between(issue, maturity, 2, d(2031, 1, 1), d(2032, 1, 1)),
+            0
+        );
+        assert_eq!(coupons_between(issue, maturity, 5, issue, maturity), 0);
+    }
+
+    #[test]
+    fn shift_schedule_across_holiday() {
+        let cal = Calendar::calc_calendar(
+            &[
+                Holiday::SingularDay(NaiveDate::from_ymd(2021, 1, 1)),
+                Holiday::WeekDay(Weekday::Sat),
+                Holiday::WeekDay(Weekday::Sun),
+            ],
+            2020,
+            2021,
+        )
+        .unwrap();
+        let schedule = vec![
+            NaiveDate::from_ymd(2020, 3, 31),
+            NaiveDate::from_ymd(2020, 6, 30),
+            NaiveDate::from_ymd(2020, 9, 30),
+            NaiveDate::from_ymd(2020, 12, 31),
+        ];
+        // New Year's Day 2021 is a Friday
+        let shifted = vec![
+            NaiveDate::from_ymd(2020, 4, 1),
+            NaiveDate::from_ymd(2020, 7, 1),
+            NaiveDate::from_ymd(2020, 10, 1),
+            NaiveDate::from_ymd(2021, 1, 4),
+        ];
+        assert_eq!(shift_schedule(&schedule, 1, &cal), shifted);
+        assert_eq!(shift_schedule(&shifted, -1, &cal), schedule);
+        assert_eq!(shift_schedule(&schedule, 0, &cal), schedule);
+        assert!(shift_schedule(&[], 1, &cal).is_empty());
+    }
+
+    #[test]
+    fn year_fractions_of_quarterly_schedule() {
+        let schedule = vec![
+            NaiveDate::from_ymd(2021, 1, 15),
+            NaiveDate::from_ymd(2021, 4, 15),
+            NaiveDate::from_ymd(2021, 7, 15),
+            NaiveDate::from_ymd(2021, 10, 15),
+            NaiveDate::from_ymd(2022, 1, 15),
+        ];
+        let fractions = schedule_year_fractions(&schedule, DayCountConv::Act360).unwrap();
+        assert_eq!(fractions.len(), 4);
+        for (fraction, days) in fractions.iter().zip(&[90., 91., 92., 92.]) {
+            assert_fuzzy_eq!(*fraction, days / 360., 1e-12);
+            assert_fuzzy_eq!(*fraction, 0.25, 0.01);
+        }
+        let full = DayCountConv::Act360
+            .year_fraction(schedule[0], schedule[4], None, None)
+            .unwrap();
+        assert_fuzzy_eq!(fractions.iter().sum::<f64>(), full, 1e-12);
+
+        assert!(
+            schedule_year_fractions(&schedule[..1], DayCountConv::Act360)
+                .unwrap()
+                .is_empty()
+        );
+        assert!(schedule_year_fractions(&schedule, DayCountConv::ActActICMA).is_err());
+    }
+}
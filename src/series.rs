@@ -0,0 +1,181 @@
+//! Statistics over time series of values, e.g. quotes, as used for charts
+
+use chrono::NaiveDate;
+use std::collections::VecDeque;
+
+/// Statistic calculated over a rolling window
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RollingStat {
+    Mean,
+    /// Sample standard deviation, i.e. normalized by the window size minus one
+    StdDev,
+    Min,
+    Max,
+    /// Exponential moving average with weight `alpha` of the most recent value,
+    /// independent of the window size
+    Ema {
+        alpha: f64,
+    },
+}
+
+/// Calculate the statistic `stat` over a rolling window of `window` consecutive points of
+/// `series`, which is expected to be ordered by date. Values are dated by the last point of
+/// the window and are emitted only once the window is full, except for the exponential moving
+/// average, which starts with the first point. Mean, standard deviation and exponential
+/// moving average are calculated with running sums, min and max with monotonic queues,
+/// i.e. all statistics are calculated in linear time.
+pub fn rolling(
+    series: &[(NaiveDate, f64)],
+    window: usize,
+    stat: RollingStat,
+) -> Vec<(NaiveDate, f64)> {
+    if let RollingStat::Ema { alpha } = stat {
+        let mut ema = match series.first() {
+            Some((_, value)) => *value,
+            None => return Vec::new(),
+        };
+        return series
+            .iter()
+            .map(|(date, value)| {
+                ema += alpha * (value - ema);
+                (*date, ema)
+            })
+            .collect();
+    }
+    if window == 0 || series.len() < window {
+        return Vec::new();
+    }
+    match stat {
+        RollingStat::Mean | RollingStat::StdDev => rolling_moments(series, window, stat),
+        _ => rolling_extremum(series, window, stat == RollingStat::Max),
+    }
+}
+
+/// Rolling mean or standard deviation via running sums. To reduce loss of precision,
+/// sums are taken of the deviations from the first value of the series.
+fn rolling_moments(
+    series: &[(NaiveDate, f64)],
+    window: usize,
+    stat: RollingStat,
+) -> Vec<(NaiveDate, f64)> {
+    let shift = series[0].1;
+    let n = window as f64;
+    let (mut sum, mut sum_sq) = (0., 0.);
+    let mut result = Vec::with_capacity(series.len() + 1 - window);
+    for (idx, (date, value)) in series.iter().enumerate() {
+        let x = value - shift;
+        sum += x;
+        sum_sq += x * x;
+        if idx >= window {
+            let old = series[idx - window].1 - shift;
+            sum -= old;
+            sum_sq -= old * old;
+        }
+        if idx + 1 < window {
+            continue;
+        }
+        let mean = sum / n;
+        let value = match stat {
+            RollingStat::Mean => shift + mean,
+            _ if window == 1 => 0.,
+            _ => ((sum_sq - n * mean * mean) / (n - 1.)).max(0.).sqrt(),
+        };
+        result.push((*date, value));
+    }
+    result
+}
+
+/// Rolling minimum (or maximum, if `max` is set) via a monotonic queue of indices
+fn rolling_extremum(
+    series: &[(NaiveDate, f64)],
+    window: usize,
+    max: bool,
+) -> Vec<(NaiveDate, f64)> {
+    // the queue holds indices of values in decreasing order of preference
+    let mut queue: VecDeque<usize> = VecDeque::new();
+    let dominates = |a: f64, b: f64| if max { a >= b } else { a <= b };
+    let mut result = Vec::with_capacity(series.len() + 1 - window);
+    for (idx, (date, value)) in series.iter().enumerate() {
+        while let Some(last) = queue.back() {
+            if dominates(*value, series[*last].1) {
+                queue.pop_back();
+            } else {
+                break;
+            }
+        }
+        queue.push_back(idx);
+        if queue[0] + window <= idx {
+            queue.pop_front();
+        }
+        if idx + 1 >= window {
+            result.push((*date, series[queue[0]].1));
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn brute_force(values: &[f64], stat: RollingStat) -> f64 {
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        match stat {
+            RollingStat::Mean => mean,
+            RollingStat::StdDev => {
+                (values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.)).sqrt()
+            }
+            RollingStat::Min => values.iter().cloned().fold(f64::INFINITY, f64::min),
+            RollingStat::Max => values.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+            RollingStat::Ema { .. } => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn rolling_statistics_match_brute_force() {
+        let start = NaiveDate::from_ymd(2020, 1, 1);
+        let series: Vec<_> = (0..40)
+            .map(|i| {
+                let x = i as f64;
+                (
+                    start + chrono::Duration::days(i),
+                    100. + 5. * (x * 0.7).sin() + 0.1 * x,
+                )
+            })
+            .collect();
+        let values: Vec<f64> = series.iter().map(|(_, v)| *v).collect();
+        let window = 7;
+        for stat in &[
+            RollingStat::Mean,
+            RollingStat::StdDev,
+            RollingStat::Min,
+            RollingStat::Max,
+        ] {
+            let result = rolling(&series, window, *stat);
+            assert_eq!(result.len(), series.len() - window + 1);
+            for (idx, (date, value)) in result.iter().enumerate() {
+                assert_eq!(*date, series[idx + window - 1].0);
+                let expected = brute_force(&values[idx..idx + window], *stat);
+                assert_fuzzy_eq!(*value, expected, 1e-10);
+            }
+        }
+        assert!(rolling(&series[..5], window, RollingStat::Mean).is_empty());
+        assert!(rolling(&series, 0, RollingStat::Max).is_empty());
+    }
+
+    #[test]
+    fn exponential_moving_average() {
+        let start = NaiveDate::from_ymd(2020, 1, 1);
+        let series: Vec<_> = [10., 12., 11., 15.]
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (start + chrono::Duration::days(i as i64), *v))
+            .collect();
+        let ema = rolling(&series, 3, RollingStat::Ema { alpha: 0.5 });
+        let values: Vec<f64> = ema.iter().map(|(_, v)| *v).collect();
+        assert_eq!(values, vec![10., 11., 11., 13.]);
+        assert_eq!(ema[3].0, series[3].0);
+        assert!(rolling(&[], 3, RollingStat::Ema { alpha: 0.5 }).is_empty());
+    }
+}
@@ -1,8 +1,12 @@
 ///! Implemenation of sqlite3 data handler
-use crate::asset::Asset;
-use crate::data_handler::{DataError, DataHandler};
+use crate::asset::{escape_like, rank_search_results, Asset, Instrument, InstrumentType};
+use crate::data_handler::{Clock, DataError, DataHandler, SystemClock, DEFAULT_PAGE_SIZE};
+use crate::helpers::to_db_id;
 use crate::transaction::Transaction;
-use rusqlite::{params, Connection, OpenFlags, NO_PARAMS};
+use chrono::NaiveDate;
+use rusqlite::types::Type;
+use rusqlite::{params, Connection, OpenFlags, Row, NO_PARAMS};
+use std::convert::TryFrom;
 
 mod raw_transaction;
 use raw_transaction::RawTransaction;
@@ -10,21 +14,43 @@ use raw_transaction::RawTransaction;
 /// Struct to handle connections to sqlite3 databases
 pub struct SqliteDB {
     conn: Connection,
+    /// Number of transactions fetched at once when iterating over all transactions
+    page_size: usize,
+    /// Clock used for the audit time stamps of transactions
+    clock: Box<dyn Clock>,
 }
 
 impl SqliteDB {
     pub fn connect(file_path: &str) -> rusqlite::Result<SqliteDB> {
         let conn = Connection::open_with_flags(file_path, OpenFlags::SQLITE_OPEN_READ_WRITE)?;
-        Ok(SqliteDB { conn })
+        Ok(SqliteDB {
+            conn,
+            page_size: DEFAULT_PAGE_SIZE,
+            clock: Box::new(SystemClock),
+        })
     }
 
     pub fn create(file_path: &str) -> rusqlite::Result<SqliteDB> {
         let conn = Connection::open(file_path)?;
-        let db = SqliteDB { conn };
+        let db = SqliteDB {
+            conn,
+            page_size: DEFAULT_PAGE_SIZE,
+            clock: Box::new(SystemClock),
+        };
         db.init()?;
         Ok(db)
     }
 
+    /// Set the number of transactions fetched at once when iterating over all transactions
+    pub fn set_page_size(&mut self, page_size: usize) {
+        self.page_size = std::cmp::max(1, page_size);
+    }
+
+    /// Set the clock used for the audit time stamps of transactions
+    pub fn set_clock(&mut self, clock: Box<dyn Clock>) {
+        self.clock = clock;
+    }
+
     /// Initialize new database by creating table, fill
     fn init(&self) -> rusqlite::Result<()> {
         self.conn.execute(
@@ -33,7 +59,12 @@ impl SqliteDB {
                 name TEXT NOT NULL UNIQUE,
                 wkn TEXT UNIQUE,
                 isin TEXT UNIQUE,
-                note TEXT
+                note TEXT,
+                instrument_type TEXT,
+                instrument TEXT,
+                tags TEXT,
+                active INTEGER NOT NULL DEFAULT 1,
+                delisted_at TEXT
             )",
             NO_PARAMS,
         )?;
@@ -48,6 +79,10 @@ impl SqliteDB {
                 related_trans KEY,
                 position REAL,
                 note TEXT,
+                tags TEXT,
+                account_id INTEGER,
+                created_at TEXT,
+                modified_at TEXT,
                 FOREIGN KEY(asset_id) REFERENCES assets(id),
                 FOREIGN KEY(related_trans) REFERENCES transactions(id)
             );",
@@ -63,8 +98,11 @@ impl SqliteDB {
             "CREATE TABLE IF NOT EXISTS ticker (
                 id INTEGER PRIMARY KEY,
                 name TEXT NOT NULL,
+                asset_id INTEGER NOT NULL,
                 source_id INTEGER NOT NULL,
                 currency TEXT NOT NULL,
+                update_policy TEXT NOT NULL DEFAULT 'daily',
+                FOREIGN KEY(asset_id) REFERENCES assets(id),
                 FOREIGN KEY(source_id) REFERENCES market_data_sources(id) );",
             NO_PARAMS,
         )?;
@@ -83,13 +121,89 @@ impl SqliteDB {
     }
 }
 
+/// Read id from the given column, ids stored in the database must not be negative
+fn id_from_row(row: &Row, idx: usize) -> rusqlite::Result<u64> {
+    let id: i64 = row.get(idx)?;
+    u64::try_from(id).map_err(|_| rusqlite::Error::IntegralValueOutOfRange(idx, id))
+}
+
+/// Build asset from a row of columns id, name, wkn, isin, note, instrument, tags,
+/// active and delisted_at
+fn asset_from_row(row: &Row) -> rusqlite::Result<Asset> {
+    let id = id_from_row(row, 0)?;
+    let instrument: Option<String> = row.get(5)?;
+    let instrument =
+        match instrument {
+            Some(json) => Some(Instrument::from_json(&json).map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(5, Type::Text, Box::new(e))
+            })?),
+            None => None,
+        };
+    let tags: Option<String> = row.get(6)?;
+    let tags = match tags {
+        Some(json) => serde_json::from_str(&json)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(6, Type::Text, Box::new(e)))?,
+        None => Vec::new(),
+    };
+    let delisted_at: Option<String> = row.get(8)?;
+    let delisted_at =
+        match delisted_at {
+            Some(date) => Some(NaiveDate::parse_from_str(&date, "%Y-%m-%d").map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(8, Type::Text, Box::new(e))
+            })?),
+            None => None,
+        };
+    Ok(Asset {
+        id: Some(id),
+        name: row.get(1)?,
+        wkn: row.get(2)?,
+        isin: row.get(3)?,
+        note: row.get(4)?,
+        instrument,
+        tags,
+        active: row.get(7)?,
+        delisted_at,
+    })
+}
+
+/// Format asset's delisting date for storage
+fn delisted_at_column(asset: &Asset) -> Option<String> {
+    asset
+        .delisted_at
+        .map(|date| date.format("%Y-%m-%d").to_string())
+}
+
+/// Serialize asset's tags to JSON for storage, no tags are stored as NULL
+fn tags_column(asset: &Asset) -> Result<Option<String>, DataError> {
+    if asset.tags.is_empty() {
+        return Ok(None);
+    }
+    serde_json::to_string(&asset.tags)
+        .map(Some)
+        .map_err(|e| DataError::InvalidAsset(e.to_string()))
+}
+
 /// Handler for globally available data
 impl DataHandler for SqliteDB {
-    fn insert_asset(&mut self, asset: &Asset) -> Result<usize, DataError> {
+    fn insert_asset(&mut self, asset: &Asset) -> Result<u64, DataError> {
+        let (instrument_type, instrument) = asset.instrument_columns()?;
+        let tags = tags_column(asset)?;
         self.conn
             .execute(
-                "INSERT INTO assets (name, wkn, isin, note) VALUES (?1, ?2, ?3, ?4)",
-                params![asset.name, asset.wkn, asset.isin, asset.note],
+                "INSERT INTO assets (name, wkn, isin, note, instrument_type, instrument, tags,
+                active, delisted_at)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    asset.name,
+                    asset.wkn,
+                    asset.isin,
+                    asset.note,
+                    instrument_type,
+                    instrument,
+                    tags,
+                    asset.active,
+                    delisted_at_column(asset)
+                ],
             )
             .map_err(|e| DataError::InsertFailed(e.to_string()))?;
         let id = self
@@ -98,31 +212,33 @@ impl DataHandler for SqliteDB {
                 "SELECT id FROM assets
         WHERE name=?;",
                 params![asset.name],
-                |row| {
-                    let id: i64 = row.get(0)?;
-                    Ok(id as usize)
-                },
+                |row| id_from_row(row, 0),
             )
             .map_err(|e| DataError::NotFound(e.to_string()))?;
         Ok(id)
     }
 
-    fn get_asset_by_id(&mut self, id: usize) -> Result<Asset, DataError> {
+    fn get_asset_by_id(&mut self, id: u64) -> Result<Asset, DataError> {
         let asset = self
             .conn
             .query_row(
-                "SELECT name, wkn, isin, note FROM assets
+                "SELECT id, name, wkn, isin, note, instrument, tags, active, delisted_at FROM assets
         WHERE id=?;",
-                &[id as i64],
-                |row| {
-                    Ok(Asset {
-                        id: Some(id),
-                        name: row.get(0)?,
-                        wkn: row.get(1)?,
-                        isin: row.get(2)?,
-                        note: row.get(3)?,
-                    })
-                },
+                &[to_db_id(id)?],
+                asset_from_row,
+            )
+            .map_err(|e| DataError::NotFound(e.to_string()))?;
+        Ok(asset)
+    }
+
+    fn get_asset_by_name(&mut self, name: &str) -> Result<Asset, DataError> {
+        let asset = self
+            .conn
+            .query_row(
+                "SELECT id, name, wkn, isin, note, instrument, tags, active, delisted_at FROM assets
+        WHERE name=?;",
+                params![name],
+                asset_from_row,
             )
             .map_err(|e| DataError::NotFound(e.to_string()))?;
         Ok(asset)
@@ -131,61 +247,108 @@ impl DataHandler for SqliteDB {
     fn get_all_assets(&mut self) -> Result<Vec<Asset>, DataError> {
         let mut stmt = self
             .conn
-            .prepare("SELECT id, name, wkn, isin, note FROM assets;")
+            .prepare("SELECT id, name, wkn, isin, note, instrument, tags, active, delisted_at FROM assets;")
             .map_err(|e| DataError::NotFound(e.to_string()))?;
         let asset_map = stmt
-            .query_map(NO_PARAMS, |row| {
-                let id: i64 = row.get(0)?;
-                let id = Some(id as usize);
-                Ok(Asset {
-                    id,
-                    name: row.get(1)?,
-                    wkn: row.get(2)?,
-                    isin: row.get(3)?,
-                    note: row.get(4)?,
-                })
-            })
+            .query_map(NO_PARAMS, asset_from_row)
             .map_err(|e| DataError::NotFound(e.to_string()))?;
         let mut assets = Vec::new();
         for asset in asset_map {
-            assets.push(asset.unwrap());
+            assets.push(asset.map_err(|e| DataError::NotFound(e.to_string()))?);
         }
         Ok(assets)
     }
 
+    fn get_assets_by_instrument_type(
+        &mut self,
+        kind: InstrumentType,
+    ) -> Result<Vec<Asset>, DataError> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id, name, wkn, isin, note, instrument, tags, active, delisted_at FROM assets
+                WHERE instrument_type=?;",
+            )
+            .map_err(|e| DataError::NotFound(e.to_string()))?;
+        let asset_map = stmt
+            .query_map(params![kind.to_string()], asset_from_row)
+            .map_err(|e| DataError::NotFound(e.to_string()))?;
+        let mut assets = Vec::new();
+        for asset in asset_map {
+            assets.push(asset.map_err(|e| DataError::NotFound(e.to_string()))?);
+        }
+        Ok(assets)
+    }
+
+    fn search_assets(&mut self, query: &str, limit: usize) -> Result<Vec<Asset>, DataError> {
+        // LIKE is case-insensitive for ASCII characters in sqlite
+        let pattern = format!("%{}%", escape_like(query));
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id, name, wkn, isin, note, instrument, tags, active, delisted_at FROM assets
+                WHERE name LIKE ?1 ESCAPE '\\' OR isin LIKE ?1 ESCAPE '\\';",
+            )
+            .map_err(|e| DataError::NotFound(e.to_string()))?;
+        let asset_map = stmt
+            .query_map(params![pattern], asset_from_row)
+            .map_err(|e| DataError::NotFound(e.to_string()))?;
+        let mut assets = Vec::new();
+        for asset in asset_map {
+            assets.push(asset.map_err(|e| DataError::NotFound(e.to_string()))?);
+        }
+        Ok(rank_search_results(assets, query, limit, false))
+    }
+
     fn update_asset(&mut self, asset: &Asset) -> Result<(), DataError> {
         if asset.id.is_none() {
             return Err(DataError::NotFound(
                 "not yet stored to database".to_string(),
             ));
         }
-        let id = asset.id.unwrap() as i64;
+        let id = to_db_id(asset.id.unwrap())?;
+        let (instrument_type, instrument) = asset.instrument_columns()?;
+        let tags = tags_column(asset)?;
         self.conn
             .execute(
-                "UPDATE assets SET name=?2, wkn=?3, isin=?4, note=?5 
+                "UPDATE assets SET name=?2, wkn=?3, isin=?4, note=?5, instrument_type=?6,
+                instrument=?7, tags=?8, active=?9, delisted_at=?10
                 WHERE id=?1;",
-                params![id, asset.name, asset.wkn, asset.isin, asset.note],
+                params![
+                    id,
+                    asset.name,
+                    asset.wkn,
+                    asset.isin,
+                    asset.note,
+                    instrument_type,
+                    instrument,
+                    tags,
+                    asset.active,
+                    delisted_at_column(asset)
+                ],
             )
             .map_err(|e| DataError::InsertFailed(e.to_string()))?;
         Ok(())
     }
 
-    fn delete_asset(&mut self, id: usize) -> Result<(), DataError> {
+    fn delete_asset(&mut self, id: u64) -> Result<(), DataError> {
         self.conn
-            .execute("DELETE FROM assets WHERE id=?1;", params![id as i64])
+            .execute("DELETE FROM assets WHERE id=?1;", params![to_db_id(id)?])
             .map_err(|e| DataError::InsertFailed(e.to_string()))?;
         Ok(())
     }
 
     // insert, get, update and delete for transactions
-    fn insert_transaction(&mut self, transaction: &Transaction) -> Result<usize, DataError> {
-        let transaction = RawTransaction::from_transaction(transaction);
+    fn insert_transaction(&mut self, transaction: &Transaction) -> Result<u64, DataError> {
+        self.validate_transfer(transaction)?;
+        let transaction = RawTransaction::from_transaction(transaction)?;
+        let now = self.clock.now().to_rfc3339();
         self.conn
             .execute(
                 "INSERT INTO transactions (trans_type, asset_id, cash_amount, 
                 cash_currency, cash_date, related_trans, position,
-                note) 
-                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8);",
+                note, tags, account_id, created_at, modified_at) 
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?11);",
                 params![
                     transaction.trans_type,
                     transaction.asset,
@@ -194,32 +357,35 @@ impl DataHandler for SqliteDB {
                     transaction.cash_date,
                     transaction.related_trans,
                     transaction.position,
-                    transaction.note
+                    transaction.note,
+                    transaction.tags,
+                    transaction.account,
+                    now
                 ],
             )
             .map_err(|e| DataError::InsertFailed(e.to_string()))?;
         let id = self
             .conn
             .query_row("SELECT last_insert_rowid();", NO_PARAMS, |row| {
-                let id: i64 = row.get(0)?;
-                Ok(id as usize)
+                id_from_row(row, 0)
             })
             .map_err(|e| DataError::NotFound(e.to_string()))?;
         Ok(id)
     }
 
-    fn get_transaction_by_id(&mut self, id: usize) -> Result<Transaction, DataError> {
+    fn get_transaction_by_id(&mut self, id: u64) -> Result<Transaction, DataError> {
+        let id = to_db_id(id)?;
         let transaction = self
             .conn
             .query_row(
                 "SELECT trans_type, asset_id, 
-        cash_amount, cash_currency, cash_date, related_trans, position, note 
-        FROM transactions
+        cash_amount, cash_currency, cash_date, related_trans, position, note, tags,
+        account_id, created_at, modified_at FROM transactions
         WHERE id=?;",
-                params![id as i64],
+                params![id],
                 |row| {
                     Ok(RawTransaction {
-                        id: Some(id as i64),
+                        id: Some(id),
                         trans_type: row.get(0)?,
                         asset: row.get(1)?,
                         cash_amount: row.get(2)?,
@@ -228,6 +394,10 @@ impl DataHandler for SqliteDB {
                         related_trans: row.get(5)?,
                         position: row.get(6)?,
                         note: row.get(7)?,
+                        tags: row.get(8)?,
+                        account: row.get(9)?,
+                        created_at: row.get(10)?,
+                        modified_at: row.get(11)?,
                     })
                 },
             )
@@ -241,8 +411,8 @@ impl DataHandler for SqliteDB {
             .conn
             .prepare(
                 "SELECT id, trans_type, asset_id, 
-        cash_amount, cash_currency, cash_date, related_trans, position, note 
-        FROM transactions;",
+        cash_amount, cash_currency, cash_date, related_trans, position, note, tags,
+        account_id, created_at, modified_at FROM transactions;",
             )
             .map_err(|e| DataError::NotFound(e.to_string()))?;
         let transaction_map = stmt
@@ -257,6 +427,10 @@ impl DataHandler for SqliteDB {
                     related_trans: row.get(6)?,
                     position: row.get(7)?,
                     note: row.get(8)?,
+                    tags: row.get(9)?,
+                    account: row.get(10)?,
+                    created_at: row.get(11)?,
+                    modified_at: row.get(12)?,
                 })
             })
             .map_err(|e| DataError::NotFound(e.to_string()))?;
@@ -267,25 +441,81 @@ impl DataHandler for SqliteDB {
         Ok(transactions)
     }
 
+    fn transaction_page_size(&self) -> usize {
+        self.page_size
+    }
+
+    fn get_transactions_after_id(
+        &mut self,
+        after: u64,
+        limit: usize,
+    ) -> Result<Vec<Transaction>, DataError> {
+        // ids are stored as signed integers, larger ones can't exist
+        let after = match i64::try_from(after) {
+            Ok(after) => after,
+            Err(_) => return Ok(Vec::new()),
+        };
+        let limit = i64::try_from(limit).unwrap_or(i64::MAX);
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id, trans_type, asset_id, 
+        cash_amount, cash_currency, cash_date, related_trans, position, note, tags,
+        account_id, created_at, modified_at FROM transactions
+        WHERE id>?1 ORDER BY id LIMIT ?2;",
+            )
+            .map_err(|e| DataError::NotFound(e.to_string()))?;
+        let transaction_map = stmt
+            .query_map(params![after, limit], |row| {
+                Ok(RawTransaction {
+                    id: row.get(0)?,
+                    trans_type: row.get(1)?,
+                    asset: row.get(2)?,
+                    cash_amount: row.get(3)?,
+                    cash_currency: row.get(4)?,
+                    cash_date: row.get(5)?,
+                    related_trans: row.get(6)?,
+                    position: row.get(7)?,
+                    note: row.get(8)?,
+                    tags: row.get(9)?,
+                    account: row.get(10)?,
+                    created_at: row.get(11)?,
+                    modified_at: row.get(12)?,
+                })
+            })
+            .map_err(|e| DataError::NotFound(e.to_string()))?;
+        let mut transactions = Vec::new();
+        for transaction in transaction_map {
+            let transaction = transaction.map_err(|e| DataError::NotFound(e.to_string()))?;
+            transactions.push(transaction.to_transaction()?);
+        }
+        Ok(transactions)
+    }
+
     fn update_transaction(&mut self, transaction: &Transaction) -> Result<(), DataError> {
         if transaction.id.is_none() {
             return Err(DataError::NotFound(
                 "not yet stored to database".to_string(),
             ));
         }
-        let id = transaction.id.unwrap() as i64;
-        let transaction = RawTransaction::from_transaction(transaction);
+        let id = to_db_id(transaction.id.unwrap())?;
+        self.validate_transfer(transaction)?;
+        let transaction = RawTransaction::from_transaction(transaction)?;
+        let now = self.clock.now().to_rfc3339();
         self.conn
             .execute(
                 "UPDATE transactions SET 
                 trans_type=?2, 
                 asset_id=?3, 
-                cash_value=?4, 
+                cash_amount=?4, 
                 cash_currency=?5,
                 cash_date=?6,
                 related_trans=?7,
                 position=?8,
-                note=?9
+                note=?9,
+                tags=?10,
+                account_id=?11,
+                modified_at=?12
             WHERE id=?1;",
                 params![
                     id,
@@ -296,18 +526,301 @@ impl DataHandler for SqliteDB {
                     transaction.cash_date,
                     transaction.related_trans,
                     transaction.position,
-                    transaction.note
+                    transaction.note,
+                    transaction.tags,
+                    transaction.account,
+                    now
                 ],
             )
             .map_err(|e| DataError::InsertFailed(e.to_string()))?;
         Ok(())
     }
-    fn delete_transaction(&mut self, id: usize) -> Result<(), DataError> {
+    fn delete_transaction(&mut self, id: u64) -> Result<(), DataError> {
         self.conn
-            .execute("DELETE FROM transactions WHERE id=?1;", params![id as i64])
+            .execute(
+                "DELETE FROM transactions WHERE id=?1;",
+                params![to_db_id(id)?],
+            )
             .map_err(|e| DataError::InsertFailed(e.to_string()))?;
         Ok(())
     }
 }
 
 mod quote_handler;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bond::Bond;
+
+    #[test]
+    fn instrument_round_trip_sqlite() {
+        let bond: Bond =
+            serde_json::from_str(include_str!("../../examples/photon_energy_bond.json")).unwrap();
+        let mut bond_asset = Asset::new(None, "Photon Energy 17/22", None, None, None);
+        bond_asset.instrument = Some(Instrument::Bond(Box::new(bond)));
+
+        let mut db = SqliteDB::create(":memory:").unwrap();
+        let bond_id = db.insert_asset(&bond_asset).unwrap();
+        let fund_id = db
+            .insert_asset(&Asset::new(None, "World Index Fund", None, None, None))
+            .unwrap();
+        let mut fund_asset = db.get_asset_by_id(fund_id).unwrap();
+        assert!(fund_asset.instrument.is_none());
+        fund_asset.instrument = Some(Instrument::Fund);
+        db.update_asset(&fund_asset).unwrap();
+
+        let stored = db.get_asset_by_name("Photon Energy 17/22").unwrap();
+        assert_eq!(stored.id, Some(bond_id));
+        assert_eq!(
+            stored.instrument.unwrap().to_json().unwrap(),
+            bond_asset.instrument.unwrap().to_json().unwrap()
+        );
+        let bonds = db
+            .get_assets_by_instrument_type(InstrumentType::Bond)
+            .unwrap();
+        assert_eq!(bonds.len(), 1);
+        assert_eq!(bonds[0].id, Some(bond_id));
+        let funds = db
+            .get_assets_by_instrument_type(InstrumentType::Fund)
+            .unwrap();
+        assert_eq!(funds.len(), 1);
+        assert_eq!(funds[0].id, Some(fund_id));
+        assert_eq!(funds[0].instrument_type(), Some(InstrumentType::Fund));
+    }
+
+    #[test]
+    fn ids_out_of_range_sqlite() {
+        let mut db = SqliteDB::create(":memory:").unwrap();
+        let result = db.get_asset_by_id(u64::MAX);
+        assert!(matches!(result, Err(DataError::InvalidId(_))));
+        let mut asset = Asset::new(None, "BASF AG", None, None, None);
+        asset.id = Some(i64::MAX as u64 + 1);
+        let result = db.update_asset(&asset);
+        assert!(matches!(result, Err(DataError::InvalidId(_))));
+    }
+
+    #[test]
+    fn search_assets_sqlite() {
+        let mut db = SqliteDB::create(":memory:").unwrap();
+        for name in &["Deutsche Bank AG", "Bank of America", "100% Solar"] {
+            db.insert_asset(&Asset::new(None, name, None, None, None))
+                .unwrap();
+        }
+        let result = db.search_assets("BANK", 10).unwrap();
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].name, "Bank of America");
+        // wildcards in queries are matched literally
+        let result = db.search_assets("%", 10).unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].name, "100% Solar");
+        // case folding of ASCII characters only, as in the other data handlers
+        db.insert_asset(&Asset::new(None, "Ökoworld AG", None, None, None))
+            .unwrap();
+        assert!(db.search_assets("ökoworld", 10).unwrap().is_empty());
+        assert_eq!(db.search_assets("ÖKOWORLD", 10).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn asset_tags_sqlite() {
+        let mut db = SqliteDB::create(":memory:").unwrap();
+        let mut solar = Asset::new(None, "Photon Energy", None, None, None);
+        solar.tags = vec!["energy".to_string(), "esg".to_string()];
+        let solar_id = db.insert_asset(&solar).unwrap();
+        let basf_id = db
+            .insert_asset(&Asset::new(None, "BASF AG", None, None, None))
+            .unwrap();
+        assert_eq!(db.get_asset_by_id(solar_id).unwrap().tags, solar.tags);
+        assert!(db.get_asset_by_id(basf_id).unwrap().tags.is_empty());
+
+        let mut basf = db.get_asset_by_id(basf_id).unwrap();
+        basf.tags.push("chemicals".to_string());
+        db.update_asset(&basf).unwrap();
+        let result = db.get_assets_by_tag("chemicals").unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].id, Some(basf_id));
+        assert_eq!(db.get_assets_by_tag("esg").unwrap()[0].id, Some(solar_id));
+    }
+
+    #[test]
+    fn stream_transactions_in_pages_sqlite() {
+        use crate::currency::Currency;
+        use crate::fixed_income::CashFlow;
+        use crate::transaction::TransactionType;
+        use std::str::FromStr;
+
+        let mut db = SqliteDB::create(":memory:").unwrap();
+        db.set_page_size(2);
+        let eur = Currency::from_str("EUR").unwrap();
+        for i in 1..=5 {
+            let transaction = Transaction::new(
+                TransactionType::Cash,
+                CashFlow::new(100. * i as f64, eur, NaiveDate::from_ymd(2020, 1, 15)),
+            );
+            db.insert_transaction(&transaction).unwrap();
+        }
+        let mut amounts = Vec::new();
+        db.for_each_transaction(&mut |t| {
+            amounts.push(t.cash_flow.amount.value());
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(amounts, vec![100., 200., 300., 400., 500.]);
+
+        // an error in the second page stops the iteration
+        let mut count = 0;
+        let result = db.for_each_transaction(&mut |t| {
+            if t.id == Some(3) {
+                return Err(DataError::InvalidTransaction("stop".to_string()));
+            }
+            count += 1;
+            Ok(())
+        });
+        assert!(result.is_err());
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn transaction_tags_sqlite() {
+        use crate::currency::Currency;
+        use crate::fixed_income::CashFlow;
+        use crate::transaction::TransactionType;
+        use std::str::FromStr;
+
+        let mut db = SqliteDB::create(":memory:").unwrap();
+        let eur = Currency::from_str("EUR").unwrap();
+        let transaction = Transaction {
+            note: Some("Employer stock plan".to_string()),
+            tags: vec![" ESPP".to_string(), "Employer ".to_string()],
+            ..Transaction::new(
+                TransactionType::Cash,
+                CashFlow::new(1000., eur, NaiveDate::from_ymd(2020, 1, 15)),
+            )
+        };
+        let id = db.insert_transaction(&transaction).unwrap();
+        let mut stored = db.get_transaction_by_id(id).unwrap();
+        assert_eq!(stored.tags, vec!["espp", "employer"]);
+        assert_eq!(stored.note, transaction.note);
+
+        stored.tags.push("Tax".to_string());
+        db.update_transaction(&stored).unwrap();
+        let result = db.get_transactions_by_tag("tax").unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].tags, vec!["espp", "employer", "tax"]);
+        assert_eq!(db.search_transactions("STOCK").unwrap().len(), 1);
+        assert!(db.search_transactions("bond").unwrap().is_empty());
+    }
+
+    #[test]
+    fn transfer_legs_sqlite() {
+        use crate::currency::Currency;
+        use crate::fixed_income::CashFlow;
+        use crate::transaction::TransactionType;
+        use std::str::FromStr;
+
+        let mut db = SqliteDB::create(":memory:").unwrap();
+        let eur = Currency::from_str("EUR").unwrap();
+        let leg = |transaction_ref, amount, account| Transaction {
+            account: Some(account),
+            ..Transaction::new(
+                TransactionType::Transfer { transaction_ref },
+                CashFlow::new(amount, eur, NaiveDate::from_ymd(2020, 1, 15)),
+            )
+        };
+        let out_id = db.insert_transaction(&leg(None, -250., 1)).unwrap();
+        assert!(db.insert_transaction(&leg(Some(out_id), 25., 2)).is_err());
+        let in_id = db.insert_transaction(&leg(Some(out_id), 250., 2)).unwrap();
+        let stored = db.get_transaction_by_id(in_id).unwrap();
+        assert_eq!(stored.account, Some(2));
+        match stored.transaction_type {
+            TransactionType::Transfer { transaction_ref } => {
+                assert_eq!(transaction_ref, Some(out_id))
+            }
+            _ => panic!("expected transfer"),
+        }
+    }
+
+    #[test]
+    fn transaction_audit_time_stamps_sqlite() {
+        use crate::currency::Currency;
+        use crate::fixed_income::CashFlow;
+        use crate::helpers::make_time;
+        use crate::transaction::TransactionType;
+        use chrono::{DateTime, Duration, Utc};
+        use std::cell::Cell;
+        use std::rc::Rc;
+        use std::str::FromStr;
+
+        struct FakeClock {
+            time: Rc<Cell<DateTime<Utc>>>,
+        }
+
+        impl Clock for FakeClock {
+            fn now(&self) -> DateTime<Utc> {
+                self.time.get()
+            }
+        }
+
+        let time = Rc::new(Cell::new(make_time(2020, 1, 15, 10, 0, 0).unwrap()));
+        let mut db = SqliteDB::create(":memory:").unwrap();
+        db.set_clock(Box::new(FakeClock { time: time.clone() }));
+        let eur = Currency::from_str("EUR").unwrap();
+        let transaction = Transaction::new(
+            TransactionType::Cash,
+            CashFlow::new(1000., eur, NaiveDate::from_ymd(2020, 1, 15)),
+        );
+        let id = db.insert_transaction(&transaction).unwrap();
+        let created = time.get();
+        let mut transaction = db.get_transaction_by_id(id).unwrap();
+        assert_eq!(transaction.created_at, Some(created));
+        assert_eq!(transaction.modified_at, Some(created));
+
+        time.set(created + Duration::hours(2));
+        transaction.note = Some("initial deposit".to_string());
+        transaction.created_at = None;
+        db.update_transaction(&transaction).unwrap();
+        let transaction = db.get_all_transactions().unwrap().pop().unwrap();
+        assert_eq!(transaction.created_at, Some(created));
+        assert_eq!(transaction.modified_at, Some(created + Duration::hours(2)));
+    }
+
+    #[test]
+    fn withholding_tax_sqlite() {
+        use crate::currency::Currency;
+        use crate::fixed_income::CashFlow;
+        use crate::transaction::{TaxKind, TransactionType};
+        use std::str::FromStr;
+
+        let mut db = SqliteDB::create(":memory:").unwrap();
+        let asset_id = db
+            .insert_asset(&Asset::new(None, "Apple", None, None, None))
+            .unwrap();
+        let usd = Currency::from_str("USD").unwrap();
+        let date = NaiveDate::from_ymd(2021, 2, 11);
+        let dividend_id = db
+            .insert_transaction(&Transaction::new(
+                TransactionType::Dividend { asset_id },
+                CashFlow::new(100., usd, date),
+            ))
+            .unwrap();
+        for kind in &[TaxKind::Withholding, TaxKind::Domestic] {
+            let tax = TransactionType::Tax {
+                transaction_ref: Some(dividend_id),
+                kind: *kind,
+            };
+            let id = db
+                .insert_transaction(&Transaction::new(tax, CashFlow::new(-15., usd, date)))
+                .unwrap();
+            match db.get_transaction_by_id(id).unwrap().transaction_type {
+                TransactionType::Tax {
+                    transaction_ref,
+                    kind: stored_kind,
+                } => {
+                    assert_eq!(transaction_ref, Some(dividend_id));
+                    assert_eq!(stored_kind, *kind);
+                }
+                _ => panic!("expected tax"),
+            }
+        }
+    }
+}
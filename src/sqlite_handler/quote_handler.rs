@@ -1,9 +1,9 @@
 ///! Implementation for quote handler with Sqlite3 database as backend
-use super::SqliteDB;
+use super::{id_from_row, SqliteDB};
 use crate::currency::Currency;
 use crate::data_handler::{DataError, QuoteHandler};
-use crate::helpers::to_time;
-use crate::quote::{MarketDataSource, Quote, Ticker};
+use crate::helpers::{from_db_id, to_db_id, to_time};
+use crate::quote::{MarketDataSource, Quote, Ticker, UpdatePeriod};
 use chrono::{DateTime, Utc};
 use rusqlite::{params, NO_PARAMS};
 use std::str::FromStr;
@@ -11,7 +11,7 @@ use std::str::FromStr;
 /// Sqlite implementation of quote handler
 impl QuoteHandler for SqliteDB {
     // insert, get, update and delete for market data sources
-    fn insert_md_source(&mut self, source: &MarketDataSource) -> Result<usize, DataError> {
+    fn insert_md_source(&mut self, source: &MarketDataSource) -> Result<u64, DataError> {
         self.conn
             .execute(
                 "INSERT INTO market_data_sources (name) VALUES (?1)",
@@ -24,20 +24,17 @@ impl QuoteHandler for SqliteDB {
                 "SELECT id FROM market_data_sources
         WHERE name=?;",
                 params![source.name],
-                |row| {
-                    let id: i64 = row.get(0)?;
-                    Ok(id as usize)
-                },
+                |row| id_from_row(row, 0),
             )
             .map_err(|e| DataError::NotFound(e.to_string()))?;
         Ok(id)
     }
-    fn get_md_source_by_id(&mut self, id: usize) -> Result<MarketDataSource, DataError> {
+    fn get_md_source_by_id(&mut self, id: u64) -> Result<MarketDataSource, DataError> {
         let source = self
             .conn
             .query_row(
                 "SELECT name FROM market_data_sources WHERE id=?",
-                params![id as i64],
+                params![to_db_id(id)?],
                 |row| {
                     Ok(MarketDataSource {
                         id: Some(id),
@@ -55,8 +52,7 @@ impl QuoteHandler for SqliteDB {
             .map_err(|e| DataError::NotFound(e.to_string()))?;
         let sources_map = stmt
             .query_map(NO_PARAMS, |row| {
-                let id: i64 = row.get(0)?;
-                let id = Some(id as usize);
+                let id = Some(id_from_row(row, 0)?);
                 Ok(MarketDataSource {
                     id,
                     name: row.get(1)?,
@@ -75,7 +71,7 @@ impl QuoteHandler for SqliteDB {
                 "not yet stored to database".to_string(),
             ));
         }
-        let id = source.id.unwrap() as i64;
+        let id = to_db_id(source.id.unwrap())?;
         self.conn
             .execute(
                 "UPDATE market_data_sources SET name=?2 WHERE id=?1",
@@ -84,25 +80,28 @@ impl QuoteHandler for SqliteDB {
             .map_err(|e| DataError::InsertFailed(e.to_string()))?;
         Ok(())
     }
-    fn delete_md_source(&mut self, id: usize) -> Result<(), DataError> {
+    fn delete_md_source(&mut self, id: u64) -> Result<(), DataError> {
         self.conn
             .execute(
                 "DELETE FROM market_data_sources WHERE id=?1;",
-                params![id as i64],
+                params![to_db_id(id)?],
             )
             .map_err(|e| DataError::InsertFailed(e.to_string()))?;
         Ok(())
     }
 
     // insert, get, update and delete for market data sources
-    fn insert_ticker(&mut self, ticker: &Ticker) -> Result<usize, DataError> {
+    fn insert_ticker(&mut self, ticker: &Ticker) -> Result<u64, DataError> {
         self.conn
             .execute(
-                "INSERT INTO ticker (name, source_id, currency) VALUES (?, ?, ?)",
+                "INSERT INTO ticker (name, asset_id, source_id, currency, update_policy)
+                VALUES (?, ?, ?, ?, ?)",
                 params![
                     ticker.name,
-                    ticker.source as i64,
-                    ticker.currency.to_string()
+                    to_db_id(ticker.asset)?,
+                    to_db_id(ticker.source)?,
+                    ticker.currency.to_string(),
+                    ticker.update_policy.to_string()
                 ],
             )
             .map_err(|e| DataError::InsertFailed(e.to_string()))?;
@@ -111,26 +110,25 @@ impl QuoteHandler for SqliteDB {
             .query_row(
                 "SELECT id FROM ticker
         WHERE name=? AND source_id=?;",
-                params![ticker.name, ticker.source as i64],
-                |row| {
-                    let id: i64 = row.get(0)?;
-                    Ok(id as usize)
-                },
+                params![ticker.name, to_db_id(ticker.source)?],
+                |row| id_from_row(row, 0),
             )
             .map_err(|e| DataError::NotFound(e.to_string()))?;
         Ok(id)
     }
-    fn get_ticker_by_id(&mut self, id: usize) -> Result<Ticker, DataError> {
-        let (name, source, currency) = self
+    fn get_ticker_by_id(&mut self, id: u64) -> Result<Ticker, DataError> {
+        let (name, asset, source, currency, update_policy) = self
             .conn
             .query_row(
-                "SELECT name, source_id, currency FROM ticker WHERE id=?;",
-                params![id as i64],
+                "SELECT name, asset_id, source_id, currency, update_policy FROM ticker WHERE id=?;",
+                params![to_db_id(id)?],
                 |row| {
                     let name: String = row.get(0)?;
-                    let source: i64 = row.get(1)?;
-                    let currency: String = row.get(2)?;
-                    Ok((name, source, currency))
+                    let asset: i64 = row.get(1)?;
+                    let source: i64 = row.get(2)?;
+                    let currency: String = row.get(3)?;
+                    let update_policy: String = row.get(4)?;
+                    Ok((name, asset, source, currency, update_policy))
                 },
             )
             .map_err(|e| DataError::NotFound(e.to_string()))?;
@@ -139,33 +137,41 @@ impl QuoteHandler for SqliteDB {
         Ok(Ticker {
             id: Some(id),
             name,
-            source: source as usize,
+            asset: from_db_id(asset)?,
+            source: from_db_id(source)?,
             currency,
+            update_policy: UpdatePeriod::from_str(&update_policy)?,
         })
     }
-    fn get_all_ticker_for_source(&mut self, source: usize) -> Result<Vec<Ticker>, DataError> {
+    fn get_all_ticker_for_source(&mut self, source: u64) -> Result<Vec<Ticker>, DataError> {
         let mut stmt = self
             .conn
-            .prepare("SELECT id, name, currency FROM ticker WHERE source_id=?;")
+            .prepare(
+                "SELECT id, name, asset_id, currency, update_policy FROM ticker WHERE source_id=?;",
+            )
             .map_err(|e| DataError::NotFound(e.to_string()))?;
         let ticker_map = stmt
-            .query_map(params![source as i64], |row| {
+            .query_map(params![to_db_id(source)?], |row| {
                 let id: i64 = row.get(0)?;
                 let name: String = row.get(1)?;
-                let currency: String = row.get(2)?;
-                Ok((id, name, currency))
+                let asset: i64 = row.get(2)?;
+                let currency: String = row.get(3)?;
+                let update_policy: String = row.get(4)?;
+                Ok((id, name, asset, currency, update_policy))
             })
             .map_err(|e| DataError::NotFound(e.to_string()))?;
         let mut all_ticker = Vec::new();
         for ticker in ticker_map {
-            let (id, name, currency) = ticker.unwrap();
+            let (id, name, asset, currency, update_policy) = ticker.unwrap();
             let currency =
                 Currency::from_str(&currency).map_err(|e| DataError::NotFound(e.to_string()))?;
             all_ticker.push(Ticker {
-                id: Some(id as usize),
+                id: Some(from_db_id(id)?),
                 name,
+                asset: from_db_id(asset)?,
                 source,
                 currency,
+                update_policy: UpdatePeriod::from_str(&update_policy)?,
             });
         }
         Ok(all_ticker)
@@ -176,35 +182,37 @@ impl QuoteHandler for SqliteDB {
                 "not yet stored to database".to_string(),
             ));
         }
-        let id = ticker.id.unwrap() as i64;
+        let id = to_db_id(ticker.id.unwrap())?;
         self.conn
             .execute(
-                "UPDATE ticker SET name=?2, source_id=?3, currency=?4
-                WHERE id=?1",
+                "UPDATE ticker SET name=?2, asset_id=?3, source_id=?4, currency=?5,
+                update_policy=?6 WHERE id=?1",
                 params![
                     id,
                     ticker.name,
-                    ticker.source as i64,
-                    ticker.currency.to_string()
+                    to_db_id(ticker.asset)?,
+                    to_db_id(ticker.source)?,
+                    ticker.currency.to_string(),
+                    ticker.update_policy.to_string()
                 ],
             )
             .map_err(|e| DataError::InsertFailed(e.to_string()))?;
         Ok(())
     }
-    fn delete_ticker(&mut self, id: usize) -> Result<(), DataError> {
+    fn delete_ticker(&mut self, id: u64) -> Result<(), DataError> {
         self.conn
-            .execute("DELETE FROM ticker WHERE id=?1;", params![id as i64])
+            .execute("DELETE FROM ticker WHERE id=?1;", params![to_db_id(id)?])
             .map_err(|e| DataError::InsertFailed(e.to_string()))?;
         Ok(())
     }
 
     // insert, get, update and delete for market data sources
-    fn insert_quote(&mut self, quote: &Quote) -> Result<usize, DataError> {
+    fn insert_quote(&mut self, quote: &Quote) -> Result<u64, DataError> {
         self.conn
             .execute(
                 "INSERT INTO quotes (ticker_id, price, time, volume) VALUES (?, ?, ?, ?)",
                 params![
-                    quote.ticker as i64,
+                    to_db_id(quote.ticker)?,
                     quote.price,
                     quote.time.to_rfc3339(),
                     quote.volume
@@ -214,15 +222,14 @@ impl QuoteHandler for SqliteDB {
         let id = self
             .conn
             .query_row("SELECT last_insert_rowid();", NO_PARAMS, |row| {
-                let id: i64 = row.get(0)?;
-                Ok(id as usize)
+                id_from_row(row, 0)
             })
             .map_err(|e| DataError::NotFound(e.to_string()))?;
         Ok(id)
     }
     fn get_last_quote_before(
         &mut self,
-        ticker: usize,
+        ticker: u64,
         time: DateTime<Utc>,
     ) -> Result<(Quote, Currency), DataError> {
         let time = time.to_rfc3339();
@@ -232,7 +239,7 @@ impl QuoteHandler for SqliteDB {
                 "SELECT q.id, q.price, q.time, q.volume, t.currency 
                 FROM quotes q, ticker t 
                 WHERE t.id=? AND t.id=q.ticker_id AND q.time<=?;",
-                params![ticker as i64, time],
+                params![to_db_id(ticker)?, time],
                 |row| {
                     let id: i64 = row.get(0)?;
                     let price: f64 = row.get(1)?;
@@ -249,7 +256,7 @@ impl QuoteHandler for SqliteDB {
         let time = to_time(&time).map_err(|e| DataError::NotFound(e.to_string()))?;
         Ok((
             Quote {
-                id: Some(id as usize),
+                id: Some(from_db_id(id)?),
                 ticker,
                 price,
                 time,
@@ -258,7 +265,47 @@ impl QuoteHandler for SqliteDB {
             currency,
         ))
     }
-    fn get_all_quotes_for_ticker(&mut self, ticker_id: usize) -> Result<Vec<Quote>, DataError> {
+    fn get_last_quote_before_by_id(
+        &mut self,
+        asset_id: u64,
+        time: DateTime<Utc>,
+    ) -> Result<(Quote, Currency), DataError> {
+        let time = time.to_rfc3339();
+        let row = self
+            .conn
+            .query_row(
+                "SELECT q.id, q.ticker_id, q.price, q.time, q.volume, t.currency 
+                FROM quotes q, ticker t 
+                WHERE t.asset_id=? AND t.id=q.ticker_id AND q.time<=?
+                ORDER BY q.time DESC LIMIT 1;",
+                params![to_db_id(asset_id)?, time],
+                |row| {
+                    let id: i64 = row.get(0)?;
+                    let ticker: i64 = row.get(1)?;
+                    let price: f64 = row.get(2)?;
+                    let time: String = row.get(3)?;
+                    let volume: Option<f64> = row.get(4)?;
+                    let currency: String = row.get(5)?;
+                    Ok((id, ticker, price, time, volume, currency))
+                },
+            )
+            .map_err(|e| DataError::NotFound(e.to_string()))?;
+        let (id, ticker, price, time, volume, currency) = row;
+        let currency =
+            Currency::from_str(&currency).map_err(|e| DataError::NotFound(e.to_string()))?;
+        let time = to_time(&time)?;
+        Ok((
+            Quote {
+                id: Some(from_db_id(id)?),
+                ticker: from_db_id(ticker)?,
+                price,
+                time,
+                volume,
+            },
+            currency,
+        ))
+    }
+    fn get_all_quotes_for_ticker(&mut self, ticker_id: u64) -> Result<Vec<Quote>, DataError> {
         let mut stmt = self
             .conn
             .prepare(
@@ -267,7 +314,7 @@ impl QuoteHandler for SqliteDB {
             )
             .map_err(|e| DataError::NotFound(e.to_string()))?;
         let quotes_map = stmt
-            .query_map(params![ticker_id as i64], |row| {
+            .query_map(params![to_db_id(ticker_id)?], |row| {
                 let id: i64 = row.get(0)?;
                 let price: f64 = row.get(1)?;
                 let time: String = row.get(2)?;
@@ -280,7 +327,7 @@ impl QuoteHandler for SqliteDB {
             let (id, price, time, volume) = quote.unwrap();
             let time = to_time(&time)?;
             quotes.push(Quote {
-                id: Some(id as usize),
+                id: Some(from_db_id(id)?),
                 ticker: ticker_id,
                 price,
                 time,
@@ -296,14 +343,14 @@ impl QuoteHandler for SqliteDB {
                 "not yet stored to database".to_string(),
             ));
         }
-        let id = quote.id.unwrap() as i64;
+        let id = to_db_id(quote.id.unwrap())?;
         self.conn
             .execute(
                 "UPDATE quotes SET ticker_id=?2, price=?2, time=?4, volume=?5
                 WHERE id=?1",
                 params![
                     id,
-                    quote.ticker as i64,
+                    to_db_id(quote.ticker)?,
                     quote.price,
                     quote.time.to_rfc3339(),
                     quote.volume
@@ -312,9 +359,9 @@ impl QuoteHandler for SqliteDB {
             .map_err(|e| DataError::InsertFailed(e.to_string()))?;
         Ok(())
     }
-    fn delete_quote(&mut self, id: usize) -> Result<(), DataError> {
+    fn delete_quote(&mut self, id: u64) -> Result<(), DataError> {
         self.conn
-            .execute("DELETE FROM quotes WHERE id=?1;", params![id as i64])
+            .execute("DELETE FROM quotes WHERE id=?1;", params![to_db_id(id)?])
             .map_err(|e| DataError::InsertFailed(e.to_string()))?;
         Ok(())
     }
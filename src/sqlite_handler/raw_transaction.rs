@@ -1,5 +1,5 @@
 use crate::data_handler::DataError;
-use crate::transaction::{Transaction, TransactionType};
+use crate::transaction::{TaxKind, Transaction, TransactionType};
 
 use crate::helpers::*;
 
@@ -13,6 +13,12 @@ pub struct RawTransaction {
     pub related_trans: Option<i64>,
     pub position: Option<f64>,
     pub note: Option<String>,
+    /// Normalized tags as JSON, no tags are stored as NULL
+    pub tags: Option<String>,
+    pub account: Option<i64>,
+    /// Audit time stamps in RFC 3339 format
+    pub created_at: Option<String>,
+    pub modified_at: Option<String>,
 }
 
 /// Raw transaction type constants
@@ -21,38 +27,52 @@ const ASSET: &str = "a";
 const DIVIDEND: &str = "d";
 const INTEREST: &str = "i";
 const TAX: &str = "t";
+const WITHHOLDING_TAX: &str = "w";
 const FEE: &str = "f";
+const TRANSFER: &str = "x";
 
 impl RawTransaction {
     pub fn to_transaction(&self) -> Result<Transaction, DataError> {
-        let id = i64_to_usize(self.id);
+        let id = i64_to_id(self.id)?;
         let cash_flow = raw_to_cash_flow(self.cash_amount, &self.cash_currency, &self.cash_date)?;
         let note = self.note.clone();
+        let tags = match &self.tags {
+            Some(json) => serde_json::from_str(json)?,
+            None => Vec::new(),
+        };
         let transaction_type = match self.trans_type.as_str() {
             CASH => TransactionType::Cash,
             ASSET => TransactionType::Asset {
-                asset_id: self.asset.ok_or(DataError::InvalidTransaction(
+                asset_id: from_db_id(self.asset.ok_or(DataError::InvalidTransaction(
                     "missing asset id".to_string(),
-                ))? as usize,
+                ))?)?,
                 position: self.position.ok_or(DataError::InvalidTransaction(
                     "missing position value".to_string(),
                 ))?,
             },
             DIVIDEND => TransactionType::Dividend {
-                asset_id: self.asset.ok_or(DataError::InvalidTransaction(
+                asset_id: from_db_id(self.asset.ok_or(DataError::InvalidTransaction(
                     "missing asset id".to_string(),
-                ))? as usize,
+                ))?)?,
             },
             INTEREST => TransactionType::Interest {
-                asset_id: self.asset.ok_or(DataError::InvalidTransaction(
+                asset_id: from_db_id(self.asset.ok_or(DataError::InvalidTransaction(
                     "missing asset id".to_string(),
-                ))? as usize,
+                ))?)?,
             },
             TAX => TransactionType::Tax {
-                transaction_ref: i64_to_usize(self.id),
+                transaction_ref: i64_to_id(self.related_trans)?,
+                kind: TaxKind::Domestic,
+            },
+            WITHHOLDING_TAX => TransactionType::Tax {
+                transaction_ref: i64_to_id(self.related_trans)?,
+                kind: TaxKind::Withholding,
             },
             FEE => TransactionType::Fee {
-                transaction_ref: i64_to_usize(self.id),
+                transaction_ref: i64_to_id(self.related_trans)?,
+            },
+            TRANSFER => TransactionType::Transfer {
+                transaction_ref: i64_to_id(self.related_trans)?,
             },
             unknown => {
                 return Err(DataError::InvalidTransaction(unknown.to_string()));
@@ -63,15 +83,25 @@ impl RawTransaction {
             transaction_type,
             cash_flow,
             note,
+            tags,
+            account: i64_to_id(self.account)?,
+            created_at: self.created_at.as_deref().map(to_time).transpose()?,
+            modified_at: self.modified_at.as_deref().map(to_time).transpose()?,
         })
     }
 
-    pub fn from_transaction(transaction: &Transaction) -> RawTransaction {
-        let id = usize_to_i64(transaction.id);
-        let cash_amount = transaction.cash_flow.amount.amount;
+    pub fn from_transaction(transaction: &Transaction) -> Result<RawTransaction, DataError> {
+        let id = id_to_i64(transaction.id)?;
+        let cash_amount = transaction.cash_flow.amount.value();
         let cash_currency = transaction.cash_flow.amount.currency.to_string();
         let cash_date = transaction.cash_flow.date.format("%Y-%m-%d").to_string();
         let note = transaction.note.clone();
+        let tags = transaction.normalized_tags();
+        let tags = if tags.is_empty() {
+            None
+        } else {
+            serde_json::to_string(&tags).ok()
+        };
         let mut raw_transaction = RawTransaction {
             id,
             trans_type: String::new(),
@@ -82,31 +112,45 @@ impl RawTransaction {
             related_trans: None,
             position: None,
             note,
+            tags,
+            account: id_to_i64(transaction.account)?,
+            created_at: transaction.created_at.map(|time| time.to_rfc3339()),
+            modified_at: transaction.modified_at.map(|time| time.to_rfc3339()),
         };
         match transaction.transaction_type {
             TransactionType::Cash => raw_transaction.trans_type = CASH.to_string(),
             TransactionType::Asset { asset_id, position } => {
                 raw_transaction.trans_type = ASSET.to_string();
-                raw_transaction.asset = Some(asset_id as i64);
+                raw_transaction.asset = Some(to_db_id(asset_id)?);
                 raw_transaction.position = Some(position);
             }
             TransactionType::Dividend { asset_id } => {
                 raw_transaction.trans_type = DIVIDEND.to_string();
-                raw_transaction.asset = Some(asset_id as i64);
+                raw_transaction.asset = Some(to_db_id(asset_id)?);
             }
             TransactionType::Interest { asset_id } => {
                 raw_transaction.trans_type = INTEREST.to_string();
-                raw_transaction.asset = Some(asset_id as i64);
+                raw_transaction.asset = Some(to_db_id(asset_id)?);
             }
-            TransactionType::Tax { transaction_ref } => {
-                raw_transaction.trans_type = TAX.to_string();
-                raw_transaction.related_trans = usize_to_i64(transaction_ref);
+            TransactionType::Tax {
+                transaction_ref,
+                kind,
+            } => {
+                raw_transaction.trans_type = match kind {
+                    TaxKind::Domestic => TAX.to_string(),
+                    TaxKind::Withholding => WITHHOLDING_TAX.to_string(),
+                };
+                raw_transaction.related_trans = id_to_i64(transaction_ref)?;
             }
             TransactionType::Fee { transaction_ref } => {
                 raw_transaction.trans_type = FEE.to_string();
-                raw_transaction.related_trans = usize_to_i64(transaction_ref);
+                raw_transaction.related_trans = id_to_i64(transaction_ref)?;
+            }
+            TransactionType::Transfer { transaction_ref } => {
+                raw_transaction.trans_type = TRANSFER.to_string();
+                raw_transaction.related_trans = id_to_i64(transaction_ref)?;
             }
         };
-        raw_transaction
+        Ok(raw_transaction)
     }
 }
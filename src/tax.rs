@@ -0,0 +1,208 @@
+//! Tax analysis of transactions. Sales at a loss are flagged as wash sales if units of
+//! the same asset are bought within a window around the sale, in which case (part of)
+//! the loss may not be deductible. Sold positions are matched with purchases by the
+//! FIFO principle. The analysis does not modify any transactions.
+
+use crate::pnl::{sell_fifo, Lot};
+use crate::transaction::{Transaction, TransactionType};
+use std::collections::{HashMap, VecDeque};
+
+/// Sale at a loss with purchases of the same asset within the wash sale window
+#[derive(Debug, Clone, PartialEq)]
+pub struct WashSaleFlag {
+    /// Id of the sale transaction
+    pub sale_id: Option<u64>,
+    pub asset_id: u64,
+    /// Number of units sold
+    pub units: f64,
+    /// Realized loss of the sale as a positive amount in the currency of the sale
+    pub loss: f64,
+    /// Ids of the purchases replacing the units sold
+    pub repurchase_ids: Vec<u64>,
+    /// Number of units bought as replacement, at most the number of units sold
+    pub replacement_units: f64,
+    /// Part of the loss that is disallowed, i.e. the loss pro rata to the replaced units
+    pub disallowed_loss: f64,
+}
+
+/// Sale at a loss found while matching lots
+struct LossSale<'a> {
+    sale: &'a Transaction,
+    asset_id: u64,
+    units: f64,
+    loss: f64,
+    /// Units sold per purchase id
+    sold_lots: HashMap<u64, f64>,
+}
+
+/// Detect wash sales in `transactions`, i.e. sales at a loss with purchases of the same asset
+/// within `window_days` calendar days before or after the sale. Purchases whose units are sold
+/// by the sale itself don't count as replacement, and each purchased unit replaces at most one
+/// sold unit, assigned to sales in chronological order. The disallowed loss is the loss times
+/// the fraction of sold units replaced. Purchases are identified by their transaction id,
+/// purchases without id are ignored as replacement. Sales exceeding the position are skipped.
+pub fn detect_wash_sales(transactions: &[Transaction], window_days: u32) -> Vec<WashSaleFlag> {
+    let mut sorted: Vec<&Transaction> = transactions.iter().collect();
+    sorted.sort_by_key(|t| (t.cash_flow.date, t.id));
+
+    let mut lots: HashMap<u64, VecDeque<Lot>> = HashMap::new();
+    let mut loss_sales = Vec::new();
+    for trans in &sorted {
+        if let TransactionType::Asset { asset_id, position } = trans.transaction_type {
+            if position == 0. {
+                continue;
+            }
+            let price = -trans.cash_flow.amount.value() / position;
+            let asset_lots = lots.entry(asset_id).or_default();
+            if position > 0. {
+                asset_lots.push_back(Lot {
+                    units: position,
+                    price,
+                    fx_rate: 1.,
+                    purchase_id: trans.id,
+                });
+                continue;
+            }
+            let mut gain = 0.;
+            let mut sold_lots = HashMap::new();
+            let sold = sell_fifo(asset_lots, asset_id, -position, |lot, units| {
+                gain += units * (price - lot.price);
+                if let Some(id) = lot.purchase_id {
+                    *sold_lots.entry(id).or_insert(0.) += units;
+                }
+            });
+            if sold.is_ok() && gain < 0. {
+                loss_sales.push(LossSale {
+                    sale: trans,
+                    asset_id,
+                    units: -position,
+                    loss: -gain,
+                    sold_lots,
+                });
+            }
+        }
+    }
+
+    // units of each purchase already used as replacement of an earlier sale
+    let mut replaced: HashMap<u64, f64> = HashMap::new();
+    let mut flags = Vec::new();
+    for loss_sale in loss_sales {
+        let sale_date = loss_sale.sale.cash_flow.date;
+        let mut replacement_units = 0.;
+        let mut repurchase_ids = Vec::new();
+        for trans in &sorted {
+            if replacement_units >= loss_sale.units {
+                break;
+            }
+            let (id, units) = match (trans.id, trans.transaction_type) {
+                (Some(id), TransactionType::Asset { asset_id, position })
+                    if asset_id == loss_sale.asset_id && position > 0. =>
+                {
+                    (id, position)
+                }
+                _ => continue,
+            };
+            let days = (trans.cash_flow.date - sale_date).num_days().abs();
+            if days > window_days as i64 {
+                continue;
+            }
+            let used = replaced.entry(id).or_insert(0.);
+            let available = units - *used - loss_sale.sold_lots.get(&id).copied().unwrap_or(0.);
+            if available <= 0. {
+                continue;
+            }
+            let units = available.min(loss_sale.units - replacement_units);
+            *used += units;
+            replacement_units += units;
+            repurchase_ids.push(id);
+        }
+        if !repurchase_ids.is_empty() {
+            flags.push(WashSaleFlag {
+                sale_id: loss_sale.sale.id,
+                asset_id: loss_sale.asset_id,
+                units: loss_sale.units,
+                loss: loss_sale.loss,
+                repurchase_ids,
+                replacement_units,
+                disallowed_loss: loss_sale.loss * replacement_units / loss_sale.units,
+            });
+        }
+    }
+    flags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::currency::Currency;
+    use crate::fixed_income::CashFlow;
+    use chrono::NaiveDate;
+    use std::str::FromStr;
+
+    fn trade(id: u64, units: f64, price: f64, date: NaiveDate) -> Transaction {
+        Transaction {
+            id: Some(id),
+            ..Transaction::new(
+                TransactionType::Asset {
+                    asset_id: 1,
+                    position: units,
+                },
+                CashFlow::new(-units * price, Currency::from_str("USD").unwrap(), date),
+            )
+        }
+    }
+
+    #[test]
+    fn wash_sale_with_repurchase_within_window() {
+        let sale_date = NaiveDate::from_ymd(2021, 6, 1);
+        let purchase = trade(1, 100., 50., NaiveDate::from_ymd(2021, 1, 4));
+        let sale = trade(2, -100., 40., sale_date);
+
+        // repurchase of 60 units 10 days later
+        let transactions = vec![
+            purchase.clone(),
+            sale.clone(),
+            trade(3, 60., 41., NaiveDate::from_ymd(2021, 6, 11)),
+        ];
+        let flags = detect_wash_sales(&transactions, 30);
+        assert_eq!(flags.len(), 1);
+        assert_eq!(flags[0].sale_id, Some(2));
+        assert_eq!(flags[0].asset_id, 1);
+        assert_eq!(flags[0].repurchase_ids, vec![3]);
+        assert_fuzzy_eq!(flags[0].loss, 1000., 1e-10);
+        assert_fuzzy_eq!(flags[0].replacement_units, 60., 1e-10);
+        assert_fuzzy_eq!(flags[0].disallowed_loss, 600., 1e-10);
+
+        // repurchase 40 days later is outside of the window
+        let transactions = vec![
+            purchase.clone(),
+            sale.clone(),
+            trade(3, 60., 41., NaiveDate::from_ymd(2021, 7, 11)),
+        ];
+        assert!(detect_wash_sales(&transactions, 30).is_empty());
+
+        // the units sold don't replace themselves, but additional units bought before do
+        let transactions = vec![
+            trade(1, 100., 50., NaiveDate::from_ymd(2021, 5, 20)),
+            trade(2, -100., 40., sale_date),
+        ];
+        assert!(detect_wash_sales(&transactions, 30).is_empty());
+        let transactions = vec![
+            purchase,
+            trade(3, 150., 45., NaiveDate::from_ymd(2021, 5, 20)),
+            sale,
+        ];
+        let flags = detect_wash_sales(&transactions, 30);
+        assert_eq!(flags.len(), 1);
+        assert_eq!(flags[0].repurchase_ids, vec![3]);
+        assert_fuzzy_eq!(flags[0].disallowed_loss, 1000., 1e-10);
+
+        // no flag for sales at a gain
+        let transactions = vec![
+            trade(1, 100., 50., NaiveDate::from_ymd(2021, 1, 4)),
+            trade(2, -100., 60., sale_date),
+            trade(3, 100., 59., NaiveDate::from_ymd(2021, 6, 11)),
+        ];
+        assert!(detect_wash_sales(&transactions, 30).is_empty());
+    }
+}
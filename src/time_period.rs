@@ -7,7 +7,8 @@ use std::fmt;
 use std::str::FromStr;
 
 use crate::calendar::{last_day_of_month, Calendar};
-use chrono::{Datelike, Duration, NaiveDate};
+use crate::day_adjust::DayAdjust;
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
 use serde::de::{self, Visitor};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::ops::{Add, AddAssign, Neg, Sub, SubAssign};
@@ -85,21 +86,24 @@ pub struct TimePeriod {
 /// Transform a string into a TimePeriod
 impl TimePeriod {
     /// Add time period to a given date.
-    /// The function call will panic is the resulting year is out
-    /// of the valid range or if not calendar is provided in case of BusinessDaily time periods
+    /// The function call will panic is the resulting year is out of the valid range.
+    /// Business days are counted in `cal`, which defaults to `Calendar::weekends_only`.
     pub fn add_to(&self, mut date: NaiveDate, cal: Option<&Calendar>) -> NaiveDate {
         match self.unit {
             TimePeriodUnit::Daily => date + Duration::days(self.num as i64),
             TimePeriodUnit::BusinessDaily => {
-                let is_neg = self.num < 0;
-                let n = self.num.abs();
-                let cal = cal.unwrap();
-                for _ in 0..n {
-                    date = if is_neg {
-                        cal.prev_bday(date)
-                    } else {
-                        cal.next_bday(date)
-                    };
+                // without calendar, step over weekends directly instead of
+                // calculating a weekends-only calendar on each call
+                let is_business_day = |date: NaiveDate| match cal {
+                    Some(cal) => cal.is_business_day(date),
+                    None => !matches!(date.weekday(), Weekday::Sat | Weekday::Sun),
+                };
+                let step = Duration::days(self.num.signum() as i64);
+                for _ in 0..self.num.abs() {
+                    date += step;
+                    while !is_business_day(date) {
+                        date += step;
+                    }
                 }
                 date
             }
@@ -129,8 +133,11 @@ impl TimePeriod {
                 }
                 NaiveDate::from_ymd(year, month as u32, day)
             }
+            // February 29 is moved to February 28 in years which are not leap years
             TimePeriodUnit::Annual => {
-                NaiveDate::from_ymd(date.year() + self.num, date.month(), date.day())
+                let year = date.year() + self.num;
+                let day = std::cmp::min(date.day(), last_day_of_month(year, date.month()));
+                NaiveDate::from_ymd(year, date.month(), day)
             }
         }
     }
@@ -148,6 +155,20 @@ impl TimePeriod {
         }
     }
 
+    /// Multiply the time period by `n`, e.g. to get "12M" from "1M".
+    /// Returns `None` if the number of periods overflows.
+    pub fn checked_mul(&self, n: i32) -> Option<TimePeriod> {
+        Some(TimePeriod {
+            num: self.num.checked_mul(n)?,
+            unit: self.unit,
+        })
+    }
+
+    /// Returns true if adding the time period moves dates forward in time
+    pub fn is_positive(&self) -> bool {
+        self.num > 0
+    }
+
     /// Returns the frequency per year, if this is possible,
     /// otherwise return error
     pub fn frequency(&self) -> Result<u16, TimePeriodError> {
@@ -173,6 +194,24 @@ impl TimePeriod {
     }
 }
 
+/// Generate pillar dates, e.g. of a curve, by adding each tenor to the `spot` date and
+/// adjusting the result to a business day according to `bd_conv`. Tenors are always added
+/// to the original spot date and not to the previous pillar, i.e. pillars do not drift
+/// by month end truncations. For example, adding "1M" twelve times to January 31, 2020
+/// is truncated at February 29 and ends up at January 29, 2021, while the "12M" pillar
+/// falls on January 31, 2021 like the "1Y" pillar (before adjustment).
+pub fn generate_pillar_dates(
+    spot: NaiveDate,
+    tenors: &[TimePeriod],
+    calendar: &Calendar,
+    bd_conv: DayAdjust,
+) -> Vec<NaiveDate> {
+    tenors
+        .iter()
+        .map(|tenor| bd_conv.adjust_date(tenor.add_to(spot, Some(calendar)), calendar))
+        .collect()
+}
+
 impl fmt::Display for TimePeriod {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}{}", self.num, self.unit)
@@ -408,7 +447,7 @@ mod tests {
             Holiday::WeekDay(Weekday::Sun),
         ];
 
-        let cal = Calendar::calc_calendar(&holiday_rules, 2019, 2020);
+        let cal = Calendar::calc_calendar(&holiday_rules, 2019, 2020).unwrap();
         let bdaily1 = TimePeriod::from_str("1B").unwrap();
         let bdaily2 = TimePeriod::from_str("2B").unwrap();
         let bdaily_1 = TimePeriod::from_str("-1B").unwrap();
@@ -444,6 +483,21 @@ mod tests {
             bdaily_1.add_to(date, Some(&cal)),
             NaiveDate::from_ymd(2019, 11, 22)
         );
+
+        // without calendar, weekends are the only non-business days
+        let date = NaiveDate::from_ymd(2019, 11, 20);
+        assert_eq!(
+            bdaily1.add_to(date, None),
+            NaiveDate::from_ymd(2019, 11, 21)
+        );
+        assert_eq!(
+            bdaily2.add_to(date, None),
+            NaiveDate::from_ymd(2019, 11, 22)
+        );
+        assert_eq!(
+            bdaily2.add_to(NaiveDate::from_ymd(2019, 11, 22), None),
+            NaiveDate::from_ymd(2019, 11, 26)
+        );
     }
 
     #[test]
@@ -487,4 +541,51 @@ mod tests {
         new_end -= period_6m;
         assert_eq!(start, new_end);
     }
+
+    #[test]
+    fn pillar_dates_do_not_drift() {
+        let d = NaiveDate::from_ymd;
+        let cal = Calendar::calc_calendar(
+            &[
+                Holiday::WeekDay(Weekday::Sat),
+                Holiday::WeekDay(Weekday::Sun),
+            ],
+            2020,
+            2021,
+        )
+        .unwrap();
+        let spot = d(2020, 1, 31);
+        let one_month = TimePeriod::from_str("1M").unwrap();
+        let one_year = TimePeriod::from_str("1Y").unwrap();
+        let monthly: Vec<TimePeriod> = (1..=12)
+            .map(|n| one_month.checked_mul(n).unwrap())
+            .collect();
+        assert_eq!(monthly[11], TimePeriod::from_str("12M").unwrap());
+
+        let pillars = generate_pillar_dates(spot, &monthly, &cal, DayAdjust::None);
+        assert_eq!(pillars[0], d(2020, 2, 29));
+        assert_eq!(pillars[1], d(2020, 3, 31));
+        assert_eq!(pillars[11], d(2021, 1, 31));
+        let annual = generate_pillar_dates(spot, &[one_year], &cal, DayAdjust::None);
+        assert_eq!(pillars[11], annual[0]);
+        // adding 1M cumulatively drifts to the 29th
+        let cumulative = (0..12).fold(spot, |date, _| one_month.add_to(date, None));
+        assert_eq!(cumulative, d(2021, 1, 29));
+
+        // Saturday, February 29 2020 and Sunday, January 31 2021 stay within the month
+        let pillars = generate_pillar_dates(spot, &monthly, &cal, DayAdjust::Modified);
+        assert_eq!(pillars[0], d(2020, 2, 28));
+        assert_eq!(pillars[11], d(2021, 1, 29));
+        let annual = generate_pillar_dates(spot, &[one_year], &cal, DayAdjust::Modified);
+        assert_eq!(pillars[11], annual[0]);
+
+        assert!(TimePeriod::from_str("2Y")
+            .unwrap()
+            .checked_mul(i32::MAX)
+            .is_none());
+        assert_eq!(
+            TimePeriod::from_str("-3M").unwrap().checked_mul(-2),
+            Some(TimePeriod::from_str("6M").unwrap())
+        );
+    }
 }
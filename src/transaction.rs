@@ -1,32 +1,119 @@
-use crate::data_handler::{DataError, DataItem};
+use crate::calendar::Calendar;
+use crate::currency::Currency;
+use crate::data_handler::{DataError, DataItem, QuoteHandler};
 ///! Implementation of basic transaction types
-use crate::fixed_income::CashFlow;
+use crate::fixed_income::{amount_from_f64, AmountSum, CashFlow};
+use crate::fx_rates::get_fx_rate;
+use crate::helpers::end_of_day;
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 /// Type of transaction
 #[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub enum TransactionType {
     Cash,
-    Asset { asset_id: usize, position: f64 },
-    Dividend { asset_id: usize },
-    Interest { asset_id: usize },
-    Tax { transaction_ref: Option<usize> },
-    Fee { transaction_ref: Option<usize> },
+    Asset {
+        asset_id: u64,
+        position: f64,
+    },
+    Dividend {
+        asset_id: u64,
+    },
+    Interest {
+        asset_id: u64,
+    },
+    Tax {
+        transaction_ref: Option<u64>,
+        #[serde(default)]
+        kind: TaxKind,
+    },
+    Fee {
+        transaction_ref: Option<u64>,
+    },
+    /// One leg of a cash transfer between accounts, the leg inserted second refers
+    /// to the first one
+    Transfer {
+        transaction_ref: Option<u64>,
+    },
+}
+
+/// Kind of a tax payment
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+pub enum TaxKind {
+    /// Tax levied in the country of residence
+    #[default]
+    Domestic,
+    /// Tax withheld at source by the country a dividend or interest payment comes from,
+    /// i.e. the tax transaction refers to that payment
+    Withholding,
+}
+
+/// Kind of a transaction, i.e. its type without any related data
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TransactionKind {
+    Cash,
+    Asset,
+    Dividend,
+    Interest,
+    Tax,
+    Fee,
+    Transfer,
+}
+
+impl TransactionType {
+    /// Kind of the transaction type
+    pub fn kind(&self) -> TransactionKind {
+        match self {
+            TransactionType::Cash => TransactionKind::Cash,
+            TransactionType::Asset { .. } => TransactionKind::Asset,
+            TransactionType::Dividend { .. } => TransactionKind::Dividend,
+            TransactionType::Interest { .. } => TransactionKind::Interest,
+            TransactionType::Tax { .. } => TransactionKind::Tax,
+            TransactionType::Fee { .. } => TransactionKind::Fee,
+            TransactionType::Transfer { .. } => TransactionKind::Transfer,
+        }
+    }
+
+    /// Id of the asset the transaction relates to, if any
+    pub fn asset_id(&self) -> Option<u64> {
+        match self {
+            TransactionType::Asset { asset_id, .. }
+            | TransactionType::Dividend { asset_id }
+            | TransactionType::Interest { asset_id } => Some(*asset_id),
+            _ => None,
+        }
+    }
 }
 
 /// Basic transaction data
+/// Optional fields may be omitted in serialized transactions, such that payloads
+/// written before a field has been added still could be parsed.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Transaction {
     // Before a transaction is stored to a database, the id maybe None
-    pub id: Option<usize>,
+    #[serde(default)]
+    pub id: Option<u64>,
     pub transaction_type: TransactionType,
     pub cash_flow: CashFlow,
+    #[serde(default)]
     pub note: Option<String>,
+    /// Labels of the transaction, normalized by `normalize_tag` when stored
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Account the transaction is booked in, if transactions are kept in several accounts
+    #[serde(default)]
+    pub account: Option<u64>,
+    // Audit information, set by the data handler when the transaction is stored or updated
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub modified_at: Option<DateTime<Utc>>,
 }
 
 impl DataItem for Transaction {
     // get id or return error if id hasn't been set yet
-    fn get_id(&self) -> Result<usize, DataError> {
+    fn get_id(&self) -> Result<u64, DataError> {
         match self.id {
             Some(id) => Ok(id),
             None => Err(DataError::DataAccessFailure(
@@ -35,7 +122,7 @@ impl DataItem for Transaction {
         }
     }
     // set id or return error if id has already been set
-    fn set_id(&mut self, id: usize) -> Result<(), DataError> {
+    fn set_id(&mut self, id: u64) -> Result<(), DataError> {
         match self.id {
             Some(_) => Err(DataError::DataAccessFailure(
                 "tried to change valid transaction id".to_string(),
@@ -47,3 +134,580 @@ impl DataItem for Transaction {
         }
     }
 }
+
+/// Normalize a tag for storage and comparison, i.e. trim whitespace and convert to lowercase
+pub fn normalize_tag(tag: &str) -> String {
+    tag.trim().to_lowercase()
+}
+
+impl Transaction {
+    /// New transaction of the given type and cash flow, which is not stored yet and has
+    /// no note, tags or account
+    pub fn new(transaction_type: TransactionType, cash_flow: CashFlow) -> Transaction {
+        Transaction {
+            id: None,
+            transaction_type,
+            cash_flow,
+            note: None,
+            tags: Vec::new(),
+            account: None,
+            created_at: None,
+            modified_at: None,
+        }
+    }
+
+    /// Tags normalized by `normalize_tag`, in the original order without empty or duplicate tags
+    pub fn normalized_tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = Vec::with_capacity(self.tags.len());
+        for tag in self.tags.iter().map(|tag| normalize_tag(tag)) {
+            if !tag.is_empty() && !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        tags
+    }
+
+    /// Returns true if the transaction is labeled with `tag`, compared after normalization
+    pub fn has_tag(&self, tag: &str) -> bool {
+        let tag = normalize_tag(tag);
+        self.tags.iter().any(|t| normalize_tag(t) == tag)
+    }
+
+    /// Returns true if the note contains `text`, ignoring case
+    pub fn note_contains(&self, text: &str) -> bool {
+        match &self.note {
+            Some(note) => note.to_lowercase().contains(&text.to_lowercase()),
+            None => false,
+        }
+    }
+
+    /// Copy of the transaction with its cash flow converted to currency `target`, using the last
+    /// fx rate available at the end of the transaction date. Fees and taxes are transactions
+    /// of their own and are converted by calling this method on them.
+    pub fn in_currency(
+        &self,
+        target: Currency,
+        fx: &mut dyn QuoteHandler,
+    ) -> Result<Transaction, DataError> {
+        let amount = self.cash_flow.amount;
+        let fx_rate = get_fx_rate(amount.currency, target, end_of_day(self.cash_flow.date), fx)?;
+        let mut transaction = self.clone();
+        transaction.cash_flow.amount.amount = amount_from_f64(amount.value() * fx_rate);
+        transaction.cash_flow.amount.currency = target;
+        Ok(transaction)
+    }
+}
+
+/// Criteria to select transactions, see `Transaction::matches`. All criteria which are set
+/// must be met, a filter without any criteria matches all transactions.
+#[derive(Debug, Clone, Default)]
+pub struct TransactionFilter {
+    asset_id: Option<u64>,
+    kind: Option<TransactionKind>,
+    start: Option<NaiveDate>,
+    end: Option<NaiveDate>,
+    min_amount: Option<f64>,
+    max_amount: Option<f64>,
+}
+
+impl TransactionFilter {
+    pub fn new() -> TransactionFilter {
+        TransactionFilter::default()
+    }
+
+    /// Only transactions relating to the asset with the given id
+    pub fn asset_id(mut self, asset_id: u64) -> Self {
+        self.asset_id = Some(asset_id);
+        self
+    }
+
+    /// Only transactions of the given kind
+    pub fn kind(mut self, kind: TransactionKind) -> Self {
+        self.kind = Some(kind);
+        self
+    }
+
+    /// Only transactions with cash flows from `start` to `end`, inclusively
+    pub fn date_range(mut self, start: NaiveDate, end: NaiveDate) -> Self {
+        self.start = Some(start);
+        self.end = Some(end);
+        self
+    }
+
+    /// Only transactions with a cash flow amount of at least `amount`, including the sign
+    pub fn min_amount(mut self, amount: f64) -> Self {
+        self.min_amount = Some(amount);
+        self
+    }
+
+    /// Only transactions with a cash flow amount of at most `amount`, including the sign
+    pub fn max_amount(mut self, amount: f64) -> Self {
+        self.max_amount = Some(amount);
+        self
+    }
+}
+
+impl Transaction {
+    /// Returns true if the transaction meets all criteria of `filter`
+    pub fn matches(&self, filter: &TransactionFilter) -> bool {
+        let date = self.cash_flow.date;
+        let amount = self.cash_flow.amount.value();
+        (filter.asset_id.is_none() || self.transaction_type.asset_id() == filter.asset_id)
+            && filter
+                .kind
+                .iter()
+                .all(|kind| self.transaction_type.kind() == *kind)
+            && filter.start.iter().all(|start| date >= *start)
+            && filter.end.iter().all(|end| date <= *end)
+            && filter.min_amount.iter().all(|min| amount >= *min)
+            && filter.max_amount.iter().all(|max| amount <= *max)
+    }
+}
+
+/// Check that two transactions are matching legs of a transfer, i.e. both are transfers
+/// with cash flows in the same currency and of equal absolute amount but opposite sign
+pub fn check_transfer_legs(leg: &Transaction, other: &Transaction) -> Result<(), DataError> {
+    let (amount, other_amount) = (&leg.cash_flow.amount, &other.cash_flow.amount);
+    if !matches!(other.transaction_type, TransactionType::Transfer { .. }) {
+        Err(DataError::InvalidTransaction(
+            "transfer refers to a transaction which is not a transfer".to_string(),
+        ))
+    } else if amount.currency != other_amount.currency {
+        Err(DataError::InvalidTransaction(format!(
+            "transfer legs in different currencies {} and {}",
+            amount.currency, other_amount.currency
+        )))
+    } else if (amount.value() + other_amount.value()).abs() > 1e-9 * amount.value().abs().max(1.) {
+        Err(DataError::InvalidTransaction(format!(
+            "transfer legs of {} and {} do not match",
+            amount.amount, other_amount.amount
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+/// Age of a transaction in business days relative to `as_of`, i.e. the number of business days
+/// from the transaction's date (inclusively) up to `as_of` (exclusively). A transaction
+/// dated on the previous business day is one business day old. Transactions dated after
+/// `as_of` have a negative age.
+pub fn transaction_business_age(txn: &Transaction, as_of: NaiveDate, cal: &Calendar) -> i64 {
+    let date = txn.cash_flow.date;
+    if date <= as_of {
+        cal.business_days_in(date, as_of) as i64
+    } else {
+        -(cal.business_days_in(as_of, date) as i64)
+    }
+}
+
+/// Trades are netted if they are in the same asset, on the same date and in the same currency
+/// Asset id, date, currency and account of trades that are netted
+type NettingKey = (u64, NaiveDate, Currency, Option<u64>);
+
+/// Collapse multiple trades of the same asset on the same date (and in the same currency and
+/// account) into a single net trade, with position and cash flow summed up. Fees and taxes referring to netted
+/// trades are summed up separately (per kind and currency), not netted with the trades' cash
+/// flows, and refer to no transaction since the net trade has not been stored yet.
+/// Net transactions take the place of the first transaction they replace; all other
+/// transactions are kept unchanged.
+pub fn net_transactions(txns: &[Transaction]) -> Vec<Transaction> {
+    let netting_key = |t: &Transaction, asset_id: u64| -> NettingKey {
+        (
+            asset_id,
+            t.cash_flow.date,
+            t.cash_flow.amount.currency,
+            t.account,
+        )
+    };
+    let mut groups: HashMap<NettingKey, Vec<usize>> = HashMap::new();
+    for (idx, t) in txns.iter().enumerate() {
+        if let TransactionType::Asset { asset_id, .. } = t.transaction_type {
+            groups
+                .entry(netting_key(t, asset_id))
+                .or_default()
+                .push(idx);
+        }
+    }
+    let mut netted_ids = HashMap::new();
+    for (key, indices) in groups.iter().filter(|(_, indices)| indices.len() > 1) {
+        for idx in indices {
+            if let Some(id) = txns[*idx].id {
+                netted_ids.insert(id, *key);
+            }
+        }
+    }
+
+    let mut netted: Vec<Transaction> = Vec::new();
+    // position of the summed fees and taxes in the result and their running sum,
+    // by trade, kind of tax (`None` for fees) and currency
+    let mut charges: HashMap<(NettingKey, Option<TaxKind>, Currency), (usize, AmountSum)> =
+        HashMap::new();
+    for (idx, t) in txns.iter().enumerate() {
+        match t.transaction_type {
+            TransactionType::Asset { asset_id, .. } => {
+                let indices = &groups[&netting_key(t, asset_id)];
+                if indices.len() == 1 {
+                    netted.push(t.clone());
+                } else if indices[0] == idx {
+                    let mut position = 0.;
+                    let mut amount = AmountSum::default();
+                    for trade in indices.iter().map(|i| &txns[*i]) {
+                        if let TransactionType::Asset { position: p, .. } = trade.transaction_type {
+                            position += p;
+                        }
+                        amount.add(trade.cash_flow.amount.amount);
+                    }
+                    let mut cash_flow = t.cash_flow;
+                    cash_flow.amount.amount = amount.amount();
+                    let mut trade =
+                        Transaction::new(TransactionType::Asset { asset_id, position }, cash_flow);
+                    trade.account = t.account;
+                    netted.push(trade);
+                }
+            }
+            TransactionType::Fee {
+                transaction_ref: Some(id),
+            }
+            | TransactionType::Tax {
+                transaction_ref: Some(id),
+                ..
+            } if netted_ids.contains_key(&id) => {
+                let tax_kind = match t.transaction_type {
+                    TransactionType::Tax { kind, .. } => Some(kind),
+                    _ => None,
+                };
+                let key = (netted_ids[&id], tax_kind, t.cash_flow.amount.currency);
+                match charges.get_mut(&key) {
+                    Some((pos, sum)) => {
+                        sum.add(t.cash_flow.amount.amount);
+                        netted[*pos].cash_flow.amount.amount = sum.amount();
+                    }
+                    None => {
+                        charges.insert(
+                            key,
+                            (netted.len(), AmountSum::from(t.cash_flow.amount.amount)),
+                        );
+                        let transaction_type = match tax_kind {
+                            Some(kind) => TransactionType::Tax {
+                                transaction_ref: None,
+                                kind,
+                            },
+                            None => TransactionType::Fee {
+                                transaction_ref: None,
+                            },
+                        };
+                        let mut charge = Transaction::new(transaction_type, t.cash_flow);
+                        charge.account = netted_ids[&id].3;
+                        netted.push(charge);
+                    }
+                }
+            }
+            _ => netted.push(t.clone()),
+        }
+    }
+    netted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::make_time;
+    use std::str::FromStr;
+
+    #[test]
+    fn deserialize_transactions() {
+        // payload without optional fields
+        let json = r#"{
+            "transaction_type": { "Asset": { "asset_id": 1, "position": 10.0 } },
+            "cash_flow": {
+                "amount": { "amount": -1000.0, "currency": "EUR" },
+                "date": "2020-01-15"
+            }
+        }"#;
+        let transaction: Transaction = serde_json::from_str(json).unwrap();
+        assert_eq!(transaction.id, None);
+        assert_eq!(transaction.note, None);
+        assert_eq!(transaction.created_at, None);
+        assert_eq!(transaction.modified_at, None);
+        assert_eq!(transaction.cash_flow.amount.value(), -1000.0);
+        assert_eq!(transaction.cash_flow.amount.currency.to_string(), "EUR");
+        assert_eq!(transaction.cash_flow.date, NaiveDate::from_ymd(2020, 1, 15));
+        match transaction.transaction_type {
+            TransactionType::Asset { asset_id, position } => {
+                assert_eq!(asset_id, 1);
+                assert_eq!(position, 10.0);
+            }
+            _ => panic!("wrong transaction type"),
+        }
+
+        // payload with all fields
+        let json = r#"{
+            "id": 7,
+            "transaction_type": { "Fee": { "transaction_ref": 3 } },
+            "cash_flow": {
+                "amount": { "amount": -5.0, "currency": "EUR" },
+                "date": "2020-01-15"
+            },
+            "note": "broker fee",
+            "created_at": "2020-01-15T10:00:00Z",
+            "modified_at": "2020-01-16T12:30:00Z"
+        }"#;
+        let transaction: Transaction = serde_json::from_str(json).unwrap();
+        assert_eq!(transaction.id, Some(7));
+        assert_eq!(transaction.note.as_deref(), Some("broker fee"));
+        assert_eq!(transaction.created_at, make_time(2020, 1, 15, 10, 0, 0));
+        assert_eq!(transaction.modified_at, make_time(2020, 1, 16, 12, 30, 0));
+        assert!(matches!(
+            transaction.transaction_type,
+            TransactionType::Fee {
+                transaction_ref: Some(3)
+            }
+        ));
+
+        // round trip
+        let json = serde_json::to_string(&transaction).unwrap();
+        let transaction2: Transaction = serde_json::from_str(&json).unwrap();
+        assert_eq!(transaction2.id, transaction.id);
+        assert_eq!(transaction2.created_at, transaction.created_at);
+    }
+
+    #[test]
+    fn net_same_day_trades() {
+        let eur = Currency::from_str("EUR").unwrap();
+        let date = NaiveDate::from_ymd(2020, 1, 15);
+        let transaction = |id: u64, transaction_type: TransactionType, amount: f64, date| {
+            let mut t = Transaction::new(transaction_type, CashFlow::new(amount, eur, date));
+            t.id = Some(id);
+            t
+        };
+        let txns = vec![
+            transaction(1, TransactionType::Cash, 10_000., date),
+            transaction(
+                2,
+                TransactionType::Asset {
+                    asset_id: 1,
+                    position: 100.,
+                },
+                -5_000.,
+                date,
+            ),
+            transaction(
+                3,
+                TransactionType::Fee {
+                    transaction_ref: Some(2),
+                },
+                -10.,
+                date,
+            ),
+            transaction(
+                4,
+                TransactionType::Asset {
+                    asset_id: 1,
+                    position: -40.,
+                },
+                2_100.,
+                date,
+            ),
+            transaction(
+                5,
+                TransactionType::Fee {
+                    transaction_ref: Some(4),
+                },
+                -7.5,
+                date,
+            ),
+            // different asset or date are not netted
+            transaction(
+                6,
+                TransactionType::Asset {
+                    asset_id: 2,
+                    position: 10.,
+                },
+                -800.,
+                date,
+            ),
+            transaction(
+                7,
+                TransactionType::Asset {
+                    asset_id: 1,
+                    position: 5.,
+                },
+                -260.,
+                date.succ(),
+            ),
+        ];
+        let netted = net_transactions(&txns);
+        assert_eq!(netted.len(), 5);
+        assert_eq!(netted[0].id, Some(1));
+        match netted[1].transaction_type {
+            TransactionType::Asset { asset_id, position } => {
+                assert_eq!(asset_id, 1);
+                assert_eq!(position, 60.);
+            }
+            _ => panic!("net trade expected"),
+        }
+        assert_eq!(netted[1].id, None);
+        assert_eq!(netted[1].cash_flow.amount.value(), -2_900.);
+        assert_eq!(netted[1].cash_flow.date, date);
+        assert!(matches!(
+            netted[2].transaction_type,
+            TransactionType::Fee {
+                transaction_ref: None
+            }
+        ));
+        assert_eq!(netted[2].cash_flow.amount.value(), -17.5);
+        assert_eq!(netted[3].id, Some(6));
+        assert_eq!(netted[4].id, Some(7));
+    }
+
+    #[test]
+    fn net_trades_per_account() {
+        let eur = Currency::from_str("EUR").unwrap();
+        let date = NaiveDate::from_ymd(2020, 1, 15);
+        let trade = |id: u64, position: f64, amount: f64, account: u64| {
+            let mut t = Transaction::new(
+                TransactionType::Asset {
+                    asset_id: 1,
+                    position,
+                },
+                CashFlow::new(amount, eur, date),
+            );
+            t.id = Some(id);
+            t.account = Some(account);
+            t
+        };
+        let mut fee = Transaction::new(
+            TransactionType::Fee {
+                transaction_ref: Some(3),
+            },
+            CashFlow::new(-5., eur, date),
+        );
+        fee.account = Some(2);
+        let txns = vec![
+            trade(1, 10., -500., 1),
+            trade(2, 20., -1_000., 1),
+            trade(3, 5., -250., 2),
+            trade(4, 5., -260., 2),
+            fee,
+        ];
+        let netted = net_transactions(&txns);
+        assert_eq!(netted.len(), 3);
+        let net_trade = |t: &Transaction| match t.transaction_type {
+            TransactionType::Asset { position, .. } => (position, t.cash_flow.amount.value()),
+            _ => panic!("net trade expected"),
+        };
+        assert_eq!(net_trade(&netted[0]), (30., -1_500.));
+        assert_eq!(netted[0].account, Some(1));
+        assert_eq!(net_trade(&netted[1]), (10., -510.));
+        assert_eq!(netted[1].account, Some(2));
+        assert_eq!(netted[2].cash_flow.amount.value(), -5.);
+        assert_eq!(netted[2].account, Some(2));
+    }
+
+    #[test]
+    fn business_age_across_holiday() {
+        use crate::calendar::Holiday;
+        use chrono::Weekday;
+        let eur = Currency::from_str("EUR").unwrap();
+        let cal = Calendar::calc_calendar(
+            &[
+                Holiday::SingularDay(NaiveDate::from_ymd(2020, 4, 10)),
+                Holiday::SingularDay(NaiveDate::from_ymd(2020, 4, 13)),
+                Holiday::WeekDay(Weekday::Sat),
+                Holiday::WeekDay(Weekday::Sun),
+            ],
+            2020,
+            2020,
+        )
+        .unwrap();
+        // Wednesday before Easter
+        let txn = Transaction::new(
+            TransactionType::Cash,
+            CashFlow::new(100., eur, NaiveDate::from_ymd(2020, 4, 8)),
+        );
+        // Wednesday, Thursday, Tuesday and Wednesday
+        let as_of = NaiveDate::from_ymd(2020, 4, 16);
+        assert_eq!(transaction_business_age(&txn, as_of, &cal), 4);
+        assert_eq!(
+            transaction_business_age(&txn, NaiveDate::from_ymd(2020, 4, 8), &cal),
+            0
+        );
+        assert_eq!(
+            transaction_business_age(&txn, NaiveDate::from_ymd(2020, 4, 7), &cal),
+            -1
+        );
+    }
+
+    #[test]
+    fn convert_transactions_to_usd() {
+        use crate::fx_rates::insert_fx_quote;
+        use crate::memory_handler::InMemoryDB;
+        use crate::quote::MarketDataSource;
+
+        let eur = Currency::from_str("EUR").unwrap();
+        let usd = Currency::from_str("USD").unwrap();
+        let mut fx = InMemoryDB::new();
+        let source = fx
+            .insert_md_source(&MarketDataSource {
+                id: None,
+                name: "manual".to_string(),
+            })
+            .unwrap();
+        let time = make_time(2021, 3, 1, 18, 0, 0).unwrap();
+        insert_fx_quote(1.2, eur, usd, source, time, &mut fx).unwrap();
+
+        let date = NaiveDate::from_ymd(2021, 3, 1);
+        let transaction = |transaction_type, amount| Transaction {
+            id: Some(1),
+            note: Some("purchase".to_string()),
+            ..Transaction::new(transaction_type, CashFlow::new(amount, eur, date))
+        };
+        let purchase = transaction(
+            TransactionType::Asset {
+                asset_id: 1,
+                position: 10.,
+            },
+            -1000.,
+        );
+        let fee = transaction(
+            TransactionType::Fee {
+                transaction_ref: Some(1),
+            },
+            -5.,
+        );
+        let tax = transaction(
+            TransactionType::Tax {
+                transaction_ref: Some(1),
+                kind: TaxKind::Domestic,
+            },
+            -2.5,
+        );
+        for (original, expected) in &[(purchase, -1200.), (fee, -6.), (tax, -3.)] {
+            let converted = original.in_currency(usd, &mut fx).unwrap();
+            assert_fuzzy_eq!(converted.cash_flow.amount.value(), *expected, 1e-10);
+            assert_eq!(converted.cash_flow.amount.currency, usd);
+            assert_eq!(converted.cash_flow.date, date);
+            assert_eq!(converted.id, original.id);
+            assert_eq!(converted.note, original.note);
+            assert_eq!(
+                converted.transaction_type.kind(),
+                original.transaction_type.kind()
+            );
+        }
+        // the inverse currency pair is used as well, and no rate is needed for the same currency
+        let back = transaction(TransactionType::Cash, 120.)
+            .in_currency(usd, &mut fx)
+            .unwrap()
+            .in_currency(eur, &mut fx)
+            .unwrap();
+        assert_fuzzy_eq!(back.cash_flow.amount.value(), 120., 1e-10);
+        let same = transaction(TransactionType::Cash, 120.)
+            .in_currency(eur, &mut InMemoryDB::new())
+            .unwrap();
+        assert_eq!(same.cash_flow.amount.value(), 120.);
+        // no rate before the first fx quote
+        let mut early = transaction(TransactionType::Cash, 120.);
+        early.cash_flow.date = NaiveDate::from_ymd(2021, 2, 26);
+        assert!(early.in_currency(usd, &mut fx).is_err());
+    }
+}
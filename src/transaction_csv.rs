@@ -0,0 +1,651 @@
+//! Import and export of transactions from and to CSV files
+//!
+//! The CSV layout has a header line followed by one transaction per line with the columns
+//!
+//! `date,asset_name,kind,quantity,price,fee,currency`
+//!
+//! * `date` is formatted as "YYYY-MM-DD"
+//! * `asset_name` is the name of the asset as stored in the data handler, it may be empty
+//!   for transactions not related to an asset (kinds `cash`, `transfer`, `tax` and `fee`)
+//! * `kind` is one of `buy`, `sell`, `dividend`, `interest`, `cash`, `transfer`, `tax` or `fee`
+//! * `quantity` and `price` give the cash amount of the transaction as their product. For buys
+//!   and sells, `quantity` is the number of units traded and `price` the price per unit, for
+//!   all other kinds `quantity` is typically 1 and `price` the paid amount. Buys, taxes and fees
+//!   result in negative cash flows, sells, dividends and interest in positive ones. For `cash`
+//!   and `transfer`, the sign of the amount is kept, i.e. deposits (or incoming transfers) are
+//!   positive and withdrawals (or outgoing transfers) negative. Accounts and the relation
+//!   between the legs of a transfer are not part of the layout.
+//! * `fee` is an optional fee paid on top of the transaction, empty or zero if there is none
+//! * `currency` is the ISO code of the currency of price and fee
+//!
+//! On import, an optional eighth column `note` may follow, which is taken as note of the
+//! transaction (but not of its fee). Empty notes are ignored. For dividends and interest, an
+//! optional ninth column `withholding_tax` may give the tax withheld at source in the same
+//! currency, which results in a tax transaction referring to the dividend or interest.
+//! The amount of the dividend or interest is the gross amount, i.e. before withholding tax.
+//! An optional tenth column `amount` may give the cash amount with the same sign as the
+//! product of `quantity` and `price`, which is used instead of the product if not empty.
+//! Exports always write all ten columns, such that amounts are reproduced exactly on import
+//! even if they are not the exact product of the (rounded) quantity and price.
+//!
+//! Fields containing commas may be enclosed in double quotes, a double quote within such a
+//! field is written as two double quotes.
+
+use crate::currency::Currency;
+use crate::data_handler::{DataError, DataHandler};
+use crate::fixed_income::{CashAmount, CashFlow};
+use crate::helpers::{quote_csv_field, split_csv_line};
+use crate::transaction::{TaxKind, Transaction, TransactionType};
+use chrono::NaiveDate;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::str::FromStr;
+
+/// Number of columns of the CSV layout, without the optional columns
+const COLUMNS: usize = 7;
+
+/// Header line of the CSV layout
+const HEADER: &str = "date,asset_name,kind,quantity,price,fee,currency";
+
+/// Names of the optional columns written on export
+const OPTIONAL_HEADER: &str = "note,withholding_tax,amount";
+
+/// Parse transactions from CSV data in the layout documented above. Asset names are
+/// resolved to asset ids with `db`. A withholding tax or fee given in a row results in a
+/// separate tax or fee transaction following the transaction it belongs to; its
+/// `transaction_ref` is `None` since the referenced transaction has not been stored yet.
+/// Malformed rows result in an error stating the (1-based) line number.
+pub fn import_transactions_csv(
+    reader: impl Read,
+    db: &mut dyn DataHandler,
+) -> Result<Vec<Transaction>, DataError> {
+    let mut transactions = Vec::new();
+    for_each_csv_row(reader, db, &mut |_, row| {
+        transactions.extend(row);
+        Ok(())
+    })?;
+    Ok(transactions)
+}
+
+/// Parse transactions from CSV data like `import_transactions_csv` and store them row by
+/// row in `db`. Tax and fee transactions refer to the transaction of the same row. Returns
+/// the ids of all stored transactions. If an error occurs, all rows before the erroneous one
+/// are stored, unless `rollback_on_error` is set, in which case `db` is restored to the state
+/// before the import. Rolling back requires a data handler supporting snapshots.
+pub fn store_transactions_csv(
+    reader: impl Read,
+    db: &mut dyn DataHandler,
+    rollback_on_error: bool,
+) -> Result<Vec<u64>, DataError> {
+    let snapshot = if rollback_on_error {
+        Some(db.snapshot()?)
+    } else {
+        None
+    };
+    let mut ids = Vec::new();
+    let result = for_each_csv_row(reader, db, &mut |db, row| {
+        let mut transaction_ref = None;
+        for mut transaction in row {
+            match transaction.transaction_type {
+                TransactionType::Fee {
+                    transaction_ref: ref mut related,
+                }
+                | TransactionType::Tax {
+                    transaction_ref: ref mut related,
+                    ..
+                } => *related = transaction_ref,
+                _ => {}
+            }
+            let id = db.insert_transaction(&transaction)?;
+            transaction_ref = transaction_ref.or(Some(id));
+            ids.push(id);
+        }
+        Ok(())
+    });
+    match (result, snapshot) {
+        (Err(err), Some(snapshot)) => {
+            db.restore(snapshot)?;
+            Err(err)
+        }
+        (result, _) => result.map(|_| ids),
+    }
+}
+
+/// Callback receiving the transactions parsed from a single row
+type RowHandler<'a> =
+    dyn FnMut(&mut dyn DataHandler, Vec<Transaction>) -> Result<(), DataError> + 'a;
+
+/// Parse the CSV data row by row, calling `f` with the transactions of each row
+fn for_each_csv_row(
+    reader: impl Read,
+    db: &mut dyn DataHandler,
+    f: &mut RowHandler<'_>,
+) -> Result<(), DataError> {
+    for (idx, line) in BufReader::new(reader).lines().enumerate() {
+        let line = line?;
+        // skip header and empty lines
+        if idx == 0 || line.trim().is_empty() {
+            continue;
+        }
+        let line_error =
+            |msg: String| DataError::InvalidTransaction(format!("line {}: {}", idx + 1, msg));
+        let fields = split_csv_line(&line).map_err(line_error)?;
+        let mut row = Vec::new();
+        parse_row(&fields, db, &mut row).map_err(line_error)?;
+        f(db, row)?;
+    }
+    Ok(())
+}
+
+/// Write transactions as CSV data in the layout documented above, such that they could be
+/// imported again with `import_transactions_csv`. Asset ids are resolved to asset names
+/// with `db`. Fees are written as separate rows of kind `fee`, the fee column is left empty.
+/// Taxes referring to a dividend or interest payment in `txns` are written as its withholding
+/// tax, such that the relation is restored by `store_transactions_csv`, unless they have a
+/// note, are in another currency, or their total is not between zero and the gross amount.
+/// The cash amount is written to the `amount` column in its shortest representation which
+/// parses to the same number, such that it survives a round trip exactly.
+pub fn export_transactions_csv(
+    txns: &[Transaction],
+    mut writer: impl Write,
+    db: &mut dyn DataHandler,
+) -> Result<(), DataError> {
+    let payments: HashMap<u64, &Transaction> = txns
+        .iter()
+        .filter(|t| {
+            matches!(
+                t.transaction_type,
+                TransactionType::Dividend { .. } | TransactionType::Interest { .. }
+            )
+        })
+        .filter_map(|t| t.id.map(|id| (id, t)))
+        .collect();
+    let mut withheld: HashMap<u64, CashAmount> = HashMap::new();
+    for tax in txns {
+        if let Some(id) = withheld_payment(tax, &payments) {
+            *withheld.entry(id).or_default() -= tax.cash_flow.amount.amount;
+        }
+    }
+    withheld.retain(|id, tax| {
+        *tax >= CashAmount::default() && *tax <= payments[id].cash_flow.amount.amount
+    });
+
+    writeln!(writer, "{},{}", HEADER, OPTIONAL_HEADER)?;
+    for transaction in txns {
+        if let Some(id) = withheld_payment(transaction, &payments) {
+            if withheld.contains_key(&id) {
+                continue;
+            }
+        }
+        let withholding_tax = transaction
+            .id
+            .and_then(|id| withheld.get(&id))
+            .map(|tax| tax.to_string())
+            .unwrap_or_default();
+        let amount = transaction.cash_flow.amount.value();
+        // the product of quantity and price, i.e. the amount with the sign of the layout
+        let (asset_id, kind, quantity, product) = match transaction.transaction_type {
+            TransactionType::Asset { asset_id, position } if position >= 0. => {
+                (Some(asset_id), "buy", position, -amount)
+            }
+            TransactionType::Asset { asset_id, position } => {
+                (Some(asset_id), "sell", -position, amount)
+            }
+            TransactionType::Dividend { asset_id } => (Some(asset_id), "dividend", 1., amount),
+            TransactionType::Interest { asset_id } => (Some(asset_id), "interest", 1., amount),
+            TransactionType::Cash => (None, "cash", 1., amount),
+            TransactionType::Transfer { .. } => (None, "transfer", 1., amount),
+            TransactionType::Tax { .. } => (None, "tax", 1., -amount),
+            TransactionType::Fee { .. } => (None, "fee", 1., -amount),
+        };
+        let price = if quantity == 0. {
+            product
+        } else {
+            product / quantity
+        };
+        let asset_name = match asset_id {
+            Some(id) => db.get_asset_by_id(id)?.name,
+            None => String::new(),
+        };
+        writeln!(
+            writer,
+            "{},{},{},{},{},,{},{},{},{}",
+            transaction.cash_flow.date.format("%Y-%m-%d"),
+            quote_csv_field(&asset_name),
+            kind,
+            quantity,
+            price,
+            transaction.cash_flow.amount.currency,
+            quote_csv_field(transaction.note.as_deref().unwrap_or_default()),
+            withholding_tax,
+            product
+        )?;
+    }
+    Ok(())
+}
+
+/// Id of the payment in `payments` a withholding tax without note in the same currency
+/// refers to, i.e. which could be exported in the withholding tax column
+fn withheld_payment(tax: &Transaction, payments: &HashMap<u64, &Transaction>) -> Option<u64> {
+    match tax.transaction_type {
+        TransactionType::Tax {
+            transaction_ref: Some(id),
+            kind: TaxKind::Withholding,
+        } if tax.note.is_none() => payments
+            .get(&id)
+            .filter(|payment| payment.cash_flow.amount.currency == tax.cash_flow.amount.currency)
+            .map(|_| id),
+        _ => None,
+    }
+}
+
+/// Parse a single row and append the resulting transactions
+fn parse_row(
+    fields: &[String],
+    db: &mut dyn DataHandler,
+    transactions: &mut Vec<Transaction>,
+) -> Result<(), String> {
+    if fields.len() < COLUMNS || fields.len() > COLUMNS + 3 {
+        return Err(format!(
+            "expected {} to {} columns, but found {}",
+            COLUMNS,
+            COLUMNS + 3,
+            fields.len()
+        ));
+    }
+    let date = NaiveDate::parse_from_str(&fields[0], "%Y-%m-%d")
+        .map_err(|_| format!("invalid date '{}'", fields[0]))?;
+    let quantity = parse_number(&fields[3], "quantity")?;
+    let price = parse_number(&fields[4], "price")?;
+    let fee = if fields[5].is_empty() {
+        0.
+    } else {
+        parse_number(&fields[5], "fee")?
+    };
+    let currency =
+        Currency::from_str(&fields[6]).map_err(|_| format!("invalid currency '{}'", fields[6]))?;
+    let mut asset_id = || -> Result<u64, String> {
+        if fields[1].is_empty() {
+            return Err(format!("missing asset name for kind '{}'", fields[2]));
+        }
+        let asset = db
+            .get_asset_by_name(&fields[1])
+            .map_err(|_| format!("unknown asset '{}'", fields[1]))?;
+        asset
+            .id
+            .ok_or_else(|| format!("unknown asset '{}'", fields[1]))
+    };
+    let amount = match fields.get(COLUMNS + 2).filter(|amount| !amount.is_empty()) {
+        Some(amount) => parse_number(amount, "amount")?,
+        None => quantity * price,
+    };
+    let (transaction_type, amount) = match fields[2].to_lowercase().as_str() {
+        "buy" => (
+            TransactionType::Asset {
+                asset_id: asset_id()?,
+                position: quantity,
+            },
+            -amount,
+        ),
+        "sell" => (
+            TransactionType::Asset {
+                asset_id: asset_id()?,
+                position: -quantity,
+            },
+            amount,
+        ),
+        "dividend" => (
+            TransactionType::Dividend {
+                asset_id: asset_id()?,
+            },
+            amount,
+        ),
+        "interest" => (
+            TransactionType::Interest {
+                asset_id: asset_id()?,
+            },
+            amount,
+        ),
+        "cash" => (TransactionType::Cash, amount),
+        "transfer" => (
+            TransactionType::Transfer {
+                transaction_ref: None,
+            },
+            amount,
+        ),
+        "tax" => (
+            TransactionType::Tax {
+                transaction_ref: None,
+                kind: TaxKind::Domestic,
+            },
+            -amount,
+        ),
+        "fee" => (
+            TransactionType::Fee {
+                transaction_ref: None,
+            },
+            -amount,
+        ),
+        kind => return Err(format!("unknown kind '{}'", kind)),
+    };
+    let withholding_tax = match fields.get(COLUMNS + 1).filter(|tax| !tax.is_empty()) {
+        Some(tax) => parse_number(tax, "withholding tax")?,
+        None => 0.,
+    };
+    if withholding_tax != 0. {
+        let is_income = matches!(
+            transaction_type,
+            TransactionType::Dividend { .. } | TransactionType::Interest { .. }
+        );
+        if !is_income {
+            return Err(format!("withholding tax for kind '{}'", fields[2]));
+        }
+        // also rejects NaN
+        let valid = withholding_tax >= 0. && withholding_tax <= amount;
+        if !valid {
+            return Err(format!(
+                "withholding tax {} not between zero and gross amount {}",
+                withholding_tax, amount
+            ));
+        }
+    }
+    let mut transaction = new_transaction(transaction_type, amount, currency, date);
+    transaction.note = fields.get(COLUMNS).filter(|note| !note.is_empty()).cloned();
+    transactions.push(transaction);
+    if withholding_tax != 0. {
+        transactions.push(new_transaction(
+            TransactionType::Tax {
+                transaction_ref: None,
+                kind: TaxKind::Withholding,
+            },
+            -withholding_tax,
+            currency,
+            date,
+        ));
+    }
+    if fee != 0. {
+        transactions.push(new_transaction(
+            TransactionType::Fee {
+                transaction_ref: None,
+            },
+            -fee,
+            currency,
+            date,
+        ));
+    }
+    Ok(())
+}
+
+fn new_transaction(
+    transaction_type: TransactionType,
+    amount: f64,
+    currency: Currency,
+    date: NaiveDate,
+) -> Transaction {
+    Transaction::new(transaction_type, CashFlow::new(amount, currency, date))
+}
+
+fn parse_number(field: &str, name: &str) -> Result<f64, String> {
+    field
+        .parse()
+        .map_err(|_| format!("invalid {} '{}'", name, field))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::Asset;
+    use crate::fixed_income::amount_from_f64;
+    use crate::memory_handler::InMemoryDB;
+
+    #[test]
+    fn import_valid_csv() {
+        let mut db = InMemoryDB::new();
+        let basf = db
+            .insert_asset(&Asset::new(None, "BASF AG", None, None, None))
+            .unwrap();
+        let csv = "date,asset_name,kind,quantity,price,fee,currency
+2020-01-15,\"BASF AG\",buy,10,60.5,4.95,EUR
+2020-05-04,BASF AG,dividend,1,33,,EUR
+";
+        let transactions = import_transactions_csv(csv.as_bytes(), &mut db).unwrap();
+        assert_eq!(transactions.len(), 3);
+        match transactions[0].transaction_type {
+            TransactionType::Asset { asset_id, position } => {
+                assert_eq!(asset_id, basf);
+                assert_eq!(position, 10.);
+            }
+            _ => panic!("wrong transaction type"),
+        }
+        assert_eq!(transactions[0].cash_flow.amount.value(), -605.);
+        assert_eq!(
+            transactions[0].cash_flow.date,
+            NaiveDate::from_ymd(2020, 1, 15)
+        );
+        assert!(matches!(
+            transactions[1].transaction_type,
+            TransactionType::Fee {
+                transaction_ref: None
+            }
+        ));
+        assert_eq!(transactions[1].cash_flow.amount.value(), -4.95);
+        assert!(matches!(
+            transactions[2].transaction_type,
+            TransactionType::Dividend { asset_id } if asset_id == basf
+        ));
+        assert_eq!(transactions[2].cash_flow.amount.value(), 33.);
+        assert_eq!(transactions[2].cash_flow.amount.currency.to_string(), "EUR");
+    }
+
+    #[test]
+    fn import_notes_from_csv() {
+        let mut db = InMemoryDB::new();
+        db.insert_asset(&Asset::new(None, "BASF AG", None, None, None))
+            .unwrap();
+        let csv = "date,asset_name,kind,quantity,price,fee,currency,note
+2020-01-15,BASF AG,buy,10,60.5,4.95,EUR,\"employer stock plan, 2020\"
+2020-05-04,BASF AG,dividend,1,33,,EUR,
+2020-06-01,BASF AG,dividend,1,12,,EUR
+";
+        let transactions = import_transactions_csv(csv.as_bytes(), &mut db).unwrap();
+        assert_eq!(transactions.len(), 4);
+        assert_eq!(
+            transactions[0].note.as_deref(),
+            Some("employer stock plan, 2020")
+        );
+        assert_eq!(transactions[1].note, None);
+        assert_eq!(transactions[2].note, None);
+        assert_eq!(transactions[3].note, None);
+    }
+
+    #[test]
+    fn import_csv_reports_bad_row() {
+        let mut db = InMemoryDB::new();
+        db.insert_asset(&Asset::new(None, "BASF AG", None, None, None))
+            .unwrap();
+        let csv = "date,asset_name,kind,quantity,price,fee,currency
+2020-01-15,BASF AG,buy,10,60.5,,EUR
+2020-02-30,BASF AG,sell,10,62,,EUR
+";
+        match import_transactions_csv(csv.as_bytes(), &mut db) {
+            Err(DataError::InvalidTransaction(msg)) => {
+                assert_eq!(msg, "line 3: invalid date '2020-02-30'")
+            }
+            _ => panic!("invalid date must be rejected"),
+        }
+
+        let csv = "date,asset_name,kind,quantity,price,fee,currency
+2020-01-15,Unknown,buy,10,60.5,,EUR
+";
+        let err = import_transactions_csv(csv.as_bytes(), &mut db).unwrap_err();
+        assert!(err.to_string().contains("line 2: unknown asset 'Unknown'"));
+    }
+
+    #[test]
+    fn csv_round_trip() {
+        let mut db = InMemoryDB::new();
+        db.insert_asset(&Asset::new(None, "BASF AG", None, None, None))
+            .unwrap();
+        db.insert_asset(&Asset::new(None, "Photon Energy, 17/22", None, None, None))
+            .unwrap();
+        let csv = "date,asset_name,kind,quantity,price,fee,currency
+2020-01-02,,cash,1,10000,,EUR
+2020-01-15,BASF AG,buy,10,60.5,,EUR
+2020-01-15,,fee,1,4.95,,EUR
+2020-02-03,\"Photon Energy, 17/22\",buy,5,102.25,,EUR
+2020-03-16,BASF AG,sell,4,50,,EUR
+2020-03-16,,tax,1,1.2,,EUR
+2020-05-04,BASF AG,dividend,1,19.8,,EUR
+2020-06-01,\"Photon Energy, 17/22\",interest,1,175,,EUR
+2020-06-30,,cash,1,-500,,USD
+";
+        let transactions = import_transactions_csv(csv.as_bytes(), &mut db).unwrap();
+        let mut output = Vec::new();
+        export_transactions_csv(&transactions, &mut output, &mut db).unwrap();
+        let expected =
+            "date,asset_name,kind,quantity,price,fee,currency,note,withholding_tax,amount
+2020-01-02,,cash,1,10000,,EUR,,,10000
+2020-01-15,BASF AG,buy,10,60.5,,EUR,,,605
+2020-01-15,,fee,1,4.95,,EUR,,,4.95
+2020-02-03,\"Photon Energy, 17/22\",buy,5,102.25,,EUR,,,511.25
+2020-03-16,BASF AG,sell,4,50,,EUR,,,200
+2020-03-16,,tax,1,1.2,,EUR,,,1.2
+2020-05-04,BASF AG,dividend,1,19.8,,EUR,,,19.8
+2020-06-01,\"Photon Energy, 17/22\",interest,1,175,,EUR,,,175
+2020-06-30,,cash,1,-500,,USD,,,-500
+";
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
+    }
+
+    #[test]
+    fn csv_round_trip_of_inexact_amounts() {
+        let mut db = InMemoryDB::new();
+        db.insert_asset(&Asset::new(None, "BASF AG", None, None, None))
+            .unwrap();
+        // neither 10.1 / 3 nor 0.1 + 0.2 are exactly representable
+        let csv = "date,asset_name,kind,quantity,price,fee,currency,note
+2020-01-15,BASF AG,buy,3,3.3666666666666667,0.3,EUR,\"first buy, small\"
+2020-02-17,BASF AG,sell,3,4.1,,EUR
+2020-03-16,,cash,1,1e-3,,EUR
+";
+        let mut transactions = import_transactions_csv(csv.as_bytes(), &mut db).unwrap();
+        transactions[0].cash_flow.amount.amount = amount_from_f64(-10.1);
+        transactions[3].cash_flow.amount.amount = amount_from_f64(0.1 + 0.2);
+        let mut output = Vec::new();
+        export_transactions_csv(&transactions, &mut output, &mut db).unwrap();
+        let imported = import_transactions_csv(output.as_slice(), &mut db).unwrap();
+        assert_eq!(imported.len(), transactions.len());
+        for (imported, original) in imported.iter().zip(&transactions) {
+            assert_eq!(imported.cash_flow.amount, original.cash_flow.amount);
+            assert_eq!(imported.cash_flow.date, original.cash_flow.date);
+            assert_eq!(
+                imported.transaction_type.kind(),
+                original.transaction_type.kind()
+            );
+            assert_eq!(imported.note, original.note);
+        }
+        assert_eq!(imported[0].cash_flow.amount.value(), -10.1);
+        // floating point amounts are exported with all digits, decimals exactly
+        #[cfg(not(feature = "decimal"))]
+        {
+            assert_eq!(imported[2].cash_flow.amount.value(), 3. * 4.1);
+            assert_eq!(
+                imported[3].cash_flow.amount.value().to_string(),
+                "0.30000000000000004"
+            );
+        }
+        #[cfg(feature = "decimal")]
+        assert_eq!(imported[3].cash_flow.amount.amount.to_string(), "0.3");
+    }
+
+    #[test]
+    fn export_withholding_tax_in_payment_row() {
+        let mut db = InMemoryDB::new();
+        db.insert_asset(&Asset::new(None, "BASF AG", None, None, None))
+            .unwrap();
+        let csv = "date,asset_name,kind,quantity,price,fee,currency,note,withholding_tax
+2020-03-16,BASF AG,sell,4,50,,EUR,,
+2020-03-16,,tax,1,1.2,,EUR,,
+2020-05-04,BASF AG,dividend,1,19.8,,EUR,,5.22
+";
+        let ids = store_transactions_csv(csv.as_bytes(), &mut db, false).unwrap();
+        assert_eq!(ids.len(), 4);
+        let transactions = db.get_all_transactions().unwrap();
+        let mut output = Vec::new();
+        export_transactions_csv(&transactions, &mut output, &mut db).unwrap();
+        let expected =
+            "date,asset_name,kind,quantity,price,fee,currency,note,withholding_tax,amount
+2020-03-16,BASF AG,sell,4,50,,EUR,,,200
+2020-03-16,,tax,1,1.2,,EUR,,,1.2
+2020-05-04,BASF AG,dividend,1,19.8,,EUR,,5.22,19.8
+";
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
+
+        // the relation to the dividend is restored when storing the exported data
+        let ids = store_transactions_csv(expected.as_bytes(), &mut db, false).unwrap();
+        let tax = db.get_transaction_by_id(ids[3]).unwrap();
+        assert!(matches!(
+            tax.transaction_type,
+            TransactionType::Tax { transaction_ref: Some(id), kind: TaxKind::Withholding }
+                if id == ids[2]
+        ));
+        assert_eq!(tax.cash_flow.amount.value(), -5.22);
+
+        // taxes with a note are kept as separate rows
+        let mut transactions = transactions;
+        transactions[3].note = Some("refundable".to_string());
+        let mut output = Vec::new();
+        export_transactions_csv(&transactions, &mut output, &mut db).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("2020-05-04,BASF AG,dividend,1,19.8,,EUR,,,19.8\n"));
+        assert!(output.ends_with("2020-05-04,,tax,1,5.22,,EUR,refundable,,5.22\n"));
+    }
+
+    #[test]
+    fn roll_back_failed_import() {
+        let mut db = InMemoryDB::new();
+        db.insert_asset(&Asset::new(None, "BASF AG", None, None, None))
+            .unwrap();
+        let csv = "date,asset_name,kind,quantity,price,fee,currency
+2020-01-02,,cash,1,10000,,EUR
+";
+        store_transactions_csv(csv.as_bytes(), &mut db, true).unwrap();
+        let dump = |db: &mut InMemoryDB| {
+            serde_json::to_string(&(
+                db.get_all_assets().unwrap(),
+                db.get_all_transactions().unwrap(),
+            ))
+            .unwrap()
+        };
+        let before = dump(&mut db);
+
+        let csv = "date,asset_name,kind,quantity,price,fee,currency
+2020-01-15,BASF AG,buy,10,60.5,4.95,EUR
+2020-02-03,Unknown,buy,5,102.25,,EUR
+";
+        // without rollback, the rows before the error are kept
+        let snapshot = db.snapshot().unwrap();
+        assert!(store_transactions_csv(csv.as_bytes(), &mut db, false).is_err());
+        let transactions = db.get_all_transactions().unwrap();
+        assert_eq!(transactions.len(), 3);
+        assert!(matches!(
+            transactions[2].transaction_type,
+            TransactionType::Fee {
+                transaction_ref: Some(2)
+            }
+        ));
+        db.restore(snapshot).unwrap();
+        assert_eq!(dump(&mut db), before);
+
+        assert!(store_transactions_csv(csv.as_bytes(), &mut db, true).is_err());
+        assert_eq!(dump(&mut db), before);
+        // ids are not used up by the rolled back import
+        let csv = "date,asset_name,kind,quantity,price,fee,currency
+2020-01-15,BASF AG,buy,10,60.5,4.95,EUR
+";
+        assert_eq!(
+            store_transactions_csv(csv.as_bytes(), &mut db, true).unwrap(),
+            vec![2, 3]
+        );
+    }
+}